@@ -0,0 +1,325 @@
+//! V26.6 Passage segmentation — Markdown → token-budgeted retrieval chunks.
+//!
+//! Headings (outside code fences) split the body into sections and give each
+//! chunk its section path. Inside a section, words are packed greedily up to
+//! `max_tokens`; the cut backs off to the last paragraph break, else sentence
+//! end, as long as the chunk keeps at least half its budget. The next chunk
+//! re-reads ~`overlap_tokens` of the previous one, snapped forward to a
+//! sentence start when there is one. Chunks never span sections.
+//!
+//! Tokens are estimated per word as ceil(chars / 4) (BPE rule of thumb), so the
+//! budget is stable without shipping a tokenizer. Offsets are UTF-16 code
+//! units into the input (JS string indices); `text` is the exact slice.
+//! `chunk_id` = SHA256("<umid>:<start>")[0..16], stable across re-runs.
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use regex::Regex;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::LazyLock;
+
+const DEFAULT_MAX_TOKENS: u32 = 512;
+const DEFAULT_OVERLAP_TOKENS: u32 = 64;
+const DEFAULT_MIN_TEXT_LEN: u32 = 200;
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+const DEFAULT_TEXT_FIELDS: &[&str] = &["body_content", "readme"];
+const OUTPUT_FILE: &str = "chunks.ndjson.zst";
+
+static RE_HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ {0,3}(#{1,6})[ \t]+(.*?)[ \t]*#*[ \t]*$").unwrap());
+static RE_FENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^ {0,3}(```+|~~~+)").unwrap());
+static RE_WORD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\S+").unwrap());
+
+#[napi(object)]
+#[derive(Default, Clone)]
+pub struct ChunkOptions {
+    /// Token budget per chunk (default 512).
+    pub max_tokens: Option<u32>,
+    /// Tokens repeated from the previous chunk (default 64).
+    pub overlap_tokens: Option<u32>,
+}
+
+#[napi(object)]
+pub struct TextChunk {
+    pub chunk_id: String,
+    pub umid: String,
+    /// Position in the document, 0-based.
+    pub index: u32,
+    /// Enclosing heading titles, outermost first.
+    pub section_path: Vec<String>,
+    pub text: String,
+    /// UTF-16 offsets into the input text.
+    pub start: u32,
+    pub end: u32,
+    pub token_estimate: u32,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct ChunkBatchOptions {
+    pub chunk: Option<ChunkOptions>,
+    /// Entity fields tried in order (default body_content, readme).
+    pub text_fields: Option<Vec<String>>,
+    /// Texts shorter than this (chars) are not chunked (default 200).
+    pub min_text_len: Option<u32>,
+    pub zstd_level: Option<i32>,
+}
+
+#[napi(object)]
+pub struct ChunkBatchStats {
+    pub entity_count: u32,
+    pub chunked_entity_count: u32,
+    pub chunk_count: u32,
+    pub shard_count: u32,
+    pub skipped_shard_count: u32,
+    pub duration_ms: u32,
+    pub output_file: String,
+}
+
+struct Section {
+    path: Vec<String>,
+    start: usize,
+    end: usize,
+}
+
+/// Split Markdown into heading-delimited body spans (byte offsets).
+fn sections(text: &str) -> Vec<Section> {
+    let mut out = Vec::new();
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut body_start = 0;
+    let mut fence: Option<String> = None;
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = pos;
+        pos += line.len();
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        if let Some(m) = RE_FENCE.captures(trimmed) {
+            let marker = &m[1];
+            match &fence {
+                Some(open) if marker.starts_with(open.as_str()) => fence = None,
+                None => fence = Some(marker.to_string()),
+                _ => {}
+            }
+            continue;
+        }
+        if fence.is_some() {
+            continue;
+        }
+        if let Some(c) = RE_HEADING.captures(trimmed) {
+            out.push(Section { path: stack.iter().map(|(_, t)| t.clone()).collect(), start: body_start, end: line_start });
+            let level = c[1].len();
+            stack.retain(|(l, _)| *l < level);
+            stack.push((level, c[2].to_string()));
+            body_start = pos;
+        }
+    }
+    out.push(Section { path: stack.into_iter().map(|(_, t)| t).collect(), start: body_start, end: text.len() });
+    out
+}
+
+fn token_cost(word: &str) -> usize {
+    word.chars().count().div_ceil(4).max(1)
+}
+
+/// Break strength after a word: 2 = paragraph, 1 = sentence / line, 0 = none.
+fn boundary(word: &str, gap: &str) -> u8 {
+    if gap.matches('\n').count() >= 2 {
+        2
+    } else if gap.contains('\n') || word.ends_with(['.', '!', '?', ':']) {
+        1
+    } else {
+        0
+    }
+}
+
+/// (start, end) word index ranges for one section's words.
+fn pack(costs: &[usize], bounds: &[u8], max: usize, overlap: usize) -> Vec<(usize, usize)> {
+    let n = costs.len();
+    let mut prefix = vec![0usize; n + 1];
+    for (i, c) in costs.iter().enumerate() {
+        prefix[i + 1] = prefix[i] + c;
+    }
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < n {
+        let mut j = i + 1;
+        while j < n && prefix[j + 1] - prefix[i] <= max {
+            j += 1;
+        }
+        if j < n {
+            let floor = max / 2;
+            let best = |strength: u8| {
+                (i + 1..=j).rev().find(|&k| bounds[k - 1] >= strength && prefix[k] - prefix[i] >= floor)
+            };
+            j = best(2).or_else(|| best(1)).unwrap_or(j);
+        }
+        out.push((i, j));
+        if j >= n {
+            break;
+        }
+        let mut s = j;
+        while s > i + 1 && prefix[j] - prefix[s - 1] <= overlap {
+            s -= 1;
+        }
+        // Start the overlap at a sentence start when one is inside it.
+        i = (s..j).find(|&k| k > 0 && bounds[k - 1] >= 1).unwrap_or(s);
+    }
+    out
+}
+
+/// Chunk Markdown `text` for document `umid`.
+pub(crate) fn chunk_text(text: &str, umid: &str, options: &ChunkOptions) -> Vec<TextChunk> {
+    let max = options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS).max(1) as usize;
+    let overlap = (options.overlap_tokens.unwrap_or(DEFAULT_OVERLAP_TOKENS) as usize).min(max / 2);
+
+    // (section, byte start, byte end, tokens) per chunk, in document order.
+    let mut spans: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let secs = sections(text);
+    for (si, sec) in secs.iter().enumerate() {
+        let words: Vec<(usize, usize)> =
+            RE_WORD.find_iter(&text[sec.start..sec.end]).map(|m| (sec.start + m.start(), sec.start + m.end())).collect();
+        if words.is_empty() {
+            continue;
+        }
+        let costs: Vec<usize> = words.iter().map(|&(s, e)| token_cost(&text[s..e])).collect();
+        let bounds: Vec<u8> = words
+            .iter()
+            .enumerate()
+            .map(|(i, &(s, e))| {
+                let next = words.get(i + 1).map(|w| w.0).unwrap_or(sec.end);
+                boundary(&text[s..e], &text[e..next])
+            })
+            .collect();
+        for (a, b) in pack(&costs, &bounds, max, overlap) {
+            spans.push((si, words[a].0, words[b - 1].1, costs[a..b].iter().sum()));
+        }
+    }
+
+    // Byte → UTF-16 offsets; starts and ends are each non-decreasing.
+    let utf16 = |positions: Vec<usize>| -> Vec<u32> {
+        let (mut byte, mut unit) = (0usize, 0usize);
+        positions
+            .into_iter()
+            .map(|p| {
+                unit += text[byte..p].encode_utf16().count();
+                byte = p;
+                unit as u32
+            })
+            .collect()
+    };
+    let starts = utf16(spans.iter().map(|s| s.1).collect());
+    let ends = utf16(spans.iter().map(|s| s.2).collect());
+    spans
+        .iter()
+        .enumerate()
+        .map(|(i, &(si, s, e, tokens))| TextChunk {
+            chunk_id: nxvf_core::sha256_hex16(&format!("{umid}:{}", starts[i])),
+            umid: umid.to_string(),
+            index: i as u32,
+            section_path: secs[si].path.clone(),
+            text: text[s..e].to_string(),
+            start: starts[i],
+            end: ends[i],
+            token_estimate: tokens as u32,
+        })
+        .collect()
+}
+
+/// Segment Markdown into retrieval passages (see module docs).
+#[napi]
+pub fn chunk_markdown(text: String, umid: String, options: Option<ChunkOptions>) -> Vec<TextChunk> {
+    chunk_text(&text, &umid, &options.unwrap_or_default())
+}
+
+/// Stream a shard directory and write every entity's chunks to
+/// `<output_dir>/chunks.ndjson.zst` (one chunk per line, with entity `id`).
+/// UMID = entity `umid`, else SHA256(id)[0..16] as in umid-generator.js.
+#[napi]
+pub fn chunk_shard_dir(
+    shard_dir: String,
+    output_dir: String,
+    options: Option<ChunkBatchOptions>,
+) -> Result<ChunkBatchStats> {
+    let start = std::time::Instant::now();
+    let opts = options.unwrap_or_default();
+    let chunk_opts = opts.chunk.clone().unwrap_or_default();
+    let min_len = opts.min_text_len.unwrap_or(DEFAULT_MIN_TEXT_LEN) as usize;
+    let fields: Vec<String> = opts
+        .text_fields
+        .clone()
+        .unwrap_or_else(|| DEFAULT_TEXT_FIELDS.iter().map(|f| f.to_string()).collect());
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| Error::from_reason(format!("create output dir: {e}")))?;
+    let out_path = Path::new(&output_dir).join(OUTPUT_FILE);
+    let file = std::fs::File::create(&out_path)
+        .map_err(|e| Error::from_reason(format!("create output: {e}")))?;
+    let mut enc = zstd::Encoder::new(BufWriter::new(file), opts.zstd_level.unwrap_or(DEFAULT_ZSTD_LEVEL))
+        .map_err(|e| Error::from_reason(format!("zstd init: {e}")))?;
+
+    let shard_files = nxvf_core::discover_shards(&shard_dir).map_err(Error::from_reason)?;
+    let (mut entity_count, mut chunked, mut chunk_count, mut skipped) = (0u32, 0u32, 0u32, 0u32);
+    let mut write_err: Option<String> = None;
+    for path in &shard_files {
+        let res = nxvf_core::for_each_entity_in_file(path, |e| {
+            entity_count += 1;
+            let id = e.get("id").and_then(|v| v.as_str()).unwrap_or("");
+            let text = fields
+                .iter()
+                .find_map(|f| e.get(f).and_then(|v| v.as_str()).filter(|s| s.chars().count() >= min_len));
+            let Some(text) = text.filter(|_| !id.is_empty()) else { return Ok(()) };
+            let umid = e
+                .get("umid")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| nxvf_core::sha256_hex16(id));
+            let chunks = chunk_text(text, &umid, &chunk_opts);
+            chunked += 1;
+            chunk_count += chunks.len() as u32;
+            for c in chunks {
+                let line = serde_json::json!({
+                    "id": id,
+                    "umid": c.umid,
+                    "chunk_id": c.chunk_id,
+                    "index": c.index,
+                    "section_path": c.section_path,
+                    "start": c.start,
+                    "end": c.end,
+                    "token_estimate": c.token_estimate,
+                    "text": c.text,
+                });
+                if let Err(e) = writeln!(enc, "{line}") {
+                    write_err = Some(format!("write: {e}"));
+                    return Err("output write failed".to_string());
+                }
+            }
+            Ok(())
+        });
+        if let Some(e) = write_err.take() {
+            return Err(Error::from_reason(e));
+        }
+        if let Err(e) = res {
+            eprintln!("[RUST-CHUNK] Skipping shard {}: {}", path, e);
+            skipped += 1;
+        }
+    }
+    enc.finish()
+        .and_then(|mut w| w.flush())
+        .map_err(|e| Error::from_reason(format!("finish output: {e}")))?;
+
+    let stats = ChunkBatchStats {
+        entity_count,
+        chunked_entity_count: chunked,
+        chunk_count,
+        shard_count: shard_files.len() as u32,
+        skipped_shard_count: skipped,
+        duration_ms: start.elapsed().as_millis() as u32,
+        output_file: out_path.to_string_lossy().into_owned(),
+    };
+    eprintln!(
+        "[RUST-CHUNK] {} entities, {} chunked → {} chunks in {}ms",
+        stats.entity_count, stats.chunked_entity_count, stats.chunk_count, stats.duration_ms
+    );
+    Ok(stats)
+}
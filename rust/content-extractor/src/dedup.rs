@@ -0,0 +1,318 @@
+//! V26.6 Near-duplicate detection — MinHash + SimHash + LSH banding.
+//!
+//! Mirrors, forks and quantised re-uploads carry the same README/abstract
+//! under different ids. Text is normalised (lowercase alphanumeric words),
+//! shingled into k-word windows and reduced to a `num_perm`-slot MinHash
+//! signature; a 64-bit SimHash over term frequencies is kept alongside as a
+//! cheap second opinion. `find_near_duplicates` streams a shard directory,
+//! buckets signatures by LSH bands and verifies every candidate pair by
+//! estimated Jaccard.
+//!
+//! Output: `near-duplicates.jsonl.zst`, one PR-C1-shaped assertion per line
+//! with `relation: "SAME_AS_CANDIDATE"` (member_a < member_b, evidence weight =
+//! estimated Jaccard). identity-cluster only folds `SAME_AS`, so these stay
+//! weak until JS promotes them.
+//!
+//! Memory: O(indexed entities × num_perm × 4 bytes) for signatures; entity
+//! bodies are dropped per shard.
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const DEFAULT_SHINGLE_SIZE: u32 = 5;
+const DEFAULT_NUM_PERM: u32 = 128;
+const DEFAULT_BANDS: u32 = 32;
+const DEFAULT_MIN_JACCARD: f64 = 0.8;
+const DEFAULT_MIN_TEXT_LEN: u32 = 200;
+/// Buckets larger than this are boilerplate collisions (empty templates,
+/// license text) — skipped instead of expanding O(n²) pairs.
+const DEFAULT_MAX_BUCKET_SIZE: u32 = 500;
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+const DEFAULT_TEXT_FIELDS: &[&str] = &["body_content", "readme", "description"];
+const OUTPUT_FILE: &str = "near-duplicates.jsonl.zst";
+
+#[napi(object)]
+#[derive(Default)]
+pub struct MinHashOptions {
+    /// Words per shingle (default 5).
+    pub shingle_size: Option<u32>,
+    /// Signature length (default 128).
+    pub num_perm: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct NearDupOptions {
+    /// Entity fields tried in order; the first non-empty string is hashed
+    /// (default body_content, readme, description).
+    pub text_fields: Option<Vec<String>>,
+    pub shingle_size: Option<u32>,
+    pub num_perm: Option<u32>,
+    /// LSH bands; must divide `num_perm` (default 32 → 4 rows per band).
+    pub bands: Option<u32>,
+    /// Estimated Jaccard a candidate must reach to be emitted (default 0.8).
+    pub min_jaccard: Option<f64>,
+    /// Texts shorter than this (chars) are not indexed (default 200).
+    pub min_text_len: Option<u32>,
+    pub max_bucket_size: Option<u32>,
+    pub zstd_level: Option<i32>,
+}
+
+#[napi(object)]
+pub struct NearDupStats {
+    pub entity_count: u32,
+    pub indexed_count: u32,
+    pub shard_count: u32,
+    pub skipped_shard_count: u32,
+    /// Distinct pairs sharing at least one band bucket.
+    pub candidate_pair_count: u32,
+    /// Pairs at or above `min_jaccard` (lines written).
+    pub pair_count: u32,
+    pub oversized_bucket_count: u32,
+    pub duration_ms: u32,
+    pub output_file: String,
+}
+
+/// FNV-1a 64 — stable across runs and platforms (no RandomState).
+fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+    let mut h = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for &b in bytes {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Shingle hashes (k-word windows); a text shorter than k is one shingle.
+fn shingles(words: &[String], k: usize) -> HashSet<u64> {
+    let k = k.max(1);
+    if words.len() <= k {
+        return std::iter::once(fnv1a(words.join(" ").as_bytes(), 0)).collect();
+    }
+    words.windows(k).map(|w| fnv1a(w.join(" ").as_bytes(), 0)).collect()
+}
+
+/// MinHash with `num_perm` seeded mixes of each shingle hash.
+fn minhash(words: &[String], k: usize, num_perm: usize) -> Vec<u32> {
+    let set = shingles(words, k);
+    let seeds: Vec<u64> = (0..num_perm as u64).map(|i| splitmix64(i ^ 0x5eed)).collect();
+    let mut sig = vec![u32::MAX; num_perm];
+    for &s in &set {
+        for (slot, seed) in sig.iter_mut().zip(&seeds) {
+            let h = (splitmix64(s ^ seed) >> 32) as u32;
+            if h < *slot {
+                *slot = h;
+            }
+        }
+    }
+    sig
+}
+
+/// 64-bit SimHash over word features weighted by term frequency.
+fn simhash_words(words: &[String]) -> u64 {
+    let mut tf: HashMap<&str, i64> = HashMap::new();
+    for w in words {
+        *tf.entry(w.as_str()).or_default() += 1;
+    }
+    let mut acc = [0i64; 64];
+    for (w, n) in tf {
+        let h = fnv1a(w.as_bytes(), 0);
+        for (bit, a) in acc.iter_mut().enumerate() {
+            *a += if h >> bit & 1 == 1 { n } else { -n };
+        }
+    }
+    acc.iter().enumerate().filter(|(_, &a)| a > 0).fold(0u64, |h, (bit, _)| h | 1 << bit)
+}
+
+fn jaccard(a: &[u32], b: &[u32]) -> f64 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).filter(|(x, y)| x == y).count() as f64 / a.len() as f64
+}
+
+/// MinHash signature of `text` (k-word shingles).
+#[napi]
+pub fn minhash_signature(text: String, options: Option<MinHashOptions>) -> Vec<u32> {
+    let opts = options.unwrap_or_default();
+    let k = opts.shingle_size.unwrap_or(DEFAULT_SHINGLE_SIZE) as usize;
+    let num_perm = opts.num_perm.unwrap_or(DEFAULT_NUM_PERM).max(1) as usize;
+    minhash(&words(&text), k, num_perm)
+}
+
+/// Estimated Jaccard similarity of two MinHash signatures of equal length.
+#[napi]
+pub fn estimate_jaccard(a: Vec<u32>, b: Vec<u32>) -> f64 {
+    jaccard(&a, &b)
+}
+
+/// 64-bit SimHash of `text` as 16 hex chars (u64 does not fit a JS number).
+#[napi]
+pub fn simhash(text: String) -> String {
+    format!("{:016x}", simhash_words(&words(&text)))
+}
+
+/// Hamming distance between two hex SimHashes.
+#[napi]
+pub fn simhash_distance(a: String, b: String) -> Result<u32> {
+    let parse = |s: &str| {
+        u64::from_str_radix(s, 16).map_err(|e| Error::from_reason(format!("bad simhash {s:?}: {e}")))
+    };
+    Ok((parse(&a)? ^ parse(&b)?).count_ones())
+}
+
+/// Stream a shard directory, index each entity's text, and write candidate
+/// near-duplicate pairs to `<output_dir>/near-duplicates.jsonl.zst`.
+#[napi]
+pub fn find_near_duplicates(
+    shard_dir: String,
+    output_dir: String,
+    options: Option<NearDupOptions>,
+) -> Result<NearDupStats> {
+    let start = std::time::Instant::now();
+    let opts = options.unwrap_or_default();
+    let k = opts.shingle_size.unwrap_or(DEFAULT_SHINGLE_SIZE) as usize;
+    let num_perm = opts.num_perm.unwrap_or(DEFAULT_NUM_PERM) as usize;
+    let bands = opts.bands.unwrap_or(DEFAULT_BANDS) as usize;
+    if bands == 0 || num_perm == 0 || !num_perm.is_multiple_of(bands) {
+        return Err(Error::from_reason(format!(
+            "bands ({bands}) must divide num_perm ({num_perm})"
+        )));
+    }
+    let rows = num_perm / bands;
+    let min_jaccard = opts.min_jaccard.unwrap_or(DEFAULT_MIN_JACCARD);
+    let min_len = opts.min_text_len.unwrap_or(DEFAULT_MIN_TEXT_LEN) as usize;
+    let max_bucket = opts.max_bucket_size.unwrap_or(DEFAULT_MAX_BUCKET_SIZE) as usize;
+    let fields: Vec<String> = opts
+        .text_fields
+        .unwrap_or_else(|| DEFAULT_TEXT_FIELDS.iter().map(|f| f.to_string()).collect());
+
+    // 1. Index: id + signature + simhash per entity with enough text.
+    let shard_files = nxvf_core::discover_shards(&shard_dir).map_err(Error::from_reason)?;
+    let mut ids: Vec<String> = Vec::new();
+    let mut signatures: Vec<u32> = Vec::new();
+    let mut simhashes: Vec<u64> = Vec::new();
+    let mut entity_count = 0u32;
+    let mut skipped = 0u32;
+    for path in &shard_files {
+        let res = nxvf_core::for_each_entity_in_file(path, |e| {
+            entity_count += 1;
+            let id = e.get("id").and_then(|v| v.as_str()).unwrap_or("");
+            let text = fields
+                .iter()
+                .find_map(|f| e.get(f).and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty()));
+            if let Some(text) = text.filter(|t| !id.is_empty() && t.chars().count() >= min_len) {
+                let w = words(text);
+                signatures.extend(minhash(&w, k, num_perm));
+                simhashes.push(simhash_words(&w));
+                ids.push(id.to_string());
+            }
+            Ok(())
+        });
+        if let Err(e) = res {
+            eprintln!("[RUST-DEDUP] Skipping corrupted shard {}: {}", path, e);
+            skipped += 1;
+        }
+    }
+    let sig = |i: usize| &signatures[i * num_perm..(i + 1) * num_perm];
+
+    // 2. LSH: one bucket per (band, band-hash); pairs sharing any bucket are candidates.
+    let mut candidates: HashSet<(u32, u32)> = HashSet::new();
+    let mut oversized = 0u32;
+    for band in 0..bands {
+        let mut buckets: HashMap<u64, Vec<u32>> = HashMap::new();
+        for i in 0..ids.len() {
+            let slots = &sig(i)[band * rows..(band + 1) * rows];
+            let bytes: Vec<u8> = slots.iter().flat_map(|s| s.to_le_bytes()).collect();
+            buckets.entry(fnv1a(&bytes, band as u64)).or_default().push(i as u32);
+        }
+        for members in buckets.values().filter(|m| m.len() > 1) {
+            if members.len() > max_bucket {
+                oversized += 1;
+                continue;
+            }
+            for (x, &a) in members.iter().enumerate() {
+                for &b in &members[x + 1..] {
+                    candidates.insert((a, b));
+                }
+            }
+        }
+    }
+
+    // 3. Verify by estimated Jaccard; deterministic order by member ids.
+    let mut pairs: Vec<(&str, &str, f64, u32)> = candidates
+        .iter()
+        .filter_map(|&(a, b)| {
+            let (a, b) = (a as usize, b as usize);
+            let j = jaccard(sig(a), sig(b));
+            if j < min_jaccard || ids[a] == ids[b] {
+                return None;
+            }
+            let (ma, mb) = if ids[a] < ids[b] { (&ids[a], &ids[b]) } else { (&ids[b], &ids[a]) };
+            Some((ma.as_str(), mb.as_str(), j, (simhashes[a] ^ simhashes[b]).count_ones()))
+        })
+        .collect();
+    pairs.sort_by(|x, y| x.0.cmp(y.0).then(x.1.cmp(y.1)));
+    pairs.dedup_by(|x, y| x.0 == y.0 && x.1 == y.1);
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| Error::from_reason(format!("create output dir: {e}")))?;
+    let out_path = Path::new(&output_dir).join(OUTPUT_FILE);
+    let file = std::fs::File::create(&out_path)
+        .map_err(|e| Error::from_reason(format!("create output: {e}")))?;
+    let mut enc = zstd::Encoder::new(BufWriter::new(file), opts.zstd_level.unwrap_or(DEFAULT_ZSTD_LEVEL))
+        .map_err(|e| Error::from_reason(format!("zstd init: {e}")))?;
+    for &(a, b, j, d) in &pairs {
+        let line = serde_json::json!({
+            "relation": "SAME_AS_CANDIDATE",
+            "member_a": a,
+            "member_b": b,
+            "method": "minhash_lsh",
+            "evidence": [{ "weight": (j * 1000.0).round() / 1000.0, "simhash_distance": d }],
+        });
+        writeln!(enc, "{line}").map_err(|e| Error::from_reason(format!("write: {e}")))?;
+    }
+    enc.finish()
+        .and_then(|mut w| w.flush())
+        .map_err(|e| Error::from_reason(format!("finish output: {e}")))?;
+
+    let stats = NearDupStats {
+        entity_count,
+        indexed_count: ids.len() as u32,
+        shard_count: shard_files.len() as u32,
+        skipped_shard_count: skipped,
+        candidate_pair_count: candidates.len() as u32,
+        pair_count: pairs.len() as u32,
+        oversized_bucket_count: oversized,
+        duration_ms: start.elapsed().as_millis() as u32,
+        output_file: out_path.to_string_lossy().into_owned(),
+    };
+    eprintln!(
+        "[RUST-DEDUP] {} entities, {} indexed, {} candidates, {} pairs ≥ {} ({} oversized buckets) in {}ms",
+        stats.entity_count,
+        stats.indexed_count,
+        stats.candidate_pair_count,
+        stats.pair_count,
+        min_jaccard,
+        stats.oversized_bucket_count,
+        stats.duration_ms
+    );
+    Ok(stats)
+}
@@ -0,0 +1,414 @@
+//! V26.6 DOM extraction path — HTML5 parse (html5ever via scraper) → main
+//! content selection → Markdown.
+//!
+//! Replaces the regex strip for nested `<div>`s, `<p>` inside tables and
+//! attributes containing `>`. Entities are decoded by the tokenizer (full
+//! HTML5 named/numeric set). Main content is picked readability-style: every
+//! element gets a content score (non-link text, weighted by class/id hints),
+//! and we descend from `<body>` into a child while that child alone carries
+//! ≥ DOMINANCE of its parent's score. Lists, code blocks and tables survive as
+//! Markdown. Headings follow the regex path (ltx_title → `##`) so section
+//! counts and classification stay comparable.
+
+use ego_tree::NodeId;
+use scraper::{ElementRef, Html};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Nesting deeper than this is flattened to plain text, so hostile or
+/// machine-generated HTML cannot overflow the stack in the recursive writers.
+pub(crate) const MAX_DEPTH: usize = 256;
+/// A child must carry this share of its parent's score to become the root.
+const DOMINANCE: f64 = 0.85;
+/// Class/id hint multipliers.
+const NEGATIVE_WEIGHT: f64 = 0.2;
+const POSITIVE_WEIGHT: f64 = 1.25;
+
+/// Never content: dropped from both scoring and output (regex path parity
+/// for script/style/nav/header/footer).
+const SKIP_TAGS: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form", "button",
+    "iframe", "svg", "template", "select", "head",
+];
+
+/// Elements that start a new Markdown block.
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "blockquote", "body", "dd", "details", "dialog", "div", "dl", "dt",
+    "fieldset", "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "li", "main",
+    "ol", "p", "pre", "section", "summary", "table", "ul",
+];
+
+static RE_NEGATIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)comment|footer|nav|sidebar|sponsor|menu|share|social|advert|banner|breadcrumb|cookie|popup|related").unwrap()
+});
+static RE_POSITIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)article|content|entry|main|post|text|ltx_document|ltx_page").unwrap()
+});
+
+pub(crate) fn tag<'a>(el: &ElementRef<'a>) -> &'a str {
+    el.value().name()
+}
+
+pub(crate) fn has_class(el: &ElementRef, class: &str) -> bool {
+    el.value().classes().any(|c| c == class)
+}
+
+fn class_weight(el: &ElementRef) -> f64 {
+    let hints = format!(
+        "{} {}",
+        el.value().attr("class").unwrap_or(""),
+        el.value().id().unwrap_or("")
+    );
+    if hints.trim().is_empty() {
+        1.0
+    } else if RE_NEGATIVE.is_match(&hints) {
+        NEGATIVE_WEIGHT
+    } else if RE_POSITIVE.is_match(&hints) {
+        POSITIVE_WEIGHT
+    } else {
+        1.0
+    }
+}
+
+/// Content score of every element under `root`: non-whitespace text outside
+/// links, × class/id weight. One bottom-up pass (reverse pre-order visits
+/// children before parents), so scoring is O(n) and needs no recursion.
+fn content_scores(root: ElementRef) -> HashMap<NodeId, f64> {
+    let elements: Vec<ElementRef> = root.descendent_elements().collect();
+    let mut scores = HashMap::with_capacity(elements.len());
+    for el in elements.iter().rev() {
+        let score = if SKIP_TAGS.contains(&tag(el)) || tag(el) == "a" {
+            0.0
+        } else {
+            let mut score = 0.0;
+            for child in el.children() {
+                if let Some(t) = child.value().as_text() {
+                    score += t.chars().filter(|c| !c.is_whitespace()).count() as f64;
+                } else if child.value().is_element() {
+                    score += scores.get(&child.id()).copied().unwrap_or(0.0);
+                }
+            }
+            score * class_weight(el)
+        };
+        scores.insert(el.id(), score);
+    }
+    scores
+}
+
+/// Readability-style root: descend while a single child dominates.
+fn main_content_root(body: ElementRef) -> ElementRef {
+    let scores = content_scores(body);
+    let score = |el: &ElementRef| scores.get(&el.id()).copied().unwrap_or(0.0);
+    let mut root = body;
+    loop {
+        let total = score(&root);
+        if total <= 0.0 {
+            return root;
+        }
+        let best = root
+            .child_elements()
+            .map(|c| (score(&c), c))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        match best {
+            Some((score, child)) if score >= DOMINANCE * total => root = child,
+            _ => return root,
+        }
+    }
+}
+
+/// Append text with whitespace runs (incl. U+00A0) collapsed to one space.
+fn push_collapsed(out: &mut String, text: &str) {
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !out.is_empty() && !out.ends_with(' ') && !out.ends_with('\n') {
+                out.push(' ');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// Collapsed text of a whole subtree (iterative; used past MAX_DEPTH).
+fn flat_text(el: &ElementRef) -> String {
+    let mut out = String::new();
+    for t in el.text() {
+        push_collapsed(&mut out, t);
+    }
+    out.trim().to_string()
+}
+
+/// Inline rendering hook: elements for which this returns true are dropped
+/// (paper.rs uses it to keep footnote bodies out of section text).
+pub(crate) type InlineSkip = fn(&ElementRef) -> bool;
+
+fn keep_all(_: &ElementRef) -> bool {
+    false
+}
+
+/// Inline text of an element's children.
+fn inline_text(el: &ElementRef, out: &mut String, skip: InlineSkip, depth: usize) {
+    for child in el.children() {
+        if let Some(t) = child.value().as_text() {
+            push_collapsed(out, t);
+        } else if let Some(c) = ElementRef::wrap(child) {
+            inline_element(&c, out, skip, depth + 1);
+        }
+    }
+}
+
+/// One element in inline context: links → text, `<code>` → backticks,
+/// `<math>` → `$alttext$`, `<br>` → newline, images dropped.
+fn inline_element(el: &ElementRef, out: &mut String, skip: InlineSkip, depth: usize) {
+    if skip(el) {
+        return;
+    }
+    if depth >= MAX_DEPTH {
+        for t in el.text() {
+            push_collapsed(out, t);
+        }
+        return;
+    }
+    match tag(el) {
+        t if SKIP_TAGS.contains(&t) => {}
+        "img" => {}
+        "br" => {
+            while out.ends_with(' ') {
+                out.pop();
+            }
+            out.push('\n');
+        }
+        "code" => {
+            let code: String = el.text().collect();
+            let code = code.trim();
+            if !code.is_empty() {
+                out.push('`');
+                out.push_str(code);
+                out.push('`');
+            }
+        }
+        "math" => match el.value().attr("alttext") {
+            Some(alt) if !alt.trim().is_empty() => {
+                out.push('$');
+                out.push_str(alt.trim());
+                out.push('$');
+            }
+            _ => inline_text(el, out, skip, depth),
+        },
+        _ => inline_text(el, out, skip, depth),
+    }
+}
+
+fn inline_of(el: &ElementRef) -> String {
+    inline_of_with(el, keep_all)
+}
+
+/// Collapsed inline text of `el` (math → `$alttext$`), lines trimmed.
+pub(crate) fn inline_of_with(el: &ElementRef, skip: InlineSkip) -> String {
+    let mut s = String::new();
+    inline_text(el, &mut s, skip, 0);
+    s.lines().map(str::trim).collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+/// Markdown writer over the selected subtree. Blocks are joined by blank lines.
+struct Markdown {
+    blocks: Vec<String>,
+    /// Current block nesting; see MAX_DEPTH.
+    depth: usize,
+}
+
+impl Markdown {
+    fn push(&mut self, block: String) {
+        if !block.trim().is_empty() {
+            self.blocks.push(block);
+        }
+    }
+
+    fn block(&mut self, el: &ElementRef) {
+        if SKIP_TAGS.contains(&tag(el)) {
+            return;
+        }
+        if self.depth >= MAX_DEPTH {
+            self.push(flat_text(el));
+            return;
+        }
+        self.depth += 1;
+        self.element(el);
+        self.depth -= 1;
+    }
+
+    fn element(&mut self, el: &ElementRef) {
+        let name = tag(el);
+        // ArXiv ltx_title (any tag) → ## (regex path parity)
+        if has_class(el, "ltx_title") {
+            let text = inline_of(el);
+            if !text.is_empty() {
+                self.push(format!("## {}", text.replace('\n', " ")));
+            }
+            return;
+        }
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(2);
+                let text = inline_of(el);
+                if !text.is_empty() {
+                    self.push(format!("{} {}", "#".repeat(level), text.replace('\n', " ")));
+                }
+            }
+            "ul" | "ol" => {
+                let mut lines = Vec::new();
+                list(el, 0, &mut lines);
+                self.push(lines.join("\n"));
+            }
+            "pre" => self.push(code_block(el)),
+            "table" => self.table(el),
+            "blockquote" => {
+                let mut inner = Markdown { blocks: Vec::new(), depth: self.depth };
+                inner.container(el);
+                let quoted = inner
+                    .finish()
+                    .lines()
+                    .map(|l| if l.is_empty() { ">".to_string() } else { format!("> {l}") })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.push(quoted);
+            }
+            "hr" => self.push("---".to_string()),
+            _ => self.container(el),
+        }
+    }
+
+    /// Mixed content: inline runs become paragraphs, block children recurse.
+    fn container(&mut self, el: &ElementRef) {
+        let mut para = String::new();
+        for child in el.children() {
+            if let Some(t) = child.value().as_text() {
+                push_collapsed(&mut para, t);
+                continue;
+            }
+            let Some(c) = ElementRef::wrap(child) else { continue };
+            if BLOCK_TAGS.contains(&tag(&c)) || has_class(&c, "ltx_title") {
+                self.flush(&mut para);
+                self.block(&c);
+            } else {
+                inline_element(&c, &mut para, keep_all, 0);
+            }
+        }
+        self.flush(&mut para);
+    }
+
+    fn flush(&mut self, para: &mut String) {
+        let text = para.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+        self.push(text.trim().to_string());
+        para.clear();
+    }
+
+    fn table(&mut self, el: &ElementRef) {
+        let rows: Vec<ElementRef> = el
+            .descendent_elements()
+            .filter(|r| tag(r) == "tr")
+            .filter(|r| r.ancestors().filter_map(ElementRef::wrap).find(|a| tag(a) == "table") == Some(*el))
+            .collect();
+        let cells: Vec<Vec<ElementRef>> = rows
+            .iter()
+            .map(|r| r.child_elements().filter(|c| matches!(tag(c), "td" | "th")).collect())
+            .collect();
+        let cols = cells.iter().map(Vec::len).max().unwrap_or(0);
+        if cols == 0 {
+            return;
+        }
+        // Single-column tables are layout, not data: render cells as blocks.
+        if cols == 1 {
+            for cell in cells.iter().flatten() {
+                self.container(cell);
+            }
+            return;
+        }
+        let mut lines = Vec::with_capacity(cells.len() + 1);
+        for (i, row) in cells.iter().enumerate() {
+            let mut texts: Vec<String> = row
+                .iter()
+                .map(|c| inline_of(c).replace('\n', " ").replace('|', "\\|"))
+                .collect();
+            texts.resize(cols, String::new());
+            lines.push(format!("| {} |", texts.join(" | ")));
+            if i == 0 {
+                lines.push(format!("|{}", " --- |".repeat(cols)));
+            }
+        }
+        self.push(lines.join("\n"));
+    }
+
+    fn finish(self) -> String {
+        self.blocks.join("\n\n")
+    }
+}
+
+/// `- item` / `1. item`, nested lists indented by two spaces per level.
+fn list(el: &ElementRef, depth: usize, lines: &mut Vec<String>) {
+    let ordered = tag(el) == "ol";
+    let indent = "  ".repeat(depth);
+    let mut n = 0;
+    for li in el.child_elements().filter(|c| tag(c) == "li") {
+        n += 1;
+        let mut text = String::new();
+        let mut nested = Vec::new();
+        for child in li.children() {
+            if let Some(t) = child.value().as_text() {
+                push_collapsed(&mut text, t);
+            } else if let Some(c) = ElementRef::wrap(child) {
+                if matches!(tag(&c), "ul" | "ol") {
+                    nested.push(c);
+                } else {
+                    inline_element(&c, &mut text, keep_all, 0);
+                    if BLOCK_TAGS.contains(&tag(&c)) && !text.ends_with(' ') {
+                        text.push(' ');
+                    }
+                }
+            }
+        }
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let marker = if ordered { format!("{n}.") } else { "-".to_string() };
+        lines.push(format!("{indent}{marker} {text}").trim_end().to_string());
+        for sub in nested {
+            if depth + 1 >= MAX_DEPTH {
+                lines.push(format!("{indent}  {}", flat_text(&sub)).trim_end().to_string());
+            } else {
+                list(&sub, depth + 1, lines);
+            }
+        }
+    }
+}
+
+/// Fenced code block; raw whitespace kept, language from `language-*`/`lang-*`.
+fn code_block(el: &ElementRef) -> String {
+    let code: String = el.text().collect();
+    let code = code.trim_matches('\n');
+    let lang = std::iter::once(*el)
+        .chain(el.child_elements().filter(|c| tag(c) == "code"))
+        .flat_map(|e| e.value().classes().collect::<Vec<_>>())
+        .find_map(|c| c.strip_prefix("language-").or_else(|| c.strip_prefix("lang-")))
+        .unwrap_or("");
+    let mut fence_len = 3;
+    let mut run = 0;
+    for ch in code.chars() {
+        run = if ch == '`' { run + 1 } else { 0 };
+        fence_len = fence_len.max(run + 1);
+    }
+    let fence = "`".repeat(fence_len);
+    format!("{fence}{lang}\n{code}\n{fence}")
+}
+
+/// DOM extraction → Markdown. Empty string when the document has no text
+/// content (caller falls back to the regex path).
+pub(crate) fn extract_main_content_dom(html: &str) -> String {
+    let doc = Html::parse_document(html);
+    let root = doc.root_element();
+    let body = root.child_elements().find(|c| tag(c) == "body").unwrap_or(root);
+    let main = main_content_root(body);
+    let mut md = Markdown { blocks: Vec::new(), depth: 0 };
+    md.block(&main);
+    md.finish()
+}
@@ -8,17 +8,54 @@
 //!   PARTIAL: length 200..1000 (stored, has_fulltext = false)
 //!   SKIP:    length < 200 (placeholder / not rendered)
 //!   FAILURE: reserved for network errors (handled in JS layer)
+//!
+//! V26.6: HTML goes through the DOM path (dom.rs); the regex path below is
+//! kept as the fallback when the DOM yields no text, and for differential tests.
+//! `extract_and_score` / `score_text` add a quality score (quality.rs) and
+//! take the bucket thresholds as options. PDFs (pdf.rs) are converted to the
+//! same Markdown shape and share the classify buckets. dedup.rs adds
+//! MinHash/SimHash near-duplicate detection over shard directories;
+//! manifest.rs builds layout-aware enrichment manifests from R2 listings;
+//! chunk.rs segments Markdown into retrieval passages.
 
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use regex::Regex;
 use std::sync::LazyLock;
 
+mod chunk;
+mod dedup;
+mod dom;
+mod links;
+mod manifest;
+mod paper;
+mod pdf;
+mod quality;
+pub use chunk::*;
+pub use dedup::*;
+pub use links::*;
+pub use manifest::*;
+pub use paper::*;
+pub use quality::*;
+
 const MIN_QUALITY_LEN: usize = 200;
 const FULLTEXT_THRESHOLD: usize = 1000;
 const MIN_SECTION_HEADERS: usize = 2;
 const MAX_HTML_SIZE: usize = 2_000_000;
 
+/// Bucket thresholds for `classify_with`; `QualityOptions` can override them.
+pub(crate) struct Thresholds {
+    pub min_quality_len: usize,
+    pub fulltext_threshold: usize,
+    pub min_section_headers: usize,
+}
+
+pub(crate) const DEFAULT_THRESHOLDS: Thresholds = Thresholds {
+    min_quality_len: MIN_QUALITY_LEN,
+    fulltext_threshold: FULLTEXT_THRESHOLD,
+    min_section_headers: MIN_SECTION_HEADERS,
+};
+
 // Pre-compiled regexes for HTML extraction
 static RE_SCRIPT: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"(?is)<script[\s\S]*?</script>").unwrap());
@@ -46,6 +83,8 @@ pub struct ExtractionResult {
     pub char_count: u32,
     pub section_count: u32,
     pub has_fulltext: bool,
+    /// Set by `extract_and_score` / `score_text` only.
+    pub quality: Option<QualityReport>,
 }
 
 /// Strip HTML tags and decode common entities.
@@ -59,11 +98,9 @@ fn strip_tags(html: &str) -> String {
         .replace("&#39;", "'")
 }
 
-/// Extract article content from ar5iv HTML, converting to Markdown.
-/// Strips nav/header/footer/script/style, preserves section headers.
-fn extract_main_content(html: &str) -> String {
-    // Truncate oversized HTML (UTF-8 boundary safe)
-    let source = if html.len() > MAX_HTML_SIZE {
+/// Truncate oversized HTML (UTF-8 boundary safe).
+fn truncate_html(html: &str) -> &str {
+    if html.len() > MAX_HTML_SIZE {
         // Walk backward from MAX_HTML_SIZE to find a valid UTF-8 char boundary
         let mut end = MAX_HTML_SIZE;
         while end > 0 && !html.is_char_boundary(end) {
@@ -71,8 +108,27 @@ fn extract_main_content(html: &str) -> String {
         }
         &html[..end]
     } else {
-        &html
-    };
+        html
+    }
+}
+
+/// Extract article content from ar5iv HTML, converting to Markdown.
+/// DOM path first; regex path when the DOM yields nothing.
+fn extract_main_content(html: &str) -> String {
+    let source = truncate_html(html);
+    let text = dom::extract_main_content_dom(source);
+    let text = RE_MULTI_NL.replace_all(&text, "\n\n");
+    let text = text.trim();
+    if text.is_empty() {
+        return extract_main_content_regex(source);
+    }
+    text.to_string()
+}
+
+/// Regex extraction (pre-V26.6 path).
+/// Strips nav/header/footer/script/style, preserves section headers.
+fn extract_main_content_regex(html: &str) -> String {
+    let source = truncate_html(html);
 
     // Remove non-content elements
     let text = RE_SCRIPT.replace_all(source, "");
@@ -121,16 +177,20 @@ fn extract_main_content(html: &str) -> String {
 }
 
 /// Count markdown section headers (## or ###).
-fn count_section_headers(text: &str) -> usize {
+pub(crate) fn count_section_headers(text: &str) -> usize {
     RE_MD_HEADING.find_iter(text).count()
 }
 
 /// Classify content into 4 buckets per Spec §2.2.
 fn classify(text: &str, section_count: usize) -> (&'static str, bool) {
+    classify_with(text, section_count, &DEFAULT_THRESHOLDS)
+}
+
+pub(crate) fn classify_with(text: &str, section_count: usize, t: &Thresholds) -> (&'static str, bool) {
     let len = text.len();
-    if len >= FULLTEXT_THRESHOLD && section_count >= MIN_SECTION_HEADERS {
+    if len >= t.fulltext_threshold && section_count >= t.min_section_headers {
         ("SUCCESS", true)
-    } else if len >= MIN_QUALITY_LEN {
+    } else if len >= t.min_quality_len {
         ("PARTIAL", false)
     } else {
         ("SKIP", false)
@@ -155,9 +215,62 @@ pub fn extract_and_classify(html: String) -> ExtractionResult {
         char_count: char_count as u32,
         section_count: section_count as u32,
         has_fulltext,
+        quality: None,
     }
 }
 
+/// Extract + classify with quality scoring (language, boilerplate, mojibake,
+/// repeated lines, text density). Low scores demote the bucket.
+#[napi]
+pub fn extract_and_score(html: String, options: Option<QualityOptions>) -> ExtractionResult {
+    let text = extract_main_content(&html);
+    let html_len = truncate_html(&html).len();
+    quality::classify_scored(text, Some(html_len), &options.unwrap_or_default())
+}
+
+/// `classify_text` with quality scoring and configurable thresholds.
+#[napi]
+pub fn score_text(text: String, options: Option<QualityOptions>) -> ExtractionResult {
+    quality::classify_scored(text, None, &options.unwrap_or_default())
+}
+
+/// Structured ar5iv/arXiv paper extraction (title, authors, abstract,
+/// section tree, captions, equations, footnotes, bibliography).
+#[napi]
+pub fn extract_paper(html: String) -> PaperDocument {
+    paper::extract_paper_document(truncate_html(&html))
+}
+
+/// Outbound references (arXiv, DOI, HF model/dataset/space, GitHub, PwC)
+/// in extracted text, with canonical ids and UTF-16 offsets.
+#[napi]
+pub fn extract_references(text: String) -> Vec<OutboundReference> {
+    links::find_references(&text)
+}
+
+/// PDF (Buffer) → text → classify. Pure Rust, no network; reading order,
+/// headings by font size/weight, running headers/footers stripped.
+#[napi]
+pub fn extract_pdf_and_classify(pdf: Buffer) -> Result<ExtractionResult> {
+    let text = pdf::extract_pdf_text(&pdf).map_err(Error::from_reason)?;
+    Ok(classify_text(text))
+}
+
+/// `extract_pdf_and_classify` for a local file path.
+#[napi]
+pub fn extract_pdf_file_and_classify(path: String) -> Result<ExtractionResult> {
+    let bytes = std::fs::read(&path).map_err(|e| Error::from_reason(format!("{path}: {e}")))?;
+    let text = pdf::extract_pdf_text(&bytes).map_err(Error::from_reason)?;
+    Ok(classify_text(text))
+}
+
+/// Regex-path extraction — rollback switch for the JS layer and for
+/// diffing against the DOM path on live pages.
+#[napi]
+pub fn extract_and_classify_regex(html: String) -> ExtractionResult {
+    classify_text(extract_main_content_regex(&html))
+}
+
 /// Classify pre-extracted text (e.g., from S2 API or existing body_content).
 /// Use when HTML extraction is not needed.
 #[napi]
@@ -172,6 +285,7 @@ pub fn classify_text(text: String) -> ExtractionResult {
         char_count: char_count as u32,
         section_count: section_count as u32,
         has_fulltext,
+        quality: None,
     }
 }
 
@@ -192,23 +306,15 @@ pub fn batch_classify(texts_buffer: Buffer) -> Vec<String> {
 
 // ── Fusion Protocol (Spec §3.2) ─────────────────────────────────
 
-static RE_R2_UMID: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"enrichment/fulltext/[a-f0-9]{2}/([a-f0-9]+)\.md\.gz$").unwrap());
-
 /// Build enrichment manifest from R2 key list.
 /// Spec §3.2: Parses R2 keys into UMID→Key map entries.
 /// Input: newline-delimited R2 keys.
-/// Returns: Vec of [umid, key] pairs (only matching keys).
+/// Returns: Vec of [umid, key] pairs (fulltext layout, `.md.gz` or `.md.zst`,
+/// fan-out prefix consistent with the UMID). See manifest.rs for the
+/// structured variants that report unmatched/inconsistent/duplicate keys.
 #[napi]
 pub fn build_enrichment_manifest(keys_buffer: Buffer) -> Vec<Vec<String>> {
-    let data = std::str::from_utf8(&keys_buffer).unwrap_or("");
-    data.lines()
-        .filter_map(|key| {
-            RE_R2_UMID.captures(key).map(|caps| {
-                vec![caps[1].to_string(), key.to_string()]
-            })
-        })
-        .collect()
+    manifest::fulltext_pairs(&keys_buffer)
 }
 
 /// Validate fused content after R2 download (Fusion quality gate).
@@ -0,0 +1,225 @@
+//! V26.6 Outbound reference extraction — body text → canonical ids + offsets.
+//!
+//! Relation extractors in JS only see metadata fields; the fulltext that
+//! passes through here carries far more evidence. Each hit is normalised to
+//! the canonical prefixes used by relations-extractors.js / mesh_graph:
+//!   arXiv (new `2106.09685`, old `hep-th/9901001`, abs/pdf URLs, `arXiv:`,
+//!          hf.co/papers, DOI 10.48550/arXiv.*)   → arxiv-paper--<id>
+//!   huggingface.co/<o>/<n>                        → hf-model--<o>--<n>
+//!   huggingface.co/datasets/<o>/<n>               → hf-dataset--<o>--<n>
+//!   huggingface.co/spaces/<o>/<n>                 → hf-space--<o>--<n>
+//!   github.com/<o>/<r>                            → gh-tool--<o>--<r>
+//!   DOIs / PapersWithCode                         → no canonical id (value only)
+//!
+//! Offsets are UTF-16 code units (JS string indices), so
+//! `text.slice(start, end) === raw` holds on the JS side.
+
+use napi_derive::napi;
+use regex::Regex;
+use std::sync::LazyLock;
+
+#[napi(object)]
+pub struct OutboundReference {
+    /// "arxiv" | "hf_model" | "hf_dataset" | "hf_space" | "github" | "doi" | "pwc"
+    pub kind: String,
+    /// Normalised identifier: arXiv id, `owner/name`, lowercase DOI, PwC path.
+    pub value: String,
+    pub canonical_id: Option<String>,
+    /// Matched source text.
+    pub raw: String,
+    /// UTF-16 offsets into the input text.
+    pub start: u32,
+    pub end: u32,
+}
+
+const ARXIV_ID: &str = r"(\d{4}\.\d{4,5}|[a-z][a-z\-]+(?:\.[A-Z]{2})?/\d{7})(?:v\d+)?";
+const URL_PREFIX: &str = r"(?:https?://)?(?:www\.)?";
+
+static RE_ARXIV: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b(?:{URL_PREFIX}(?:arxiv\.org/(?:abs|pdf)/|(?:huggingface\.co|hf\.co)/papers/)|arxiv:\s?){ARXIV_ID}(?:\.pdf)?"
+    ))
+    .unwrap()
+});
+static RE_HF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b{URL_PREFIX}(?:huggingface\.co|hf\.co)/(?:(datasets|spaces)/)?([A-Za-z0-9][\w.\-]*)/([A-Za-z0-9][\w.\-]*)"
+    ))
+    .unwrap()
+});
+static RE_GITHUB: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(r"(?i)\b{URL_PREFIX}github\.com/([A-Za-z0-9][\w\-]*)/([\w.\-]+)")).unwrap()
+});
+static RE_PWC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b{URL_PREFIX}paperswithcode\.com/(paper|dataset|method|task|sota)/([\w\-]+)"
+    ))
+    .unwrap()
+});
+static RE_DOI: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(?:\bhttps?://(?:dx\.)?doi\.org/|\bdoi:\s?)?\b(10\.\d{4,9}/[^\s"<>]+)"#).unwrap()
+});
+static RE_ARXIV_DOI: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^10\.48550/arxiv\.(\d{4}\.\d{4,5})(?:v\d+)?$").unwrap());
+
+/// First path segments on huggingface.co that are site pages, not repos.
+/// `datasets` / `spaces` are here for the single-segment legacy form
+/// (`/datasets/squad`), which RE_HF otherwise backtracks into owner=`datasets`.
+const HF_RESERVED: &[&str] = &[
+    "api", "blog", "chat", "collections", "course", "datasets", "docs", "enterprise", "join",
+    "learn", "login", "models", "organizations", "papers", "posts", "pricing", "settings",
+    "spaces", "tasks",
+];
+/// github.com/<segment>/… that are site pages, not owners.
+const GH_RESERVED: &[&str] = &[
+    "about", "apps", "collections", "customer-stories", "enterprise", "explore", "features",
+    "issues", "login", "marketplace", "notifications", "orgs", "pricing", "pulls", "security",
+    "settings", "site", "sponsors", "topics", "trending",
+];
+
+/// Trailing sentence punctuation is never part of an id.
+fn trim_trailing(text: &str, start: usize, mut end: usize) -> usize {
+    while end > start {
+        match text[..end].chars().next_back() {
+            Some('.' | ',' | ';' | ':' | ')' | ']' | '}' | '\'' | '"') => end -= 1,
+            _ => break,
+        }
+    }
+    end
+}
+
+/// Old-style ids carry a slash; canonical ids use '-' (id-manager.js sanitize).
+fn arxiv_canonical(id: &str) -> String {
+    format!("arxiv-paper--{}", id.to_lowercase().replace('/', "-"))
+}
+
+fn owner_name_canonical(prefix: &str, owner: &str, name: &str) -> String {
+    format!("{prefix}--{}--{}", owner.to_lowercase(), name.to_lowercase())
+}
+
+struct Hit {
+    kind: &'static str,
+    value: String,
+    canonical_id: Option<String>,
+    start: usize,
+    end: usize,
+}
+
+fn collect_hits(text: &str) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    for c in RE_ARXIV.captures_iter(text) {
+        let m = c.get(0).unwrap();
+        let id = c[1].to_string();
+        hits.push(Hit {
+            kind: "arxiv",
+            canonical_id: Some(arxiv_canonical(&id)),
+            value: id,
+            start: m.start(),
+            end: m.end(),
+        });
+    }
+    for c in RE_HF.captures_iter(text) {
+        let m = c.get(0).unwrap();
+        let owner = &c[2];
+        let end = trim_trailing(text, m.start(), m.end());
+        let name = c[3][..c[3].len() - (m.end() - end).min(c[3].len())].trim_end_matches(".git");
+        let (kind, prefix) = match c.get(1).map(|s| s.as_str().to_ascii_lowercase()) {
+            Some(s) if s == "datasets" => ("hf_dataset", "hf-dataset"),
+            Some(_) => ("hf_space", "hf-space"),
+            None if HF_RESERVED.contains(&owner.to_ascii_lowercase().as_str()) => continue,
+            None => ("hf_model", "hf-model"),
+        };
+        if name.is_empty() {
+            continue;
+        }
+        hits.push(Hit {
+            kind,
+            value: format!("{owner}/{name}"),
+            canonical_id: Some(owner_name_canonical(prefix, owner, name)),
+            start: m.start(),
+            end,
+        });
+    }
+    for c in RE_GITHUB.captures_iter(text) {
+        let m = c.get(0).unwrap();
+        let owner = &c[1];
+        if GH_RESERVED.contains(&owner.to_ascii_lowercase().as_str()) {
+            continue;
+        }
+        let end = trim_trailing(text, m.start(), m.end());
+        let repo = c[2][..c[2].len() - (m.end() - end).min(c[2].len())].trim_end_matches(".git");
+        if repo.is_empty() {
+            continue;
+        }
+        hits.push(Hit {
+            kind: "github",
+            value: format!("{owner}/{repo}"),
+            canonical_id: Some(owner_name_canonical("gh-tool", owner, repo)),
+            start: m.start(),
+            end,
+        });
+    }
+    for c in RE_PWC.captures_iter(text) {
+        let m = c.get(0).unwrap();
+        hits.push(Hit {
+            kind: "pwc",
+            value: format!("{}/{}", c[1].to_lowercase(), c[2].to_lowercase()),
+            canonical_id: None,
+            start: m.start(),
+            end: m.end(),
+        });
+    }
+    for c in RE_DOI.captures_iter(text) {
+        let m = c.get(0).unwrap();
+        let end = trim_trailing(text, m.start(), m.end());
+        let doi_start = c.get(1).unwrap().start();
+        let doi = text[doi_start..end].to_lowercase();
+        let hit = match RE_ARXIV_DOI.captures(&doi) {
+            Some(a) => Hit {
+                kind: "arxiv",
+                canonical_id: Some(arxiv_canonical(&a[1])),
+                value: a[1].to_string(),
+                start: m.start(),
+                end,
+            },
+            None => Hit { kind: "doi", value: doi, canonical_id: None, start: m.start(), end },
+        };
+        hits.push(hit);
+    }
+    // Overlaps (e.g. a DOI URL that is also an arXiv DOI): earliest, then
+    // longest match wins.
+    hits.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+    let mut kept: Vec<Hit> = Vec::with_capacity(hits.len());
+    for h in hits {
+        if kept.last().is_none_or(|k| h.start >= k.end) {
+            kept.push(h);
+        }
+    }
+    kept
+}
+
+/// All outbound references in `text`, in document order.
+pub(crate) fn find_references(text: &str) -> Vec<OutboundReference> {
+    let hits = collect_hits(text);
+    // Byte → UTF-16 offsets in one forward pass (hits are sorted by start).
+    let mut out = Vec::with_capacity(hits.len());
+    let (mut byte_pos, mut utf16_pos) = (0usize, 0usize);
+    let mut advance = |to: usize| {
+        utf16_pos += text[byte_pos..to].encode_utf16().count();
+        byte_pos = to;
+        utf16_pos as u32
+    };
+    for h in hits {
+        let start = advance(h.start);
+        let end = advance(h.end);
+        out.push(OutboundReference {
+            kind: h.kind.to_string(),
+            value: h.value,
+            canonical_id: h.canonical_id,
+            raw: text[h.start..h.end].to_string(),
+            start,
+            end,
+        });
+    }
+    out
+}
@@ -0,0 +1,260 @@
+//! V26.6 Enrichment manifest — R2 key listings → UMID→key entries per layout.
+//!
+//! A layout is a key template with placeholders:
+//!   {prefix}  two-char fan-out directory; must equal the UMID's first chars
+//!   {umid}    lowercase hex UMID (SHA256(canonical_id)[0..16], umid-generator.js)
+//!   {ext}     compression suffix, `gz` or `zst`
+//!   {file}    any single path segment (per-UMID asset directories)
+//! Keys that fit no layout are `unmatched`; keys that fit a layout's shape but
+//! fail the UMID / prefix checks are `inconsistent`; a second key for the same
+//! UMID in a single-key layout (e.g. both `.md.gz` and `.md.zst`) is a
+//! duplicate — the first key in listing order stays the manifest entry.
+//!
+//! Listings come in as plain keys, the `{key: etag}` map from r2-engine
+//! `fetch_all_r2_etags`, or r2-engine `walk_dir_with_md5` `WalkEntry` rows.
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use regex::Regex;
+use std::collections::HashMap;
+
+const DEFAULT_UMID_LENGTH: u32 = 16;
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct ManifestLayout {
+    /// "fulltext" | "summaries" | "images" | caller-defined.
+    pub name: String,
+    pub template: String,
+    /// Several keys per UMID are expected (asset directories); no duplicate check.
+    pub multi: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct ManifestOptions {
+    /// Default: fulltext, summaries, images (see `default_layouts`).
+    pub layouts: Option<Vec<ManifestLayout>>,
+    /// Hex length of a valid UMID (default 16).
+    pub umid_length: Option<u32>,
+    /// Prepended to `WalkEntry.rel_path` to form the bucket key.
+    pub key_prefix: Option<String>,
+}
+
+/// Field-for-field the shape of r2-engine `WalkEntry`, so its output can be
+/// passed straight through from JS.
+#[napi(object)]
+pub struct WalkEntry {
+    pub rel_path: String,
+    pub size: i64,
+    pub md5: String,
+}
+
+#[napi(object)]
+pub struct ManifestEntry {
+    pub layout: String,
+    pub umid: String,
+    pub key: String,
+    pub etag: Option<String>,
+    pub size: Option<i64>,
+}
+
+#[napi(object)]
+pub struct ManifestIssue {
+    pub key: String,
+    pub layout: String,
+    pub umid: String,
+    /// "bad_umid" | "prefix_mismatch"
+    pub reason: String,
+}
+
+#[napi(object)]
+pub struct ManifestDuplicate {
+    pub layout: String,
+    pub umid: String,
+    /// All keys for the UMID; the first is the manifest entry.
+    pub keys: Vec<String>,
+}
+
+#[napi(object)]
+pub struct EnrichmentManifest {
+    pub key_count: u32,
+    pub entries: Vec<ManifestEntry>,
+    pub unmatched: Vec<String>,
+    pub inconsistent: Vec<ManifestIssue>,
+    pub duplicates: Vec<ManifestDuplicate>,
+}
+
+pub(crate) fn default_layouts() -> Vec<ManifestLayout> {
+    let layout = |name: &str, template: &str, multi: bool| ManifestLayout {
+        name: name.to_string(),
+        template: template.to_string(),
+        multi: Some(multi),
+    };
+    vec![
+        layout("fulltext", "enrichment/fulltext/{prefix}/{umid}.md.{ext}", false),
+        layout("summaries", "enrichment/summaries/{prefix}/{umid}.json.{ext}", false),
+        layout("images", "enrichment/images/{prefix}/{umid}/{file}", true),
+    ]
+}
+
+struct CompiledLayout {
+    name: String,
+    multi: bool,
+    re: Regex,
+}
+
+/// Template → anchored regex. Shapes are loose on purpose (any segment for
+/// {prefix}/{umid}) so malformed UMIDs surface as `inconsistent`, not `unmatched`.
+fn compile(layout: &ManifestLayout) -> std::result::Result<CompiledLayout, String> {
+    if !layout.template.contains("{umid}") {
+        return Err(format!("layout {:?}: template has no {{umid}}", layout.name));
+    }
+    let mut pattern = String::from("^");
+    let mut rest = layout.template.as_str();
+    while let Some(open) = rest.find('{') {
+        pattern.push_str(&regex::escape(&rest[..open]));
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("layout {:?}: unclosed placeholder", layout.name))?;
+        pattern.push_str(match &rest[open + 1..open + close] {
+            "prefix" => "(?P<prefix>[^/]+)",
+            "umid" => "(?P<umid>[^/.]+)",
+            "ext" => "(?:gz|zst)",
+            "file" => "[^/]+",
+            other => return Err(format!("layout {:?}: unknown placeholder {{{other}}}", layout.name)),
+        });
+        rest = &rest[open + close + 1..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+    Ok(CompiledLayout {
+        name: layout.name.clone(),
+        multi: layout.multi.unwrap_or(false),
+        re: Regex::new(&pattern).map_err(|e| format!("layout {:?}: {e}", layout.name))?,
+    })
+}
+
+/// Core builder over (key, etag, size) rows in listing order.
+pub(crate) fn build_manifest<'a, I>(
+    rows: I,
+    options: &ManifestOptions,
+) -> std::result::Result<EnrichmentManifest, String>
+where
+    I: IntoIterator<Item = (&'a str, Option<&'a str>, Option<i64>)>,
+{
+    let layouts = options.layouts.clone().unwrap_or_else(default_layouts);
+    let compiled = layouts.iter().map(compile).collect::<std::result::Result<Vec<_>, _>>()?;
+    let umid_len = options.umid_length.unwrap_or(DEFAULT_UMID_LENGTH) as usize;
+
+    let mut out = EnrichmentManifest {
+        key_count: 0,
+        entries: Vec::new(),
+        unmatched: Vec::new(),
+        inconsistent: Vec::new(),
+        duplicates: Vec::new(),
+    };
+    // (layout, umid) → index into `duplicates` or entry key for first sighting.
+    let mut seen: HashMap<(usize, String), (String, Option<usize>)> = HashMap::new();
+    for (key, etag, size) in rows {
+        out.key_count += 1;
+        let Some((li, caps)) = compiled.iter().enumerate().find_map(|(i, l)| l.re.captures(key).map(|c| (i, c)))
+        else {
+            out.unmatched.push(key.to_string());
+            continue;
+        };
+        let layout = &compiled[li];
+        let umid = &caps["umid"];
+        let reason = if umid.len() != umid_len || !umid.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            Some("bad_umid")
+        } else {
+            caps.name("prefix").filter(|p| !umid.starts_with(p.as_str())).map(|_| "prefix_mismatch")
+        };
+        if let Some(reason) = reason {
+            out.inconsistent.push(ManifestIssue {
+                key: key.to_string(),
+                layout: layout.name.clone(),
+                umid: umid.to_string(),
+                reason: reason.to_string(),
+            });
+            continue;
+        }
+        if !layout.multi {
+            if let Some((first, dup)) = seen.get_mut(&(li, umid.to_string())) {
+                match dup {
+                    Some(d) => out.duplicates[*d].keys.push(key.to_string()),
+                    None => {
+                        *dup = Some(out.duplicates.len());
+                        out.duplicates.push(ManifestDuplicate {
+                            layout: layout.name.clone(),
+                            umid: umid.to_string(),
+                            keys: vec![first.clone(), key.to_string()],
+                        });
+                    }
+                }
+                continue;
+            }
+            seen.insert((li, umid.to_string()), (key.to_string(), None));
+        }
+        out.entries.push(ManifestEntry {
+            layout: layout.name.clone(),
+            umid: umid.to_string(),
+            key: key.to_string(),
+            etag: etag.map(str::to_string),
+            size,
+        });
+    }
+    Ok(out)
+}
+
+/// Legacy pair list: fulltext layout only, valid entries only.
+pub(crate) fn fulltext_pairs(keys: &[u8]) -> Vec<Vec<String>> {
+    let data = std::str::from_utf8(keys).unwrap_or("");
+    let options = ManifestOptions {
+        layouts: Some(default_layouts().into_iter().take(1).collect()),
+        ..Default::default()
+    };
+    build_manifest(data.lines().map(|k| (k.trim(), None, None)), &options)
+        .map(|m| m.entries.into_iter().map(|e| vec![e.umid, e.key]).collect())
+        .unwrap_or_default()
+}
+
+/// Structured manifest from a plain key list.
+#[napi]
+pub fn build_enrichment_manifest_from_keys(
+    keys: Vec<String>,
+    options: Option<ManifestOptions>,
+) -> Result<EnrichmentManifest> {
+    build_manifest(keys.iter().map(|k| (k.as_str(), None, None)), &options.unwrap_or_default())
+        .map_err(Error::from_reason)
+}
+
+/// Structured manifest from r2-engine `fetch_all_r2_etags` output
+/// (`{key: etag}`); keys are processed in sorted order.
+#[napi]
+pub fn build_enrichment_manifest_from_etags(
+    etags: HashMap<String, String>,
+    options: Option<ManifestOptions>,
+) -> Result<EnrichmentManifest> {
+    let mut rows: Vec<(&str, Option<&str>, Option<i64>)> =
+        etags.iter().map(|(k, e)| (k.as_str(), Some(e.as_str()), None)).collect();
+    rows.sort_unstable_by_key(|r| r.0);
+    build_manifest(rows, &options.unwrap_or_default()).map_err(Error::from_reason)
+}
+
+/// Structured manifest from r2-engine `walk_dir_with_md5` entries (local mirror of
+/// the bucket); `options.key_prefix` maps `rel_path` onto the bucket key.
+#[napi]
+pub fn build_enrichment_manifest_from_walk(
+    entries: Vec<WalkEntry>,
+    options: Option<ManifestOptions>,
+) -> Result<EnrichmentManifest> {
+    let options = options.unwrap_or_default();
+    let prefix = options.key_prefix.as_deref().unwrap_or("");
+    let keys: Vec<String> = entries.iter().map(|e| format!("{prefix}{}", e.rel_path.replace('\\', "/"))).collect();
+    let rows = keys
+        .iter()
+        .zip(&entries)
+        .map(|(k, e)| (k.as_str(), Some(e.md5.as_str()), Some(e.size)));
+    build_manifest(rows, &options).map_err(Error::from_reason)
+}
@@ -0,0 +1,353 @@
+//! V26.6 Structured paper extraction — ar5iv / arXiv LaTeXML HTML → `PaperDocument`.
+//!
+//! Builds on the DOM path (dom.rs) and the LaTeXML class vocabulary the flat
+//! extractor already keys on (`ltx_title`, `ltx_p`):
+//!   title      h1.ltx_title_document
+//!   authors    .ltx_authors .ltx_role_author .ltx_personname (first line)
+//!   abstract   .ltx_abstract paragraphs
+//!   sections   section.ltx_{section,subsection,subsubsection,paragraph,appendix}
+//!   captions   figure.ltx_figure / figure.ltx_table → figcaption
+//!   equations  math[alttext] (or the TeX annotation), display if in ltx_equation
+//!   footnotes  .ltx_note.ltx_role_footnote
+//!   references li.ltx_bibitem → .ltx_bibblock (authors, title, venue)
+//!
+//! References carry the arXiv id / DOI / URL found in the entry (via
+//! links.rs) plus its canonical id, so the JS layer can emit CITES relations
+//! for satellite-tasks.
+
+use napi_derive::napi;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use std::sync::LazyLock;
+
+use crate::dom::{has_class, inline_of_with, tag, MAX_DEPTH};
+use crate::links::find_references;
+
+#[napi(object)]
+pub struct PaperSection {
+    /// Index of this section in `sections` (stable handle for `parent`).
+    pub index: u32,
+    pub id: Option<String>,
+    /// 1 = section/appendix, 2 = subsection, 3 = subsubsection, 4 = paragraph.
+    pub level: u32,
+    pub title: String,
+    /// Index of the enclosing section (None for top level).
+    pub parent: Option<u32>,
+    /// Paragraph text directly under this section (nested sections excluded).
+    pub text: String,
+}
+
+#[napi(object)]
+pub struct PaperCaption {
+    /// "figure" or "table".
+    pub kind: String,
+    pub id: Option<String>,
+    /// e.g. "Figure 3" / "Table 1".
+    pub label: Option<String>,
+    pub caption: String,
+}
+
+#[napi(object)]
+pub struct PaperEquation {
+    pub id: Option<String>,
+    pub latex: String,
+    pub display: bool,
+    /// Equation number, e.g. "(2)".
+    pub label: Option<String>,
+}
+
+#[napi(object)]
+pub struct PaperFootnote {
+    pub id: Option<String>,
+    pub mark: Option<String>,
+    pub text: String,
+}
+
+#[napi(object)]
+pub struct PaperReference {
+    pub id: Option<String>,
+    /// Reference label without brackets, e.g. "12" or "Vaswani et al., 2017".
+    pub label: Option<String>,
+    pub raw: String,
+    pub authors: Option<String>,
+    pub title: Option<String>,
+    pub year: Option<u32>,
+    /// New (`2106.09685`) or old (`hep-th/9901001`) style, version stripped.
+    pub arxiv_id: Option<String>,
+    pub doi: Option<String>,
+    pub url: Option<String>,
+    /// First canonical id found in the entry (`arxiv-paper--…`, `hf-model--…`, `gh-tool--…`).
+    pub canonical_id: Option<String>,
+}
+
+#[napi(object)]
+pub struct PaperDocument {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub abstract_text: Option<String>,
+    pub sections: Vec<PaperSection>,
+    pub captions: Vec<PaperCaption>,
+    pub equations: Vec<PaperEquation>,
+    pub footnotes: Vec<PaperFootnote>,
+    pub references: Vec<PaperReference>,
+}
+
+fn sel(s: &str) -> Selector {
+    Selector::parse(s).unwrap()
+}
+
+static SEL_TITLE: LazyLock<Selector> = LazyLock::new(|| sel("h1.ltx_title_document"));
+static SEL_AUTHOR: LazyLock<Selector> =
+    LazyLock::new(|| sel(".ltx_authors .ltx_role_author .ltx_personname"));
+static SEL_ABSTRACT: LazyLock<Selector> = LazyLock::new(|| sel(".ltx_abstract"));
+static SEL_FIGURE: LazyLock<Selector> = LazyLock::new(|| sel("figure.ltx_figure, figure.ltx_table"));
+static SEL_CAPTION: LazyLock<Selector> = LazyLock::new(|| sel("figcaption"));
+static SEL_TAG: LazyLock<Selector> = LazyLock::new(|| sel(".ltx_tag"));
+static SEL_MATH: LazyLock<Selector> = LazyLock::new(|| sel("math"));
+static SEL_TEX: LazyLock<Selector> =
+    LazyLock::new(|| sel(r#"annotation[encoding="application/x-tex"]"#));
+static SEL_FOOTNOTE: LazyLock<Selector> = LazyLock::new(|| sel(".ltx_note.ltx_role_footnote"));
+static SEL_NOTE_MARK: LazyLock<Selector> = LazyLock::new(|| sel(".ltx_note_mark"));
+static SEL_NOTE_CONTENT: LazyLock<Selector> = LazyLock::new(|| sel(".ltx_note_content"));
+static SEL_BIBITEM: LazyLock<Selector> = LazyLock::new(|| sel("li.ltx_bibitem"));
+static SEL_BIBBLOCK: LazyLock<Selector> = LazyLock::new(|| sel(".ltx_bibblock"));
+static SEL_BIBTAG: LazyLock<Selector> = LazyLock::new(|| sel(".ltx_tag_bibitem"));
+static SEL_LINK: LazyLock<Selector> = LazyLock::new(|| sel("a[href]"));
+
+static RE_YEAR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b(19[5-9]\d|20\d{2})[a-z]?\b").unwrap());
+static RE_AUTHOR_SPLIT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s*(?:,|\band\b|&|·)\s*").unwrap());
+
+/// Section level by LaTeXML class (None → not a sectioning element).
+fn section_level(el: &ElementRef) -> Option<u32> {
+    if tag(el) != "section" || has_class(el, "ltx_bibliography") {
+        return None;
+    }
+    for (class, level) in [
+        ("ltx_section", 1),
+        ("ltx_appendix", 1),
+        ("ltx_subsection", 2),
+        ("ltx_subsubsection", 3),
+        ("ltx_paragraph", 4),
+    ] {
+        if has_class(el, class) {
+            return Some(level);
+        }
+    }
+    None
+}
+
+/// Footnote bodies are reported separately; keep them out of running text.
+fn skip_notes(el: &ElementRef) -> bool {
+    has_class(el, "ltx_note_outer")
+}
+
+fn text_of(el: &ElementRef) -> String {
+    inline_of_with(el, skip_notes)
+}
+
+fn non_empty(s: String) -> Option<String> {
+    let s = s.trim().to_string();
+    (!s.is_empty()).then_some(s)
+}
+
+/// Paragraphs directly under a section (stops at nested sections/figures).
+fn collect_paragraphs(el: &ElementRef, out: &mut Vec<String>, depth: usize) {
+    if depth >= MAX_DEPTH {
+        return;
+    }
+    for c in el.child_elements() {
+        if section_level(&c).is_some() || has_class(&c, "ltx_title") || tag(&c) == "figure" {
+            continue;
+        }
+        if has_class(&c, "ltx_p") || tag(&c) == "p" {
+            if let Some(t) = non_empty(text_of(&c)) {
+                out.push(t);
+            }
+        } else {
+            collect_paragraphs(&c, out, depth + 1);
+        }
+    }
+}
+
+fn walk_sections(el: &ElementRef, parent: Option<u32>, out: &mut Vec<PaperSection>, depth: usize) {
+    if depth >= MAX_DEPTH {
+        return;
+    }
+    for c in el.child_elements() {
+        match section_level(&c) {
+            Some(level) => {
+                let title = c
+                    .child_elements()
+                    .find(|h| has_class(h, "ltx_title"))
+                    .map(|h| text_of(&h).replace('\n', " "))
+                    .unwrap_or_default();
+                let mut paras = Vec::new();
+                collect_paragraphs(&c, &mut paras, 0);
+                let index = out.len() as u32;
+                out.push(PaperSection {
+                    index,
+                    id: c.value().id().map(str::to_string),
+                    level,
+                    title,
+                    parent,
+                    text: paras.join("\n\n"),
+                });
+                walk_sections(&c, Some(index), out, depth + 1);
+            }
+            None => walk_sections(&c, parent, out, depth + 1),
+        }
+    }
+}
+
+fn authors(doc: &Html) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for person in doc.select(&SEL_AUTHOR) {
+        // Affiliations/emails follow a <br>; footnote marks are dropped.
+        let text = inline_of_with(&person, |e| has_class(e, "ltx_note") || tag(e) == "sup");
+        let first = text.lines().next().unwrap_or("").trim();
+        for name in RE_AUTHOR_SPLIT.split(first) {
+            let name = name.trim();
+            if !name.is_empty() && !out.iter().any(|n| n == name) {
+                out.push(name.to_string());
+            }
+        }
+    }
+    out
+}
+
+fn captions(doc: &Html) -> Vec<PaperCaption> {
+    doc.select(&SEL_FIGURE)
+        .filter_map(|fig| {
+            let cap = fig.select(&SEL_CAPTION).next()?;
+            let label = cap.select(&SEL_TAG).next().map(|t| text_of(&t));
+            let full = text_of(&cap);
+            let caption = match &label {
+                Some(l) => full.strip_prefix(l.as_str()).unwrap_or(&full).to_string(),
+                None => full.clone(),
+            };
+            Some(PaperCaption {
+                kind: if has_class(&fig, "ltx_table") { "table" } else { "figure" }.to_string(),
+                id: fig.value().id().map(str::to_string),
+                label: label.map(|l| l.trim_end_matches(':').trim().to_string()),
+                caption: caption.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn equations(doc: &Html) -> Vec<PaperEquation> {
+    doc.select(&SEL_MATH)
+        .filter_map(|m| {
+            let latex = m
+                .value()
+                .attr("alttext")
+                .map(str::to_string)
+                .or_else(|| m.select(&SEL_TEX).next().map(|a| a.text().collect::<String>()))?;
+            let latex = latex.trim().to_string();
+            if latex.is_empty() {
+                return None;
+            }
+            let eq = m.ancestors().filter_map(ElementRef::wrap).find(|a| {
+                has_class(a, "ltx_equation") || has_class(a, "ltx_equationgroup")
+            });
+            let label = eq
+                .as_ref()
+                .and_then(|e| e.select(&SEL_TAG).next())
+                .map(|t| text_of(&t))
+                .filter(|t| !t.is_empty());
+            Some(PaperEquation {
+                id: eq
+                    .as_ref()
+                    .and_then(|e| e.value().id())
+                    .or_else(|| m.value().id())
+                    .map(str::to_string),
+                display: eq.is_some() || m.value().attr("display") == Some("block"),
+                latex,
+                label,
+            })
+        })
+        .collect()
+}
+
+fn footnotes(doc: &Html) -> Vec<PaperFootnote> {
+    doc.select(&SEL_FOOTNOTE)
+        .filter_map(|note| {
+            let content = note.select(&SEL_NOTE_CONTENT).next()?;
+            let text = inline_of_with(&content, |e| has_class(e, "ltx_note_mark") || has_class(e, "ltx_tag_note"));
+            Some(PaperFootnote {
+                id: note.value().id().map(str::to_string),
+                mark: note.select(&SEL_NOTE_MARK).next().and_then(|m| non_empty(text_of(&m))),
+                text: non_empty(text)?,
+            })
+        })
+        .collect()
+}
+
+/// Parse one bibliography entry. LaTeXML splits entries into bibblocks:
+/// authors, title, then venue/year/ids.
+fn parse_reference(item: &ElementRef) -> PaperReference {
+    let blocks: Vec<String> = item
+        .select(&SEL_BIBBLOCK)
+        .filter_map(|b| non_empty(text_of(&b).replace('\n', " ")))
+        .collect();
+    let raw = if blocks.is_empty() {
+        text_of(item).replace('\n', " ")
+    } else {
+        blocks.join(" ")
+    };
+    let links: Vec<&str> = item
+        .select(&SEL_LINK)
+        .filter_map(|a| a.value().attr("href"))
+        .filter(|h| h.starts_with("http"))
+        .collect();
+    // Ids may live only in an href (e.g. "[link]" anchors).
+    let haystack = format!("{} {}", raw, links.join(" "));
+    let found = find_references(&haystack);
+    let first_of = |kind: &str| found.iter().find(|r| r.kind == kind).map(|r| r.value.clone());
+    PaperReference {
+        id: item.value().id().map(str::to_string),
+        label: item
+            .select(&SEL_BIBTAG)
+            .next()
+            .and_then(|t| non_empty(text_of(&t).trim_matches(['[', ']']).to_string())),
+        authors: blocks.first().map(|a| a.trim_end_matches('.').to_string()),
+        title: blocks.get(1).map(|t| t.trim_end_matches(['.', ',']).to_string()),
+        year: RE_YEAR
+            .captures_iter(&raw)
+            .last()
+            .and_then(|c| c[1].parse().ok()),
+        arxiv_id: first_of("arxiv"),
+        doi: first_of("doi"),
+        canonical_id: found.iter().find_map(|r| r.canonical_id.clone()),
+        url: links.first().map(|l| l.to_string()),
+        raw,
+    }
+}
+
+/// Structured extraction of an ar5iv/arXiv HTML paper.
+pub(crate) fn extract_paper_document(html: &str) -> PaperDocument {
+    let doc = Html::parse_document(html);
+    let title = doc
+        .select(&SEL_TITLE)
+        .next()
+        .and_then(|t| non_empty(text_of(&t).replace('\n', " ")));
+    let abstract_text = doc.select(&SEL_ABSTRACT).next().and_then(|a| {
+        let mut paras = Vec::new();
+        collect_paragraphs(&a, &mut paras, 0);
+        non_empty(paras.join("\n\n"))
+    });
+    let mut sections = Vec::new();
+    walk_sections(&doc.root_element(), None, &mut sections, 0);
+
+    PaperDocument {
+        title,
+        authors: authors(&doc),
+        abstract_text,
+        sections,
+        captions: captions(&doc),
+        equations: equations(&doc),
+        footnotes: footnotes(&doc),
+        references: doc.select(&SEL_BIBITEM).map(|b| parse_reference(&b)).collect(),
+    }
+}
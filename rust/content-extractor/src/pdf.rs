@@ -0,0 +1,494 @@
+//! V26.6 PDF text path — pure Rust (lopdf), no network, for papers ar5iv
+//! never rendered.
+//!
+//! Per page we interpret the content stream's text operators (BT/ET, Tf, Td,
+//! TD, Tm, T*, TL, Tj, TJ, ', ", cm, q/Q) into positioned runs carrying the
+//! effective font size and a bold flag (BaseFont name / FontDescriptor), then:
+//!   1. drop rotated runs (arXiv side stamps),
+//!   2. reconstruct reading order — full-width lines split the page into bands;
+//!      inside a band the left column is read before the right column,
+//!   3. strip running headers/footers (same top/bottom line, digits masked, on
+//!      ≥ half the pages) and bare page numbers,
+//!   4. mark headings by size relative to the body font (mode by chars) or a
+//!      short bold line, merge body lines into paragraphs (de-hyphenated).
+//!
+//! Output is the same Markdown shape as the HTML path, so `classify` applies.
+//!
+//! Form XObjects and Type3 fonts are not interpreted; glyph widths come from
+//! /Widths when present, else 0.5 em.
+
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Encoding, Object};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+
+const MAX_PDF_SIZE: usize = 50_000_000;
+const MAX_PAGES: usize = 300;
+/// Fallback glyph advance (em) when the font has no /Widths.
+const DEFAULT_GLYPH_WIDTH: f64 = 0.5;
+/// TJ offsets (thousandths of an em) beyond this are word gaps.
+const TJ_SPACE_THRESHOLD: f64 = 200.0;
+/// Line is a heading when its size is ≥ body × this.
+const HEADING_SIZE_RATIO: f64 = 1.15;
+const TITLE_SIZE_RATIO: f64 = 1.6;
+const MAX_HEADING_CHARS: usize = 100;
+
+static RE_PAGE_NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\s*(?:page\s*)?\d{1,4}(?:\s*(?:of|/)\s*\d{1,4})?\s*$|^\s*-\s*\d{1,4}\s*-\s*$").unwrap()
+});
+static RE_DIGITS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").unwrap());
+static RE_NUMBERED_HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:[A-Z]|\d+)(\.\d+)*\.?\s+\S").unwrap());
+
+/// Affine matrix [a b c d e f] (PDF row-vector convention).
+#[derive(Clone, Copy)]
+struct Matrix([f64; 6]);
+
+impl Matrix {
+    const IDENTITY: Matrix = Matrix([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    fn translate(tx: f64, ty: f64) -> Matrix {
+        Matrix([1.0, 0.0, 0.0, 1.0, tx, ty])
+    }
+
+    /// self × other
+    fn mul(&self, o: &Matrix) -> Matrix {
+        let [a1, b1, c1, d1, e1, f1] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = o.0;
+        Matrix([
+            a1 * a2 + b1 * c2,
+            a1 * b2 + b1 * d2,
+            c1 * a2 + d1 * c2,
+            c1 * b2 + d1 * d2,
+            e1 * a2 + f1 * c2 + e2,
+            e1 * b2 + f1 * d2 + f2,
+        ])
+    }
+}
+
+struct FontInfo<'a> {
+    encoding: Option<Encoding<'a>>,
+    bold: bool,
+    first_char: i64,
+    widths: Vec<f64>,
+}
+
+/// A positioned piece of text in page space.
+#[derive(Clone)]
+struct Run {
+    x: f64,
+    y: f64,
+    width: f64,
+    size: f64,
+    bold: bool,
+    text: String,
+}
+
+#[derive(Clone)]
+struct Line {
+    y: f64,
+    size: f64,
+    bold: bool,
+    text: String,
+}
+
+fn num(o: &Object) -> f64 {
+    match o {
+        Object::Integer(i) => *i as f64,
+        Object::Real(r) => *r as f64,
+        _ => 0.0,
+    }
+}
+
+fn font_info<'a>(doc: &'a Document, font: &'a Dictionary) -> FontInfo<'a> {
+    let base = font
+        .get(b"BaseFont")
+        .and_then(Object::as_name)
+        .map(|n| String::from_utf8_lossy(n).to_ascii_lowercase())
+        .unwrap_or_default();
+    let descriptor = font
+        .get(b"FontDescriptor")
+        .ok()
+        .and_then(|d| doc.dereference(d).ok())
+        .and_then(|(_, d)| d.as_dict().ok());
+    let weight = descriptor
+        .and_then(|d| d.get(b"FontWeight").ok())
+        .map(num)
+        .unwrap_or(0.0);
+    // FontDescriptor /Flags bit 19 = ForceBold
+    let force_bold = descriptor
+        .and_then(|d| d.get(b"Flags").ok())
+        .and_then(|f| f.as_i64().ok())
+        .is_some_and(|f| f & (1 << 18) != 0);
+    let bold = ["bold", "black", "heavy", "semibold", "demi"].iter().any(|w| base.contains(w))
+        || weight >= 600.0
+        || force_bold;
+    let widths = font
+        .get(b"Widths")
+        .ok()
+        .and_then(|w| doc.dereference(w).ok())
+        .and_then(|(_, w)| w.as_array().ok())
+        .map(|a| a.iter().map(|w| num(w) / 1000.0).collect())
+        .unwrap_or_default();
+    FontInfo {
+        encoding: font.get_font_encoding(doc).ok(),
+        bold,
+        first_char: font.get(b"FirstChar").and_then(Object::as_i64).unwrap_or(0),
+        widths,
+    }
+}
+
+/// Decode string bytes with the font encoding; Latin-1 when unknown.
+fn decode(font: Option<&FontInfo>, bytes: &[u8]) -> String {
+    font.and_then(|f| f.encoding.as_ref())
+        .and_then(|e| Document::decode_text(e, bytes).ok())
+        .unwrap_or_else(|| bytes.iter().map(|&b| b as char).collect())
+}
+
+/// Advance in text space units (before font size scaling).
+fn advance(font: Option<&FontInfo>, bytes: &[u8], chars: usize) -> f64 {
+    match font {
+        Some(f) if !f.widths.is_empty() && matches!(f.encoding, Some(Encoding::OneByteEncoding(_)) | None) => bytes
+            .iter()
+            .map(|&b| {
+                let i = b as i64 - f.first_char;
+                f.widths.get(i as usize).copied().filter(|_| i >= 0).unwrap_or(DEFAULT_GLYPH_WIDTH)
+            })
+            .sum(),
+        _ => chars as f64 * DEFAULT_GLYPH_WIDTH,
+    }
+}
+
+struct TextState<'a> {
+    ctm: Matrix,
+    stack: Vec<Matrix>,
+    tm: Matrix,
+    tlm: Matrix,
+    leading: f64,
+    size: f64,
+    font: Option<&'a FontInfo<'a>>,
+}
+
+impl TextState<'_> {
+    fn next_line(&mut self, tx: f64, ty: f64) {
+        self.tlm = Matrix::translate(tx, ty).mul(&self.tlm);
+        self.tm = self.tlm;
+    }
+
+    /// Emit a run at the current text position and advance by its width.
+    fn show(&mut self, text: String, adv: f64, runs: &mut Vec<Run>) {
+        let trm = self.tm.mul(&self.ctm);
+        let [a, b, c, d, e, f] = trm.0;
+        // Rotated text (b dominates a) is margin furniture, not body text.
+        let rotated = b.abs() > a.abs() || c.abs() > d.abs();
+        let scale = (c * c + d * d).sqrt();
+        let width = adv * self.size * (a * a + b * b).sqrt();
+        if !rotated && !text.trim().is_empty() {
+            runs.push(Run {
+                x: e,
+                y: f,
+                width,
+                size: self.size * scale,
+                bold: self.font.is_some_and(|f| f.bold),
+                text,
+            });
+        }
+        self.tm = Matrix::translate(adv * self.size, 0.0).mul(&self.tm);
+    }
+}
+
+fn page_runs(doc: &Document, page_id: lopdf::ObjectId) -> Result<Vec<Run>, String> {
+    let fonts: BTreeMap<Vec<u8>, FontInfo> = doc
+        .get_page_fonts(page_id)
+        .map_err(|e| format!("page fonts: {e}"))?
+        .into_iter()
+        .map(|(name, f)| (name, font_info(doc, f)))
+        .collect();
+    let data = doc.get_page_content(page_id).map_err(|e| format!("page content: {e}"))?;
+    let content = Content::decode(&data).map_err(|e| format!("content stream: {e}"))?;
+
+    let mut st = TextState {
+        ctm: Matrix::IDENTITY,
+        stack: Vec::new(),
+        tm: Matrix::IDENTITY,
+        tlm: Matrix::IDENTITY,
+        leading: 0.0,
+        size: 0.0,
+        font: None,
+    };
+    let mut runs = Vec::new();
+    for op in &content.operations {
+        let o = &op.operands;
+        match op.operator.as_str() {
+            "q" => st.stack.push(st.ctm),
+            "Q" => st.ctm = st.stack.pop().unwrap_or(Matrix::IDENTITY),
+            "cm" if o.len() == 6 => {
+                let m = Matrix([num(&o[0]), num(&o[1]), num(&o[2]), num(&o[3]), num(&o[4]), num(&o[5])]);
+                st.ctm = m.mul(&st.ctm);
+            }
+            "BT" => {
+                st.tm = Matrix::IDENTITY;
+                st.tlm = Matrix::IDENTITY;
+            }
+            "Tf" if o.len() == 2 => {
+                st.font = o[0].as_name().ok().and_then(|n| fonts.get(n));
+                st.size = num(&o[1]);
+            }
+            "TL" if o.len() == 1 => st.leading = num(&o[0]),
+            "Td" if o.len() == 2 => st.next_line(num(&o[0]), num(&o[1])),
+            "TD" if o.len() == 2 => {
+                st.leading = -num(&o[1]);
+                st.next_line(num(&o[0]), num(&o[1]));
+            }
+            "Tm" if o.len() == 6 => {
+                st.tlm = Matrix([num(&o[0]), num(&o[1]), num(&o[2]), num(&o[3]), num(&o[4]), num(&o[5])]);
+                st.tm = st.tlm;
+            }
+            "T*" => st.next_line(0.0, -st.leading),
+            "Tj" | "'" | "\"" => {
+                if op.operator != "Tj" {
+                    st.next_line(0.0, -st.leading);
+                }
+                if let Some(Ok(bytes)) = o.last().map(Object::as_str) {
+                    let text = decode(st.font, bytes);
+                    let adv = advance(st.font, bytes, text.chars().count());
+                    st.show(text, adv, &mut runs);
+                }
+            }
+            "TJ" => {
+                let Some(Ok(items)) = o.first().map(Object::as_array) else { continue };
+                let mut text = String::new();
+                let mut adv = 0.0;
+                for item in items {
+                    match item {
+                        Object::String(bytes, _) => {
+                            let t = decode(st.font, bytes);
+                            adv += advance(st.font, bytes, t.chars().count());
+                            text.push_str(&t);
+                        }
+                        other => {
+                            let n = num(other);
+                            if -n > TJ_SPACE_THRESHOLD && !text.ends_with(' ') {
+                                text.push(' ');
+                            }
+                            adv -= n / 1000.0;
+                        }
+                    }
+                }
+                st.show(text, adv, &mut runs);
+            }
+            _ => {}
+        }
+    }
+    Ok(runs)
+}
+
+/// Runs → lines (same baseline within half a glyph), left to right.
+fn build_lines(mut runs: Vec<Run>) -> Vec<Line> {
+    runs.sort_by(|a, b| b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x)));
+    let mut lines: Vec<Vec<Run>> = Vec::new();
+    for r in runs {
+        match lines.last_mut() {
+            Some(l) if (l[0].y - r.y).abs() < 0.5 * l[0].size.max(r.size) => l.push(r),
+            _ => lines.push(vec![r]),
+        }
+    }
+    lines
+        .into_iter()
+        .map(|mut l| {
+            l.sort_by(|a, b| a.x.total_cmp(&b.x));
+            let mut text = String::new();
+            let mut prev_end: Option<f64> = None;
+            for r in &l {
+                if let Some(end) = prev_end {
+                    if r.x - end > 0.15 * r.size && !text.ends_with(' ') && !r.text.starts_with(' ') {
+                        text.push(' ');
+                    }
+                }
+                text.push_str(&r.text);
+                prev_end = Some(r.x + r.width);
+            }
+            let chars: usize = l.iter().map(|r| r.text.chars().count()).sum::<usize>().max(1);
+            Line {
+                y: l[0].y,
+                size: l.iter().map(|r| r.size * r.text.chars().count() as f64).sum::<f64>() / chars as f64,
+                bold: l.iter().all(|r| r.bold),
+                text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+            }
+        })
+        .filter(|l| !l.text.is_empty())
+        .collect()
+}
+
+/// Reading order: full-width lines delimit bands; within a band, the left
+/// column (ends before the gutter) is read before the right column.
+fn reading_order(runs: Vec<Run>, page_width: f64) -> Vec<Line> {
+    let mid = page_width / 2.0;
+    let slack = page_width * 0.02;
+    let (mut left, mut right, mut full) = (Vec::new(), Vec::new(), Vec::new());
+    for r in runs {
+        if r.x + r.width <= mid + slack && r.x < mid {
+            left.push(r);
+        } else if r.x >= mid - slack {
+            right.push(r);
+        } else {
+            full.push(r);
+        }
+    }
+    let two_column = build_lines(left.clone()).len() >= 3 && build_lines(right.clone()).len() >= 3;
+    if !two_column {
+        let mut all = left;
+        all.extend(right);
+        all.extend(full);
+        return build_lines(all);
+    }
+    let full = build_lines(full);
+    let (left, right) = (build_lines(left), build_lines(right));
+    let mut out = Vec::new();
+    let mut upper = f64::INFINITY;
+    let take_band = |upper: f64, lower: f64, out: &mut Vec<Line>| {
+        for col in [&left, &right] {
+            for l in col.iter().filter(|l| l.y < upper && l.y >= lower) {
+                out.push(l.clone());
+            }
+        }
+    };
+    for f in full {
+        take_band(upper, f.y, &mut out);
+        upper = f.y;
+        out.push(f);
+    }
+    take_band(upper, f64::NEG_INFINITY, &mut out);
+    out
+}
+
+/// Remove running headers/footers and page numbers from each page's top and
+/// bottom two lines (by position).
+fn strip_furniture(pages: &mut [Vec<Line>]) {
+    let edge_keys = |lines: &Vec<Line>| -> Vec<(usize, String)> {
+        let mut by_y: Vec<usize> = (0..lines.len()).collect();
+        by_y.sort_by(|&a, &b| lines[b].y.total_cmp(&lines[a].y));
+        let n = by_y.len();
+        let mut edges: Vec<usize> = by_y.iter().take(2).chain(by_y.iter().skip(n.saturating_sub(2))).copied().collect();
+        edges.dedup();
+        edges
+            .into_iter()
+            .map(|i| (i, RE_DIGITS.replace_all(&lines[i].text.to_lowercase(), "#").trim().to_string()))
+            .collect()
+    };
+    let mut freq: HashMap<String, usize> = HashMap::new();
+    for page in pages.iter() {
+        let mut seen: Vec<String> = edge_keys(page).into_iter().map(|(_, k)| k).collect();
+        seen.sort();
+        seen.dedup();
+        for k in seen {
+            *freq.entry(k).or_default() += 1;
+        }
+    }
+    let page_count = pages.len();
+    for page in pages.iter_mut() {
+        let mut drop: Vec<usize> = edge_keys(page)
+            .into_iter()
+            .filter(|(i, k)| {
+                RE_PAGE_NUMBER.is_match(&page[*i].text)
+                    || freq.get(k).is_some_and(|&n| n >= 2 && n * 2 >= page_count)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        drop.sort_unstable();
+        drop.dedup();
+        for i in drop.into_iter().rev() {
+            page.remove(i);
+        }
+    }
+}
+
+/// Most common font size weighted by characters (rounded to 0.5pt).
+fn body_size(pages: &[Vec<Line>]) -> f64 {
+    let mut hist: HashMap<i64, usize> = HashMap::new();
+    for l in pages.iter().flatten() {
+        *hist.entry((l.size * 2.0).round() as i64).or_default() += l.text.chars().count();
+    }
+    hist.into_iter().max_by_key(|&(k, n)| (n, -k)).map(|(k, _)| k as f64 / 2.0).unwrap_or(10.0)
+}
+
+fn heading_prefix(l: &Line, body: f64) -> Option<&'static str> {
+    let chars = l.text.chars().count();
+    if chars > MAX_HEADING_CHARS || !l.text.chars().any(char::is_alphabetic) {
+        return None;
+    }
+    if l.size >= body * TITLE_SIZE_RATIO {
+        Some("#")
+    } else if l.size >= body * HEADING_SIZE_RATIO {
+        Some(if RE_NUMBERED_HEADING.captures(&l.text).is_some_and(|c| c.get(1).is_some()) { "###" } else { "##" })
+    } else if l.bold && !l.text.ends_with('.') && chars <= 80 {
+        Some("###")
+    } else {
+        None
+    }
+}
+
+/// Lines → Markdown: headings, paragraphs split on vertical gaps, hyphenated
+/// line breaks re-joined.
+fn to_markdown(pages: &[Vec<Line>]) -> String {
+    let body = body_size(pages);
+    let mut blocks: Vec<String> = Vec::new();
+    let mut para = String::new();
+    let flush = |para: &mut String, blocks: &mut Vec<String>| {
+        if !para.trim().is_empty() {
+            blocks.push(para.trim().to_string());
+        }
+        para.clear();
+    };
+    for page in pages {
+        let mut prev: Option<&Line> = None;
+        for l in page {
+            if let Some(h) = heading_prefix(l, body) {
+                flush(&mut para, &mut blocks);
+                blocks.push(format!("{h} {}", l.text));
+                prev = None;
+                continue;
+            }
+            // New paragraph on a large vertical gap or a column/band jump up.
+            let gap = prev.map(|p| p.y - l.y);
+            if gap.is_some_and(|g| g > 1.8 * l.size.max(body) || g < 0.0) {
+                flush(&mut para, &mut blocks);
+            }
+            if para.ends_with('-') && l.text.starts_with(|c: char| c.is_lowercase()) {
+                para.pop();
+            } else if !para.is_empty() {
+                para.push(' ');
+            }
+            para.push_str(&l.text);
+            prev = Some(l);
+        }
+    }
+    flush(&mut para, &mut blocks);
+    blocks.join("\n\n")
+}
+
+/// PDF bytes → Markdown text.
+pub(crate) fn extract_pdf_text(bytes: &[u8]) -> Result<String, String> {
+    if bytes.len() > MAX_PDF_SIZE {
+        return Err(format!("PDF too large: {} bytes", bytes.len()));
+    }
+    let doc = Document::load_mem(bytes).map_err(|e| format!("PDF parse: {e}"))?;
+    let mut pages = Vec::new();
+    for (_, page_id) in doc.get_pages().into_iter().take(MAX_PAGES) {
+        let width = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|p| p.get(b"MediaBox").ok())
+            .and_then(|m| m.as_array().ok())
+            .filter(|m| m.len() == 4)
+            .map(|m| num(&m[2]) - num(&m[0]))
+            .unwrap_or(612.0);
+        match page_runs(&doc, page_id) {
+            Ok(runs) => pages.push(reading_order(runs, width)),
+            Err(e) => eprintln!("[RUST-PDF] Skipping page {:?}: {}", page_id, e),
+        }
+    }
+    strip_furniture(&mut pages);
+    Ok(to_markdown(&pages))
+}
@@ -0,0 +1,333 @@
+//! V26.6 Quality scoring — script-aware signals on top of the 4-bucket classify.
+//!
+//! Length + `##` count alone lets cookie walls, boilerplate, mojibake and
+//! non-English pages through as SUCCESS. The scorer multiplies independent
+//! penalties into a 0..1 score and records why:
+//!   text_density     extracted text / HTML bytes (HTML path only)
+//!   language         offline ID — script census, then Latin character-trigram
+//!                    profiles (Cavnar–Trenkle style, rank-weighted overlap)
+//!   repeated_lines   share of non-trivial lines seen earlier in the text
+//!   bad_chars        U+FFFD, C0/C1 controls and UTF-8-as-Latin-1 mojibake
+//!   boilerplate      cookie / JS-wall / captcha / login phrase blacklist
+//! A low score demotes the length-based bucket (SUCCESS → PARTIAL → SKIP).
+
+use napi_derive::napi;
+
+use crate::{classify_with, count_section_headers, ExtractionResult, Thresholds, DEFAULT_THRESHOLDS};
+
+const DEFAULT_MIN_QUALITY_SCORE: f64 = 0.5;
+const DEFAULT_SKIP_QUALITY_SCORE: f64 = 0.2;
+/// Below this many letters the language is "und" (undetermined).
+const MIN_LANG_LETTERS: usize = 40;
+const MIN_DENSITY: f64 = 0.02;
+const MAX_REPEATED_RATIO: f64 = 0.3;
+const MAX_BAD_CHAR_RATIO: f64 = 0.01;
+
+#[napi(object)]
+#[derive(Default)]
+pub struct QualityOptions {
+    /// Bytes below which content is SKIP (default 200).
+    pub min_quality_len: Option<u32>,
+    /// Bytes needed (with enough sections) for SUCCESS (default 1000).
+    pub fulltext_threshold: Option<u32>,
+    /// `##`/`###` headers needed for SUCCESS (default 2).
+    pub min_section_headers: Option<u32>,
+    /// Score below which SUCCESS is demoted to PARTIAL (default 0.5).
+    pub min_quality_score: Option<f64>,
+    /// Score below which content is SKIP regardless of length (default 0.2).
+    pub skip_quality_score: Option<f64>,
+    /// Accepted ISO 639-1 codes (default ["en"]; empty = accept any).
+    pub expected_languages: Option<Vec<String>>,
+}
+
+#[napi(object)]
+pub struct QualityReport {
+    /// 0..1, product of the penalties below.
+    pub score: f64,
+    /// ISO 639-1 code, or "und" when undetermined.
+    pub language: String,
+    /// Share of the best language among all candidates (0..1).
+    pub language_confidence: f64,
+    /// Extracted text bytes / HTML bytes (None for pre-extracted text).
+    pub text_density: Option<f64>,
+    pub repeated_line_ratio: f64,
+    pub bad_char_ratio: f64,
+    pub boilerplate_hits: u32,
+    /// Machine-readable reasons, e.g. "language:de", "boilerplate:accept cookies".
+    pub reasons: Vec<String>,
+}
+
+const BOILERPLATE: &[&str] = &[
+    "accept cookies",
+    "accept all cookies",
+    "we use cookies",
+    "cookie policy",
+    "enable javascript",
+    "javascript is disabled",
+    "javascript is required",
+    "checking your browser",
+    "verify you are human",
+    "are you a robot",
+    "captcha",
+    "access denied",
+    "page not found",
+    "404 not found",
+    "sign in to continue",
+    "log in to continue",
+    "subscribe to our newsletter",
+    "all rights reserved",
+];
+
+/// Top character trigrams per Latin-script language, most frequent first
+/// ('_' = word boundary).
+const PROFILES: &[(&str, &[&str])] = &[
+    ("en", &[
+        "_th", "the", "he_", "_an", "and", "nd_", "_of", "of_", "ion", "ing", "ng_", "_in", "tio",
+        "ent", "_to", "to_", "ed_", "is_", "er_", "es_", "re_", "in_", "on_", "at_", "_co", "for",
+        "_fo", "or_", "_be", "_re", "hat", "tha", "ati", "ter", "_is", "al_", "his", "_wh", "ly_",
+        "_pr", "ere", "_it", "it_", "con", "ver", "all", "ons", "_wi", "wit", "ith",
+    ]),
+    ("de", &[
+        "en_", "er_", "_de", "der", "ich", "ein", "sch", "_di", "die", "ie_", "che", "_ei", "nd_",
+        "und", "_un", "den", "in_", "cht", "ch_", "ung", "gen", "ine", "te_", "_da", "_ge", "es_",
+        "ten", "_in", "_zu", "ier", "das", "as_", "ver", "_ve", "ng_", "_be", "nde", "eit", "ter",
+        "ist", "_is", "st_", "auf", "_au", "mit", "_mi", "ht_", "ber", "_si", "sie",
+    ]),
+    ("fr", &[
+        "_de", "es_", "de_", "le_", "ent", "_le", "ion", "nt_", "_la", "la_", "les", "_pa", "ati",
+        "tio", "on_", "_co", "re_", "_et", "et_", "que", "ue_", "_qu", "ne_", "men", "des", "_un",
+        "_pr", "ons", "_po", "our", "ns_", "par", "_re", "est", "eme", "une", "ur_", "lle", "_da",
+        "dan", "ans", "_en", "ait", "ir_", "_so", "pou", "ais", "aux", "_ce", "_du",
+    ]),
+    ("es", &[
+        "_de", "de_", "os_", "_la", "la_", "el_", "_el", "es_", "_en", "en_", "as_", "ent", "_co",
+        "que", "ue_", "_qu", "ion", "ci\u{f3}", "aci", "ado", "_lo", "los", "_pa", "_se", "nte",
+        "con", "_es", "ien", "ra_", "est", "por", "_po", "ara", "par", "_un", "una", "er_", "cio",
+        "al_", "_re", "res", "ida", "tra", "_ex", "men", "ar_", "to_", "_pr", "_y_", "del",
+    ]),
+    ("pt", &[
+        "_de", "de_", "os_", "_qu", "que", "ue_", "_co", "\u{e3}o_", "\u{e7}\u{e3}o", "_a_", "_pa",
+        "ent", "ra_", "as_", "_do", "do_", "da_", "_da", "_se", "es_", "com", "_es", "nte", "_em",
+        "em_", "ara", "par", "_e_", "_um", "uma", "men", "est", "_no", "ica", "_pr", "_po", "por",
+        "or_", "ado", "ida", "_re", "\u{f5}es", "con", "res", "ter", "_na", "n\u{e3}o", "ais",
+        "ade", "_ao",
+    ]),
+    ("it", &[
+        "_di", "di_", "che", "_la", "la_", "_ch", "to_", "he_", "ell", "lla", "_de", "del", "zio",
+        "ion", "_co", "one", "_il", "il_", "re_", "_in", "no_", "_pe", "per", "ent", "ato", "_un",
+        "na_", "ne_", "_e_", "_no", "le_", "con", "ta_", "_ne", "nte", "ra_", "_so", "er_", "tti",
+        "_al", "are", "gli", "_gl", "_re", "_ma", "ia_", "ame", "men", "_a_", "sta",
+    ]),
+    ("nl", &[
+        "en_", "_de", "de_", "an_", "_va", "van", "_he", "het", "et_", "_en", "een", "_ee", "er_",
+        "ij_", "_in", "in_", "te_", "ing", "ng_", "_ge", "sch", "_te", "oor", "_vo", "_da", "dat",
+        "at_", "ver", "_ve", "_zi", "_is", "is_", "nde", "aar", "den", "_op", "_ni", "nie", "_me",
+        "_wo", "_be", "ord", "eer", "cht", "ijk", "lij", "_ze", "_di", "die", "_aa",
+    ]),
+];
+
+/// Script census → language for non-Latin scripts, None when Latin dominates.
+fn script_language(text: &str) -> (Option<&'static str>, usize) {
+    let (mut latin, mut han, mut kana, mut hangul, mut cyr, mut arabic, mut greek, mut deva) =
+        (0usize, 0usize, 0usize, 0usize, 0usize, 0usize, 0usize, 0usize);
+    for c in text.chars() {
+        match c as u32 {
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F => latin += 1,
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => han += 1,
+            0x3040..=0x30FF => kana += 1,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => hangul += 1,
+            0x400..=0x4FF => cyr += 1,
+            0x600..=0x6FF => arabic += 1,
+            0x370..=0x3FF => greek += 1,
+            0x900..=0x97F => deva += 1,
+            _ => {}
+        }
+    }
+    let letters = latin + han + kana + hangul + cyr + arabic + greek + deva;
+    // Japanese mixes kana into Han text; any real kana share means "ja".
+    let candidates = [
+        ("ja", if kana * 10 >= han + kana && kana > 0 { han + kana } else { 0 }),
+        ("zh", han),
+        ("ko", hangul),
+        ("ru", cyr),
+        ("ar", arabic),
+        ("el", greek),
+        ("hi", deva),
+    ];
+    let best = candidates.iter().max_by_key(|(_, n)| *n).copied().unwrap_or(("und", 0));
+    if best.1 * 2 > letters {
+        (Some(best.0), letters)
+    } else {
+        (None, letters)
+    }
+}
+
+/// Language ID: (code, confidence). Latin text is scored against `PROFILES`
+/// by rank-weighted trigram frequency.
+pub(crate) fn detect_language(text: &str) -> (String, f64) {
+    let (script_lang, letters) = script_language(text);
+    if letters < MIN_LANG_LETTERS {
+        return ("und".to_string(), 0.0);
+    }
+    if let Some(lang) = script_lang {
+        return (lang.to_string(), 1.0);
+    }
+    let mut counts: std::collections::HashMap<String, u32> = std::collections::HashMap::new();
+    for word in text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .take(5000)
+    {
+        let padded: Vec<char> = std::iter::once('_')
+            .chain(word.chars().flat_map(char::to_lowercase))
+            .chain(std::iter::once('_'))
+            .collect();
+        for w in padded.windows(3) {
+            *counts.entry(w.iter().collect()).or_default() += 1;
+        }
+    }
+    let scores: Vec<(&str, f64)> = PROFILES
+        .iter()
+        .map(|(lang, profile)| {
+            let n = profile.len() as f64;
+            let s = profile
+                .iter()
+                .enumerate()
+                .map(|(rank, t)| counts.get(*t).copied().unwrap_or(0) as f64 * (n - rank as f64) / n)
+                .sum::<f64>();
+            (*lang, s)
+        })
+        .collect();
+    let total: f64 = scores.iter().map(|(_, s)| s).sum();
+    match scores.iter().max_by(|a, b| a.1.total_cmp(&b.1)) {
+        Some((lang, s)) if total > 0.0 => (lang.to_string(), round3(s / total)),
+        _ => ("und".to_string(), 0.0),
+    }
+}
+
+fn repeated_line_ratio(text: &str) -> f64 {
+    let mut seen = std::collections::HashSet::new();
+    let (mut total, mut repeated) = (0usize, 0usize);
+    for line in text.lines().map(str::trim).filter(|l| l.len() >= 3) {
+        total += 1;
+        if !seen.insert(line) {
+            repeated += 1;
+        }
+    }
+    if total == 0 { 0.0 } else { repeated as f64 / total as f64 }
+}
+
+/// Replacement chars, controls (except \t \n \r) and mojibake markers.
+fn bad_char_ratio(text: &str) -> f64 {
+    let mut total = 0usize;
+    let mut bad = 0usize;
+    let mut prev = '\0';
+    for c in text.chars() {
+        total += 1;
+        let control = c.is_control() && !matches!(c, '\n' | '\t' | '\r');
+        // UTF-8 read as Latin-1/CP1252: "Ã©", "â€™", "Â "…
+        let mojibake = matches!(prev, '\u{c3}' | '\u{c2}' | '\u{e2}')
+            && matches!(c, '\u{80}'..='\u{bf}' | '\u{20ac}' | '\u{2122}' | '\u{201c}' | '\u{201d}');
+        if c == '\u{fffd}' || control || mojibake {
+            bad += 1;
+        }
+        prev = c;
+    }
+    if total == 0 { 0.0 } else { bad as f64 / total as f64 }
+}
+
+fn round3(x: f64) -> f64 {
+    (x * 1000.0).round() / 1000.0
+}
+
+/// Score `text`; `html_len` enables the text-density signal.
+pub(crate) fn score_quality(text: &str, html_len: Option<usize>, opts: &QualityOptions) -> QualityReport {
+    let mut score = 1.0;
+    let mut reasons = Vec::new();
+
+    let text_density = html_len.filter(|&n| n > 0).map(|n| round3(text.len() as f64 / n as f64));
+    if let Some(d) = text_density {
+        if d < MIN_DENSITY {
+            score *= 0.8;
+            reasons.push("low_text_density".to_string());
+        }
+    }
+
+    let (language, language_confidence) = detect_language(text);
+    let expected = opts.expected_languages.clone().unwrap_or_else(|| vec!["en".to_string()]);
+    if language != "und" && !expected.is_empty() && !expected.contains(&language) {
+        score *= 0.4;
+        reasons.push(format!("language:{language}"));
+    }
+
+    let repeated = repeated_line_ratio(text);
+    if repeated > MAX_REPEATED_RATIO {
+        score *= 1.0 - repeated;
+        reasons.push("repeated_lines".to_string());
+    }
+
+    let bad = bad_char_ratio(text);
+    if bad > MAX_BAD_CHAR_RATIO {
+        score *= (1.0 - bad * 20.0).max(0.0);
+        reasons.push("bad_chars".to_string());
+    }
+
+    let lower = text.to_lowercase();
+    let mut boilerplate_hits = 0u32;
+    for phrase in BOILERPLATE {
+        if lower.contains(phrase) {
+            boilerplate_hits += 1;
+            reasons.push(format!("boilerplate:{phrase}"));
+        }
+    }
+    if boilerplate_hits > 0 {
+        // A phrase in a long paper is noise; in a short page it is the page.
+        let weight = (2000.0 / (text.len() as f64 + 1.0)).min(1.0);
+        score *= 0.6f64.powi(boilerplate_hits.min(5) as i32).powf(weight.max(0.25));
+    }
+
+    QualityReport {
+        score: round3(score.clamp(0.0, 1.0)),
+        language,
+        language_confidence,
+        text_density,
+        repeated_line_ratio: round3(repeated),
+        bad_char_ratio: round3(bad),
+        boilerplate_hits,
+        reasons,
+    }
+}
+
+/// Classify with configurable thresholds, then demote by quality score.
+pub(crate) fn classify_scored(text: String, html_len: Option<usize>, opts: &QualityOptions) -> ExtractionResult {
+    let thresholds = Thresholds {
+        min_quality_len: opts.min_quality_len.map(|n| n as usize).unwrap_or(DEFAULT_THRESHOLDS.min_quality_len),
+        fulltext_threshold: opts
+            .fulltext_threshold
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_THRESHOLDS.fulltext_threshold),
+        min_section_headers: opts
+            .min_section_headers
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_THRESHOLDS.min_section_headers),
+    };
+    let section_count = count_section_headers(&text);
+    let (mut classification, _) = classify_with(&text, section_count, &thresholds);
+    let quality = score_quality(&text, html_len, opts);
+    if quality.score < opts.skip_quality_score.unwrap_or(DEFAULT_SKIP_QUALITY_SCORE) {
+        classification = "SKIP";
+    } else if classification == "SUCCESS"
+        && quality.score < opts.min_quality_score.unwrap_or(DEFAULT_MIN_QUALITY_SCORE)
+    {
+        classification = "PARTIAL";
+    }
+
+    ExtractionResult {
+        char_count: text.len() as u32,
+        section_count: section_count as u32,
+        has_fulltext: classification == "SUCCESS",
+        classification: classification.to_string(),
+        text,
+        quality: Some(quality),
+    }
+}
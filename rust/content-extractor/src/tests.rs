@@ -64,6 +64,19 @@ fn test_build_manifest() {
     assert_eq!(result[1][0], "ff00112233445566");
 }
 
+#[test]
+fn test_build_manifest_zst_trim_and_duplicates() {
+    let keys = " enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.zst \n\
+                enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.gz\n\
+                enrichment/fulltext/ff/0a1b2c3d4e5f6789.md.gz\n\
+                enrichment/fulltext/ab/abcdef.md.gz\n";
+    let result = build_enrichment_manifest(Buffer::from(keys.as_bytes().to_vec()));
+    assert_eq!(
+        result,
+        vec![vec!["0a1b2c3d4e5f6789".to_string(), "enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.zst".to_string()]]
+    );
+}
+
 #[test]
 fn test_validate_fusion_upgrade() {
     let original = "Short abstract only.".to_string();
@@ -80,3 +93,556 @@ fn test_validate_fusion_no_downgrade() {
     let result = validate_fusion_content(shorter, original.clone());
     assert_eq!(result.text, original);
 }
+
+// ── V26.6 DOM path ──────────────────────────────────────────────
+
+/// HTML fixtures from the tests above, run through both engines.
+fn legacy_fixtures() -> Vec<String> {
+    let body = "A ".repeat(600);
+    vec![
+        "<p>Short.</p>".to_string(),
+        format!("<p>{}</p>", "A ".repeat(150)),
+        format!(
+            "<h2>Introduction</h2><p>{}</p><h2>Methods</h2><p>{}</p><h3>Results</h3><p>{}</p>",
+            body, body, body
+        ),
+        "<script>alert('x')</script><p>Content here</p><style>.x{}</style>".to_string(),
+        "<h2>Section One</h2><p>Body text here.</p>".to_string(),
+    ]
+}
+
+#[test]
+fn test_dom_and_regex_agree_on_fixtures() {
+    for html in legacy_fixtures() {
+        let dom = extract_and_classify(html.clone());
+        let regex = extract_and_classify_regex(html.clone());
+        assert_eq!(dom.classification, regex.classification, "{html}");
+        assert_eq!(dom.section_count, regex.section_count, "{html}");
+        let words = |t: &str| t.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(words(&dom.text), words(&regex.text), "{html}");
+    }
+}
+
+#[test]
+fn test_dom_nested_chrome_and_attribute_gt() {
+    let html = r#"<html><body>
+        <nav><div><nav>inner</nav> Leaked nav text</div></nav>
+        <div class="sidebar"><a href="/x">Home</a> <a href="/y">About</a></div>
+        <article><h2>Intro</h2><p title="a>b">Real paragraph &eacute;t&eacute; &#x2014; &copy;</p>
+        <div><div><p>Nested deep text.</p></div></div></article>
+        </body></html>"#;
+    let dom = extract_main_content(html);
+    assert!(!dom.contains("Leaked"), "{dom}");
+    assert!(!dom.contains("Home"), "{dom}");
+    assert!(dom.contains("Real paragraph été — ©"), "{dom}");
+    assert!(!dom.contains("b\">"), "{dom}");
+    assert!(dom.contains("Nested deep text."));
+    // The regex path trips on both the nested <nav> and the `>` attribute.
+    let regex = extract_main_content_regex(html);
+    assert!(regex.contains("Leaked") || regex.contains("b\">"), "{regex}");
+}
+
+#[test]
+fn test_dom_preserves_lists_code_tables() {
+    let html = r#"<main>
+        <ul><li>one</li><li>two<ol><li>sub</li></ol></li></ul>
+        <pre><code class="language-python">def f():
+    return 1</code></pre>
+        <table><tr><th>Model</th><th>Acc</th></tr><tr><td>a|b</td><td>0.9</td></tr></table>
+        <table><tr><td><p>Layout cell para.</p></td></tr></table>
+        </main>"#;
+    let md = extract_main_content(html);
+    assert!(md.contains("- one\n- two\n  1. sub"), "{md}");
+    assert!(md.contains("```python\ndef f():\n    return 1\n```"), "{md}");
+    assert!(md.contains("| Model | Acc |\n| --- | --- |\n| a\\|b | 0.9 |"), "{md}");
+    assert!(md.contains("Layout cell para."));
+    assert!(!md.contains("| Layout"));
+}
+
+#[test]
+fn test_dom_ar5iv_sections_and_math() {
+    let para = "Text with a formula <math alttext=\"x^2\"><mi>x</mi></math> inline. ".repeat(20);
+    let html = format!(
+        r#"<body><header class="ltx_page_header">Header</header><div class="ltx_page_main"><article class="ltx_document">
+        <h1 class="ltx_title ltx_title_document">Paper</h1>
+        <section class="ltx_section"><h2 class="ltx_title ltx_title_section">1 Introduction</h2><div class="ltx_para"><p class="ltx_p">{para}</p></div></section>
+        <section class="ltx_section"><h2 class="ltx_title ltx_title_section">2 Method</h2><div class="ltx_para"><p class="ltx_p">{para}</p></div></section>
+        </article></div><footer>Footer</footer></body>"#
+    );
+    let result = extract_and_classify(html);
+    assert!(result.text.contains("## 1 Introduction"));
+    assert!(result.text.contains("## 2 Method"));
+    assert!(result.text.contains("$x^2$"));
+    assert!(!result.text.contains("Header") && !result.text.contains("Footer"));
+    assert_eq!(result.classification, "SUCCESS");
+}
+
+#[test]
+fn test_dom_empty_falls_back_to_regex() {
+    assert_eq!(extract_main_content("<script>x</script>"), "");
+    // <aside> is chrome for the DOM path; with nothing else the regex path answers.
+    assert_eq!(extract_main_content("<aside>Only aside text</aside>"), "Only aside text");
+}
+
+// ── V26.6 PaperDocument ─────────────────────────────────────────
+
+const AR5IV_FIXTURE: &str = r#"<html><body><div class="ltx_page_main"><article class="ltx_document">
+<h1 class="ltx_title ltx_title_document">Low-Rank Adaptation of Large Models</h1>
+<div class="ltx_authors"><span class="ltx_creator ltx_role_author"><span class="ltx_personname">Edward Hu, Yelong Shen<sup>1</sup><br class="ltx_break">Microsoft</span></span>
+<span class="ltx_creator ltx_role_author"><span class="ltx_personname">Weizhu Chen</span></span></div>
+<div class="ltx_abstract"><h6 class="ltx_title ltx_title_abstract">Abstract</h6><p class="ltx_p">We propose LoRA.</p></div>
+<section id="S1" class="ltx_section"><h2 class="ltx_title ltx_title_section"><span class="ltx_tag ltx_tag_section">1 </span>Introduction</h2>
+<div class="ltx_para"><p class="ltx_p">Fine-tuning is costly<span class="ltx_note ltx_role_footnote" id="footnote1"><sup class="ltx_note_mark">1</sup><span class="ltx_note_outer"><span class="ltx_note_content"><sup class="ltx_note_mark">1</sup>Code at github.</span></span></span> and we use <math class="ltx_Math" alttext="W_0+BA" display="inline"><mi>W</mi></math> updates.</p></div>
+<table id="S1.E1" class="ltx_equation"><tr><td><math alttext="h=W_0x+BAx" display="block"><semantics><mi>h</mi><annotation encoding="application/x-tex">ignored</annotation></semantics></math></td><td><span class="ltx_tag ltx_tag_equation">(1)</span></td></tr></table>
+<section id="S1.SS1" class="ltx_subsection"><h3 class="ltx_title ltx_title_subsection">1.1 Setup</h3><div class="ltx_para"><p class="ltx_p">Setup text.</p></div>
+<figure id="S1.F1" class="ltx_figure"><img src="x.png"><figcaption class="ltx_caption"><span class="ltx_tag ltx_tag_figure">Figure 1: </span>Reparametrization.</figcaption></figure></section></section>
+<section id="S2" class="ltx_section"><h2 class="ltx_title ltx_title_section">2 Experiments</h2>
+<figure id="S2.T1" class="ltx_table"><figcaption class="ltx_caption"><span class="ltx_tag ltx_tag_table">Table 1: </span>GLUE results.</figcaption><table><tr><td>x</td></tr></table></figure></section>
+<section id="bib" class="ltx_bibliography"><h2 class="ltx_title ltx_title_bibliography">References</h2><ul class="ltx_biblist">
+<li id="bib.bib1" class="ltx_bibitem"><span class="ltx_tag ltx_role_refnum ltx_tag_bibitem">[1]</span><span class="ltx_bibblock">A. Vaswani and N. Shazeer.</span><span class="ltx_bibblock">Attention is all you need.</span><span class="ltx_bibblock"><em>arXiv preprint arXiv:1706.03762v5</em>, 2017.</span></li>
+<li id="bib.bib2" class="ltx_bibitem"><span class="ltx_tag ltx_tag_bibitem">[2]</span><span class="ltx_bibblock">J. Smith.</span><span class="ltx_bibblock">Old physics.</span><span class="ltx_bibblock">Nature 1999, doi:10.1038/nature14539. <a href="https://arxiv.org/abs/hep-th/9901001">link</a></span></li>
+</ul></section></article></div></body></html>"#;
+
+#[test]
+fn test_extract_paper_structure() {
+    let doc = extract_paper(AR5IV_FIXTURE.to_string());
+    assert_eq!(doc.title.as_deref(), Some("Low-Rank Adaptation of Large Models"));
+    assert_eq!(doc.authors, vec!["Edward Hu", "Yelong Shen", "Weizhu Chen"]);
+    assert_eq!(doc.abstract_text.as_deref(), Some("We propose LoRA."));
+
+    let titles: Vec<(&str, u32, Option<u32>)> =
+        doc.sections.iter().map(|s| (s.title.as_str(), s.level, s.parent)).collect();
+    assert_eq!(
+        titles,
+        vec![("1 Introduction", 1, None), ("1.1 Setup", 2, Some(0)), ("2 Experiments", 1, None)]
+    );
+    let intro = &doc.sections[0].text;
+    assert!(intro.contains("Fine-tuning is costly1 and we use $W_0+BA$ updates."), "{intro}");
+    assert!(!intro.contains("Setup text") && !intro.contains("Code at github"));
+
+    assert_eq!(doc.captions.len(), 2);
+    assert_eq!(doc.captions[0].label.as_deref(), Some("Figure 1"));
+    assert_eq!(doc.captions[0].caption, "Reparametrization.");
+    assert_eq!(doc.captions[1].kind, "table");
+
+    assert_eq!(doc.equations.len(), 2);
+    assert!(!doc.equations[0].display);
+    assert_eq!(doc.equations[1].latex, "h=W_0x+BAx");
+    assert_eq!(doc.equations[1].label.as_deref(), Some("(1)"));
+    assert_eq!(doc.equations[1].id.as_deref(), Some("S1.E1"));
+
+    assert_eq!(doc.footnotes.len(), 1);
+    assert_eq!(doc.footnotes[0].mark.as_deref(), Some("1"));
+    assert_eq!(doc.footnotes[0].text, "Code at github.");
+}
+
+#[test]
+fn test_extract_paper_bibliography() {
+    let refs = extract_paper(AR5IV_FIXTURE.to_string()).references;
+    assert_eq!(refs.len(), 2);
+    assert_eq!(refs[0].label.as_deref(), Some("1"));
+    assert_eq!(refs[0].authors.as_deref(), Some("A. Vaswani and N. Shazeer"));
+    assert_eq!(refs[0].title.as_deref(), Some("Attention is all you need"));
+    assert_eq!(refs[0].year, Some(2017));
+    assert_eq!(refs[0].arxiv_id.as_deref(), Some("1706.03762"));
+    assert_eq!(refs[1].arxiv_id.as_deref(), Some("hep-th/9901001"));
+    assert_eq!(refs[1].doi.as_deref(), Some("10.1038/nature14539"));
+    assert_eq!(refs[1].year, Some(1999));
+    assert_eq!(refs[1].url.as_deref(), Some("https://arxiv.org/abs/hep-th/9901001"));
+    assert_eq!(refs[0].canonical_id.as_deref(), Some("arxiv-paper--1706.03762"));
+    assert_eq!(refs[1].canonical_id.as_deref(), Some("arxiv-paper--hep-th-9901001"));
+}
+
+// ── V26.6 Outbound references ───────────────────────────────────
+
+#[test]
+fn test_extract_references_canonical_ids() {
+    let text = "Fine-tuned from https://huggingface.co/meta-llama/Llama-2-7b-hf (see arXiv:2106.09685v2). \
+        Data: huggingface.co/datasets/tatsu-lab/alpaca, demo at https://hf.co/spaces/gradio/chat. \
+        Code: https://github.com/microsoft/LoRA.git. Older: arxiv.org/abs/hep-th/9901001. \
+        DOI https://doi.org/10.48550/arXiv.1706.03762 and doi:10.1038/nature14539. \
+        PwC https://paperswithcode.com/paper/lora-low-rank. Docs: https://huggingface.co/docs/peft, github.com/features/copilot";
+    let refs = extract_references(text.to_string());
+    let got: Vec<(&str, Option<&str>)> =
+        refs.iter().map(|r| (r.kind.as_str(), r.canonical_id.as_deref())).collect();
+    assert_eq!(
+        got,
+        vec![
+            ("hf_model", Some("hf-model--meta-llama--llama-2-7b-hf")),
+            ("arxiv", Some("arxiv-paper--2106.09685")),
+            ("hf_dataset", Some("hf-dataset--tatsu-lab--alpaca")),
+            ("hf_space", Some("hf-space--gradio--chat")),
+            ("github", Some("gh-tool--microsoft--lora")),
+            ("arxiv", Some("arxiv-paper--hep-th-9901001")),
+            ("arxiv", Some("arxiv-paper--1706.03762")),
+            ("doi", None),
+            ("pwc", None),
+        ]
+    );
+    assert_eq!(refs[7].value, "10.1038/nature14539");
+    assert_eq!(refs[4].raw, "https://github.com/microsoft/LoRA.git");
+    assert_eq!(refs[4].value, "microsoft/LoRA");
+}
+
+#[test]
+fn test_extract_references_single_segment_hf_dataset_and_space() {
+    // Owner-less legacy repos must not come back as hf-model--datasets--squad.
+    let text = "Trained on https://huggingface.co/datasets/squad and huggingface.co/spaces/gradio. \
+        Also https://huggingface.co/datasets/rajpurkar/squad.";
+    let refs = extract_references(text.to_string());
+    let got: Vec<(&str, Option<&str>)> =
+        refs.iter().map(|r| (r.kind.as_str(), r.canonical_id.as_deref())).collect();
+    assert_eq!(got, vec![("hf_dataset", Some("hf-dataset--rajpurkar--squad"))]);
+}
+
+#[test]
+fn test_extract_references_utf16_offsets() {
+    // 'é' is 1 UTF-16 unit / 2 bytes; '𝔸' is 2 units / 4 bytes.
+    let text = "é𝔸 arXiv:2301.12345.";
+    let refs = extract_references(text.to_string());
+    assert_eq!(refs.len(), 1);
+    let utf16: Vec<u16> = text.encode_utf16().collect();
+    let slice = String::from_utf16(&utf16[refs[0].start as usize..refs[0].end as usize]).unwrap();
+    assert_eq!(slice, "arXiv:2301.12345");
+    assert_eq!(refs[0].raw, slice);
+}
+
+// ── V26.6 Quality scoring ───────────────────────────────────────
+
+fn english_paper() -> String {
+    let para = "The model is trained on a large corpus of text and evaluated with \
+        standard benchmarks, which shows that the method improves accuracy for all tasks. ";
+    format!("## Introduction\n{}\n## Methods\n{}", para.repeat(6), para.repeat(6))
+}
+
+#[test]
+fn test_score_clean_english_keeps_success() {
+    let r = score_text(english_paper(), None);
+    assert_eq!(r.classification, "SUCCESS");
+    let q = r.quality.unwrap();
+    assert_eq!(q.language, "en");
+    assert!(q.score >= 0.5, "{:?}", q.reasons);
+    // Plain classify_text stays unscored.
+    assert!(classify_text(english_paper()).quality.is_none());
+}
+
+#[test]
+fn test_language_detection() {
+    let de = "Die Methode wurde auf einem großen Datensatz trainiert und mit den \
+        üblichen Benchmarks bewertet, und sie ist in allen Aufgaben besser als die anderen.";
+    let fr = "La méthode est entraînée sur un grand corpus de textes et évaluée avec les \
+        tests habituels, et elle améliore la précision pour toutes les tâches du projet.";
+    let es = "El modelo se entrena con un gran corpus de textos y se evalúa con las pruebas \
+        habituales, lo que muestra que el método mejora la precisión en todas las tareas.";
+    assert_eq!(quality::detect_language(de).0, "de");
+    assert_eq!(quality::detect_language(fr).0, "fr");
+    assert_eq!(quality::detect_language(es).0, "es");
+    assert_eq!(quality::detect_language("深度学习模型在大规模语料上进行训练，并在标准基准上进行评估，结果表明该方法在所有任务上都提高了准确率。").0, "zh");
+    assert_eq!(quality::detect_language("short").0, "und");
+}
+
+#[test]
+fn test_non_english_and_boilerplate_demoted() {
+    let german = format!(
+        "## Einleitung\n{}\n## Methode\n{}",
+        "Die Methode wurde auf einem großen Datensatz trainiert und bewertet. ".repeat(12),
+        "Sie ist in allen Aufgaben besser als die anderen Verfahren der Studie. ".repeat(12)
+    );
+    let r = score_text(german.clone(), None);
+    assert_eq!(r.classification, "PARTIAL");
+    assert!(r.quality.as_ref().unwrap().reasons.contains(&"language:de".to_string()));
+    // Explicitly accepted languages are not penalised.
+    let opts = QualityOptions { expected_languages: Some(vec!["en".into(), "de".into()]), ..Default::default() };
+    assert_eq!(score_text(german, Some(opts)).classification, "SUCCESS");
+
+    let wall = "We use cookies. Please enable JavaScript. Checking your browser before accessing.";
+    let html = format!("<html><body><p>{}</p></body></html>", wall);
+    let r = extract_and_score(html, None);
+    assert_eq!(r.classification, "SKIP");
+    assert!(r.quality.unwrap().boilerplate_hits >= 3);
+}
+
+#[test]
+fn test_mojibake_and_repeats_penalised() {
+    let moji = english_paper().replace("the", "thÃ©â€™");
+    let q = score_text(moji, None).quality.unwrap();
+    assert!(q.reasons.contains(&"bad_chars".to_string()), "{:?}", q.reasons);
+
+    let repeated = format!("## A\n## B\n{}", "Share this page on social media\n".repeat(60));
+    let r = score_text(repeated, None);
+    assert!(r.quality.as_ref().unwrap().repeated_line_ratio > 0.9);
+    assert_eq!(r.classification, "SKIP");
+}
+
+#[test]
+fn test_configurable_thresholds() {
+    let text = format!("## A\n## B\n{}", "The results of the study are in the table. ".repeat(12));
+    assert_eq!(score_text(text.clone(), None).classification, "PARTIAL");
+    let opts = QualityOptions { fulltext_threshold: Some(400), ..Default::default() };
+    assert_eq!(score_text(text.clone(), Some(opts)).classification, "SUCCESS");
+    let opts = QualityOptions { min_quality_len: Some(10_000), ..Default::default() };
+    assert_eq!(score_text(text, Some(opts)).classification, "SKIP");
+}
+
+// ── PDF path ────────────────────────────────────────────────────────────────
+
+fn pdf_fixture(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+#[test]
+fn test_pdf_two_column_paper() {
+    let text = pdf::extract_pdf_text(&pdf_fixture("paper-two-column.pdf")).unwrap();
+    assert!(text.starts_with("# Sparse Adapters for Efficient Transfer"), "{text}");
+    for h in ["### Abstract", "## 1 Introduction", "## 2 Method", "## 3 Results"] {
+        assert!(text.lines().any(|l| l == h), "missing {h:?} in {text}");
+    }
+    // De-hyphenated across the line break; compound hyphens kept.
+    assert!(text.contains("reduces memory during adaptation and matches"));
+    assert!(text.contains("fine-tuning on all eight"));
+    // Left column is read before the right one.
+    let method = text.find("Each adapter is a low rank").unwrap();
+    let fair = text.find("comparison fair.").unwrap();
+    let results = text.find("## 3 Results").unwrap();
+    let sparse = text.find("Sparse adapters reach").unwrap();
+    assert!(method < fair && fair < results && results < sparse);
+    // Running header, rotated stamp and page numbers are gone.
+    assert!(!text.contains("Preprint"));
+    assert!(!text.contains("arXiv:2401.00001"));
+    assert!(!text.lines().any(|l| l.trim() == "1" || l.trim() == "2"));
+
+    let r = classify_text(text);
+    assert_eq!(r.classification, "SUCCESS");
+    assert_eq!(r.section_count, 4);
+}
+
+#[test]
+fn test_pdf_kerned_note_and_bad_input() {
+    let text = pdf::extract_pdf_text(&pdf_fixture("short-note.pdf")).unwrap();
+    assert_eq!(text, "Short note: kerned text. No sections here.");
+    assert_eq!(classify_text(text).classification, "SKIP");
+
+    assert!(pdf::extract_pdf_text(b"<html>not a pdf</html>").is_err());
+}
+
+// ── Near-duplicates ─────────────────────────────────────────────────────────
+
+#[test]
+fn test_minhash_and_simhash_similarity() {
+    let base = english_paper();
+    let mirror = base.replace("Introduction", "Overview");
+    let other = "Quantum error correction codes protect logical qubits from noise in superconducting hardware. "
+        .repeat(20);
+    let sig = |t: &str| minhash_signature(t.to_string(), None);
+    assert_eq!(sig(&base).len(), 128);
+    assert_eq!(estimate_jaccard(sig(&base), sig(&base)), 1.0);
+    assert!(estimate_jaccard(sig(&base), sig(&mirror)) > 0.8);
+    assert!(estimate_jaccard(sig(&base), sig(&other)) < 0.1);
+
+    let d = |a: &str, b: &str| simhash_distance(simhash(a.to_string()), simhash(b.to_string())).unwrap();
+    assert_eq!(simhash(base.clone()).len(), 16);
+    assert!(d(&base, &mirror) < d(&base, &other), "{} vs {}", d(&base, &mirror), d(&base, &other));
+    assert!(simhash_distance("xyz".into(), "0".into()).is_err());
+}
+
+#[test]
+fn test_find_near_duplicates_over_shard_dir() {
+    let base = std::env::temp_dir().join("ce_near_dup");
+    let _ = std::fs::remove_dir_all(&base);
+    let shards = base.join("shards");
+    std::fs::create_dir_all(&shards).unwrap();
+    let readme = english_paper();
+    let other = "Quantum error correction codes protect logical qubits from noise in superconducting hardware. "
+        .repeat(20);
+    let shard = |entities: serde_json::Value, name: &str| {
+        std::fs::write(shards.join(name), serde_json::json!({ "entities": entities }).to_string()).unwrap();
+    };
+    shard(
+        serde_json::json!([
+            {"id": "hf-model--org--base", "readme": readme},
+            {"id": "hf-model--other--q4", "body_content": "", "readme": format!("{readme}\nQuantised to 4 bits.")},
+        ]),
+        "part-000.json",
+    );
+    shard(
+        serde_json::json!([
+            {"id": "hf-model--x--unrelated", "readme": other},
+            {"id": "hf-model--x--short", "readme": "too short"},
+        ]),
+        "part-001.json",
+    );
+
+    let out = base.join("out");
+    let stats = find_near_duplicates(
+        shards.to_string_lossy().into_owned(),
+        out.to_string_lossy().into_owned(),
+        None,
+    )
+    .unwrap();
+    assert_eq!((stats.entity_count, stats.indexed_count, stats.shard_count), (4, 3, 2));
+    assert_eq!(stats.pair_count, 1);
+
+    let raw = zstd::decode_all(std::fs::File::open(&stats.output_file).unwrap()).unwrap();
+    let lines: Vec<serde_json::Value> =
+        String::from_utf8(raw).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["relation"], "SAME_AS_CANDIDATE");
+    assert_eq!(lines[0]["member_a"], "hf-model--org--base");
+    assert_eq!(lines[0]["member_b"], "hf-model--other--q4");
+    assert!(lines[0]["evidence"][0]["weight"].as_f64().unwrap() >= 0.8);
+
+    let bad = NearDupOptions { bands: Some(7), ..Default::default() };
+    assert!(find_near_duplicates(shards.to_string_lossy().into_owned(), out.to_string_lossy().into_owned(), Some(bad)).is_err());
+}
+
+// ── Enrichment manifest ─────────────────────────────────────────────────────
+
+#[test]
+fn test_manifest_layouts_and_validation() {
+    let keys: Vec<String> = [
+        "enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.zst",
+        "enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.gz",
+        "enrichment/fulltext/ff/0a1b2c3d4e5f6789.md.zst",
+        "enrichment/fulltext/ab/ABCDEF0123456789.md.zst",
+        "enrichment/summaries/12/1234567890abcdef.json.gz",
+        "enrichment/images/12/1234567890abcdef/fig1.png",
+        "enrichment/images/12/1234567890abcdef/fig2.png",
+        "enrichment/fulltext/0a/0a1b2c3d4e5f6789.txt",
+        "cold/shard/part-000.jsonl.zst",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let m = build_enrichment_manifest_from_keys(keys, None).unwrap();
+    assert_eq!(m.key_count, 9);
+    let entries: Vec<(&str, &str)> = m.entries.iter().map(|e| (e.layout.as_str(), e.key.as_str())).collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("fulltext", "enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.zst"),
+            ("summaries", "enrichment/summaries/12/1234567890abcdef.json.gz"),
+            ("images", "enrichment/images/12/1234567890abcdef/fig1.png"),
+            ("images", "enrichment/images/12/1234567890abcdef/fig2.png"),
+        ]
+    );
+    assert_eq!(m.duplicates.len(), 1);
+    assert_eq!(m.duplicates[0].keys.len(), 2);
+    assert_eq!(m.duplicates[0].keys[0], "enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.zst");
+    let reasons: Vec<&str> = m.inconsistent.iter().map(|i| i.reason.as_str()).collect();
+    assert_eq!(reasons, vec!["prefix_mismatch", "bad_umid"]);
+    assert_eq!(m.unmatched, vec!["enrichment/fulltext/0a/0a1b2c3d4e5f6789.txt", "cold/shard/part-000.jsonl.zst"]);
+}
+
+#[test]
+fn test_manifest_from_etags_and_walk() {
+    let mut etags = std::collections::HashMap::new();
+    etags.insert("enrichment/fulltext/ff/ff00112233445566.md.zst".to_string(), "e2".to_string());
+    etags.insert("enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.zst".to_string(), "e1".to_string());
+    let m = build_enrichment_manifest_from_etags(etags, None).unwrap();
+    assert_eq!(m.entries.len(), 2);
+    assert_eq!(m.entries[0].umid, "0a1b2c3d4e5f6789");
+    assert_eq!(m.entries[0].etag.as_deref(), Some("e1"));
+
+    let walk = vec![WalkEntry { rel_path: "fulltext/0a/0a1b2c3d4e5f6789.md.zst".into(), size: 42, md5: "abc".into() }];
+    let opts = ManifestOptions { key_prefix: Some("enrichment/".into()), ..Default::default() };
+    let m = build_enrichment_manifest_from_walk(walk, Some(opts)).unwrap();
+    assert_eq!(m.entries[0].key, "enrichment/fulltext/0a/0a1b2c3d4e5f6789.md.zst");
+    assert_eq!((m.entries[0].size, m.entries[0].etag.as_deref()), (Some(42), Some("abc")));
+
+    let custom = ManifestLayout { name: "x".into(), template: "x/{prefix}/{umid}.{bad}".into(), multi: None };
+    let opts = ManifestOptions { layouts: Some(vec![custom]), umid_length: Some(8), ..Default::default() };
+    assert!(build_enrichment_manifest_from_keys(vec![], Some(opts)).is_err());
+    let short = ManifestLayout { name: "s".into(), template: "s/{prefix}/{umid}.md".into(), multi: None };
+    let opts = ManifestOptions { layouts: Some(vec![short]), umid_length: Some(8), ..Default::default() };
+    let m = build_enrichment_manifest_from_keys(vec!["s/ab/abcdef01.md".into()], Some(opts)).unwrap();
+    assert_eq!(m.entries.len(), 1);
+}
+
+// ── Chunking ────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_chunk_markdown_sections_budget_and_offsets() {
+    let sentence = "Sparse adapters keep the backbone frozen while training few weights. ";
+    let md = format!(
+        "# Paper\nIntro line.\n\n## Method\n{}\n\n{}\n```\n# not a heading\n```\n### Détails\nÜber π ≈ 3.14 — short.\n## Results\n",
+        sentence.repeat(20),
+        sentence.repeat(20)
+    );
+    let opts = ChunkOptions { max_tokens: Some(120), overlap_tokens: Some(20) };
+    let chunks = chunk_markdown(md.clone(), "0a1b2c3d4e5f6789".into(), Some(opts));
+
+    assert_eq!(chunks[0].section_path, vec!["Paper"]);
+    assert_eq!(chunks[0].text, "Intro line.");
+    let method: Vec<&TextChunk> = chunks.iter().filter(|c| c.section_path == ["Paper", "Method"]).collect();
+    assert!(method.len() >= 3, "{}", method.len());
+    // The fenced "# not a heading" stays inside the Method section.
+    assert!(method.last().unwrap().text.contains("# not a heading"));
+    let last = chunks.last().unwrap();
+    assert_eq!(last.section_path, vec!["Paper", "Method", "Détails"]);
+    assert!(chunks.iter().all(|c| !c.section_path.contains(&"Results".to_string())));
+
+    let utf16: Vec<u16> = md.encode_utf16().collect();
+    for (i, c) in chunks.iter().enumerate() {
+        assert_eq!(c.index as usize, i);
+        assert!(c.token_estimate <= 120);
+        assert_eq!(String::from_utf16(&utf16[c.start as usize..c.end as usize]).unwrap(), c.text);
+    }
+    // Consecutive chunks of a section overlap and start on a sentence.
+    for w in method.windows(2) {
+        assert!(w[1].start < w[0].end);
+        assert!(w[1].text.starts_with("Sparse adapters"), "{:?}", &w[1].text[..30]);
+    }
+    // Stable ids: same UMID + offset → same id; different UMID → different id.
+    let again = chunk_markdown(md.clone(), "0a1b2c3d4e5f6789".into(), Some(ChunkOptions { max_tokens: Some(120), overlap_tokens: Some(20) }));
+    assert_eq!(again[1].chunk_id, chunks[1].chunk_id);
+    let other = chunk_markdown(md, "ff00112233445566".into(), None);
+    assert_ne!(other[0].chunk_id, chunks[0].chunk_id);
+}
+
+#[test]
+fn test_chunk_shard_dir_writes_ndjson_zst() {
+    let base = std::env::temp_dir().join("ce_chunk_batch");
+    let _ = std::fs::remove_dir_all(&base);
+    let shards = base.join("shards");
+    std::fs::create_dir_all(&shards).unwrap();
+    let doc = english_paper();
+    let shard = serde_json::json!({ "entities": [
+        {"id": "arxiv-paper--2401.00001", "body_content": doc},
+        {"id": "hf-model--a--b", "umid": "0a1b2c3d4e5f6789", "readme": doc},
+        {"id": "hf-model--a--tiny", "readme": "tiny"},
+    ]});
+    std::fs::write(shards.join("part-000.json"), shard.to_string()).unwrap();
+
+    let stats = chunk_shard_dir(
+        shards.to_string_lossy().into_owned(),
+        base.join("out").to_string_lossy().into_owned(),
+        None,
+    )
+    .unwrap();
+    assert_eq!((stats.entity_count, stats.chunked_entity_count), (3, 2));
+    let raw = zstd::decode_all(std::fs::File::open(&stats.output_file).unwrap()).unwrap();
+    let lines: Vec<serde_json::Value> =
+        String::from_utf8(raw).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len() as u32, stats.chunk_count);
+    assert_eq!(lines[0]["id"], "arxiv-paper--2401.00001");
+    assert_eq!(lines[0]["umid"], nxvf_core::sha256_hex16("arxiv-paper--2401.00001"));
+    assert_eq!(lines[0]["section_path"][0], "Introduction");
+    assert!(lines.iter().any(|l| l["umid"] == "0a1b2c3d4e5f6789"));
+}
+
+#[test]
+fn test_dom_deep_nesting_does_not_overflow() {
+    let n = 5_000;
+    let html = format!(
+        "<body>{}<p>Deep text survives.</p>{}<p>{}inline{}</p></body>",
+        "<div>".repeat(n),
+        "</div>".repeat(n),
+        "<span>".repeat(n),
+        "</span>".repeat(n)
+    );
+    let md = extract_main_content(&html);
+    assert!(md.contains("Deep text survives."), "{md}");
+    assert!(md.contains("inline"), "{md}");
+    let list = format!("<body>{}<li>leaf</li>{}</body>", "<ul><li>x".repeat(1_000), "</li></ul>".repeat(1_000));
+    assert!(extract_main_content(&list).contains("leaf"));
+}
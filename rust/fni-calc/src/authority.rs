@@ -0,0 +1,368 @@
+//! Graph Authority — the A component computed from the mesh adjacency.
+//!
+//! Reads the satellite-tasks relations/mesh graph (`{edges: {src: [[tgt, type,
+//! conf_pct, ...]]}}`, raw/.zst/.gz) and runs weighted PageRank or HITS with
+//! per-relation-type weights × edge confidence. Authority flows source → target
+//! (a CITES b, a BASED_ON b … raise b). Scores are log-normalised onto the
+//! 0..99.9 scale of the other FNI components and can replace the
+//! externally-supplied `mesh_points` in `compute_fni`.
+//!
+//! Determinism: node ids are sorted, adjacency is built and summed in that
+//! order on a single thread, so identical graphs give bit-identical scores.
+
+use std::collections::HashMap;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde_json::Value;
+
+const DEFAULT_DAMPING: f64 = 0.85;
+const DEFAULT_MAX_ITERATIONS: u32 = 100;
+const DEFAULT_TOLERANCE: f64 = 1e-9;
+/// Weight for relation types missing from the table.
+const DEFAULT_RELATION_WEIGHT: f64 = 0.5;
+const MAX_AUTHORITY: f64 = 99.9;
+
+/// Default per-relation-type weights. Structural/navigational edges minted by
+/// the mesh stage (EXPLAINS, FEATURED_IN, FOLLOWS, TRENDING) carry no
+/// endorsement and are excluded (weight 0).
+fn default_relation_weight(rel: &str) -> f64 {
+    match rel {
+        "BASED_ON" | "CITES" => 1.0,
+        "IMPLEMENTS" => 0.9,
+        "TRAINED_ON" | "USES" => 0.8,
+        "EVALUATED_ON" | "DEMO_OF" => 0.6,
+        "STACK" => 0.4,
+        "FEATURES" => 0.2,
+        "EXPLAINS" | "FEATURED_IN" | "FOLLOWS" | "TRENDING" => 0.0,
+        _ => DEFAULT_RELATION_WEIGHT,
+    }
+}
+
+#[napi(object)]
+pub struct AuthorityOptions {
+    /// "pagerank" (default) or "hits" (authority vector).
+    pub algorithm: Option<String>,
+    /// PageRank damping factor (default 0.85). Ignored by HITS.
+    pub damping: Option<f64>,
+    pub max_iterations: Option<u32>,
+    /// L1 change between iterations below which we stop (default 1e-9).
+    pub tolerance: Option<f64>,
+    /// Per-relation-type weight overrides, e.g. `{ "STACK": 0.0 }`.
+    pub relation_weights: Option<HashMap<String, f64>>,
+}
+
+#[napi(object)]
+pub struct AuthorityResult {
+    pub algorithm: String,
+    pub node_count: u32,
+    /// Edges that contributed (weight × confidence > 0, self-loops dropped).
+    pub edge_count: u32,
+    pub iterations: u32,
+    pub converged: bool,
+    /// L1 change of the last iteration.
+    pub final_delta: f64,
+    /// id → authority on the 0..99.9 A-component scale.
+    pub scores: HashMap<String, f64>,
+}
+
+/// Convergence report of one power-iteration run.
+pub(crate) struct Convergence {
+    pub iterations: u32,
+    pub converged: bool,
+    pub final_delta: f64,
+}
+
+/// Weighted adjacency over sorted node ids. `out[i]` = (target index, weight).
+pub(crate) struct AuthorityGraph {
+    pub ids: Vec<String>,
+    pub out: Vec<Vec<(usize, f64)>>,
+    pub edge_count: usize,
+}
+
+impl AuthorityGraph {
+    /// Build from a relations/mesh graph value. Parallel edges between the same
+    /// pair (different relation types) accumulate their weights.
+    pub fn from_value(graph: &Value, overrides: Option<&HashMap<String, f64>>) -> Self {
+        let edges = graph.get("edges").and_then(|v| v.as_object());
+        let mut ids: Vec<String> = graph
+            .get("nodes")
+            .and_then(|v| v.as_object())
+            .map(|n| n.keys().cloned().collect())
+            .unwrap_or_default();
+        if let Some(edges) = edges {
+            for (src, targets) in edges {
+                ids.push(src.clone());
+                for edge in targets.as_array().into_iter().flatten() {
+                    if let Some(t) = edge.get(0).and_then(|v| v.as_str()) {
+                        ids.push(t.to_string());
+                    }
+                }
+            }
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        let index: HashMap<&str, usize> =
+            ids.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+
+        let mut out: Vec<Vec<(usize, f64)>> = vec![Vec::new(); ids.len()];
+        let mut edge_count = 0usize;
+        if let Some(edges) = edges {
+            for (src, targets) in edges {
+                let si = index[src.as_str()];
+                for edge in targets.as_array().into_iter().flatten() {
+                    let Some(tgt) = edge.get(0).and_then(|v| v.as_str()) else { continue };
+                    let ti = index[tgt];
+                    if ti == si {
+                        continue;
+                    }
+                    let rel = edge.get(1).and_then(|v| v.as_str()).unwrap_or("RELATED");
+                    let type_w = overrides
+                        .and_then(|m| m.get(rel).copied())
+                        .unwrap_or_else(|| default_relation_weight(rel));
+                    // conf is an integer percent in the V14.5.2 format; absent → 100.
+                    let conf = edge.get(2).and_then(|v| v.as_f64()).unwrap_or(100.0);
+                    let w = type_w * (conf / 100.0).clamp(0.0, 1.0);
+                    if w <= 0.0 {
+                        continue;
+                    }
+                    edge_count += 1;
+                    match out[si].iter_mut().find(|(t, _)| *t == ti) {
+                        Some(slot) => slot.1 += w,
+                        None => out[si].push((ti, w)),
+                    }
+                }
+            }
+        }
+        for adj in &mut out {
+            adj.sort_unstable_by_key(|(t, _)| *t);
+        }
+        AuthorityGraph { ids, out, edge_count }
+    }
+}
+
+/// Weighted PageRank by power iteration. Dangling mass (nodes without weighted
+/// out-edges) is redistributed uniformly. Ranks sum to 1.
+pub(crate) fn pagerank(
+    g: &AuthorityGraph,
+    damping: f64,
+    max_iterations: u32,
+    tolerance: f64,
+) -> (Vec<f64>, Convergence) {
+    let n = g.ids.len();
+    if n == 0 {
+        return (Vec::new(), Convergence { iterations: 0, converged: true, final_delta: 0.0 });
+    }
+    let out_w: Vec<f64> = g.out.iter().map(|a| a.iter().map(|(_, w)| w).sum()).collect();
+    let mut rank = vec![1.0 / n as f64; n];
+    let mut next = vec![0.0; n];
+    let mut conv = Convergence { iterations: 0, converged: false, final_delta: f64::INFINITY };
+
+    for it in 1..=max_iterations {
+        let dangling: f64 = (0..n).filter(|&i| out_w[i] == 0.0).map(|i| rank[i]).sum();
+        let base = (1.0 - damping) / n as f64 + damping * dangling / n as f64;
+        next.iter_mut().for_each(|x| *x = base);
+        for (i, adj) in g.out.iter().enumerate() {
+            if out_w[i] == 0.0 {
+                continue;
+            }
+            let share = damping * rank[i] / out_w[i];
+            for &(t, w) in adj {
+                next[t] += share * w;
+            }
+        }
+        let delta: f64 = rank.iter().zip(&next).map(|(a, b)| (a - b).abs()).sum();
+        std::mem::swap(&mut rank, &mut next);
+        conv.iterations = it;
+        conv.final_delta = delta;
+        if delta < tolerance {
+            conv.converged = true;
+            break;
+        }
+    }
+    (rank, conv)
+}
+
+/// Weighted HITS; returns the authority vector (L2-normalised).
+pub(crate) fn hits_authority(
+    g: &AuthorityGraph,
+    max_iterations: u32,
+    tolerance: f64,
+) -> (Vec<f64>, Convergence) {
+    let n = g.ids.len();
+    if n == 0 {
+        return (Vec::new(), Convergence { iterations: 0, converged: true, final_delta: 0.0 });
+    }
+    let mut auth = vec![1.0 / (n as f64).sqrt(); n];
+    let mut hub = vec![1.0 / (n as f64).sqrt(); n];
+    let mut conv = Convergence { iterations: 0, converged: false, final_delta: f64::INFINITY };
+
+    for it in 1..=max_iterations {
+        let mut next_auth = vec![0.0; n];
+        for (i, adj) in g.out.iter().enumerate() {
+            for &(t, w) in adj {
+                next_auth[t] += w * hub[i];
+            }
+        }
+        l2_normalize(&mut next_auth);
+        for (i, adj) in g.out.iter().enumerate() {
+            hub[i] = adj.iter().map(|&(t, w)| w * next_auth[t]).sum();
+        }
+        l2_normalize(&mut hub);
+        let delta: f64 = auth.iter().zip(&next_auth).map(|(a, b)| (a - b).abs()).sum();
+        auth = next_auth;
+        conv.iterations = it;
+        conv.final_delta = delta;
+        if delta < tolerance {
+            conv.converged = true;
+            break;
+        }
+    }
+    (auth, conv)
+}
+
+fn l2_normalize(v: &mut [f64]) {
+    let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Map raw ranks onto 0..99.9: `99.9 · ln(1 + r·N) / ln(1 + max·N)`.
+/// Scaling by N makes the average PageRank node ≈ 1 before the log compressor,
+/// mirroring how `mesh_points` is log-compressed in `compute_fni`.
+fn to_authority_scale(ranks: &[f64]) -> Vec<f64> {
+    let n = ranks.len() as f64;
+    let max = ranks.iter().cloned().fold(0.0, f64::max);
+    let denom = (1.0 + max * n).ln();
+    ranks
+        .iter()
+        .map(|r| {
+            if denom <= 0.0 {
+                return 0.0;
+            }
+            let a = MAX_AUTHORITY * (1.0 + r * n).ln() / denom;
+            (a * 10.0).round() / 10.0
+        })
+        .collect()
+}
+
+/// Load a graph file and compute id → authority (0..99.9).
+pub(crate) fn authority_scores(
+    graph_path: &str,
+    opts: Option<&AuthorityOptions>,
+) -> std::result::Result<(String, AuthorityGraph, Vec<f64>, Convergence), String> {
+    let graph = nxvf_core::load_json_file(graph_path)?;
+    let algorithm = opts
+        .and_then(|o| o.algorithm.clone())
+        .unwrap_or_else(|| "pagerank".to_string());
+    let max_iterations = opts
+        .and_then(|o| o.max_iterations)
+        .unwrap_or(DEFAULT_MAX_ITERATIONS);
+    let tolerance = opts.and_then(|o| o.tolerance).unwrap_or(DEFAULT_TOLERANCE);
+    let damping = opts.and_then(|o| o.damping).unwrap_or(DEFAULT_DAMPING);
+    if !(0.0..1.0).contains(&damping) {
+        return Err(format!("damping must be in [0, 1): {damping}"));
+    }
+
+    let g = AuthorityGraph::from_value(&graph, opts.and_then(|o| o.relation_weights.as_ref()));
+    let (ranks, conv) = match algorithm.as_str() {
+        "pagerank" => pagerank(&g, damping, max_iterations, tolerance),
+        "hits" => hits_authority(&g, max_iterations, tolerance),
+        other => return Err(format!("unknown authority algorithm: {other}")),
+    };
+    eprintln!(
+        "[RUST-FNI] authority {}: {} nodes, {} edges, {} iterations (converged={}, delta={:.3e})",
+        algorithm,
+        g.ids.len(),
+        g.edge_count,
+        conv.iterations,
+        conv.converged,
+        conv.final_delta
+    );
+    Ok((algorithm, g, to_authority_scale(&ranks), conv))
+}
+
+/// Compute graph authority for every node of a relations/mesh graph file.
+#[napi]
+pub fn compute_mesh_authority(
+    graph_path: String,
+    options: Option<AuthorityOptions>,
+) -> Result<AuthorityResult> {
+    let (algorithm, g, scores, conv) =
+        authority_scores(&graph_path, options.as_ref()).map_err(Error::from_reason)?;
+    Ok(AuthorityResult {
+        algorithm,
+        node_count: g.ids.len() as u32,
+        edge_count: g.edge_count as u32,
+        iterations: conv.iterations,
+        converged: conv.converged,
+        final_delta: conv.final_delta,
+        scores: g.ids.into_iter().zip(scores).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn star_graph() -> Value {
+        // b, c, d all cite a; a uses b at 50% confidence; d FEATURED_IN a report.
+        json!({
+            "nodes": { "a": {}, "b": {}, "c": {}, "d": {} },
+            "edges": {
+                "b": [["a", "CITES", 100, [], "e1"]],
+                "c": [["a", "CITES", 100, [], "e2"]],
+                "d": [["a", "BASED_ON", 100], ["report--x", "FEATURED_IN", 80]],
+                "a": [["b", "USES", 50]]
+            }
+        })
+    }
+
+    #[test]
+    fn test_pagerank_converges_and_ranks_hub_first() {
+        let g = AuthorityGraph::from_value(&star_graph(), None);
+        assert_eq!(g.ids, vec!["a", "b", "c", "d", "report--x"]);
+        assert_eq!(g.edge_count, 4, "FEATURED_IN must carry no weight");
+        let (ranks, conv) = pagerank(&g, 0.85, 200, 1e-12);
+        assert!(conv.converged);
+        assert!((ranks.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(ranks[0] > ranks[1] && ranks[1] > ranks[2]);
+        let scaled = to_authority_scale(&ranks);
+        assert_eq!(scaled[0], 99.9);
+    }
+
+    #[test]
+    fn test_pagerank_is_deterministic() {
+        let g1 = AuthorityGraph::from_value(&star_graph(), None);
+        let g2 = AuthorityGraph::from_value(&star_graph(), None);
+        let (r1, _) = pagerank(&g1, 0.85, 50, 0.0);
+        let (r2, _) = pagerank(&g2, 0.85, 50, 0.0);
+        assert_eq!(
+            r1.iter().map(|x| x.to_bits()).collect::<Vec<_>>(),
+            r2.iter().map(|x| x.to_bits()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_relation_weight_override_and_hits() {
+        let mut overrides = HashMap::new();
+        overrides.insert("CITES".to_string(), 0.0);
+        let g = AuthorityGraph::from_value(&star_graph(), Some(&overrides));
+        assert_eq!(g.edge_count, 2);
+        let (auth, conv) = hits_authority(&g, 100, 1e-12);
+        assert!(conv.converged);
+        // a (BASED_ON target) dominates; c has no endorsement left.
+        assert!(auth[0] > auth[2]);
+        assert_eq!(auth[2], 0.0);
+    }
+
+    #[test]
+    fn test_iteration_cap_reports_not_converged() {
+        let g = AuthorityGraph::from_value(&star_graph(), None);
+        let (_, conv) = pagerank(&g, 0.85, 1, 1e-15);
+        assert_eq!(conv.iterations, 1);
+        assert!(!conv.converged);
+    }
+}
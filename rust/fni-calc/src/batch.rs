@@ -0,0 +1,599 @@
+//! Streaming, parallel FNI batch scoring over a shard directory.
+//!
+//! One shard at a time: nxvf-core typed decode straight into `EntityInput`
+//! (serde skips body_content/readme) → score in parallel chunks on a rayon pool
+//! → write in input order to the configured sink. Peak memory is one shard of
+//! slim inputs plus its results; output order is deterministic regardless of
+//! thread count.
+//!
+//! With `history` set, each id's momentum M is derived from the FNI history
+//! file before scoring and the run's snapshot is appended (then compacted)
+//! after — see momentum.rs. M only moves the score via `profile.w_momentum`.
+
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use rayon::prelude::*;
+
+use crate::authority::{authority_scores, AuthorityOptions};
+use crate::momentum::{FniHistory, FniHistoryOptions, HistoryRun};
+use crate::{compute_fni_with, get_ks, EntityInput, FniProfile, FniResult, Weights, DEFAULT_WEIGHTS};
+
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 10_000; // Spec §5.1 batch size
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Same value as stream-aggregator `PARSE_ACCOUNTING_PROTOCOL` (W3-O1 v1).
+const PARSE_ACCOUNTING_PROTOCOL: u32 = 1;
+
+#[napi(object)]
+pub struct FniBatchOptions {
+    /// "zstd" (default, `fni-scores.json.zst` NDJSON), "ndjson"
+    /// (`fni-scores.ndjson`), or "nxvf" (per-shard `fni-<part>.bin` side-column,
+    /// entry i = score of the i-th decoded entity of that shard).
+    pub format: Option<String>,
+    /// Entities per parallel scoring chunk (default 10000).
+    pub chunk_size: Option<u32>,
+    /// Worker threads (default: rayon global pool = logical CPUs).
+    pub threads: Option<u32>,
+    /// Zstd level for "zstd" and "nxvf" payloads (default 3).
+    pub zstd_level: Option<i32>,
+    /// Relations/mesh graph file. When set, graph authority replaces
+    /// `mesh_points` as the A component for every id present in the graph.
+    pub mesh_graph_path: Option<String>,
+    pub authority: Option<AuthorityOptions>,
+    /// Component weights (default: canonical V2.0, momentum off).
+    pub profile: Option<FniProfile>,
+    /// FNI history file for the momentum component (read, appended, compacted).
+    pub history: Option<FniHistoryOptions>,
+}
+
+/// W3-O1 drop record — field-for-field the shape of stream-aggregator
+/// `ParseDropRecord` so JS can consume both with one reader.
+#[napi(object, use_nullable = true)]
+pub struct ParseDropRecord {
+    pub part: String,
+    pub entry_index: u32,
+    pub error_class: String,
+    pub serde_line: Option<u32>,
+    pub serde_column: Option<u32>,
+    pub payload_length: u32,
+    pub payload_fingerprint: Option<String>,
+    pub fingerprint_status: String,
+    pub attribution_status: String,
+}
+
+/// Per-shard parse accounting — same shape as stream-aggregator
+/// `ParseAccounting`. `engine_path` is "binary" for NXVF shards and
+/// "json_stream" for brace-matched JSON shards (declared = objects seen).
+#[napi(object)]
+pub struct ParseAccounting {
+    pub protocol_version: u32,
+    pub engine_path: String,
+    pub part: String,
+    pub declared_entity_count: u32,
+    pub parsed_entity_count: u32,
+    pub dropped_entity_count: u32,
+    pub parse_error_count: u32,
+    pub conserved: bool,
+    pub drop_records: Vec<ParseDropRecord>,
+}
+
+#[napi(object)]
+pub struct FniBatchStats {
+    pub entity_count: u32,
+    pub shard_count: u32,
+    pub skipped_shard_count: u32,
+    pub duration_ms: u32,
+    pub entities_per_sec: f64,
+    pub output_format: String,
+    pub output_files: Vec<String>,
+    pub declared_entity_count: u32,
+    pub dropped_entity_count: u32,
+    pub parse_accounting: Vec<ParseAccounting>,
+    /// Graph-authority power-iteration report (None without `mesh_graph_path`).
+    pub authority_iterations: Option<u32>,
+    pub authority_converged: Option<bool>,
+    /// Scored entities whose A component came from the graph.
+    pub authority_applied_count: u32,
+    /// Ids kept in the history file after compaction (None without `history`).
+    pub history_id_count: Option<u32>,
+    /// Ids dropped from the history by `max_age_days` this run.
+    pub history_dropped_id_count: Option<u32>,
+    /// Scored entities with a non-zero momentum component.
+    pub momentum_applied_count: u32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Zstd,
+    Ndjson,
+    Nxvf,
+}
+
+impl OutputFormat {
+    fn parse(s: Option<&str>) -> Result<Self> {
+        match s.unwrap_or("zstd") {
+            "zstd" | "json.zst" => Ok(OutputFormat::Zstd),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "nxvf" | "bin" => Ok(OutputFormat::Nxvf),
+            other => Err(Error::from_reason(format!("unknown output format: {other}"))),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            OutputFormat::Zstd => "zstd",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Nxvf => "nxvf",
+        }
+    }
+}
+
+/// Single-file NDJSON sink (plain or Zstd). NXVF writes per shard instead.
+enum Sink {
+    Zstd(zstd::Encoder<'static, BufWriter<std::fs::File>>),
+    Plain(BufWriter<std::fs::File>),
+    None,
+}
+
+impl Sink {
+    fn write_line(&mut self, r: &FniResult) -> std::result::Result<(), String> {
+        let w: &mut dyn Write = match self {
+            Sink::Zstd(e) => e,
+            Sink::Plain(b) => b,
+            Sink::None => return Ok(()),
+        };
+        serde_json::to_writer(&mut *w, r).map_err(|e| format!("write: {e}"))?;
+        w.write_all(b"\n").map_err(|e| format!("write: {e}"))
+    }
+
+    fn finish(self) -> std::result::Result<(), String> {
+        match self {
+            Sink::Zstd(e) => e
+                .finish()
+                .and_then(|mut w| w.flush())
+                .map_err(|e| format!("zstd finish: {e}")),
+            Sink::Plain(mut b) => b.flush().map_err(|e| format!("flush: {e}")),
+            Sink::None => Ok(()),
+        }
+    }
+}
+
+fn build_parse_accounting(report: &nxvf_core::ShardParseReport, binary: bool) -> ParseAccounting {
+    let drop_records = report
+        .records
+        .iter()
+        .map(|r| {
+            let json = r.error_class == nxvf_core::DropClass::JsonParse;
+            ParseDropRecord {
+                part: r.part.clone(),
+                entry_index: r.entry_index,
+                error_class: r.error_class.as_str().to_string(),
+                serde_line: json.then_some(r.serde_line),
+                serde_column: json.then_some(r.serde_column),
+                payload_length: r.payload_length,
+                payload_fingerprint: r.payload_fingerprint.clone(),
+                fingerprint_status: r.fingerprint_status.to_string(),
+                attribution_status: r.attribution_status.to_string(),
+            }
+        })
+        .collect();
+    ParseAccounting {
+        protocol_version: PARSE_ACCOUNTING_PROTOCOL,
+        engine_path: if binary { "binary" } else { "json_stream" }.to_string(),
+        part: report.part.clone(),
+        declared_entity_count: report.declared_entity_count,
+        parsed_entity_count: report.parsed_entity_count,
+        dropped_entity_count: report.dropped_entity_count(),
+        parse_error_count: report.parse_error_count(),
+        conserved: report.is_conserved(),
+        drop_records,
+    }
+}
+
+/// Score entities in parallel chunks; result order == input order.
+pub(crate) fn score_parallel(inputs: &[EntityInput], chunk_size: usize, weights: &Weights) -> Vec<FniResult> {
+    inputs
+        .par_chunks(chunk_size.max(1))
+        .flat_map_iter(|chunk| chunk.iter().map(|e| compute_fni_with(e, weights)))
+        .collect()
+}
+
+/// V26.5 → streaming + parallel: FNI from a shard directory.
+/// O(shard_size) memory; see `FniBatchOptions` for output sinks.
+#[napi]
+pub fn batch_calculate_fni_from_dir(
+    shard_dir: String,
+    output_dir: String,
+    options: Option<FniBatchOptions>,
+) -> Result<FniBatchStats> {
+    let start = std::time::Instant::now();
+    let opts = options.unwrap_or(FniBatchOptions {
+        format: None,
+        chunk_size: None,
+        threads: None,
+        zstd_level: None,
+        mesh_graph_path: None,
+        authority: None,
+        profile: None,
+        history: None,
+    });
+    let format = OutputFormat::parse(opts.format.as_deref())?;
+    let chunk_size = opts.chunk_size.map(|c| c as usize).unwrap_or(DEFAULT_CHUNK_SIZE);
+    let level = opts.zstd_level.unwrap_or(DEFAULT_ZSTD_LEVEL);
+    let weights = opts.profile.as_ref().map(|p| p.weights()).unwrap_or(DEFAULT_WEIGHTS);
+
+    let pool = match opts.threads {
+        Some(n) if n > 0 => Some(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(n as usize)
+                .build()
+                .map_err(|e| Error::from_reason(format!("thread pool: {e}")))?,
+        ),
+        _ => None,
+    };
+
+    // Graph authority is computed once up front; shards only look ids up.
+    let (authority, authority_conv) = match opts.mesh_graph_path.as_deref() {
+        Some(p) if !p.is_empty() => {
+            let (_, g, scores, conv) =
+                authority_scores(p, opts.authority.as_ref()).map_err(Error::from_reason)?;
+            let map: HashMap<String, f64> = g.ids.into_iter().zip(scores).collect();
+            (map, Some(conv))
+        }
+        _ => (HashMap::new(), None),
+    };
+    let mut authority_applied = 0u32;
+
+    let history_run = opts.history.as_ref().map(HistoryRun::from_options);
+    let mut history = match &history_run {
+        Some(run) => Some(FniHistory::load(&run.path).map_err(Error::from_reason)?),
+        None => None,
+    };
+    let mut momentum_applied = 0u32;
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| Error::from_reason(format!("create output dir: {e}")))?;
+    let out_dir = Path::new(&output_dir);
+    let mut output_files = Vec::new();
+    let mut sink = match format {
+        OutputFormat::Zstd | OutputFormat::Ndjson => {
+            let name = if format == OutputFormat::Zstd { "fni-scores.json.zst" } else { "fni-scores.ndjson" };
+            let path = out_dir.join(name);
+            let file = std::fs::File::create(&path)
+                .map_err(|e| Error::from_reason(format!("create output: {e}")))?;
+            output_files.push(path.to_string_lossy().into_owned());
+            if format == OutputFormat::Zstd {
+                Sink::Zstd(
+                    zstd::Encoder::new(BufWriter::new(file), level)
+                        .map_err(|e| Error::from_reason(format!("zstd init: {e}")))?,
+                )
+            } else {
+                Sink::Plain(BufWriter::new(file))
+            }
+        }
+        OutputFormat::Nxvf => Sink::None,
+    };
+
+    let shard_files = nxvf_core::discover_shards(&shard_dir).map_err(Error::from_reason)?;
+    let mut entity_count = 0u32;
+    let mut skipped = 0u32;
+    let mut declared = 0u32;
+    let mut dropped = 0u32;
+    let mut parse_accounting = Vec::with_capacity(shard_files.len());
+
+    for (si, path) in shard_files.iter().enumerate() {
+        let (mut inputs, report) = match nxvf_core::read_typed_shard_with_report::<EntityInput>(path) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("[RUST-FNI] Skipping corrupted shard {}: {}", path, e);
+                skipped += 1;
+                continue;
+            }
+        };
+        declared += report.declared_entity_count;
+        dropped += report.dropped_entity_count();
+        parse_accounting.push(build_parse_accounting(&report, path.ends_with(".bin")));
+        if !authority.is_empty() {
+            for input in inputs.iter_mut() {
+                if let Some(&a) = authority.get(&input.id) {
+                    input.authority = Some(a);
+                    authority_applied += 1;
+                }
+            }
+        }
+
+        if let (Some(h), Some(run)) = (&history, &history_run) {
+            for input in inputs.iter_mut() {
+                let m = h.momentum(&input.id, run.now_days, input.raw_metrics * get_ks(&input.id));
+                if m > 0.0 {
+                    momentum_applied += 1;
+                }
+                input.momentum = Some(m);
+            }
+        }
+
+        let results = match &pool {
+            Some(p) => p.install(|| score_parallel(&inputs, chunk_size, &weights)),
+            None => score_parallel(&inputs, chunk_size, &weights),
+        };
+        drop(inputs);
+        if let (Some(h), Some(run)) = (history.as_mut(), &history_run) {
+            for r in &results {
+                h.record(&r.id, run.now_days, r.raw_pop, r.fni_score);
+            }
+        }
+
+        if format == OutputFormat::Nxvf {
+            let payloads = results
+                .par_iter()
+                .map(|r| {
+                    let json = serde_json::to_vec(r).map_err(|e| format!("serialize: {e}"))?;
+                    zstd::encode_all(json.as_slice(), level).map_err(|e| format!("zstd: {e}"))
+                })
+                .collect::<std::result::Result<Vec<_>, String>>()
+                .map_err(Error::from_reason)?;
+            let stem = Path::new(path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.split('.').next())
+                .unwrap_or("part");
+            let side = out_dir.join(format!("fni-{stem}.bin"));
+            let side = side.to_string_lossy().into_owned();
+            nxvf_core::write_nxvf_shard(&side, si as u16, &payloads).map_err(Error::from_reason)?;
+            output_files.push(side);
+        } else {
+            for r in &results {
+                sink.write_line(r).map_err(Error::from_reason)?;
+            }
+        }
+        entity_count += results.len() as u32;
+    }
+    sink.finish().map_err(Error::from_reason)?;
+
+    let compaction = match (history.as_mut(), &history_run) {
+        (Some(h), Some(run)) => {
+            let c = h.compact(run.now_days, run.max_snapshots, run.max_age_days);
+            h.save(&run.path, run.now_days).map_err(Error::from_reason)?;
+            eprintln!(
+                "[RUST-FNI] History {}: {} ids ({} stale dropped, {} points trimmed)",
+                run.path, c.id_count, c.dropped_ids, c.dropped_points
+            );
+            Some(c)
+        }
+        _ => None,
+    };
+
+    let elapsed = start.elapsed();
+    let secs = elapsed.as_secs_f64();
+    eprintln!(
+        "[RUST-FNI] Scored {} entities from {} shards → {} ({}ms, {} dropped)",
+        entity_count,
+        shard_files.len(),
+        format.as_str(),
+        elapsed.as_millis(),
+        dropped
+    );
+    Ok(FniBatchStats {
+        entity_count,
+        shard_count: shard_files.len() as u32,
+        skipped_shard_count: skipped,
+        duration_ms: elapsed.as_millis() as u32,
+        entities_per_sec: if secs > 0.0 { (entity_count as f64 / secs).round() } else { 0.0 },
+        output_format: format.as_str().to_string(),
+        output_files,
+        declared_entity_count: declared,
+        dropped_entity_count: dropped,
+        parse_accounting,
+        authority_iterations: authority_conv.as_ref().map(|c| c.iterations),
+        authority_converged: authority_conv.as_ref().map(|c| c.converged),
+        authority_applied_count: authority_applied,
+        history_id_count: compaction.as_ref().map(|c| c.id_count),
+        history_dropped_id_count: compaction.as_ref().map(|c| c.dropped_ids),
+        momentum_applied_count: momentum_applied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_json_shard(dir: &Path, name: &str, body: &str) {
+        std::fs::write(dir.join(name), body).unwrap();
+    }
+
+    #[test]
+    fn test_formats_agree_and_account_for_drops() {
+        let base = std::env::temp_dir().join(format!("fni-batch-{}", std::process::id()));
+        let shards = base.join("shards");
+        std::fs::create_dir_all(&shards).unwrap();
+        write_json_shard(
+            &shards,
+            "part-000.json",
+            r#"{"entities":[
+                {"id":"hf-model--a","entity_type":"model","raw_metrics":1000,"readme":"skip me"},
+                {"id":"gh-tool--b","entity_type":"tool","raw_metrics":"oops"},
+                {"id":"arxiv-paper--c","entity_type":"paper","mesh_points":3}
+            ]}"#,
+        );
+
+        let mut lines_by_format = Vec::new();
+        for fmt in ["ndjson", "zstd"] {
+            let out = base.join(fmt);
+            let stats = batch_calculate_fni_from_dir(
+                shards.to_string_lossy().into_owned(),
+                out.to_string_lossy().into_owned(),
+                Some(FniBatchOptions {
+                    format: Some(fmt.to_string()),
+                    chunk_size: Some(1),
+                    threads: Some(2),
+                    zstd_level: None,
+                    mesh_graph_path: None,
+                    authority: None,
+                    profile: None,
+                    history: None,
+                }),
+            )
+            .unwrap();
+            assert_eq!(stats.entity_count, 2);
+            assert_eq!(stats.declared_entity_count, 3);
+            assert_eq!(stats.dropped_entity_count, 1);
+            assert_eq!(stats.parse_accounting[0].engine_path, "json_stream");
+            assert!(stats.parse_accounting[0].conserved);
+            let raw = std::fs::read(&stats.output_files[0]).unwrap();
+            let text = nxvf_core::auto_decompress(&raw).unwrap();
+            lines_by_format.push(String::from_utf8(text).unwrap());
+        }
+        assert_eq!(lines_by_format[0], lines_by_format[1]);
+        let ids: Vec<&str> = lines_by_format[0]
+            .lines()
+            .map(|l| if l.contains("hf-model--a") { "a" } else { "c" })
+            .collect();
+        assert_eq!(ids, vec!["a", "c"], "output must keep input order");
+
+        std::fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_nxvf_side_column_is_index_aligned() {
+        let base = std::env::temp_dir().join(format!("fni-batch-nxvf-{}", std::process::id()));
+        let shards = base.join("shards");
+        std::fs::create_dir_all(&shards).unwrap();
+        write_json_shard(
+            &shards,
+            "part-007.json",
+            r#"{"entities":[{"id":"hf-model--x"},{"id":"hf-model--y","raw_metrics":50}]}"#,
+        );
+        let out = base.join("out");
+        let stats = batch_calculate_fni_from_dir(
+            shards.to_string_lossy().into_owned(),
+            out.to_string_lossy().into_owned(),
+            Some(FniBatchOptions {
+                format: Some("nxvf".into()),
+                chunk_size: None,
+                threads: None,
+                zstd_level: None,
+                mesh_graph_path: None,
+                authority: None,
+                profile: None,
+                history: None,
+            }),
+        )
+        .unwrap();
+        assert!(stats.output_files[0].ends_with("fni-part-007.bin"));
+        let col = nxvf_core::read_binary_shard(&stats.output_files[0]).unwrap();
+        assert_eq!(col.len(), 2);
+        assert_eq!(col[0]["id"], "hf-model--x");
+        assert_eq!(col[1]["id"], "hf-model--y");
+        std::fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_mesh_graph_authority_feeds_a_component() {
+        let base = std::env::temp_dir().join(format!("fni-batch-auth-{}", std::process::id()));
+        let shards = base.join("shards");
+        std::fs::create_dir_all(&shards).unwrap();
+        write_json_shard(
+            &shards,
+            "part-000.json",
+            r#"{"entities":[{"id":"hf-model--hub"},{"id":"hf-model--leaf"},{"id":"hf-model--offgraph","mesh_points":10}]}"#,
+        );
+        let graph = base.join("graph.json");
+        std::fs::write(
+            &graph,
+            r#"{"edges":{"hf-model--leaf":[["hf-model--hub","BASED_ON",100]]}}"#,
+        )
+        .unwrap();
+        let out = base.join("out");
+        let stats = batch_calculate_fni_from_dir(
+            shards.to_string_lossy().into_owned(),
+            out.to_string_lossy().into_owned(),
+            Some(FniBatchOptions {
+                format: Some("ndjson".into()),
+                chunk_size: None,
+                threads: None,
+                zstd_level: None,
+                mesh_graph_path: Some(graph.to_string_lossy().into_owned()),
+                authority: None,
+                profile: None,
+                history: None,
+            }),
+        )
+        .unwrap();
+        assert_eq!(stats.authority_applied_count, 2);
+        assert_eq!(stats.authority_converged, Some(true));
+        let text = std::fs::read_to_string(&stats.output_files[0]).unwrap();
+        let rows: Vec<serde_json::Value> =
+            text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(rows[0]["a"], 99.9);
+        assert!(rows[1]["a"].as_f64().unwrap() < 99.9);
+        // Off-graph id keeps the mesh_points compressor: 99.9·(1 − 10^(−log10(11)/4)).
+        assert_eq!(rows[2]["a"], 45.0);
+        std::fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_history_momentum_only_with_weight() {
+        let base = std::env::temp_dir().join(format!("fni-batch-hist-{}", std::process::id()));
+        let out = base.join("out");
+        let history_path = base.join("fni-history.json.zst").to_string_lossy().into_owned();
+        let run = |day: f64, downloads: f64, w_momentum: Option<f64>| {
+            let shards = base.join(format!("shards-{day}"));
+            std::fs::create_dir_all(&shards).unwrap();
+            write_json_shard(
+                &shards,
+                "part-000.json",
+                &format!(
+                    r#"{{"entities":[{{"id":"hf-model--grow","raw_metrics":{downloads}}},{{"id":"hf-model--flat","raw_metrics":5000}}]}}"#
+                ),
+            );
+            let stats = batch_calculate_fni_from_dir(
+                shards.to_string_lossy().into_owned(),
+                out.to_string_lossy().into_owned(),
+                Some(FniBatchOptions {
+                    format: Some("ndjson".into()),
+                    chunk_size: None,
+                    threads: None,
+                    zstd_level: None,
+                    mesh_graph_path: None,
+                    authority: None,
+                    profile: Some(FniProfile { w_momentum, ..Default::default() }),
+                    history: Some(FniHistoryOptions {
+                        history_path: history_path.clone(),
+                        max_snapshots: Some(3),
+                        max_age_days: None,
+                        now_ms: Some(day * 86_400_000.0),
+                    }),
+                }),
+            )
+            .unwrap();
+            let text = std::fs::read_to_string(&stats.output_files[0]).unwrap();
+            let rows: Vec<serde_json::Value> =
+                text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+            (stats, rows)
+        };
+
+        let (first, _) = run(0.0, 100.0, None);
+        assert_eq!(first.momentum_applied_count, 0);
+        assert_eq!(first.history_id_count, Some(2));
+        run(7.0, 400.0, None);
+        let (unweighted, plain) = run(14.0, 1600.0, None);
+        assert_eq!(unweighted.momentum_applied_count, 1);
+        assert!(plain[0]["m"].as_f64().unwrap() > 90.0);
+        assert_eq!(plain[1]["m"], 0.0);
+
+        // Same snapshot day → history point replaced, momentum now weighted in.
+        let (_, boosted) = run(14.0, 1600.0, Some(0.1));
+        assert!(boosted[0]["fni_score"].as_f64().unwrap() > plain[0]["fni_score"].as_f64().unwrap());
+        assert_eq!(boosted[1]["fni_score"], plain[1]["fni_score"]);
+        std::fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_unknown_format_rejected() {
+        assert!(OutputFormat::parse(Some("parquet")).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 //! FNI V2.0 — Canonical Scoring Engine (Rust FFI)
 //!
 //! Formula: FNI = min(99.9, 0.35*S + 0.25*A + 0.15*P + 0.15*R + 0.10*Q) × staleness
+//! (default `FniProfile`; an optional momentum term M can be weighted in).
 //! Upgraded from V18.9 (Phase 6, 768-dim bge-base-en-v1.5)
 //! Processes 10k entity batches via N-API Buffer protocol (Spec §5.1).
 
@@ -8,6 +9,15 @@ use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use serde::{Deserialize, Serialize};
 
+mod authority;
+mod batch;
+mod momentum;
+mod regression;
+pub use authority::*;
+pub use batch::*;
+pub use momentum::FniHistoryOptions;
+pub use regression::*;
+
 // V2.0 Source Coefficients (Ks) — Spec §3.2
 const KS_HF: f64 = 1.0;       // Model Forge (HuggingFace) - Baseline
 const KS_GH: f64 = 5.0;       // Tool Source (GitHub)
@@ -25,6 +35,14 @@ const STALENESS_LAMBDA_PAPER: f64 = 0.001;
 const STALENESS_LAMBDA_PROMPT: f64 = 0.008;
 const STALENESS_LAMBDA_DATASET: f64 = 0.003;
 
+// V2.0 Master Formula weights — the default FniProfile
+const W_SEMANTIC: f64 = 0.35;
+const W_AUTHORITY: f64 = 0.25;
+const W_POPULARITY: f64 = 0.15;
+const W_RECENCY: f64 = 0.15;
+const W_QUALITY: f64 = 0.10;
+const W_MOMENTUM: f64 = 0.0;
+
 const LOG10_E: f64 = std::f64::consts::LOG10_E;
 const NULL_TIME_DAYS: f64 = 365.0;
 const DEFAULT_SEMANTIC_SCORE: f64 = 50.0;
@@ -52,6 +70,61 @@ struct EntityInput {
     /// Days since last harvest (_last_seen), for staleness decay
     #[serde(default)]
     days_since_harvest: Option<f64>,
+    /// Graph authority (0..99.9, see `compute_mesh_authority`). When set it
+    /// replaces the `mesh_points` log compressor as the A component.
+    #[serde(default)]
+    authority: Option<f64>,
+    /// Momentum M (0..99.9) from the FNI history file (see momentum.rs).
+    #[serde(default)]
+    momentum: Option<f64>,
+}
+
+/// Scoring profile: component weights plus the optional momentum weight.
+/// Unset fields keep the V2.0 defaults, so an empty profile reproduces the
+/// canonical formula bit-for-bit. Gate any change with `fni_regression_report`.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct FniProfile {
+    pub w_semantic: Option<f64>,
+    pub w_authority: Option<f64>,
+    pub w_popularity: Option<f64>,
+    pub w_recency: Option<f64>,
+    pub w_quality: Option<f64>,
+    /// Weight of the momentum component M (default 0 = disabled).
+    pub w_momentum: Option<f64>,
+}
+
+/// Resolved weights used by `compute_fni_with`.
+#[derive(Clone, Copy)]
+pub(crate) struct Weights {
+    s: f64,
+    a: f64,
+    p: f64,
+    r: f64,
+    q: f64,
+    m: f64,
+}
+
+pub(crate) const DEFAULT_WEIGHTS: Weights = Weights {
+    s: W_SEMANTIC,
+    a: W_AUTHORITY,
+    p: W_POPULARITY,
+    r: W_RECENCY,
+    q: W_QUALITY,
+    m: W_MOMENTUM,
+};
+
+impl FniProfile {
+    pub(crate) fn weights(&self) -> Weights {
+        Weights {
+            s: self.w_semantic.unwrap_or(W_SEMANTIC),
+            a: self.w_authority.unwrap_or(W_AUTHORITY),
+            p: self.w_popularity.unwrap_or(W_POPULARITY),
+            r: self.w_recency.unwrap_or(W_RECENCY),
+            q: self.w_quality.unwrap_or(W_QUALITY),
+            m: self.w_momentum.unwrap_or(W_MOMENTUM),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -65,6 +138,7 @@ pub struct FniResult {
     pub p: f64,
     pub r: f64,
     pub q: f64,
+    pub m: f64,
 }
 
 fn get_ks(id: &str) -> f64 {
@@ -102,6 +176,10 @@ fn log10(x: f64) -> f64 {
 }
 
 fn compute_fni(e: &EntityInput) -> FniResult {
+    compute_fni_with(e, &DEFAULT_WEIGHTS)
+}
+
+fn compute_fni_with(e: &EntityInput, w: &Weights) -> FniResult {
     let ks = get_ks(&e.id);
     let raw_pop = e.raw_metrics * ks;
 
@@ -120,14 +198,23 @@ fn compute_fni(e: &EntityInput) -> FniResult {
     };
     let r = f64::min(99.9, 100.0 * f64::exp(-lambda * days));
 
-    // A: Authority (Asymptotic Gravity Field, base 4)
-    let a = f64::min(99.9, 99.9 * (1.0 - f64::powf(10.0, -(log10(e.mesh_points + 1.0) / 4.0))));
+    // A: Authority — graph PageRank/HITS when supplied, else Asymptotic Gravity Field (base 4)
+    let a = match e.authority {
+        Some(auth) => auth.clamp(0.0, 99.9),
+        None => f64::min(99.9, 99.9 * (1.0 - f64::powf(10.0, -(log10(e.mesh_points + 1.0) / 4.0)))),
+    };
 
     // Q: Quality (Completeness + Utility, normalized)
     let q = f64::min(99.9, (e.completeness + e.utility) / 2.0);
 
-    // Master Formula V2.0: FNI = min(99.9, 0.35*S + 0.25*A + 0.15*P + 0.15*R + 0.10*Q)
-    let base_fni = f64::min(99.9, (0.35 * s) + (0.25 * a) + (0.15 * p) + (0.15 * r) + (0.10 * q));
+    // M: Momentum (history velocity; 0 without a history file)
+    let m = e.momentum.unwrap_or(0.0).clamp(0.0, 99.9);
+
+    // Master Formula V2.0: FNI = min(99.9, 0.35*S + 0.25*A + 0.15*P + 0.15*R + 0.10*Q [+ wM*M])
+    let base_fni = f64::min(
+        99.9,
+        (w.s * s) + (w.a * a) + (w.p * p) + (w.r * r) + (w.q * q) + (w.m * m),
+    );
 
     // Staleness decay — penalize entities not recently harvested
     let staleness_factor = match e.days_since_harvest {
@@ -150,51 +237,19 @@ fn compute_fni(e: &EntityInput) -> FniResult {
         p: (p * 10.0).round() / 10.0,
         r: (r * 10.0).round() / 10.0,
         q: (q * 10.0).round() / 10.0,
+        m: (m * 10.0).round() / 10.0,
     }
 }
 
 /// Batch FNI calculation from JSON array buffer.
+/// Parsed straight from the Buffer bytes, scored in parallel chunks (input order kept).
 #[napi]
-pub fn batch_calculate_fni(json_buffer: Buffer) -> Result<Vec<FniResult>> {
-    let data = std::str::from_utf8(&json_buffer)
-        .map_err(|e| Error::from_reason(format!("Invalid UTF-8: {}", e)))?;
-    let entities: Vec<EntityInput> = serde_json::from_str(data)
+pub fn batch_calculate_fni(json_buffer: Buffer, profile: Option<FniProfile>) -> Result<Vec<FniResult>> {
+    let entities: Vec<EntityInput> = serde_json::from_slice(&json_buffer)
         .map_err(|e| Error::from_reason(format!("JSON parse error: {}", e)))?;
+    let weights = profile.map(|p| p.weights()).unwrap_or(DEFAULT_WEIGHTS);
 
-    Ok(entities.iter().map(compute_fni).collect())
-}
-
-/// V26.5: Streaming FNI from shard directory — O(shard_size) memory.
-#[napi]
-pub fn batch_calculate_fni_from_dir(shard_dir: String, output_dir: String) -> Result<u32> {
-    use std::io::{BufWriter, Write};
-
-    let out_path = std::path::Path::new(&output_dir).join("fni-scores.json.zst");
-    if let Some(parent) = out_path.parent() {
-        std::fs::create_dir_all(parent).ok();
-    }
-    let file = std::fs::File::create(&out_path)
-        .map_err(|e| Error::from_reason(format!("create output: {e}")))?;
-    let mut encoder = zstd::Encoder::new(BufWriter::new(file), 3)
-        .map_err(|e| Error::from_reason(format!("zstd init: {e}")))?;
-
-    let mut count = 0u32;
-    nxvf_core::for_each_shard(&shard_dir, |entities| {
-        for e in &entities {
-            if let Ok(input) = serde_json::from_value::<EntityInput>(e.clone()) {
-                let result = compute_fni(&input);
-                serde_json::to_writer(&mut encoder, &result)
-                    .map_err(|e| format!("write: {e}"))?;
-                encoder.write_all(b"\n").map_err(|e| format!("write: {e}"))?;
-                count += 1;
-            }
-        }
-        Ok(())
-    })
-    .map_err(|e| Error::from_reason(e))?;
-
-    encoder.finish().map_err(|e| Error::from_reason(format!("zstd finish: {e}")))?;
-    Ok(count)
+    Ok(batch::score_parallel(&entities, batch::DEFAULT_CHUNK_SIZE, &weights))
 }
 
 /// Single entity FNI calculation.
@@ -220,6 +275,8 @@ pub fn calculate_fni_single(
         mesh_points,
         semantic_score: None,
         days_since_harvest: None,
+        authority: None,
+        momentum: None,
     })
 }
 
@@ -240,6 +297,8 @@ mod tests {
             mesh_points: 1e6,
             semantic_score: Some(99.0),
             days_since_harvest: None,
+            authority: None,
+            momentum: None,
         });
         assert!(result.fni_score <= 99.9);
     }
@@ -257,6 +316,8 @@ mod tests {
             mesh_points: 0.0,
             semantic_score: None, // Should default to 50.0
             days_since_harvest: None,
+            authority: None,
+            momentum: None,
         });
         // S=50, A=0, P=0, R=exp(-0.002*365)≈48, Q=0
         // baseFNI = 0.35*50 + 0.15*48 = 17.5 + 7.2 = 24.7
@@ -277,6 +338,8 @@ mod tests {
             mesh_points: 0.0,
             semantic_score: None,
             days_since_harvest: None,
+            authority: None,
+            momentum: None,
         });
         // With 365-day decay at lambda=0.002, R should be ~48
         assert!(result.r < 50.0);
@@ -295,6 +358,8 @@ mod tests {
             mesh_points: 0.0,
             semantic_score: None,
             days_since_harvest: None,
+            authority: None,
+            momentum: None,
         });
         let hf = compute_fni(&EntityInput {
             id: "hf-model--test".to_string(),
@@ -307,7 +372,36 @@ mod tests {
             mesh_points: 0.0,
             semantic_score: None,
             days_since_harvest: None,
+            authority: None,
+            momentum: None,
         });
         assert!(arxiv.p > hf.p, "ArXiv Ks=30 should produce higher P");
     }
+
+    #[test]
+    fn test_default_profile_is_canonical_and_momentum_is_opt_in() {
+        let mut e = EntityInput {
+            id: "hf-model--test".to_string(),
+            entity_type: "model".to_string(),
+            raw_metrics: 5000.0,
+            completeness: 60.0,
+            utility: 40.0,
+            days_since_update: 10.0,
+            date_valid: true,
+            mesh_points: 20.0,
+            semantic_score: None,
+            days_since_harvest: Some(3.0),
+            authority: None,
+            momentum: Some(80.0),
+        };
+        let canonical = compute_fni(&e);
+        let empty_profile = compute_fni_with(&e, &FniProfile::default().weights());
+        assert_eq!(canonical.fni_score.to_bits(), empty_profile.fni_score.to_bits());
+        assert_eq!(canonical.m, 80.0);
+
+        let boosted = FniProfile { w_momentum: Some(0.1), ..Default::default() };
+        assert!(compute_fni_with(&e, &boosted.weights()).fni_score > canonical.fni_score);
+        e.momentum = None;
+        assert_eq!(compute_fni_with(&e, &boosted.weights()).fni_score, canonical.fni_score);
+    }
 }
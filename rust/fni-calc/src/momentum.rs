@@ -0,0 +1,249 @@
+//! FNI History + Momentum — time-series signal across scoring runs.
+//!
+//! fni-calc keeps a compact per-id history of the last N `(t, raw_pop, fni)`
+//! snapshots (`fni-history.json.zst`). Each run reads it, derives a velocity
+//! (least-squares slope of ln(1 + raw_pop) per day over history + the current
+//! run) and maps positive growth onto the 0..99.9 M component, then appends the
+//! current snapshot and compacts the file (last N points per id, ids unseen for
+//! `max_age_days` dropped). M only enters the score through a profile's
+//! `w_momentum` (default 0 → canonical V2.0 output).
+
+use std::collections::HashMap;
+
+use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+
+const HISTORY_VERSION: u32 = 1;
+const DEFAULT_MAX_SNAPSHOTS: u32 = 8;
+const DEFAULT_MAX_AGE_DAYS: f64 = 90.0;
+/// Two snapshots closer than this (days) are the same run: replace, not append.
+const SAME_RUN_EPSILON_DAYS: f64 = 0.5;
+/// Log-growth per 30 days that maps to M ≈ 76 (tanh(1)): one doubling a month.
+const MOMENTUM_SCALE: f64 = std::f64::consts::LN_2;
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+#[napi(object)]
+pub struct FniHistoryOptions {
+    /// History file (read if present, rewritten each run). `.zst` recommended.
+    pub history_path: String,
+    /// Snapshots kept per id after compaction (default 8).
+    pub max_snapshots: Option<u32>,
+    /// Ids whose newest snapshot is older than this are dropped (default 90).
+    pub max_age_days: Option<f64>,
+    /// Run timestamp in epoch ms (default: now). Pin it for reproducible runs.
+    pub now_ms: Option<f64>,
+}
+
+/// On-disk shape. Points are `[t_days, raw_pop, fni]`, oldest first.
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct FniHistory {
+    #[serde(rename = "_v", default)]
+    version: u32,
+    #[serde(default)]
+    updated: f64,
+    #[serde(default)]
+    series: HashMap<String, Vec<[f64; 3]>>,
+}
+
+pub(crate) struct CompactionStats {
+    pub id_count: u32,
+    pub dropped_ids: u32,
+    pub dropped_points: u32,
+}
+
+impl FniHistory {
+    /// Load a history file; a missing file is an empty history (first run).
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !std::path::Path::new(path).exists() {
+            return Ok(FniHistory::default());
+        }
+        let val = nxvf_core::load_json_file(path)?;
+        let h: FniHistory =
+            serde_json::from_value(val).map_err(|e| format!("history parse {}: {}", path, e))?;
+        if h.version > HISTORY_VERSION {
+            return Err(format!("history {} has unsupported _v={}", path, h.version));
+        }
+        Ok(h)
+    }
+
+    /// Momentum M (0..99.9) for `id` given the current run's point.
+    pub fn momentum(&self, id: &str, now_days: f64, raw_pop: f64) -> f64 {
+        let Some(points) = self.series.get(id) else { return 0.0 };
+        let mut ts: Vec<f64> = Vec::with_capacity(points.len() + 1);
+        let mut ys: Vec<f64> = Vec::with_capacity(points.len() + 1);
+        for p in points {
+            if now_days - p[0] >= SAME_RUN_EPSILON_DAYS {
+                ts.push(p[0]);
+                ys.push(p[1].max(0.0).ln_1p());
+            }
+        }
+        ts.push(now_days);
+        ys.push(raw_pop.max(0.0).ln_1p());
+        momentum_from_velocity(velocity(&ts, &ys))
+    }
+
+    /// Append the current run's snapshot (replacing a same-run point).
+    pub fn record(&mut self, id: &str, now_days: f64, raw_pop: f64, fni: f64) {
+        let points = self.series.entry(id.to_string()).or_default();
+        if let Some(last) = points.last() {
+            if (now_days - last[0]).abs() < SAME_RUN_EPSILON_DAYS {
+                points.pop();
+            }
+        }
+        points.push([now_days, raw_pop, fni]);
+    }
+
+    /// Trim to the last `max_snapshots` points per id; drop stale ids.
+    pub fn compact(&mut self, now_days: f64, max_snapshots: usize, max_age_days: f64) -> CompactionStats {
+        let mut dropped_points = 0u32;
+        let before = self.series.len();
+        self.series.retain(|_, points| {
+            let newest = points.last().map(|p| p[0]).unwrap_or(f64::NEG_INFINITY);
+            if now_days - newest > max_age_days {
+                dropped_points += points.len() as u32;
+                return false;
+            }
+            if points.len() > max_snapshots {
+                let excess = points.len() - max_snapshots;
+                points.drain(..excess);
+                dropped_points += excess as u32;
+            }
+            true
+        });
+        CompactionStats {
+            id_count: self.series.len() as u32,
+            dropped_ids: (before - self.series.len()) as u32,
+            dropped_points,
+        }
+    }
+
+    /// Write atomically (temp file + rename) — a crash never leaves half a history.
+    pub fn save(&mut self, path: &str, now_days: f64) -> Result<(), String> {
+        self.version = HISTORY_VERSION;
+        self.updated = now_days;
+        let bytes = serde_json::to_vec(self).map_err(|e| format!("history serialize: {e}"))?;
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let tmp = format!("{}.tmp", path);
+        if path.ends_with(".zst") {
+            nxvf_core::write_zstd(&tmp, &bytes, 3)?;
+        } else {
+            std::fs::write(&tmp, &bytes).map_err(|e| format!("Write {}: {}", tmp, e))?;
+        }
+        std::fs::rename(&tmp, path).map_err(|e| format!("Rename {}: {}", tmp, e))
+    }
+}
+
+/// Resolved history options for one run.
+pub(crate) struct HistoryRun {
+    pub path: String,
+    pub now_days: f64,
+    pub max_snapshots: usize,
+    pub max_age_days: f64,
+}
+
+impl HistoryRun {
+    pub fn from_options(o: &FniHistoryOptions) -> Self {
+        let now_ms = o.now_ms.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as f64
+        });
+        HistoryRun {
+            path: o.history_path.clone(),
+            now_days: now_ms / MS_PER_DAY,
+            max_snapshots: o.max_snapshots.unwrap_or(DEFAULT_MAX_SNAPSHOTS).max(1) as usize,
+            max_age_days: o.max_age_days.unwrap_or(DEFAULT_MAX_AGE_DAYS),
+        }
+    }
+}
+
+/// Least-squares slope dy/dt (per day). 0 with < 2 points or no time spread.
+fn velocity(ts: &[f64], ys: &[f64]) -> f64 {
+    let n = ts.len() as f64;
+    if ts.len() < 2 {
+        return 0.0;
+    }
+    let mt = ts.iter().sum::<f64>() / n;
+    let my = ys.iter().sum::<f64>() / n;
+    let (mut num, mut den) = (0.0, 0.0);
+    for (t, y) in ts.iter().zip(ys) {
+        num += (t - mt) * (y - my);
+        den += (t - mt) * (t - mt);
+    }
+    if den == 0.0 {
+        0.0
+    } else {
+        num / den
+    }
+}
+
+/// Positive 30-day log-growth → 0..99.9 via tanh. Decline is not penalised
+/// here (R and staleness already cover decay); M is a bonus signal only.
+fn momentum_from_velocity(v: f64) -> f64 {
+    let monthly = (v * 30.0).max(0.0);
+    99.9 * (monthly / MOMENTUM_SCALE).tanh()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_growing_entity_has_momentum_static_has_none() {
+        let mut h = FniHistory::default();
+        for (day, pop) in [(0.0, 100.0), (7.0, 200.0), (14.0, 400.0)] {
+            h.record("grow", day, pop, 50.0);
+            h.record("flat", day, 1000.0, 50.0);
+        }
+        let grow = h.momentum("grow", 21.0, 800.0);
+        let flat = h.momentum("flat", 21.0, 1000.0);
+        assert_eq!(flat, 0.0);
+        // Doubling weekly ≫ one doubling a month → near the cap.
+        assert!(grow > 95.0, "got {grow}");
+        assert_eq!(h.momentum("unknown", 21.0, 5.0), 0.0);
+    }
+
+    #[test]
+    fn test_same_run_point_is_replaced() {
+        let mut h = FniHistory::default();
+        h.record("x", 10.0, 1.0, 1.0);
+        h.record("x", 10.1, 2.0, 2.0);
+        assert_eq!(h.series["x"], vec![[10.1, 2.0, 2.0]]);
+        // The same-run point is also ignored when computing momentum.
+        assert_eq!(h.momentum("x", 10.2, 1000.0), 0.0);
+    }
+
+    #[test]
+    fn test_compaction_trims_and_drops_stale() {
+        let mut h = FniHistory::default();
+        for d in 0..12 {
+            h.record("live", d as f64, 1.0, 1.0);
+        }
+        h.record("stale", 0.0, 1.0, 1.0);
+        let stats = h.compact(100.0, 4, 90.0);
+        assert_eq!(stats.id_count, 1);
+        assert_eq!(stats.dropped_ids, 1);
+        assert_eq!(stats.dropped_points, 8 + 1);
+        assert_eq!(h.series["live"].len(), 4);
+        assert_eq!(h.series["live"][0][0], 8.0);
+    }
+
+    #[test]
+    fn test_save_load_roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("fni-history-{}.json.zst", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let mut h = FniHistory::default();
+        h.record("a", 1.0, 2.0, 3.0);
+        h.save(&path, 1.0).unwrap();
+        let back = FniHistory::load(&path).unwrap();
+        assert_eq!(back.version, HISTORY_VERSION);
+        assert_eq!(back.series["a"], vec![[1.0, 2.0, 3.0]]);
+        std::fs::remove_file(&path).ok();
+        assert!(FniHistory::load(&path).unwrap().series.is_empty());
+    }
+}
@@ -0,0 +1,621 @@
+//! FNI Regression Gate — score-delta report between two FNI runs.
+//!
+//! Compares a baseline and a candidate scoring (either `fni-scores.json.zst`
+//! outputs of `batch_calculate_fni_from_dir` or two registry shard dirs) and
+//! reports the distribution shift per entity type: rank correlation
+//! (Spearman ρ + Kendall τ-b), top movers, and how many entities cross each
+//! percentile tier boundary. Intended as the pre-merge gate for any change to
+//! the scoring formula.
+//!
+//! Percentiles are recomputed here with the SAME tie-aware effective-rank
+//! formula as stream-aggregator `percentile::calculate_rankings` (cdylib crates
+//! cannot depend on each other). If one changes, the other MUST follow.
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Percentile tier boundaries (`fni_percentile >= b`). Mirrors the badge tiers
+/// in scripts/l5/fni-percentile.js plus the median, on the integer 1..=100
+/// scale emitted by `calculate_rankings`.
+const PERCENTILE_BOUNDARIES: [u8; 6] = [99, 95, 90, 75, 50, 25];
+const DEFAULT_TOP_N: usize = 20;
+
+#[napi(object)]
+pub struct ScoreMover {
+    pub id: String,
+    pub entity_type: String,
+    pub baseline_score: f64,
+    pub candidate_score: f64,
+    pub delta: f64,
+    pub baseline_percentile: u32,
+    pub candidate_percentile: u32,
+}
+
+#[napi(object)]
+pub struct BoundaryCrossing {
+    /// Tier boundary on the 1..=100 percentile scale (`>= boundary`).
+    pub boundary: u32,
+    /// Entities below the boundary in baseline, at/above it in candidate.
+    pub promoted: u32,
+    /// Entities at/above the boundary in baseline, below it in candidate.
+    pub demoted: u32,
+}
+
+#[napi(object)]
+pub struct TypeShift {
+    pub entity_type: String,
+    pub count: u32,
+    pub baseline_mean: f64,
+    pub candidate_mean: f64,
+    pub mean_delta: f64,
+    pub baseline_p50: f64,
+    pub candidate_p50: f64,
+    pub baseline_p90: f64,
+    pub candidate_p90: f64,
+    pub spearman: f64,
+    pub kendall_tau: f64,
+    pub changed_count: u32,
+}
+
+#[napi(object)]
+pub struct FniRegressionReport {
+    pub baseline_count: u32,
+    pub candidate_count: u32,
+    /// Ids present in both runs — every statistic below is over this set.
+    pub matched_count: u32,
+    pub added_count: u32,
+    pub removed_count: u32,
+    pub spearman: f64,
+    pub kendall_tau: f64,
+    pub by_type: Vec<TypeShift>,
+    pub boundary_crossings: Vec<BoundaryCrossing>,
+    pub top_gainers: Vec<ScoreMover>,
+    pub top_losers: Vec<ScoreMover>,
+    /// True when global Spearman ρ >= `min_spearman` (always true if unset).
+    pub gate_passed: bool,
+}
+
+/// One scored entity as read from either input shape.
+pub(crate) struct ScoreRecord {
+    pub id: String,
+    pub entity_type: String,
+    pub score: f64,
+}
+
+/// Build a regression report between two FNI runs.
+///
+/// `baseline_path`/`candidate_path`: a score output file (NDJSON or JSON array,
+/// raw/.zst/.gz) or a shard directory (`part-*` via nxvf-core discovery).
+/// `top_n` defaults to 20. `min_spearman` turns the report into a pass/fail gate.
+#[napi]
+pub fn fni_regression_report(
+    baseline_path: String,
+    candidate_path: String,
+    top_n: Option<u32>,
+    min_spearman: Option<f64>,
+) -> Result<FniRegressionReport> {
+    let baseline = load_score_records(&baseline_path).map_err(Error::from_reason)?;
+    let candidate = load_score_records(&candidate_path).map_err(Error::from_reason)?;
+    eprintln!(
+        "[RUST-FNI] regression: baseline={} candidate={}",
+        baseline.len(),
+        candidate.len()
+    );
+    Ok(build_report(
+        &baseline,
+        &candidate,
+        top_n.map(|n| n as usize).unwrap_or(DEFAULT_TOP_N),
+        min_spearman,
+    ))
+}
+
+pub(crate) fn build_report(
+    baseline: &[ScoreRecord],
+    candidate: &[ScoreRecord],
+    top_n: usize,
+    min_spearman: Option<f64>,
+) -> FniRegressionReport {
+    let base_pct = percentile_ranks(baseline);
+    let cand_pct = percentile_ranks(candidate);
+
+    let base_by_id: HashMap<&str, (usize, &ScoreRecord)> = baseline
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (r.id.as_str(), (i, r)))
+        .collect();
+
+    // Matched pairs in candidate order (deterministic for equal inputs).
+    let mut matched: Vec<ScoreMover> = Vec::new();
+    let mut matched_ids: HashSet<&str> = HashSet::new();
+    for (ci, c) in candidate.iter().enumerate() {
+        if let Some(&(bi, b)) = base_by_id.get(c.id.as_str()) {
+            matched_ids.insert(b.id.as_str());
+            // Candidate type wins: a reclassified entity is judged under its new type.
+            let entity_type = if c.entity_type.is_empty() { &b.entity_type } else { &c.entity_type };
+            matched.push(ScoreMover {
+                id: c.id.clone(),
+                entity_type: entity_type.clone(),
+                baseline_score: b.score,
+                candidate_score: c.score,
+                delta: round1(c.score - b.score),
+                baseline_percentile: base_pct[bi] as u32,
+                candidate_percentile: cand_pct[ci] as u32,
+            });
+        }
+    }
+    let matched_count = matched.len();
+    // Duplicate candidate ids can match one baseline row several times, so
+    // removals are counted against distinct matched baseline ids.
+    let removed_count = baseline.iter().filter(|b| !matched_ids.contains(b.id.as_str())).count();
+
+    // Global correlation
+    let xs: Vec<f64> = matched.iter().map(|m| m.baseline_score).collect();
+    let ys: Vec<f64> = matched.iter().map(|m| m.candidate_score).collect();
+    let spearman = spearman_rho(&xs, &ys);
+    let kendall_tau = kendall_tau_b(&xs, &ys);
+
+    // Per-type shift (BTreeMap → stable alphabetical output)
+    let mut groups: BTreeMap<&str, Vec<&ScoreMover>> = BTreeMap::new();
+    for m in &matched {
+        groups.entry(m.entity_type.as_str()).or_default().push(m);
+    }
+    let by_type = groups
+        .into_iter()
+        .map(|(t, ms)| {
+            let bx: Vec<f64> = ms.iter().map(|m| m.baseline_score).collect();
+            let cx: Vec<f64> = ms.iter().map(|m| m.candidate_score).collect();
+            let baseline_mean = mean(&bx);
+            let candidate_mean = mean(&cx);
+            TypeShift {
+                entity_type: t.to_string(),
+                count: ms.len() as u32,
+                baseline_mean: round1(baseline_mean),
+                candidate_mean: round1(candidate_mean),
+                mean_delta: round1(candidate_mean - baseline_mean),
+                baseline_p50: quantile(&bx, 0.5),
+                candidate_p50: quantile(&cx, 0.5),
+                baseline_p90: quantile(&bx, 0.9),
+                candidate_p90: quantile(&cx, 0.9),
+                spearman: spearman_rho(&bx, &cx),
+                kendall_tau: kendall_tau_b(&bx, &cx),
+                changed_count: ms.iter().filter(|m| m.delta != 0.0).count() as u32,
+            }
+        })
+        .collect();
+
+    let boundary_crossings = PERCENTILE_BOUNDARIES
+        .iter()
+        .map(|&b| {
+            let b = b as u32;
+            let mut promoted = 0u32;
+            let mut demoted = 0u32;
+            for m in &matched {
+                let was = m.baseline_percentile >= b;
+                let is = m.candidate_percentile >= b;
+                if !was && is {
+                    promoted += 1;
+                } else if was && !is {
+                    demoted += 1;
+                }
+            }
+            BoundaryCrossing { boundary: b, promoted, demoted }
+        })
+        .collect();
+
+    // Top movers: largest |delta| first, id as tie-break for determinism.
+    let mut movers: Vec<ScoreMover> = matched.into_iter().filter(|m| m.delta != 0.0).collect();
+    movers.sort_by(|a, b| {
+        b.delta
+            .abs()
+            .partial_cmp(&a.delta.abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    let (mut top_gainers, mut top_losers) = (Vec::new(), Vec::new());
+    for m in movers {
+        if m.delta > 0.0 && top_gainers.len() < top_n {
+            top_gainers.push(m);
+        } else if m.delta < 0.0 && top_losers.len() < top_n {
+            top_losers.push(m);
+        }
+        if top_gainers.len() >= top_n && top_losers.len() >= top_n {
+            break;
+        }
+    }
+
+    let gate_passed = min_spearman.map(|min| spearman >= min).unwrap_or(true);
+
+    FniRegressionReport {
+        baseline_count: baseline.len() as u32,
+        candidate_count: candidate.len() as u32,
+        matched_count: matched_count as u32,
+        added_count: (candidate.len() - matched_count) as u32,
+        removed_count: removed_count as u32,
+        spearman,
+        kendall_tau,
+        by_type,
+        boundary_crossings,
+        top_gainers,
+        top_losers,
+        gate_passed,
+    }
+}
+
+// ── Input Loading ───────────────────────────────────────────────────
+
+/// Load score records from a score file or a shard directory.
+pub(crate) fn load_score_records(path: &str) -> std::result::Result<Vec<ScoreRecord>, String> {
+    let mut out = Vec::new();
+    if std::path::Path::new(path).is_dir() {
+        nxvf_core::for_each_shard(path, |entities| {
+            out.extend(entities.iter().filter_map(record_from_value));
+            Ok(())
+        })?;
+        return Ok(out);
+    }
+
+    let data = std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let raw = nxvf_core::auto_decompress(&data)?;
+    let text = String::from_utf8_lossy(&raw);
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with("{\"entities\"") {
+        let sanitized = nxvf_core::sanitize_json_escapes(trimmed);
+        let val: serde_json::Value = serde_json::from_str(&sanitized)
+            .map_err(|e| format!("JSON parse error in {}: {}", path, e))?;
+        let arr = val
+            .as_array()
+            .or_else(|| val.get("entities").and_then(|v| v.as_array()))
+            .ok_or_else(|| format!("Unsupported score file shape: {}", path))?;
+        out.extend(arr.iter().filter_map(record_from_value));
+    } else {
+        // NDJSON (batch_calculate_fni_from_dir output)
+        for line in trimmed.lines().filter(|l| !l.trim().is_empty()) {
+            if let Ok(v) = serde_json::from_str::<serde_json::Value>(line) {
+                out.extend(record_from_value(&v));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn record_from_value(v: &serde_json::Value) -> Option<ScoreRecord> {
+    let id = v.get("id")?.as_str()?;
+    if id.is_empty() {
+        return None;
+    }
+    let score = v
+        .get("fni_score")
+        .and_then(|s| s.as_f64())
+        .or_else(|| v.get("fni").and_then(|s| s.as_f64()))
+        .unwrap_or(0.0);
+    let entity_type = v
+        .get("entity_type")
+        .or_else(|| v.get("type"))
+        .and_then(|t| t.as_str())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| infer_entity_type(id));
+    Some(ScoreRecord {
+        id: id.to_string(),
+        entity_type: entity_type.to_string(),
+        score,
+    })
+}
+
+/// Fallback type from the canonical id prefix (score outputs carry no type).
+/// Coarse subset of satellite-tasks mesh_graph `get_node_type`.
+fn infer_entity_type(id: &str) -> &'static str {
+    if id.starts_with("arxiv-") || id.starts_with("s2-") || id.contains("paper--") {
+        "paper"
+    } else if id.contains("model--") || id.starts_with("huggingface--") {
+        "model"
+    } else if id.contains("dataset--") {
+        "dataset"
+    } else if id.contains("space--") {
+        "space"
+    } else if id.contains("agent--") || id.starts_with("mcp-server--") {
+        "agent"
+    } else if id.contains("tool--") || id.starts_with("gh-repo--") {
+        "tool"
+    } else if id.contains("prompt--") {
+        "prompt"
+    } else {
+        "unknown"
+    }
+}
+
+// ── Statistics ──────────────────────────────────────────────────────
+
+/// Tie-aware percentile per record, index-aligned with `records`.
+/// Parity mirror of stream-aggregator `calculate_rankings`:
+/// effective_rank = first_desc_rank + (count_at_score - 1) / 2,
+/// percentile = max(1, round((1 - effective_rank / N) * 100)).
+pub(crate) fn percentile_ranks(records: &[ScoreRecord]) -> Vec<u8> {
+    let n = records.len();
+    let mut sorted: Vec<f64> = records.iter().map(|r| r.score).collect();
+    sorted.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    let mut first_rank: HashMap<u64, (usize, usize)> = HashMap::new();
+    for (i, s) in sorted.iter().enumerate() {
+        first_rank.entry(s.to_bits()).or_insert((i, 0)).1 += 1;
+    }
+    records
+        .iter()
+        .map(|r| {
+            let (rank, count) = first_rank.get(&r.score.to_bits()).copied().unwrap_or((0, 1));
+            let effective_rank = rank as f64 + (count as f64 - 1.0) / 2.0;
+            ((1.0 - effective_rank / n as f64) * 100.0).round().max(1.0) as u8
+        })
+        .collect()
+}
+
+/// Fractional (average-of-ties) ranks, 1-based.
+fn average_ranks(xs: &[f64]) -> Vec<f64> {
+    let mut idx: Vec<usize> = (0..xs.len()).collect();
+    idx.sort_by(|&a, &b| xs[a].partial_cmp(&xs[b]).unwrap_or(std::cmp::Ordering::Equal));
+    let mut ranks = vec![0.0; xs.len()];
+    let mut i = 0;
+    while i < idx.len() {
+        let mut j = i;
+        while j + 1 < idx.len() && xs[idx[j + 1]] == xs[idx[i]] {
+            j += 1;
+        }
+        let avg = (i + j) as f64 / 2.0 + 1.0;
+        for &k in &idx[i..=j] {
+            ranks[k] = avg;
+        }
+        i = j + 1;
+    }
+    ranks
+}
+
+/// Spearman ρ = Pearson correlation of average ranks (tie-correct).
+/// Returns 1.0 for degenerate inputs (n < 2 or a constant side) — no shift.
+pub(crate) fn spearman_rho(xs: &[f64], ys: &[f64]) -> f64 {
+    if xs.len() < 2 {
+        return 1.0;
+    }
+    let rx = average_ranks(xs);
+    let ry = average_ranks(ys);
+    let (mx, my) = (mean(&rx), mean(&ry));
+    let (mut cov, mut vx, mut vy) = (0.0, 0.0, 0.0);
+    for (a, b) in rx.iter().zip(&ry) {
+        cov += (a - mx) * (b - my);
+        vx += (a - mx) * (a - mx);
+        vy += (b - my) * (b - my);
+    }
+    if vx == 0.0 || vy == 0.0 {
+        return if vx == vy { 1.0 } else { 0.0 };
+    }
+    round4(cov / (vx * vy).sqrt())
+}
+
+/// Kendall τ-b in O(n log n) (Knight 1966): sort by (x, y), count discordant
+/// pairs as merge-sort swaps on y, correct for ties in x, y and joint ties.
+pub(crate) fn kendall_tau_b(xs: &[f64], ys: &[f64]) -> f64 {
+    let n = xs.len();
+    if n < 2 {
+        return 1.0;
+    }
+    let cmp = |a: f64, b: f64| a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal);
+    let mut pairs: Vec<(f64, f64)> = xs.iter().copied().zip(ys.iter().copied()).collect();
+    pairs.sort_by(|a, b| cmp(a.0, b.0).then_with(|| cmp(a.1, b.1)));
+
+    let n0 = (n * (n - 1) / 2) as f64;
+    let (mut n1, mut n3) = (0.0, 0.0); // ties in x, joint ties in (x, y)
+    let mut i = 0;
+    while i < n {
+        let mut j = i;
+        while j + 1 < n && pairs[j + 1].0 == pairs[i].0 {
+            j += 1;
+        }
+        let t = (j - i + 1) as f64;
+        n1 += t * (t - 1.0) / 2.0;
+        let mut k = i;
+        while k <= j {
+            let mut l = k;
+            while l < j && pairs[l + 1].1 == pairs[k].1 {
+                l += 1;
+            }
+            let u = (l - k + 1) as f64;
+            n3 += u * (u - 1.0) / 2.0;
+            k = l + 1;
+        }
+        i = j + 1;
+    }
+
+    let mut ys_sorted: Vec<f64> = pairs.iter().map(|p| p.1).collect();
+    let mut buf = vec![0.0; n];
+    let swaps = merge_count(&mut ys_sorted, &mut buf) as f64;
+
+    let mut n2 = 0.0; // ties in y
+    let mut i = 0;
+    while i < n {
+        let mut j = i;
+        while j + 1 < n && ys_sorted[j + 1] == ys_sorted[i] {
+            j += 1;
+        }
+        let t = (j - i + 1) as f64;
+        n2 += t * (t - 1.0) / 2.0;
+        i = j + 1;
+    }
+
+    let denom = ((n0 - n1) * (n0 - n2)).sqrt();
+    if denom == 0.0 {
+        return if n1 == n2 { 1.0 } else { 0.0 };
+    }
+    round4((n0 - n1 - n2 + n3 - 2.0 * swaps) / denom)
+}
+
+/// Bottom-up merge sort (ascending) returning the number of inversions.
+fn merge_count(a: &mut [f64], buf: &mut [f64]) -> u64 {
+    let n = a.len();
+    let mut swaps = 0u64;
+    let mut width = 1;
+    while width < n {
+        let mut lo = 0;
+        while lo < n {
+            let mid = (lo + width).min(n);
+            let hi = (lo + 2 * width).min(n);
+            let (mut i, mut j, mut k) = (lo, mid, lo);
+            while i < mid && j < hi {
+                if a[j] < a[i] {
+                    buf[k] = a[j];
+                    swaps += (mid - i) as u64;
+                    j += 1;
+                } else {
+                    buf[k] = a[i];
+                    i += 1;
+                }
+                k += 1;
+            }
+            buf[k..k + (mid - i)].copy_from_slice(&a[i..mid]);
+            k += mid - i;
+            buf[k..k + (hi - j)].copy_from_slice(&a[j..hi]);
+            lo = hi;
+        }
+        a.copy_from_slice(buf);
+        width *= 2;
+    }
+    swaps
+}
+
+fn mean(xs: &[f64]) -> f64 {
+    if xs.is_empty() {
+        return 0.0;
+    }
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+/// Nearest-rank quantile (q in 0..=1) over an unsorted slice.
+fn quantile(xs: &[f64], q: f64) -> f64 {
+    if xs.is_empty() {
+        return 0.0;
+    }
+    let mut s = xs.to_vec();
+    s.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let idx = ((q * s.len() as f64).ceil() as usize).clamp(1, s.len()) - 1;
+    s[idx]
+}
+
+fn round1(x: f64) -> f64 {
+    (x * 10.0).round() / 10.0
+}
+
+fn round4(x: f64) -> f64 {
+    (x * 10_000.0).round() / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(id: &str, t: &str, score: f64) -> ScoreRecord {
+        ScoreRecord { id: id.to_string(), entity_type: t.to_string(), score }
+    }
+
+    #[test]
+    fn test_percentile_parity_with_calculate_rankings() {
+        // Same fixtures as stream-aggregator percentile tests.
+        let r = percentile_ranks(&[rec("a", "", 90.0), rec("b", "", 50.0), rec("c", "", 10.0)]);
+        assert_eq!(r, vec![100, 67, 33]);
+        let r = percentile_ranks(&[
+            rec("a", "", 100.0),
+            rec("b", "", 0.0),
+            rec("c", "", 0.0),
+            rec("d", "", 0.0),
+        ]);
+        assert_eq!(r, vec![100, 50, 50, 50]);
+    }
+
+    #[test]
+    fn test_rank_correlation_extremes() {
+        let xs = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let rev = [5.0, 4.0, 3.0, 2.0, 1.0];
+        assert_eq!(spearman_rho(&xs, &xs), 1.0);
+        assert_eq!(kendall_tau_b(&xs, &xs), 1.0);
+        assert_eq!(spearman_rho(&xs, &rev), -1.0);
+        assert_eq!(kendall_tau_b(&xs, &rev), -1.0);
+    }
+
+    #[test]
+    fn test_kendall_matches_naive_with_ties() {
+        let xs = [1.0, 2.0, 2.0, 3.0, 4.0, 4.0, 5.0];
+        let ys = [2.0, 1.0, 3.0, 3.0, 5.0, 4.0, 4.0];
+        // Naive O(n^2) tau-b reference.
+        let (mut c, mut d, mut tx, mut ty) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+        for i in 0..xs.len() {
+            for j in i + 1..xs.len() {
+                let dx = xs[i] - xs[j];
+                let dy = ys[i] - ys[j];
+                if dx == 0.0 && dy == 0.0 {
+                } else if dx == 0.0 {
+                    tx += 1.0;
+                } else if dy == 0.0 {
+                    ty += 1.0;
+                } else if dx * dy > 0.0 {
+                    c += 1.0;
+                } else {
+                    d += 1.0;
+                }
+            }
+        }
+        let naive = (c - d) / ((c + d + tx) * (c + d + ty)).sqrt();
+        assert!((kendall_tau_b(&xs, &ys) - naive).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_report_movers_and_crossings() {
+        let baseline = vec![
+            rec("hf-model--a", "model", 80.0),
+            rec("hf-model--b", "model", 60.0),
+            rec("arxiv-paper--c", "paper", 40.0),
+            rec("arxiv-paper--d", "paper", 20.0),
+            rec("gone", "model", 10.0),
+        ];
+        let candidate = vec![
+            rec("hf-model--a", "model", 50.0),
+            rec("hf-model--b", "model", 60.0),
+            rec("arxiv-paper--c", "paper", 70.0),
+            rec("arxiv-paper--d", "paper", 20.0),
+            rec("new", "model", 5.0),
+        ];
+        let report = build_report(&baseline, &candidate, 5, Some(0.9));
+        assert_eq!(report.matched_count, 4);
+        assert_eq!(report.added_count, 1);
+        assert_eq!(report.removed_count, 1);
+        assert_eq!(report.top_gainers[0].id, "arxiv-paper--c");
+        assert_eq!(report.top_gainers[0].delta, 30.0);
+        assert_eq!(report.top_losers[0].id, "hf-model--a");
+        assert_eq!(report.by_type.len(), 2);
+        assert_eq!(report.by_type[0].entity_type, "model");
+        // a: 100 → 60 and c: 60 → 100 swap sides of the 75 boundary.
+        let b75 = report.boundary_crossings.iter().find(|b| b.boundary == 75).unwrap();
+        assert_eq!((b75.promoted, b75.demoted), (1, 1));
+        assert!(!report.gate_passed, "rank swap must fail a 0.9 Spearman gate");
+    }
+
+    #[test]
+    fn test_identical_runs_pass_gate() {
+        let runs: Vec<ScoreRecord> = (0..50)
+            .map(|i| rec(&format!("hf-model--m{i}"), "model", i as f64))
+            .collect();
+        let report = build_report(&runs, &runs, 5, Some(0.999));
+        assert!(report.gate_passed);
+        assert!(report.top_gainers.is_empty() && report.top_losers.is_empty());
+        assert!(report.boundary_crossings.iter().all(|b| b.promoted == 0 && b.demoted == 0));
+    }
+
+    #[test]
+    fn test_duplicate_candidate_ids_do_not_underflow() {
+        let baseline = vec![rec("a", "model", 10.0), rec("gone", "model", 5.0)];
+        let candidate = vec![
+            rec("a", "model", 10.0),
+            rec("a", "model", 12.0),
+            rec("a", "model", 14.0),
+        ];
+        let report = build_report(&baseline, &candidate, 5, None);
+        assert_eq!(report.matched_count, 3);
+        assert_eq!(report.added_count, 0);
+        assert_eq!(report.removed_count, 1);
+    }
+}
@@ -0,0 +1,240 @@
+//! V26.6 Shard-directory rendering — one FFI call instead of one per entity.
+//!
+//! One shard at a time: nxvf-core streams entities and only (umid, source
+//! text) is kept → `render_document` in parallel on a rayon pool → records
+//! written in input order to `<output_dir>/rendered.ndjson.zst`:
+//!   {"umid": "..", "html": "..", "toc": [..], "word_count": N}
+//! The sanitiser policy is built once and shared by all workers. A panic
+//! while rendering one entity is caught and counted, not fatal; a shard that
+//! fails to decode part-way keeps the entities read before the error. The
+//! sidecar is written under `<name>.tmp` and renamed once complete, so a
+//! failed or killed run never leaves a truncated file behind.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use rayon::prelude::*;
+use serde_json::{json, Value};
+
+use crate::document::{self, DocumentOptions, TocEntry};
+use crate::policy::Policy;
+
+const OUTPUT_FILE: &str = "rendered.ndjson.zst";
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+/// Same fallback order as v25-distiller.js `rawReadme`.
+const DEFAULT_SOURCE_FIELDS: &[&str] = &["readme", "html_readme", "body_content", "content", "description"];
+
+#[napi(object)]
+#[derive(Default)]
+pub struct RenderBatchOptions {
+    /// Entity fields tried in order; the first non-empty string is rendered
+    /// (default readme, html_readme, body_content, content, description).
+    pub source_fields: Option<Vec<String>>,
+    /// Sanitiser policy + reading speed, as for `render_document`.
+    pub document: Option<DocumentOptions>,
+    /// Worker threads (default: rayon global pool = logical CPUs).
+    pub threads: Option<u32>,
+    pub zstd_level: Option<i32>,
+}
+
+#[napi(object)]
+pub struct ShardRenderStats {
+    /// Shard file name.
+    pub part: String,
+    pub entity_count: u32,
+    pub rendered_count: u32,
+    /// Entities without an id/umid or whose render panicked.
+    pub error_count: u32,
+    /// Decode error that cut the shard short, if any.
+    pub parse_error: Option<String>,
+    pub duration_ms: u32,
+}
+
+#[napi(object)]
+pub struct RenderBatchStats {
+    pub entity_count: u32,
+    pub rendered_count: u32,
+    pub error_count: u32,
+    pub shard_count: u32,
+    pub skipped_shard_count: u32,
+    pub duration_ms: u32,
+    pub output_file: String,
+    pub shards: Vec<ShardRenderStats>,
+}
+
+fn toc_json(toc: &[TocEntry]) -> Value {
+    Value::Array(
+        toc.iter()
+            .map(|t| json!({"level": t.level, "text": t.text, "anchor": t.anchor, "children": toc_json(&t.children)}))
+            .collect(),
+    )
+}
+
+pub struct RenderShardDirTask {
+    pub(crate) shard_dir: String,
+    pub(crate) output_dir: String,
+    pub(crate) options: RenderBatchOptions,
+}
+
+impl napi::Task for RenderShardDirTask {
+    type Output = RenderBatchStats;
+    type JsValue = RenderBatchStats;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let start = std::time::Instant::now();
+        let opts = std::mem::take(&mut self.options);
+        let doc_opts = opts.document.unwrap_or_default();
+        let policy = document::document_policy(&doc_opts).map_err(Error::from_reason)?;
+        let fields: Vec<String> = opts
+            .source_fields
+            .unwrap_or_else(|| DEFAULT_SOURCE_FIELDS.iter().map(|f| f.to_string()).collect());
+
+        let pool = match opts.threads {
+            Some(n) if n > 0 => Some(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(n as usize)
+                    .build()
+                    .map_err(|e| Error::from_reason(format!("thread pool: {e}")))?,
+            ),
+            _ => None,
+        };
+
+        let output_dir = &self.output_dir;
+        std::fs::create_dir_all(output_dir)
+            .map_err(|e| Error::from_reason(format!("create output dir: {e}")))?;
+        let out_path = Path::new(output_dir).join(OUTPUT_FILE);
+        let tmp_path = Path::new(output_dir).join(format!("{OUTPUT_FILE}.tmp"));
+        let shard_files = nxvf_core::discover_shards(&self.shard_dir).map_err(Error::from_reason)?;
+
+        let file = File::create(&tmp_path).map_err(|e| Error::from_reason(format!("create output: {e}")))?;
+        let written = write_sidecar(file, &shard_files, &fields, &policy, &doc_opts, pool.as_ref(), opts.zstd_level);
+        let (shards, skipped) = match written {
+            Ok(w) => w,
+            Err(e) => {
+                std::fs::remove_file(&tmp_path).ok();
+                return Err(e);
+            }
+        };
+        std::fs::rename(&tmp_path, &out_path).map_err(|e| Error::from_reason(format!("rename output: {e}")))?;
+
+        let stats = RenderBatchStats {
+            entity_count: shards.iter().map(|s| s.entity_count).sum(),
+            rendered_count: shards.iter().map(|s| s.rendered_count).sum(),
+            error_count: shards.iter().map(|s| s.error_count).sum(),
+            shard_count: shard_files.len() as u32,
+            skipped_shard_count: skipped,
+            duration_ms: start.elapsed().as_millis() as u32,
+            output_file: out_path.to_string_lossy().into_owned(),
+            shards,
+        };
+        eprintln!(
+            "[RUST-RENDER] {} entities, {} rendered, {} errors across {} shards in {}ms",
+            stats.entity_count, stats.rendered_count, stats.error_count, stats.shard_count, stats.duration_ms
+        );
+        Ok(stats)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Render a shard directory's Markdown to a Zstd NDJSON sidecar (see module
+/// docs). AsyncTask: runs on a libuv worker thread and returns a Promise, so
+/// the event loop stays free for the whole run.
+#[napi]
+pub fn render_shard_dir(
+    shard_dir: String,
+    output_dir: String,
+    options: Option<RenderBatchOptions>,
+) -> AsyncTask<RenderShardDirTask> {
+    AsyncTask::new(RenderShardDirTask { shard_dir, output_dir, options: options.unwrap_or_default() })
+}
+
+/// Render every shard into `file` (the temp sidecar) and fsync it.
+/// Returns per-shard stats and the number of shards skipped entirely.
+fn write_sidecar(
+    file: File,
+    shard_files: &[String],
+    fields: &[String],
+    policy: &Policy,
+    doc_opts: &DocumentOptions,
+    pool: Option<&rayon::ThreadPool>,
+    zstd_level: Option<i32>,
+) -> Result<(Vec<ShardRenderStats>, u32)> {
+    let mut enc = zstd::Encoder::new(BufWriter::new(file), zstd_level.unwrap_or(DEFAULT_ZSTD_LEVEL))
+        .map_err(|e| Error::from_reason(format!("zstd init: {e}")))?;
+    let mut shards = Vec::with_capacity(shard_files.len());
+    let mut skipped = 0u32;
+    for path in shard_files {
+        let shard_start = std::time::Instant::now();
+        let (mut entity_count, mut error_count) = (0u32, 0u32);
+        let mut inputs: Vec<(String, String)> = Vec::new();
+        let res = nxvf_core::for_each_entity_in_file(path, |e| {
+            entity_count += 1;
+            let text = fields.iter().find_map(|f| e.get(f).and_then(Value::as_str).filter(|s| !s.trim().is_empty()));
+            let Some(text) = text else { return Ok(()) };
+            let umid = match (e.get("umid").and_then(Value::as_str), e.get("id").and_then(Value::as_str)) {
+                (Some(u), _) if !u.is_empty() => u.to_string(),
+                (_, Some(id)) if !id.is_empty() => nxvf_core::sha256_hex16(id),
+                _ => {
+                    error_count += 1;
+                    return Ok(());
+                }
+            };
+            inputs.push((umid, text.to_string()));
+            Ok(())
+        });
+        let parse_error = res.err();
+        if let Some(e) = &parse_error {
+            eprintln!("[RUST-RENDER] Shard {} cut short: {}", path, e);
+            if entity_count == 0 {
+                skipped += 1;
+            }
+        }
+
+        let render_all = || -> Vec<Option<String>> {
+            inputs
+                .par_iter()
+                .map(|(umid, text)| {
+                    let doc = catch_unwind(AssertUnwindSafe(|| document::render_with(policy, text, doc_opts.words_per_minute))).ok()?;
+                    Some(
+                        json!({"umid": umid, "html": doc.html, "toc": toc_json(&doc.toc), "word_count": doc.metadata.word_count})
+                            .to_string(),
+                    )
+                })
+                .collect()
+        };
+        let lines = match pool {
+            Some(p) => p.install(render_all),
+            None => render_all(),
+        };
+        let mut rendered_count = 0u32;
+        for line in lines {
+            match line {
+                Some(line) => {
+                    writeln!(enc, "{line}").map_err(|e| Error::from_reason(format!("write: {e}")))?;
+                    rendered_count += 1;
+                }
+                None => error_count += 1,
+            }
+        }
+        shards.push(ShardRenderStats {
+            part: Path::new(path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            entity_count,
+            rendered_count,
+            error_count,
+            parse_error,
+            duration_ms: shard_start.elapsed().as_millis() as u32,
+        });
+    }
+    enc.finish()
+        .and_then(|w| w.into_inner().map_err(|e| e.into_error()))
+        .and_then(|f| f.sync_all())
+        .map_err(|e| Error::from_reason(format!("finish output: {e}")))?;
+    Ok((shards, skipped))
+}
@@ -0,0 +1,204 @@
+//! V26.6 `render_document` — HTML + heading anchors + TOC + metadata.
+//!
+//! One pulldown-cmark pass collects heading text, code-block languages and
+//! word counts; heading `id`s are then set on the event stream before HTML
+//! rendering, so JS no longer re-parses `render_html` output. Slugs follow
+//! GitHub's scheme (lowercase, punctuation dropped, spaces → `-`, `-1`, `-2`
+//! for repeats) so existing `#anchor` links in READMEs keep working.
+//! YAML front matter is stripped before rendering and returned as JSON.
+
+use crate::frontmatter;
+use crate::policy::{Policy, SanitizeOptions};
+use napi_derive::napi;
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Tag, TagEnd};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+const DEFAULT_WORDS_PER_MINUTE: u32 = 200;
+
+static RE_IMG_SRC: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"<img\s[^>]*?src="([^"]*)""#).unwrap());
+
+#[napi(object)]
+#[derive(Default)]
+pub struct DocumentOptions {
+    pub sanitize: Option<SanitizeOptions>,
+    /// Reading speed for `reading_time_minutes` (default 200).
+    pub words_per_minute: Option<u32>,
+}
+
+#[napi(object)]
+pub struct TocEntry {
+    /// 1-6
+    pub level: u32,
+    pub text: String,
+    /// Heading `id` (without `#`).
+    pub anchor: String,
+    pub children: Vec<TocEntry>,
+}
+
+#[napi(object)]
+pub struct DocumentMetadata {
+    /// First image in the rendered HTML (after link/image rewriting).
+    pub first_image: Option<String>,
+    /// Prose words (fenced/indented code blocks excluded).
+    pub word_count: u32,
+    pub reading_time_minutes: u32,
+    /// Fenced code languages in order of first appearance, lowercase.
+    pub code_languages: Vec<String>,
+    /// YAML front matter as a JSON string; None when absent or unparseable.
+    pub front_matter: Option<String>,
+    /// Parser message when a front matter block was present but invalid.
+    pub front_matter_error: Option<String>,
+}
+
+#[napi(object)]
+pub struct RenderedDocument {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+    pub metadata: DocumentMetadata,
+}
+
+/// GitHub-style heading slug.
+pub(crate) fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Unique anchors: repeats get `-1`, `-2`, ...; empty slugs become `section`.
+struct Slugger(HashMap<String, u32>);
+
+impl Slugger {
+    fn slug(&mut self, text: &str) -> String {
+        let base = match slugify(text) {
+            s if s.is_empty() => "section".to_string(),
+            s => s,
+        };
+        let mut candidate = base.clone();
+        while self.0.contains_key(&candidate) {
+            let n = self.0.entry(base.clone()).or_default();
+            *n += 1;
+            candidate = format!("{base}-{n}");
+        }
+        self.0.insert(candidate.clone(), 0);
+        candidate
+    }
+}
+
+fn words_in(text: &str) -> u32 {
+    text.split_whitespace().filter(|w| w.chars().any(char::is_alphanumeric)).count() as u32
+}
+
+/// Nest flat (level, text, anchor) headings under the nearest shallower one.
+fn toc_tree(flat: Vec<(u32, String, String)>) -> Vec<TocEntry> {
+    fn attach(list: &mut Vec<TocEntry>, entry: TocEntry) {
+        match list.last_mut() {
+            Some(last) if last.level < entry.level => attach(&mut last.children, entry),
+            _ => list.push(entry),
+        }
+    }
+    let mut root = Vec::new();
+    for (level, text, anchor) in flat {
+        attach(&mut root, TocEntry { level, text, anchor, children: Vec::new() });
+    }
+    root
+}
+
+/// Sanitiser policy for `render_with`: the options' policy + heading ids.
+pub(crate) fn document_policy(options: &DocumentOptions) -> Result<Policy, String> {
+    let mut policy = Policy::from_options(&options.sanitize.clone().unwrap_or_default())?;
+    policy.allow_heading_ids();
+    Ok(policy)
+}
+
+pub(crate) fn render(raw_markdown: &str, options: &DocumentOptions) -> Result<RenderedDocument, String> {
+    let policy = document_policy(options)?;
+    Ok(render_with(&policy, raw_markdown, options.words_per_minute))
+}
+
+/// Render with a prebuilt `document_policy` (shared across batch workers).
+pub(crate) fn render_with(policy: &Policy, raw_markdown: &str, words_per_minute: Option<u32>) -> RenderedDocument {
+    let (body, front_matter, front_matter_error) = match frontmatter::split_front_matter(raw_markdown) {
+        Some((yaml, body)) => match frontmatter::parse_yaml(yaml) {
+            Ok(v) => (body, Some(v.to_string()), None),
+            Err(e) => (body, None, Some(e)),
+        },
+        None => (raw_markdown, None, None),
+    };
+
+    let mut events: Vec<Event> = crate::markdown_events(body, policy.math).collect();
+
+    let mut slugger = Slugger(HashMap::new());
+    let mut flat = Vec::new();
+    let mut languages: Vec<String> = Vec::new();
+    let mut word_count = 0u32;
+    // (start event index, explicit `{#id}` if any, text so far)
+    let mut heading: Option<(usize, Option<String>, String)> = None;
+    let mut anchors: Vec<(usize, String)> = Vec::new();
+    let mut in_code_block = false;
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::Heading { id, .. }) => heading = Some((i, id.as_ref().map(|s| s.to_string()), String::new())),
+            Event::End(TagEnd::Heading(level)) => {
+                if let Some((start, explicit, text)) = heading.take() {
+                    let text = text.trim().to_string();
+                    let anchor = explicit.unwrap_or_else(|| slugger.slug(&text));
+                    anchors.push((start, anchor.clone()));
+                    flat.push((*level as u32, text, anchor));
+                }
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code_block = true;
+                if let CodeBlockKind::Fenced(info) = kind {
+                    let lang = info.split([' ', ',', '{']).next().unwrap_or("").to_lowercase();
+                    if !lang.is_empty() && !languages.contains(&lang) {
+                        languages.push(lang);
+                    }
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(t) | Event::Code(t) => {
+                if let Some((_, _, h)) = heading.as_mut() {
+                    h.push_str(t);
+                }
+                if !in_code_block {
+                    word_count += words_in(t);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (start, anchor) in anchors {
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+            *id = Some(CowStr::from(anchor));
+        }
+    }
+
+    let mut html_buf = String::with_capacity(body.len() * 2);
+    html::push_html(&mut html_buf, crate::enhance(events.into_iter(), policy).into_iter());
+    let html = policy.clean(&html_buf);
+    let first_image = RE_IMG_SRC.captures(&html).map(|c| c[1].replace("&amp;", "&"));
+    let wpm = words_per_minute.unwrap_or(DEFAULT_WORDS_PER_MINUTE).max(1);
+
+    RenderedDocument {
+        html,
+        toc: toc_tree(flat),
+        metadata: DocumentMetadata {
+            first_image,
+            word_count,
+            reading_time_minutes: word_count.div_ceil(wpm),
+            code_languages: languages,
+            front_matter,
+            front_matter_error,
+        },
+    }
+}
@@ -0,0 +1,517 @@
+//! V26.6 YAML front matter — split + a tolerant YAML-subset parser → JSON.
+//!
+//! Covers what README/model-card front matter actually uses: block mappings
+//! and sequences (including `- key: v` items and sequences at the parent
+//! key's indent), flow `[..]` / `{..}` collections (may span lines), quoted
+//! and plain scalars, `|` / `>` block scalars, comments, and null/bool/number
+//! resolution per the YAML 1.2 core schema. Tags (`!!str`) and anchors are
+//! dropped; aliases stay literal strings. Anything else is an `Err`, never a
+//! panic or a hang: stray closers and empty entries in flow collections
+//! (`[}]`, `{]}`, `[,]`) are rejected rather than skipped, and block or flow
+//! nesting past `MAX_DEPTH` is an error instead of a stack overflow.
+
+use serde_json::{Map, Number, Value};
+
+/// Deepest block or flow nesting accepted; real front matter stays under 10.
+const MAX_DEPTH: usize = 64;
+
+/// Split `---\n<yaml>\n---` (or `...`) off the start of `text`.
+/// Returns (yaml, body); None when there is no closed front matter block.
+pub(crate) fn split_front_matter(text: &str) -> Option<(&str, &str)> {
+    let t = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let first = t.split_inclusive('\n').next()?;
+    if first.trim_end() != "---" {
+        return None;
+    }
+    let yaml_start = first.len();
+    let mut pos = yaml_start;
+    for line in t[yaml_start..].split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Some((&t[yaml_start..pos], &t[pos + line.len()..]));
+        }
+        pos += line.len();
+    }
+    None
+}
+
+struct Line {
+    indent: usize,
+    text: String,
+    /// Raw line (after the indent), kept for block scalars.
+    raw: String,
+    blank: bool,
+}
+
+/// Cut a trailing `# comment` that is outside quotes and preceded by space.
+fn strip_comment(s: &str) -> &str {
+    let (mut single, mut double, mut prev_space) = (false, false, true);
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' if double => escaped = !escaped,
+            '"' if !single && !escaped => double = !double,
+            '\'' if !double => single = !single,
+            '#' if !single && !double && prev_space => return s[..i].trim_end(),
+            _ => escaped = false,
+        }
+        if c != '\\' {
+            escaped = false;
+        }
+        prev_space = c == ' ' || c == '\t';
+    }
+    s.trim_end()
+}
+
+fn lines(src: &str) -> Result<Vec<Line>, String> {
+    let mut out = Vec::new();
+    for (n, raw) in src.lines().enumerate() {
+        let indent = raw.len() - raw.trim_start_matches(' ').len();
+        if raw[indent..].starts_with('\t') {
+            return Err(format!("line {}: tab indentation", n + 1));
+        }
+        let text = strip_comment(&raw[indent..]).to_string();
+        out.push(Line { indent, blank: text.is_empty(), text, raw: raw[indent..].to_string() });
+    }
+    Ok(out)
+}
+
+/// Parse a YAML document (subset) into JSON.
+pub(crate) fn parse_yaml(src: &str) -> Result<Value, String> {
+    let mut p = Parser { lines: lines(src)?, i: 0, depth: 0 };
+    p.skip_blank();
+    if p.i >= p.lines.len() {
+        return Ok(Value::Null);
+    }
+    let indent = p.lines[p.i].indent;
+    let v = p.block(indent)?;
+    p.skip_blank();
+    if p.i < p.lines.len() {
+        return Err(format!("line {}: unexpected content {:?}", p.i + 1, p.lines[p.i].text));
+    }
+    Ok(v)
+}
+
+struct Parser {
+    lines: Vec<Line>,
+    i: usize,
+    depth: usize,
+}
+
+fn is_seq_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Byte index of the `:` separating a mapping key, outside quotes/brackets.
+fn key_colon(text: &str) -> Option<usize> {
+    let (mut single, mut double, mut depth) = (false, false, 0i32);
+    let bytes = text.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'"' if !single && (i == 0 || bytes[i - 1] != b'\\') => double = !double,
+            b'\'' if !double => single = !single,
+            b'[' | b'{' if !single && !double => depth += 1,
+            b']' | b'}' if !single && !double => depth -= 1,
+            b':' if !single && !double && depth == 0 && (i + 1 == bytes.len() || bytes[i + 1] == b' ') => {
+                return Some(i);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+impl Parser {
+    fn skip_blank(&mut self) {
+        while self.i < self.lines.len() && self.lines[self.i].blank {
+            self.i += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<&Line> {
+        self.skip_blank();
+        self.lines.get(self.i)
+    }
+
+    fn block(&mut self, indent: usize) -> Result<Value, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("line {}: nesting too deep", self.i + 1));
+        }
+        self.depth += 1;
+        let v = match self.peek() {
+            Some(l) if is_seq_item(&l.text) => self.sequence(indent),
+            Some(_) => self.mapping(indent),
+            None => Ok(Value::Null),
+        };
+        self.depth -= 1;
+        v
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Value, String> {
+        let mut items = Vec::new();
+        while let Some(l) = self.peek() {
+            if l.indent != indent || !is_seq_item(&l.text) {
+                break;
+            }
+            let rest = l.text[1..].trim_start().to_string();
+            let rest_raw = l.raw[1..].trim_start().to_string();
+            if rest.is_empty() {
+                self.i += 1;
+                items.push(self.child(indent)?);
+                continue;
+            }
+            if rest.starts_with('|') || rest.starts_with('>') {
+                self.i += 1;
+                items.push(self.block_scalar(indent, &rest));
+            } else if is_seq_item(&rest) || key_colon(&rest).is_some() {
+                // Re-read the item body as a block at its own column.
+                let col = indent + (l.text.len() - rest.len());
+                self.lines[self.i] = Line { indent: col, text: rest, raw: rest_raw, blank: false };
+                items.push(self.block(col)?);
+            } else {
+                self.i += 1;
+                items.push(self.inline(indent, rest)?);
+            }
+        }
+        Ok(Value::Array(items))
+    }
+
+    /// Value of a key/item with nothing after the indicator on its own line.
+    fn child(&mut self, indent: usize) -> Result<Value, String> {
+        match self.peek() {
+            Some(l) if l.indent > indent => {
+                let i = l.indent;
+                self.block(i)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Value, String> {
+        let mut map = Map::new();
+        loop {
+            self.skip_blank();
+            let line_no = self.i + 1;
+            let Some(l) = self.lines.get(self.i) else { break };
+            if l.indent < indent {
+                break;
+            }
+            if l.indent > indent {
+                return Err(format!("line {line_no}: unexpected indentation"));
+            }
+            if is_seq_item(&l.text) {
+                break;
+            }
+            let text = l.text.clone();
+            let Some(colon) = key_colon(&text) else {
+                return Err(format!("line {line_no}: expected `key: value`, got {text:?}"));
+            };
+            let key = match parse_scalar(text[..colon].trim())? {
+                Value::String(s) => s,
+                Value::Null => "null".to_string(),
+                other => other.to_string(),
+            };
+            let value = text[colon + 1..].trim().to_string();
+            self.i += 1;
+            let v = if value.is_empty() {
+                // YAML allows a sequence at the parent key's own indent.
+                match self.peek() {
+                    Some(n) if n.indent == indent && is_seq_item(&n.text) => self.sequence(indent)?,
+                    _ => self.child(indent)?,
+                }
+            } else if value.starts_with('|') || value.starts_with('>') {
+                self.block_scalar(indent, &value)
+            } else {
+                self.inline(indent, value)?
+            };
+            map.insert(key, v);
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// `|` keeps newlines, `>` folds them; `-` strips the final newline.
+    fn block_scalar(&mut self, indent: usize, header: &str) -> Value {
+        let mut body: Vec<String> = Vec::new();
+        let mut block_indent = None;
+        while let Some(l) = self.lines.get(self.i) {
+            if !l.blank && l.indent <= indent {
+                break;
+            }
+            if l.blank {
+                body.push(String::new());
+            } else {
+                let bi = *block_indent.get_or_insert(l.indent);
+                body.push(format!("{}{}", " ".repeat(l.indent.saturating_sub(bi)), l.raw));
+            }
+            self.i += 1;
+        }
+        while body.last().is_some_and(|s| s.is_empty()) {
+            body.pop();
+        }
+        let mut s = if header.starts_with('>') {
+            body.join("\n").split("\n\n").map(|p| p.replace('\n', " ")).collect::<Vec<_>>().join("\n")
+        } else {
+            body.join("\n")
+        };
+        if !header.contains('-') && !s.is_empty() {
+            s.push('\n');
+        }
+        Value::String(s)
+    }
+
+    /// Inline value; flow collections may continue on following lines and
+    /// plain scalars may fold more-indented continuation lines.
+    fn inline(&mut self, indent: usize, mut value: String) -> Result<Value, String> {
+        if value.starts_with('[') || value.starts_with('{') {
+            while !flow_balanced(&value) {
+                match self.peek() {
+                    Some(l) => {
+                        value.push(' ');
+                        value.push_str(&l.text);
+                        self.i += 1;
+                    }
+                    None => return Err("unterminated flow collection".to_string()),
+                }
+            }
+        } else if !value.starts_with('"') && !value.starts_with('\'') {
+            while let Some(l) = self.peek() {
+                if l.indent <= indent || key_colon(&l.text).is_some() || is_seq_item(&l.text) {
+                    break;
+                }
+                value.push(' ');
+                value.push_str(&l.text);
+                self.i += 1;
+            }
+        }
+        let mut f = Flow { s: value.as_bytes(), i: 0, depth: 0 };
+        let v = f.value()?;
+        f.ws();
+        if f.i != f.s.len() {
+            return Err(format!("trailing characters after value {value:?}"));
+        }
+        Ok(v)
+    }
+}
+
+fn flow_balanced(s: &str) -> bool {
+    let (mut depth, mut single, mut double) = (0i32, false, false);
+    let bytes = s.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'"' if !single && (i == 0 || bytes[i - 1] != b'\\') => double = !double,
+            b'\'' if !double => single = !single,
+            b'[' | b'{' if !single && !double => depth += 1,
+            b']' | b'}' if !single && !double => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0 && !single && !double
+}
+
+/// Scalar resolution (YAML 1.2 core schema) for an unquoted or quoted token.
+fn parse_scalar(token: &str) -> Result<Value, String> {
+    let mut f = Flow { s: token.as_bytes(), i: 0, depth: 0 };
+    if token.starts_with('"') || token.starts_with('\'') {
+        return f.value();
+    }
+    Ok(plain_scalar(token))
+}
+
+fn plain_scalar(token: &str) -> Value {
+    let mut t = token.trim();
+    // Drop tags and anchors; keep the value.
+    while t.starts_with('!') || t.starts_with('&') {
+        t = t.split_once(' ').map(|(_, r)| r.trim_start()).unwrap_or("");
+    }
+    match t {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+    let numeric = t.trim_start_matches(['-', '+']);
+    if !numeric.is_empty() && numeric.bytes().all(|b| b.is_ascii_digit()) {
+        if let Ok(n) = t.parse::<i64>() {
+            return Value::Number(n.into());
+        }
+    }
+    if t.bytes().any(|b| b.is_ascii_digit())
+        && t.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'-' | b'+'))
+    {
+        if let Some(n) = t.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Value::Number(n);
+        }
+    }
+    Value::String(t.to_string())
+}
+
+/// Flow-style value parser (`[..]`, `{..}`, quoted and plain scalars).
+struct Flow<'a> {
+    s: &'a [u8],
+    i: usize,
+    depth: usize,
+}
+
+impl Flow<'_> {
+    fn ws(&mut self) {
+        while self.i < self.s.len() && (self.s[self.i] == b' ' || self.s[self.i] == b'\t') {
+            self.i += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.ws();
+        match self.s.get(self.i) {
+            Some(b'[' | b'{') => self.collection(),
+            Some(b'"') => self.double().map(Value::String),
+            Some(b'\'') => self.single().map(Value::String),
+            _ => Ok(plain_scalar(&self.plain(false))),
+        }
+    }
+
+    /// Plain token; inside flow collections it stops at `,` `]` `}` (and `:`
+    /// followed by space when reading a key).
+    fn plain(&mut self, key: bool) -> String {
+        let start = self.i;
+        let in_flow = self.s.first().is_some_and(|b| *b == b'[' || *b == b'{');
+        while self.i < self.s.len() {
+            let b = self.s[self.i];
+            if in_flow && matches!(b, b',' | b']' | b'}') {
+                break;
+            }
+            if key && b == b':' && self.s.get(self.i + 1).is_none_or(|n| *n == b' ') {
+                break;
+            }
+            self.i += 1;
+        }
+        String::from_utf8_lossy(&self.s[start..self.i]).trim().to_string()
+    }
+
+    fn double(&mut self) -> Result<String, String> {
+        self.i += 1;
+        let mut out = Vec::new();
+        while let Some(&b) = self.s.get(self.i) {
+            self.i += 1;
+            match b {
+                b'"' => return String::from_utf8(out).map_err(|e| e.to_string()),
+                b'\\' => {
+                    let Some(&e) = self.s.get(self.i) else { break };
+                    self.i += 1;
+                    match e {
+                        b'n' => out.push(b'\n'),
+                        b't' => out.push(b'\t'),
+                        b'r' => out.push(b'\r'),
+                        b'0' => out.push(0),
+                        b'u' => {
+                            let hex = self.s.get(self.i..self.i + 4).ok_or("bad \\u escape")?;
+                            let code = u32::from_str_radix(std::str::from_utf8(hex).unwrap_or(""), 16)
+                                .map_err(|_| "bad \\u escape".to_string())?;
+                            let c = char::from_u32(code).unwrap_or('\u{fffd}');
+                            out.extend_from_slice(c.to_string().as_bytes());
+                            self.i += 4;
+                        }
+                        other => out.push(other),
+                    }
+                }
+                other => out.push(other),
+            }
+        }
+        Err("unterminated double-quoted string".to_string())
+    }
+
+    fn single(&mut self) -> Result<String, String> {
+        self.i += 1;
+        let mut out = Vec::new();
+        while let Some(&b) = self.s.get(self.i) {
+            self.i += 1;
+            if b == b'\'' {
+                if self.s.get(self.i) == Some(&b'\'') {
+                    out.push(b'\'');
+                    self.i += 1;
+                } else {
+                    return String::from_utf8(out).map_err(|e| e.to_string());
+                }
+            } else {
+                out.push(b);
+            }
+        }
+        Err("unterminated single-quoted string".to_string())
+    }
+
+    fn collection(&mut self) -> Result<Value, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("nesting too deep".to_string());
+        }
+        self.depth += 1;
+        let v = if self.s[self.i] == b'[' { self.seq() } else { self.map() };
+        self.depth -= 1;
+        v
+    }
+
+    fn seq(&mut self) -> Result<Value, String> {
+        self.i += 1;
+        let mut items = Vec::new();
+        // True right after `[` or `,`: an item (or the closer) must come next.
+        let mut expect_item = true;
+        loop {
+            self.ws();
+            match self.s.get(self.i) {
+                Some(b']') => {
+                    self.i += 1;
+                    return Ok(Value::Array(items));
+                }
+                Some(b',') if !expect_item => {
+                    self.i += 1;
+                    expect_item = true;
+                }
+                Some(&b) if !expect_item || matches!(b, b',' | b'}') => {
+                    return Err(format!("unexpected {:?} in flow sequence", b as char));
+                }
+                Some(_) => {
+                    items.push(self.value()?);
+                    expect_item = false;
+                }
+                None => return Err("unterminated flow sequence".to_string()),
+            }
+        }
+    }
+
+    fn map(&mut self) -> Result<Value, String> {
+        self.i += 1;
+        let mut map = Map::new();
+        let mut expect_item = true;
+        loop {
+            self.ws();
+            match self.s.get(self.i) {
+                Some(b'}') => {
+                    self.i += 1;
+                    return Ok(Value::Object(map));
+                }
+                Some(b',') if !expect_item => {
+                    self.i += 1;
+                    expect_item = true;
+                }
+                Some(&b) if !expect_item || matches!(b, b',' | b']' | b':') => {
+                    return Err(format!("unexpected {:?} in flow mapping", b as char));
+                }
+                Some(_) => {
+                    let key = match self.s[self.i] {
+                        b'"' => self.double()?,
+                        b'\'' => self.single()?,
+                        _ => self.plain(true),
+                    };
+                    self.ws();
+                    let v = if self.s.get(self.i) == Some(&b':') {
+                        self.i += 1;
+                        self.value()?
+                    } else {
+                        Value::Null
+                    };
+                    map.insert(key, v);
+                    expect_item = false;
+                }
+                None => return Err("unterminated flow mapping".to_string()),
+            }
+        }
+    }
+}
@@ -0,0 +1,642 @@
+//! V26.6 Server-side syntax highlighting for fenced code blocks.
+//!
+//! A small table-driven lexer with a built-in grammar set (the languages that
+//! dominate model / tool READMEs) rather than syntect: syntect would add its
+//! bundled syntax dump and a regex engine to every build, and its scope-named
+//! classes would replace the fixed `hl-*` set that site CSS and the sanitiser
+//! allowlist are written against. Output is class-based like syntect's
+//! `ClassStyle`: tokens become `<span class="hl-…">` inside the usual
+//! `<pre><code class="language-…">`. The only classes produced are
+//! `CLASSES`; the sanitiser allows exactly those on `<span>`. The lexer only
+//! slices at char boundaries (fuzzed over arbitrary UTF-8 in the tests).
+//! Unknown languages keep pulldown-cmark's plain output.
+
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag, TagEnd};
+
+/// Every class the highlighter can emit (the policy allowlist).
+pub(crate) const CLASSES: &[&str] = &[
+    "hl-comment",
+    "hl-string",
+    "hl-number",
+    "hl-keyword",
+    "hl-literal",
+    "hl-type",
+    "hl-builtin",
+    "hl-function",
+    "hl-attr",
+    "hl-variable",
+    "hl-key",
+    "hl-tag",
+    "hl-meta",
+];
+
+struct Grammar {
+    names: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Longest first (`"""` before `"`).
+    quotes: &'static [&'static str],
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    types: &'static [&'static str],
+    builtins: &'static [&'static str],
+    /// Capitalised identifiers are types.
+    type_case: bool,
+    case_insensitive: bool,
+    /// `'` only opens a char literal (`'a'`, `'\n'`), not a lifetime.
+    char_literals: bool,
+    /// Prefix for decorators / annotations (`@`) — or `#` for `#[attr]`.
+    attr_prefix: Option<char>,
+    /// `$name`, `${..}`, `$1` variables.
+    dollar_vars: bool,
+    /// `#include` etc. at line start.
+    preprocessor: bool,
+    /// `key:` / `key =` at line start (YAML / TOML).
+    line_keys: Option<char>,
+    /// A quoted string followed by `:` is a key (JSON, YAML).
+    quoted_keys: bool,
+    /// `name!` is a macro call (Rust).
+    macros: bool,
+    markup: bool,
+}
+
+const BASE: Grammar = Grammar {
+    names: &[],
+    line_comments: &[],
+    block_comment: None,
+    quotes: &["\"", "'"],
+    keywords: &[],
+    literals: &[],
+    types: &[],
+    builtins: &[],
+    type_case: false,
+    case_insensitive: false,
+    char_literals: false,
+    attr_prefix: None,
+    dollar_vars: false,
+    preprocessor: false,
+    line_keys: None,
+    quoted_keys: false,
+    macros: false,
+    markup: false,
+};
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "const", "continue", "default", "do", "else", "enum", "extern", "for", "goto", "if",
+    "inline", "register", "restrict", "return", "sizeof", "static", "struct", "switch", "typedef", "union",
+    "volatile", "while",
+];
+const C_TYPES: &[&str] = &[
+    "bool", "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void", "size_t", "int8_t",
+    "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t",
+];
+const JS_KEYWORDS: &[&str] = &[
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do",
+    "else", "export", "extends", "finally", "for", "from", "function", "if", "import", "in", "instanceof", "let",
+    "new", "of", "return", "static", "super", "switch", "this", "throw", "try", "typeof", "var", "void", "while",
+    "with", "yield",
+];
+const JS_LITERALS: &[&str] = &["true", "false", "null", "undefined", "NaN", "Infinity"];
+const JS_BUILTINS: &[&str] = &["console", "window", "document", "require", "module", "process", "JSON", "Math", "Promise"];
+
+static GRAMMARS: &[Grammar] = &[
+    Grammar {
+        names: &["rust", "rs"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\"", "'"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "fn",
+            "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+            "Self", "static", "struct", "super", "trait", "type", "unsafe", "use", "where", "while",
+        ],
+        literals: &["true", "false", "None", "Some", "Ok", "Err"],
+        types: &[
+            "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128",
+            "isize", "f32", "f64",
+        ],
+        type_case: true,
+        char_literals: true,
+        attr_prefix: Some('#'),
+        macros: true,
+        ..BASE
+    },
+    Grammar {
+        names: &["python", "py", "python3", "py3", "ipython"],
+        line_comments: &["#"],
+        quotes: &["\"\"\"", "'''", "\"", "'"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else",
+            "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not",
+            "or", "pass", "raise", "return", "try", "while", "with", "yield",
+        ],
+        literals: &["True", "False", "None"],
+        types: &["int", "float", "str", "bool", "list", "dict", "set", "tuple", "bytes", "object"],
+        builtins: &["print", "len", "range", "open", "super", "self", "isinstance", "enumerate", "zip", "map"],
+        attr_prefix: Some('@'),
+        ..BASE
+    },
+    Grammar {
+        names: &["javascript", "js", "jsx", "mjs", "cjs", "node"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\"", "'", "`"],
+        keywords: JS_KEYWORDS,
+        literals: JS_LITERALS,
+        builtins: JS_BUILTINS,
+        ..BASE
+    },
+    Grammar {
+        names: &["typescript", "ts", "tsx"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\"", "'", "`"],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "declare", "default", "delete",
+            "do", "else", "enum", "export", "extends", "finally", "for", "from", "function", "if", "implements",
+            "import", "in", "instanceof", "interface", "keyof", "let", "namespace", "new", "of", "private",
+            "protected", "public", "readonly", "return", "static", "super", "switch", "this", "throw", "try", "type",
+            "typeof", "var", "void", "while", "yield",
+        ],
+        literals: JS_LITERALS,
+        types: &["string", "number", "boolean", "any", "unknown", "never", "object", "symbol", "bigint"],
+        builtins: JS_BUILTINS,
+        type_case: true,
+        attr_prefix: Some('@'),
+        ..BASE
+    },
+    Grammar {
+        names: &["bash", "sh", "shell", "zsh", "console", "shell-session"],
+        line_comments: &["#"],
+        keywords: &[
+            "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac", "in",
+            "function", "return", "export", "local",
+        ],
+        builtins: &[
+            "cd", "echo", "source", "pip", "pip3", "python", "python3", "git", "npm", "npx", "cargo", "docker",
+            "curl", "wget", "conda", "sudo", "make",
+        ],
+        dollar_vars: true,
+        ..BASE
+    },
+    Grammar {
+        names: &["json", "jsonc", "json5", "jsonl"],
+        line_comments: &["//"],
+        quotes: &["\""],
+        literals: &["true", "false", "null"],
+        quoted_keys: true,
+        ..BASE
+    },
+    Grammar {
+        names: &["yaml", "yml"],
+        line_comments: &["#"],
+        literals: &["true", "false", "null", "yes", "no", "~"],
+        line_keys: Some(':'),
+        quoted_keys: true,
+        ..BASE
+    },
+    Grammar {
+        names: &["toml", "ini", "cfg"],
+        line_comments: &["#", ";"],
+        quotes: &["\"\"\"", "'''", "\"", "'"],
+        literals: &["true", "false"],
+        line_keys: Some('='),
+        ..BASE
+    },
+    Grammar {
+        names: &["c", "h"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        keywords: C_KEYWORDS,
+        literals: &["NULL", "true", "false"],
+        types: C_TYPES,
+        char_literals: true,
+        preprocessor: true,
+        ..BASE
+    },
+    Grammar {
+        names: &["cpp", "c++", "cc", "cxx", "hpp", "cuda", "cu"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        keywords: &[
+            "auto", "break", "case", "catch", "class", "const", "constexpr", "continue", "default", "delete", "do",
+            "else", "enum", "explicit", "extern", "for", "friend", "goto", "if", "inline", "namespace", "new",
+            "noexcept", "operator", "private", "protected", "public", "return", "sizeof", "static", "struct",
+            "switch", "template", "this", "throw", "try", "typedef", "typename", "union", "using", "virtual",
+            "volatile", "while",
+        ],
+        literals: &["nullptr", "NULL", "true", "false"],
+        types: C_TYPES,
+        builtins: &["std"],
+        char_literals: true,
+        preprocessor: true,
+        ..BASE
+    },
+    Grammar {
+        names: &["go", "golang"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\"", "`", "'"],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "for", "func",
+            "go", "goto", "if", "import", "interface", "map", "package", "range", "return", "select", "struct",
+            "switch", "type", "var",
+        ],
+        literals: &["true", "false", "nil", "iota"],
+        types: &[
+            "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32", "int64", "rune",
+            "string", "uint", "uint8", "uint16", "uint32", "uint64", "uintptr", "any",
+        ],
+        builtins: &["append", "cap", "close", "delete", "len", "make", "new", "panic", "print", "println", "recover"],
+        ..BASE
+    },
+    Grammar {
+        names: &["java", "kotlin", "kt", "scala"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        keywords: &[
+            "abstract", "break", "case", "catch", "class", "continue", "default", "do", "else", "enum", "extends",
+            "final", "finally", "for", "fun", "if", "implements", "import", "instanceof", "interface", "new",
+            "package", "private", "protected", "public", "return", "static", "super", "switch", "synchronized",
+            "this", "throw", "throws", "try", "val", "var", "void", "while",
+        ],
+        literals: &["true", "false", "null"],
+        types: &["boolean", "byte", "char", "double", "float", "int", "long", "short"],
+        type_case: true,
+        char_literals: true,
+        attr_prefix: Some('@'),
+        ..BASE
+    },
+    Grammar {
+        names: &["sql", "postgresql", "postgres", "mysql", "sqlite"],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["'", "\""],
+        keywords: &[
+            "select", "from", "where", "and", "or", "not", "insert", "into", "values", "update", "set", "delete",
+            "create", "table", "index", "drop", "alter", "join", "left", "right", "inner", "outer", "on", "as",
+            "group", "by", "order", "having", "limit", "offset", "distinct", "union", "with", "case", "when",
+            "then", "else", "end", "in", "is", "like", "primary", "key", "references", "asc", "desc",
+        ],
+        literals: &["null", "true", "false"],
+        types: &["int", "integer", "bigint", "text", "varchar", "boolean", "real", "float", "date", "timestamp", "json", "jsonb"],
+        builtins: &["count", "sum", "avg", "min", "max", "coalesce"],
+        case_insensitive: true,
+        ..BASE
+    },
+    Grammar { names: &["html", "xml", "svg", "xhtml", "vue"], markup: true, ..BASE },
+];
+
+fn grammar(lang: &str) -> Option<&'static Grammar> {
+    let lang = lang.to_ascii_lowercase();
+    GRAMMARS.iter().find(|g| g.names.contains(&lang.as_str()))
+}
+
+fn escape_into(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// HTML output; every token is escaped.
+struct Out {
+    html: String,
+}
+
+impl Out {
+    fn plain(&mut self, s: &str) {
+        escape_into(&mut self.html, s);
+    }
+
+    fn span(&mut self, class: &str, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        self.html.push_str("<span class=\"");
+        self.html.push_str(class);
+        self.html.push_str("\">");
+        escape_into(&mut self.html, s);
+        self.html.push_str("</span>");
+    }
+}
+
+/// End of a quoted string opened at `start` with `quote`; unterminated
+/// single-line strings stop at the newline.
+fn string_end(code: &str, start: usize, quote: &str, backslash: bool) -> usize {
+    let multiline = quote.len() == 3 || quote == "`";
+    let mut i = start + quote.len();
+    while i < code.len() {
+        let rest = &code[i..];
+        if rest.starts_with(quote) {
+            return i + quote.len();
+        }
+        let c = rest.chars().next().unwrap_or(' ');
+        if c == '\\' && backslash {
+            i += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+        if c == '\n' && !multiline {
+            return i;
+        }
+        i += c.len_utf8();
+    }
+    code.len()
+}
+
+fn number_end(code: &str, start: usize) -> usize {
+    let b = code.as_bytes();
+    let mut i = start;
+    while i < b.len() {
+        let c = b[i];
+        let exp_sign = matches!(c, b'+' | b'-') && i > start && matches!(b[i - 1], b'e' | b'E') && !code[start..].starts_with("0x");
+        if c.is_ascii_alphanumeric() || c == b'_' || exp_sign || (c == b'.' && b.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+fn classify(g: &Grammar, word: &str, next: Option<char>) -> Option<&'static str> {
+    let lower;
+    let w = if g.case_insensitive {
+        lower = word.to_ascii_lowercase();
+        lower.as_str()
+    } else {
+        word
+    };
+    if g.keywords.contains(&w) {
+        Some("hl-keyword")
+    } else if g.literals.contains(&w) {
+        Some("hl-literal")
+    } else if g.types.contains(&w) {
+        Some("hl-type")
+    } else if g.builtins.contains(&w) {
+        Some("hl-builtin")
+    } else if next == Some('(') || (next == Some('!') && g.macros) {
+        Some("hl-function")
+    } else if g.type_case && word.starts_with(|c: char| c.is_ascii_uppercase()) && word.chars().any(|c| c.is_ascii_lowercase()) {
+        Some("hl-type")
+    } else {
+        None
+    }
+}
+
+/// YAML/TOML `key:` / `key =` at the start of `line` (after indent / `- `).
+fn line_key(line: &str, sep: char) -> Option<(usize, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let mut start = indent;
+    if sep == ':' && line[start..].starts_with("- ") {
+        start += 2;
+    }
+    let rest = &line[start..];
+    let end = rest.find(sep)?;
+    let key = &rest[..end];
+    let valid = !key.trim().is_empty()
+        && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ' ' | '/'))
+        && (sep != ':' || rest[end + 1..].is_empty() || rest[end + 1..].starts_with(' '));
+    valid.then(|| (start, start + key.trim_end().len()))
+}
+
+fn highlight_source(g: &Grammar, code: &str) -> String {
+    let mut out = Out { html: String::with_capacity(code.len() * 2) };
+    let mut i = 0;
+    let mut line_start = true;
+    while i < code.len() {
+        let rest = &code[i..];
+        let c = rest.chars().next().unwrap_or('\0');
+        let prev = code[..i].chars().next_back();
+        let at_line_start = line_start && c != ' ' && c != '\t';
+
+        if line_start {
+            if let Some(sep) = g.line_keys {
+                let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+                if let Some((ks, ke)) = line_key(line, sep) {
+                    out.plain(&line[..ks]);
+                    out.span("hl-key", &line[ks..ke]);
+                    i += ke;
+                    line_start = false;
+                    continue;
+                }
+            }
+        }
+        if c == '\n' {
+            out.plain("\n");
+            i += 1;
+            line_start = true;
+            continue;
+        }
+        if at_line_start {
+            line_start = false;
+            if g.preprocessor && c == '#' {
+                let end = rest.find('\n').map_or(code.len(), |n| i + n);
+                out.span("hl-meta", &code[i..end]);
+                i = end;
+                continue;
+            }
+            if g.line_keys == Some('=') && c == '[' {
+                let end = rest.find('\n').map_or(code.len(), |n| i + n);
+                out.span("hl-meta", &code[i..end]);
+                i = end;
+                continue;
+            }
+        }
+        if c == ' ' || c == '\t' {
+            out.plain(&rest[..1]);
+            i += 1;
+            continue;
+        }
+        line_start = false;
+
+        let hash_ok = c != '#' || prev.is_none_or(|p| p.is_whitespace() || matches!(p, ';' | '(' | ','));
+        if hash_ok && g.line_comments.iter().any(|lc| rest.starts_with(lc)) {
+            let end = rest.find('\n').map_or(code.len(), |n| i + n);
+            out.span("hl-comment", &code[i..end]);
+            i = end;
+            continue;
+        }
+        if let Some((open, close)) = g.block_comment.filter(|(o, _)| rest.starts_with(o)) {
+            let end = rest[open.len()..].find(close).map_or(code.len(), |n| i + open.len() + n + close.len());
+            out.span("hl-comment", &code[i..end]);
+            i = end;
+            continue;
+        }
+        if g.attr_prefix == Some('#') && (rest.starts_with("#[") || rest.starts_with("#![")) {
+            let end = rest.find(']').map_or(code.len(), |n| i + n + 1);
+            out.span("hl-attr", &code[i..end]);
+            i = end;
+            continue;
+        }
+        if g.attr_prefix == Some('@') && c == '@' && rest[1..].starts_with(is_ident_start) {
+            let end = i + 1 + rest[1..].find(|ch: char| !is_ident(ch) && ch != '.').unwrap_or(rest.len() - 1);
+            out.span("hl-attr", &code[i..end]);
+            i = end;
+            continue;
+        }
+        if g.dollar_vars && c == '$' {
+            let end = if rest[1..].starts_with('{') {
+                rest.find('}').map_or(code.len(), |n| i + n + 1)
+            } else if rest[1..].starts_with(is_ident_start) {
+                i + 1 + rest[1..].find(|ch: char| !is_ident(ch)).unwrap_or(rest.len() - 1)
+            } else if rest[1..].starts_with(|ch: char| ch.is_ascii_digit() || "?#@*!$".contains(ch)) {
+                i + 2
+            } else {
+                i + 1
+            };
+            out.span("hl-variable", &code[i..end]);
+            i = end;
+            continue;
+        }
+        if let Some(q) = g.quotes.iter().find(|q| rest.starts_with(**q)) {
+            let is_char = *q != "'"
+                || !g.char_literals
+                || rest[1..].starts_with('\\')
+                || rest[1..].chars().nth(1) == Some('\'');
+            if is_char {
+                let end = string_end(code, i, q, true);
+                let after = code[end..].trim_start_matches([' ', '\t']);
+                let class = if g.quoted_keys && after.starts_with(':') { "hl-key" } else { "hl-string" };
+                out.span(class, &code[i..end]);
+                i = end;
+                continue;
+            }
+        }
+        if c.is_ascii_digit() && !prev.is_some_and(is_ident) {
+            let end = number_end(code, i);
+            out.span("hl-number", &code[i..end]);
+            i = end;
+            continue;
+        }
+        if is_ident_start(c) || (c == '~' && g.line_keys == Some(':')) {
+            let end = if c == '~' { i + 1 } else { i + rest.find(|ch: char| !is_ident(ch)).unwrap_or(rest.len()) };
+            let word = &code[i..end];
+            match classify(g, word, code[end..].chars().next()) {
+                Some(class) => out.span(class, word),
+                None => out.plain(word),
+            }
+            i = end;
+            continue;
+        }
+        out.plain(&rest[..c.len_utf8()]);
+        i += c.len_utf8();
+    }
+    out.html
+}
+
+/// `<tag attr="v">` → tag / attr / string spans; comments; text plain.
+fn highlight_markup(code: &str) -> String {
+    let mut out = Out { html: String::with_capacity(code.len() * 2) };
+    let mut i = 0;
+    while i < code.len() {
+        let rest = &code[i..];
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(code.len(), |n| i + n + 3);
+            out.span("hl-comment", &code[i..end]);
+            i = end;
+        } else if let Some(second) = rest
+            .strip_prefix('<')
+            .and_then(|r| r.chars().next())
+            .filter(|c| c.is_alphabetic() || "/!?".contains(*c))
+        {
+            // `</name`, `<!DOCTYPE`, `<?xml` keep their second char.
+            let name_start = 1 + second.len_utf8();
+            let name_end = name_start
+                + rest[name_start..].find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(rest.len() - name_start);
+            out.span("hl-tag", &rest[..name_end]);
+            i += name_end;
+            // Attributes until `>`.
+            while i < code.len() {
+                let r = &code[i..];
+                let c = r.chars().next().unwrap_or('\0');
+                if c == '>' || r.starts_with("/>") {
+                    let len = if c == '>' { 1 } else { 2 };
+                    out.span("hl-tag", &r[..len]);
+                    i += len;
+                    break;
+                } else if c == '"' || c == '\'' {
+                    let end = string_end(code, i, &r[..1], false);
+                    out.span("hl-string", &code[i..end]);
+                    i = end;
+                } else if c.is_alphabetic() {
+                    let end = i + r.find(|ch: char| !(ch.is_alphanumeric() || "-_:.".contains(ch))).unwrap_or(r.len());
+                    out.span("hl-attr", &code[i..end]);
+                    i = end;
+                } else {
+                    out.plain(&r[..c.len_utf8()]);
+                    i += c.len_utf8();
+                }
+            }
+        } else {
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            let end = rest[first..].find('<').map_or(code.len(), |n| i + first + n);
+            out.plain(&code[i..end]);
+            i = end;
+        }
+    }
+    out.html
+}
+
+/// Highlighted `<pre><code>` for `lang`, or None for unknown languages.
+pub(crate) fn highlight(lang: &str, code: &str) -> Option<String> {
+    let g = grammar(lang)?;
+    let body = if g.markup { highlight_markup(code) } else { highlight_source(g, code) };
+    let mut html = String::with_capacity(body.len() + 48);
+    html.push_str("<pre><code class=\"language-");
+    escape_into(&mut html, lang);
+    html.push_str("\">");
+    html.push_str(&body);
+    html.push_str("</code></pre>\n");
+    Some(html)
+}
+
+/// Replace fenced code blocks in a known language by highlighted HTML.
+pub(crate) fn code_blocks<'a>(events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+    let mut out = Vec::new();
+    let mut block: Option<(Vec<Event<'a>>, String)> = None;
+    for event in events {
+        match (&mut block, event) {
+            (None, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))) => {
+                let lang = info.split(' ').next().unwrap_or("").to_string();
+                block = Some((vec![Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))], lang));
+            }
+            (Some((buffered, lang)), Event::End(TagEnd::CodeBlock)) => {
+                let code: String = buffered
+                    .iter()
+                    .filter_map(|e| match e {
+                        Event::Text(t) => Some(t.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+                match highlight(lang, &code) {
+                    Some(html) => out.push(Event::Html(CowStr::from(html))),
+                    None => {
+                        out.append(buffered);
+                        out.push(Event::End(TagEnd::CodeBlock));
+                    }
+                }
+                block = None;
+            }
+            (Some((buffered, _)), event) => buffered.push(event),
+            (None, event) => out.push(event),
+        }
+    }
+    if let Some((buffered, _)) = block {
+        out.extend(buffered);
+    }
+    out
+}
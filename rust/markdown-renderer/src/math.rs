@@ -0,0 +1,419 @@
+//! V26.6 `$..$` / `$$..$$` → MathML.
+//!
+//! pulldown-cmark's `ENABLE_MATH` yields the TeX source; this converts the
+//! subset that READMEs and abstracts actually use: scripts, `\frac`,
+//! `\sqrt`, Greek, operators/relations, big operators with limits,
+//! function names, accents, `\mathbb`-style fonts, `\text`, `\left..\right`.
+//! Environments (`\begin`), alignment and anything unknown are an error, and
+//! the caller falls back to the literal `$..$` text — exactly what the page
+//! showed before. So is nesting past `MAX_DEPTH`, which would otherwise
+//! overflow the stack. Produces only the tags / attributes in `TAGS` /
+//! `ATTRIBUTES`.
+
+use pulldown_cmark::{CowStr, Event};
+
+/// Deepest group / command nesting accepted in one formula.
+const MAX_DEPTH: usize = 64;
+
+/// Every MathML element we emit (the policy allowlist).
+pub(crate) const TAGS: &[&str] = &[
+    "math", "mrow", "mi", "mn", "mo", "mtext", "msup", "msub", "msubsup", "mfrac", "msqrt", "mroot", "mover",
+    "munder", "munderover",
+];
+
+/// (tag, attribute, allowed values).
+pub(crate) const ATTRIBUTES: &[(&str, &str, &[&str])] = &[
+    ("math", "display", &["block"]),
+    ("mi", "mathvariant", &["normal", "bold", "italic", "double-struck", "script", "fraktur", "sans-serif", "monospace"]),
+    ("mover", "accent", &["true"]),
+];
+
+const GREEK: &[(&str, char)] = &[
+    ("alpha", 'α'), ("beta", 'β'), ("gamma", 'γ'), ("delta", 'δ'), ("epsilon", 'ϵ'), ("varepsilon", 'ε'),
+    ("zeta", 'ζ'), ("eta", 'η'), ("theta", 'θ'), ("vartheta", 'ϑ'), ("iota", 'ι'), ("kappa", 'κ'),
+    ("lambda", 'λ'), ("mu", 'μ'), ("nu", 'ν'), ("xi", 'ξ'), ("pi", 'π'), ("varpi", 'ϖ'), ("rho", 'ρ'),
+    ("varrho", 'ϱ'), ("sigma", 'σ'), ("varsigma", 'ς'), ("tau", 'τ'), ("upsilon", 'υ'), ("phi", 'ϕ'),
+    ("varphi", 'φ'), ("chi", 'χ'), ("psi", 'ψ'), ("omega", 'ω'), ("Gamma", 'Γ'), ("Delta", 'Δ'),
+    ("Theta", 'Θ'), ("Lambda", 'Λ'), ("Xi", 'Ξ'), ("Pi", 'Π'), ("Sigma", 'Σ'), ("Upsilon", 'Υ'),
+    ("Phi", 'Φ'), ("Psi", 'Ψ'), ("Omega", 'Ω'),
+    // Letter-like symbols render as identifiers too.
+    ("infty", '∞'), ("partial", '∂'), ("nabla", '∇'), ("ell", 'ℓ'), ("hbar", 'ℏ'), ("emptyset", '∅'),
+];
+
+const OPERATORS: &[(&str, &str)] = &[
+    ("cdot", "⋅"), ("times", "×"), ("div", "÷"), ("pm", "±"), ("mp", "∓"), ("ast", "∗"), ("star", "⋆"),
+    ("circ", "∘"), ("bullet", "∙"), ("oplus", "⊕"), ("otimes", "⊗"), ("odot", "⊙"), ("leq", "≤"), ("le", "≤"),
+    ("geq", "≥"), ("ge", "≥"), ("neq", "≠"), ("ne", "≠"), ("approx", "≈"), ("sim", "∼"), ("simeq", "≃"),
+    ("equiv", "≡"), ("cong", "≅"), ("propto", "∝"), ("ll", "≪"), ("gg", "≫"), ("in", "∈"), ("notin", "∉"),
+    ("ni", "∋"), ("subset", "⊂"), ("subseteq", "⊆"), ("supset", "⊃"), ("supseteq", "⊇"), ("cup", "∪"),
+    ("cap", "∩"), ("setminus", "∖"), ("wedge", "∧"), ("land", "∧"), ("vee", "∨"), ("lor", "∨"), ("neg", "¬"),
+    ("lnot", "¬"), ("forall", "∀"), ("exists", "∃"), ("to", "→"), ("rightarrow", "→"), ("leftarrow", "←"),
+    ("gets", "←"), ("Rightarrow", "⇒"), ("Leftarrow", "⇐"), ("leftrightarrow", "↔"), ("Leftrightarrow", "⇔"),
+    ("iff", "⟺"), ("implies", "⟹"), ("mapsto", "↦"), ("mid", "∣"), ("parallel", "∥"), ("perp", "⊥"),
+    ("ldots", "…"), ("cdots", "⋯"), ("dots", "…"), ("vdots", "⋮"), ("ddots", "⋱"), ("langle", "⟨"),
+    ("rangle", "⟩"), ("lfloor", "⌊"), ("rfloor", "⌋"), ("lceil", "⌈"), ("rceil", "⌉"), ("vert", "|"),
+    ("Vert", "‖"), ("lvert", "|"), ("rvert", "|"), ("lVert", "‖"), ("rVert", "‖"), ("top", "⊤"),
+    ("prime", "′"), ("{", "{"), ("}", "}"), ("|", "‖"), ("_", "_"), ("%", "%"), ("$", "$"), ("#", "#"), ("&", "&"),
+];
+
+/// Large operators; `limits` ones put scripts under/over in display mode.
+const BIG_OPERATORS: &[(&str, &str, bool)] = &[
+    ("sum", "∑", true), ("prod", "∏", true), ("coprod", "∐", true), ("bigcup", "⋃", true), ("bigcap", "⋂", true),
+    ("bigoplus", "⨁", true), ("bigotimes", "⨂", true), ("int", "∫", false), ("iint", "∬", false),
+    ("iiint", "∭", false), ("oint", "∮", false),
+];
+
+/// Function names; the `limits` ones (lim, max, ...) behave like `\sum`.
+const FUNCTIONS: &[(&str, bool)] = &[
+    ("sin", false), ("cos", false), ("tan", false), ("cot", false), ("sec", false), ("csc", false),
+    ("arcsin", false), ("arccos", false), ("arctan", false), ("sinh", false), ("cosh", false), ("tanh", false),
+    ("log", false), ("ln", false), ("lg", false), ("exp", false), ("det", false), ("dim", false), ("ker", false),
+    ("deg", false), ("arg", false), ("gcd", false), ("Pr", false), ("lim", true), ("max", true), ("min", true),
+    ("sup", true), ("inf", true), ("argmax", true), ("argmin", true), ("limsup", true), ("liminf", true),
+];
+
+const ACCENTS: &[(&str, &str)] = &[
+    ("hat", "^"), ("widehat", "^"), ("bar", "¯"), ("overline", "¯"), ("vec", "→"), ("tilde", "~"),
+    ("widetilde", "~"), ("dot", "˙"), ("ddot", "¨"),
+];
+
+const FONTS: &[(&str, &str)] = &[
+    ("mathrm", "normal"), ("operatorname", "normal"), ("mathbf", "bold"), ("boldsymbol", "bold"),
+    ("mathit", "italic"), ("mathbb", "double-struck"), ("mathcal", "script"), ("mathscr", "script"),
+    ("mathfrak", "fraktur"), ("mathsf", "sans-serif"), ("mathtt", "monospace"),
+];
+
+/// Spacing commands become space characters in `<mtext>`.
+const SPACES: &[(&str, &str)] = &[
+    (",", "\u{2009}"), (":", "\u{205f}"), (";", "\u{2004}"), (" ", "\u{a0}"), ("quad", "\u{2003}"),
+    ("qquad", "\u{2003}\u{2003}"), ("!", ""),
+];
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn leaf(tag: &str, text: &str) -> String {
+    format!("<{tag}>{}</{tag}>", escape(text))
+}
+
+fn row(nodes: Vec<String>) -> String {
+    if nodes.len() == 1 {
+        nodes.into_iter().next().unwrap_or_default()
+    } else {
+        format!("<mrow>{}</mrow>", nodes.concat())
+    }
+}
+
+/// A parsed atom plus how scripts attach to it.
+struct Atom {
+    html: String,
+    /// Scripts go under/over (display-mode `\sum`, `\lim`).
+    limits: bool,
+}
+
+impl Atom {
+    fn plain(html: String) -> Self {
+        Atom { html, limits: false }
+    }
+}
+
+struct Tex<'a> {
+    s: &'a str,
+    i: usize,
+    display: bool,
+    depth: usize,
+}
+
+impl Tex<'_> {
+    /// Run `f` one nesting level deeper; every recursive path goes through here.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("nesting too deep".to_string());
+        }
+        self.depth += 1;
+        let r = f(self);
+        self.depth -= 1;
+        r
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.s[self.i..].chars().next()
+    }
+
+    fn ws(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.i += c.len_utf8();
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.i += c.len_utf8();
+        Some(c)
+    }
+
+    /// `\name` (letters) or `\c` (one non-letter).
+    fn command(&mut self) -> Result<&str, String> {
+        let start = self.i;
+        let letters = self.s[start..].find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(self.s.len() - start);
+        if letters > 0 {
+            self.i += letters;
+        } else {
+            self.bump().ok_or("trailing backslash")?;
+        }
+        Ok(&self.s[start..self.i])
+    }
+
+    /// Raw `{...}` contents (for `\text`, fonts).
+    fn raw_group(&mut self) -> Result<String, String> {
+        self.ws();
+        if self.peek() != Some('{') {
+            return self.bump().map(String::from).ok_or_else(|| "missing argument".to_string());
+        }
+        self.i += 1;
+        let start = self.i;
+        let mut depth = 1;
+        while let Some(c) = self.bump() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(self.s[start..self.i - 1].to_string());
+                    }
+                }
+                '\\' => {
+                    self.bump();
+                }
+                _ => {}
+            }
+        }
+        Err("unclosed group".to_string())
+    }
+
+    /// One argument: a `{group}` or a single token.
+    fn arg(&mut self) -> Result<String, String> {
+        self.ws();
+        match self.peek() {
+            Some('{') => {
+                self.i += 1;
+                let nodes = self.nested(|p| p.expr(Some('}')))?;
+                Ok(row(nodes))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                self.i += 1;
+                Ok(leaf("mn", &c.to_string()))
+            }
+            Some(_) => self.atom()?.map(|a| a.html).ok_or_else(|| "missing argument".to_string()),
+            None => Err("missing argument".to_string()),
+        }
+    }
+
+    /// Delimiter after `\left`, `\right`, `\big`...; `.` is empty.
+    fn delimiter(&mut self) -> Result<String, String> {
+        self.ws();
+        match self.bump() {
+            Some('.') => Ok(String::new()),
+            Some('\\') => {
+                let name = self.command()?;
+                OPERATORS
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, op)| leaf("mo", op))
+                    .ok_or_else(|| format!("unknown delimiter \\{name}"))
+            }
+            Some(c) if "()[]|/<>".contains(c) => Ok(leaf("mo", &c.to_string())),
+            _ => Err("missing delimiter".to_string()),
+        }
+    }
+
+    fn control(&mut self) -> Result<Option<Atom>, String> {
+        let name = self.command()?.to_string();
+        let name = name.as_str();
+        if let Some((_, c)) = GREEK.iter().find(|(n, _)| *n == name) {
+            return Ok(Some(Atom::plain(leaf("mi", &c.to_string()))));
+        }
+        if let Some((_, op)) = OPERATORS.iter().find(|(n, _)| *n == name) {
+            return Ok(Some(Atom::plain(leaf("mo", op))));
+        }
+        if let Some((_, op, limits)) = BIG_OPERATORS.iter().find(|(n, ..)| *n == name) {
+            return Ok(Some(Atom { html: leaf("mo", op), limits: *limits && self.display }));
+        }
+        if let Some((_, limits)) = FUNCTIONS.iter().find(|(n, _)| *n == name) {
+            let text = match name {
+                "argmax" => "arg max",
+                "argmin" => "arg min",
+                "limsup" => "lim sup",
+                "liminf" => "lim inf",
+                other => other,
+            };
+            return Ok(Some(Atom { html: leaf("mi", text), limits: *limits && self.display }));
+        }
+        if let Some((_, space)) = SPACES.iter().find(|(n, _)| *n == name) {
+            return Ok((!space.is_empty()).then(|| Atom::plain(leaf("mtext", space))));
+        }
+        if let Some((_, accent)) = ACCENTS.iter().find(|(n, _)| *n == name) {
+            let base = self.arg()?;
+            return Ok(Some(Atom::plain(format!("<mover accent=\"true\">{base}{}</mover>", leaf("mo", accent)))));
+        }
+        if let Some((_, variant)) = FONTS.iter().find(|(n, _)| *n == name) {
+            let text = self.raw_group()?;
+            if text.contains('\\') || text.contains('{') {
+                return Err(format!("nested markup in \\{name}"));
+            }
+            let html = format!("<mi mathvariant=\"{variant}\">{}</mi>", escape(text.trim()));
+            return Ok(Some(Atom::plain(html)));
+        }
+        match name {
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.arg()?;
+                let den = self.arg()?;
+                Ok(Some(Atom::plain(format!("<mfrac>{num}{den}</mfrac>"))))
+            }
+            "sqrt" => {
+                self.ws();
+                let index = if self.peek() == Some('[') {
+                    self.i += 1;
+                    Some(row(self.expr(Some(']'))?))
+                } else {
+                    None
+                };
+                let base = self.arg()?;
+                Ok(Some(Atom::plain(match index {
+                    Some(n) => format!("<mroot>{base}{n}</mroot>"),
+                    None => format!("<msqrt>{base}</msqrt>"),
+                })))
+            }
+            "text" | "textrm" | "textit" | "textbf" | "mbox" => Ok(Some(Atom::plain(leaf("mtext", &self.raw_group()?)))),
+            "left" | "right" | "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" | "middle" => {
+                let d = self.delimiter()?;
+                Ok((!d.is_empty()).then(|| Atom::plain(d)))
+            }
+            "limits" | "nolimits" | "displaystyle" | "textstyle" => Ok(None),
+            other => Err(format!("unsupported command \\{other}")),
+        }
+    }
+
+    /// One atom (without scripts); None for tokens that render nothing.
+    fn atom(&mut self) -> Result<Option<Atom>, String> {
+        let Some(c) = self.peek() else { return Ok(None) };
+        if c.is_ascii_digit() || (c == '.' && self.s[self.i + 1..].starts_with(|d: char| d.is_ascii_digit())) {
+            let len = self.s[self.i..]
+                .char_indices()
+                .find(|&(k, ch)| {
+                    !(ch.is_ascii_digit() || (ch == '.' && self.s[self.i + k + 1..].starts_with(|d: char| d.is_ascii_digit())))
+                })
+                .map_or(self.s.len() - self.i, |(k, _)| k);
+            let n = &self.s[self.i..self.i + len];
+            self.i += len;
+            return Ok(Some(Atom::plain(leaf("mn", n))));
+        }
+        self.i += c.len_utf8();
+        match c {
+            '\\' => self.nested(Self::control),
+            '{' => {
+                let nodes = self.nested(|p| p.expr(Some('}')))?;
+                Ok(Some(Atom::plain(row(nodes))))
+            }
+            '&' => Err("alignment `&` outside an environment".to_string()),
+            '#' | '%' | '$' => Err(format!("unexpected `{c}`")),
+            '~' => Ok(Some(Atom::plain(leaf("mtext", "\u{a0}")))),
+            '-' => Ok(Some(Atom::plain(leaf("mo", "−")))),
+            '\'' => Ok(Some(Atom::plain(leaf("mo", "′")))),
+            c if c.is_alphabetic() => Ok(Some(Atom::plain(leaf("mi", &c.to_string())))),
+            c => Ok(Some(Atom::plain(leaf("mo", &c.to_string())))),
+        }
+    }
+
+    /// Atom followed by any `^` / `_` / `'` scripts.
+    fn scripted(&mut self, base: Atom) -> Result<String, String> {
+        let (mut sub, mut sup): (Option<String>, Option<String>) = (None, None);
+        loop {
+            self.ws();
+            match self.peek() {
+                Some('_') if sub.is_none() => {
+                    self.i += 1;
+                    sub = Some(self.arg()?);
+                }
+                Some('^') if sup.is_none() => {
+                    self.i += 1;
+                    sup = Some(self.arg()?);
+                }
+                Some('\'') if sup.is_none() => {
+                    let primes = self.s[self.i..].chars().take_while(|c| *c == '\'').count();
+                    self.i += primes;
+                    sup = Some(leaf("mo", &"′".repeat(primes)));
+                }
+                Some('_' | '^') => return Err("double subscript or superscript".to_string()),
+                _ => break,
+            }
+        }
+        let b = base.html;
+        Ok(match (sub, sup, base.limits) {
+            (None, None, _) => b,
+            (Some(s), None, false) => format!("<msub>{b}{s}</msub>"),
+            (None, Some(p), false) => format!("<msup>{b}{p}</msup>"),
+            (Some(s), Some(p), false) => format!("<msubsup>{b}{s}{p}</msubsup>"),
+            (Some(s), None, true) => format!("<munder>{b}{s}</munder>"),
+            (None, Some(p), true) => format!("<mover>{b}{p}</mover>"),
+            (Some(s), Some(p), true) => format!("<munderover>{b}{s}{p}</munderover>"),
+        })
+    }
+
+    /// Nodes up to `close` (consumed) or the end of input.
+    fn expr(&mut self, close: Option<char>) -> Result<Vec<String>, String> {
+        let mut nodes = Vec::new();
+        loop {
+            self.ws();
+            match self.peek() {
+                None if close.is_none() => return Ok(nodes),
+                None => return Err("unclosed group".to_string()),
+                Some(c) if Some(c) == close => {
+                    self.i += 1;
+                    return Ok(nodes);
+                }
+                Some('}') => return Err("unbalanced `}`".to_string()),
+                Some('_' | '^') => {
+                    // Script with no base (`^2`, `{}_n`).
+                    let html = self.scripted(Atom::plain("<mrow></mrow>".to_string()))?;
+                    nodes.push(html);
+                }
+                Some(_) => {
+                    if let Some(atom) = self.atom()? {
+                        nodes.push(self.scripted(atom)?);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// TeX → `<math>`; `display` selects block layout and limits placement.
+pub(crate) fn tex_to_mathml(tex: &str, display: bool) -> Result<String, String> {
+    let mut p = Tex { s: tex, i: 0, display, depth: 0 };
+    let nodes = p.expr(None)?;
+    if nodes.is_empty() {
+        return Err("empty formula".to_string());
+    }
+    let open = if display { "<math display=\"block\">" } else { "<math>" };
+    Ok(format!("{open}{}</math>", row(nodes)))
+}
+
+/// Replace math events by MathML, or by their literal source on error.
+pub(crate) fn render_events<'a>(events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+    events
+        .into_iter()
+        .map(|e| match e {
+            Event::InlineMath(tex) => match tex_to_mathml(&tex, false) {
+                Ok(html) => Event::InlineHtml(CowStr::from(html)),
+                Err(_) => Event::Text(CowStr::from(format!("${tex}$"))),
+            },
+            Event::DisplayMath(tex) => match tex_to_mathml(&tex, true) {
+                Ok(html) => Event::InlineHtml(CowStr::from(html)),
+                Err(_) => Event::Text(CowStr::from(format!("$${tex}$$"))),
+            },
+            other => other,
+        })
+        .collect()
+}
@@ -0,0 +1,269 @@
+//! V26.6 Hugging Face model-card front matter → typed fields.
+//!
+//! Field shapes follow the HF card spec plus what real cards do in practice
+//! (cf. hf-relation-extractors.js / openllm-adapter.js): `base_model`,
+//! `datasets`, `language` as a string or a list; `datasets` items as
+//! `{name: ..}` maps; a singular `dataset`; `base_model` inside
+//! `model-index[0]`; metric values as numbers or strings ("71.2", "71.2%").
+//!
+//! Malformed YAML is common (tabs, unclosed flow lists, stray text, nesting
+//! past the parser's depth cap). When the whole block does not parse, each
+//! top-level key is re-parsed on its own so one broken key does not lose the
+//! rest (`status: "recovered"`); everything dropped is listed in `warnings`.
+//! Never fails.
+
+use crate::frontmatter;
+use napi_derive::napi;
+use serde_json::{Map, Value};
+
+#[napi(object)]
+pub struct EvalResult {
+    /// `model-index[].name`
+    pub model_name: Option<String>,
+    pub task_type: Option<String>,
+    pub task_name: Option<String>,
+    pub dataset_type: Option<String>,
+    pub dataset_name: Option<String>,
+    pub dataset_config: Option<String>,
+    pub dataset_split: Option<String>,
+    pub metric_type: Option<String>,
+    pub metric_name: Option<String>,
+    /// Numeric score; strings like "71.2%" are parsed, the `%` is not scaled.
+    pub value: Option<f64>,
+    /// Original value when it was not a plain number.
+    pub value_text: Option<String>,
+    pub verified: Option<bool>,
+    pub source_url: Option<String>,
+}
+
+#[napi(object)]
+pub struct ModelCardMetadata {
+    /// "ok" | "recovered" (some keys dropped) | "invalid" (nothing usable) | "absent"
+    pub status: String,
+    pub license: Option<String>,
+    pub license_name: Option<String>,
+    pub license_link: Option<String>,
+    pub base_model: Vec<String>,
+    /// "finetune" | "adapter" | "quantized" | "merge" when declared.
+    pub base_model_relation: Option<String>,
+    pub datasets: Vec<String>,
+    pub language: Vec<String>,
+    pub tags: Vec<String>,
+    pub pipeline_tag: Option<String>,
+    pub library_name: Option<String>,
+    pub metrics: Vec<String>,
+    pub eval_results: Vec<EvalResult>,
+    /// Parsed (or recovered) front matter as a JSON object string.
+    pub raw: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+#[napi(object)]
+pub struct ModelCard {
+    pub metadata: ModelCardMetadata,
+    /// README with the front matter block removed.
+    pub body: String,
+}
+
+impl ModelCardMetadata {
+    fn empty(status: &str) -> Self {
+        ModelCardMetadata {
+            status: status.to_string(),
+            license: None,
+            license_name: None,
+            license_link: None,
+            base_model: Vec::new(),
+            base_model_relation: None,
+            datasets: Vec::new(),
+            language: Vec::new(),
+            tags: Vec::new(),
+            pipeline_tag: None,
+            library_name: None,
+            metrics: Vec::new(),
+            eval_results: Vec::new(),
+            raw: None,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Top-level keys one at a time: a chunk is a column-0 line plus every
+/// following indented / `- ` line. Unparseable chunks are skipped.
+fn recover(yaml: &str, warnings: &mut Vec<String>) -> Map<String, Value> {
+    let mut chunks: Vec<(usize, String)> = Vec::new();
+    for (n, line) in yaml.lines().enumerate() {
+        let starts_key = !line.is_empty() && !line.starts_with([' ', '\t', '#', '-']);
+        match chunks.last_mut() {
+            Some((_, chunk)) if !starts_key => {
+                chunk.push('\n');
+                chunk.push_str(line);
+            }
+            _ if starts_key => chunks.push((n + 1, line.to_string())),
+            _ => {}
+        }
+    }
+    let mut map = Map::new();
+    for (line_no, chunk) in chunks {
+        match frontmatter::parse_yaml(&chunk) {
+            Ok(Value::Object(m)) => map.extend(m),
+            Ok(_) => warnings.push(format!("line {line_no}: skipped non-mapping content")),
+            Err(e) => {
+                let key = chunk.split(':').next().unwrap_or("").trim();
+                warnings.push(format!("line {line_no}: skipped key {key:?}: {e}"));
+            }
+        }
+    }
+    map
+}
+
+fn scalar_text(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// String or list of strings; `{name: ..}` items (datasets) use `name`.
+fn string_list(v: Option<&Value>, key: &str, warnings: &mut Vec<String>) -> Vec<String> {
+    let item = |v: &Value| scalar_text(v).or_else(|| v.get("name").and_then(scalar_text));
+    let mut out: Vec<String> = Vec::new();
+    match v {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(items)) => {
+            for v in items {
+                match item(v) {
+                    Some(s) if !out.contains(&s) => out.push(s),
+                    Some(_) => {}
+                    None if v.is_null() => {}
+                    None => warnings.push(format!("{key}: ignored non-string item {v}")),
+                }
+            }
+        }
+        Some(v) => match item(v) {
+            Some(s) => out.push(s),
+            None => warnings.push(format!("{key}: expected string or list, got {v}")),
+        },
+    }
+    out
+}
+
+fn single(map: &Map<String, Value>, key: &str, warnings: &mut Vec<String>) -> Option<String> {
+    let mut list = string_list(map.get(key), key, warnings);
+    if list.len() > 1 {
+        warnings.push(format!("{key}: expected one value, got {}; using the first", list.len()));
+    }
+    (!list.is_empty()).then(|| list.swap_remove(0))
+}
+
+fn text_at(v: &Value, path: &[&str]) -> Option<String> {
+    path.iter().try_fold(v, |v, k| v.get(k)).and_then(scalar_text)
+}
+
+/// Score → (value, value_text). "71.2%" → 71.2; non-numeric text is kept.
+fn metric_value(v: Option<&Value>) -> (Option<f64>, Option<String>) {
+    match v {
+        Some(Value::Number(n)) => (n.as_f64(), None),
+        Some(Value::String(s)) => {
+            let t = s.trim();
+            (t.trim_end_matches('%').trim().parse::<f64>().ok().filter(|f| f.is_finite()), Some(t.to_string()))
+        }
+        Some(Value::Null) | None => (None, None),
+        Some(other) => (None, Some(other.to_string())),
+    }
+}
+
+fn eval_results(model_index: &Value, warnings: &mut Vec<String>) -> Vec<EvalResult> {
+    let mut out = Vec::new();
+    let Some(models) = model_index.as_array() else {
+        warnings.push("model-index: expected a list".to_string());
+        return out;
+    };
+    for model in models {
+        let model_name = text_at(model, &["name"]);
+        let results = model.get("results").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        for result in results {
+            let Some(metrics) = result.get("metrics").and_then(Value::as_array) else {
+                warnings.push("model-index: result without metrics list".to_string());
+                continue;
+            };
+            for metric in metrics {
+                let (value, value_text) = metric_value(metric.get("value"));
+                out.push(EvalResult {
+                    model_name: model_name.clone(),
+                    task_type: text_at(result, &["task", "type"]),
+                    task_name: text_at(result, &["task", "name"]),
+                    dataset_type: text_at(result, &["dataset", "type"]),
+                    dataset_name: text_at(result, &["dataset", "name"]),
+                    dataset_config: text_at(result, &["dataset", "config"]),
+                    dataset_split: text_at(result, &["dataset", "split"]),
+                    metric_type: text_at(metric, &["type"]),
+                    metric_name: text_at(metric, &["name"]),
+                    value,
+                    value_text,
+                    verified: metric.get("verified").and_then(Value::as_bool),
+                    source_url: text_at(result, &["source", "url"]),
+                });
+            }
+        }
+    }
+    out
+}
+
+fn typed(map: &Map<String, Value>, meta: &mut ModelCardMetadata) {
+    let w = &mut meta.warnings;
+    meta.license = single(map, "license", w);
+    meta.license_name = single(map, "license_name", w);
+    meta.license_link = single(map, "license_link", w);
+    meta.base_model = string_list(map.get("base_model"), "base_model", w);
+    meta.base_model_relation = single(map, "base_model_relation", w);
+    meta.datasets = string_list(map.get("datasets"), "datasets", w);
+    for d in string_list(map.get("dataset"), "dataset", w) {
+        if !meta.datasets.contains(&d) {
+            meta.datasets.push(d);
+        }
+    }
+    meta.language = string_list(map.get("language"), "language", w);
+    meta.tags = string_list(map.get("tags"), "tags", w);
+    meta.pipeline_tag = single(map, "pipeline_tag", w);
+    meta.library_name = single(map, "library_name", w);
+    meta.metrics = string_list(map.get("metrics"), "metrics", w);
+    if let Some(index) = map.get("model-index").or_else(|| map.get("model_index")) {
+        meta.eval_results = eval_results(index, w);
+        if meta.base_model.is_empty() {
+            meta.base_model = string_list(index.get(0).and_then(|m| m.get("base_model")), "model-index.base_model", w);
+        }
+    }
+}
+
+/// Strip + parse the card front matter. Pure; never fails.
+pub(crate) fn parse(readme: &str) -> ModelCard {
+    let Some((yaml, body)) = frontmatter::split_front_matter(readme) else {
+        let mut meta = ModelCardMetadata::empty("absent");
+        if readme.trim_start_matches('\u{feff}').starts_with("---") {
+            meta.warnings.push("front matter block is not closed".to_string());
+        }
+        return ModelCard { metadata: meta, body: readme.to_string() };
+    };
+    let mut meta = ModelCardMetadata::empty("ok");
+    let map = match frontmatter::parse_yaml(yaml) {
+        Ok(Value::Object(m)) => m,
+        Ok(Value::Null) => Map::new(),
+        Ok(other) => {
+            meta.warnings.push(format!("front matter is not a mapping: {other}"));
+            Map::new()
+        }
+        Err(e) => {
+            meta.warnings.push(format!("YAML error: {e}"));
+            meta.status = "recovered".to_string();
+            recover(yaml, &mut meta.warnings)
+        }
+    };
+    if map.is_empty() && !meta.warnings.is_empty() {
+        meta.status = "invalid".to_string();
+    }
+    typed(&map, &mut meta);
+    meta.raw = Some(Value::Object(map).to_string());
+    ModelCard { metadata: meta, body: body.to_string() }
+}
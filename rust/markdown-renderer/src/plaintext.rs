@@ -0,0 +1,182 @@
+//! V26.6 Markdown → plain text and search/listing snippets.
+//!
+//! Replaces the `#` / `*` / backtick character stripping in search_indexer
+//! `truncate` and stream-aggregator `get_description`, which left link
+//! syntax, HTML, badges and tables in snippets. Built on pulldown-cmark
+//! events: link text is kept; badge images, shields.io links, raw HTML,
+//! tables, code blocks, footnote definitions and front matter are dropped.
+//!
+//! `summarize` takes prose blocks only (no headings) and cuts at the last
+//! sentence end, else the last word break, past half the budget; the result
+//! never splits a char or a combining sequence and is at most `max_chars`
+//! chars including the `…`.
+
+use crate::frontmatter;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+const ELLIPSIS: char = '…';
+const BADGE_HOSTS: &[&str] = &["shields.io", "badgen.net", "badge.fury.io"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum BlockKind {
+    Heading,
+    Prose,
+    Item,
+}
+
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, r)| r);
+    rest.split(['/', '?', '#']).next().unwrap_or("")
+}
+
+fn is_badge_host(url: &str) -> bool {
+    let h = host(url).to_ascii_lowercase();
+    BADGE_HOSTS.iter().any(|b| h == *b || h.ends_with(&format!(".{b}")))
+}
+
+/// Shields / CI / colab / HF badge images.
+fn is_badge_image(url: &str) -> bool {
+    is_badge_host(url) || url.to_ascii_lowercase().contains("badge")
+}
+
+/// Text blocks in document order.
+fn blocks(raw_markdown: &str) -> Vec<(BlockKind, String)> {
+    let body = frontmatter::split_front_matter(raw_markdown).map_or(raw_markdown, |(_, b)| b);
+    let mut out: Vec<(BlockKind, String)> = Vec::new();
+    let mut kind = BlockKind::Prose;
+    let mut buf = String::new();
+    // Depth of the container being skipped (0 = not skipping).
+    let mut skip = 0u32;
+    let mut flush = |buf: &mut String, kind: BlockKind| {
+        let text = buf.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            out.push((kind, text));
+        }
+        buf.clear();
+    };
+    for event in Parser::new_ext(body, *crate::MD_OPTIONS) {
+        if skip > 0 {
+            match event {
+                Event::Start(_) => skip += 1,
+                Event::End(_) => skip -= 1,
+                _ => {}
+            }
+            continue;
+        }
+        match event {
+            Event::Start(Tag::Table(_) | Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::FootnoteDefinition(_) | Tag::MetadataBlock(_)) => {
+                skip = 1
+            }
+            Event::Start(Tag::Image { dest_url, .. }) if is_badge_image(&dest_url) => skip = 1,
+            Event::Start(Tag::Link { dest_url, .. }) if is_badge_host(&dest_url) => skip = 1,
+            Event::Start(Tag::Heading { .. }) => {
+                flush(&mut buf, kind);
+                kind = BlockKind::Heading;
+            }
+            Event::Start(Tag::Paragraph) => {
+                flush(&mut buf, kind);
+                if kind != BlockKind::Item {
+                    kind = BlockKind::Prose;
+                }
+            }
+            Event::Start(Tag::Item) => {
+                flush(&mut buf, kind);
+                kind = BlockKind::Item;
+            }
+            Event::End(TagEnd::Heading(_) | TagEnd::Paragraph | TagEnd::Item) => {
+                flush(&mut buf, kind);
+                if matches!(event, Event::End(TagEnd::Heading(_))) {
+                    kind = BlockKind::Prose;
+                }
+            }
+            Event::End(TagEnd::List(_)) => kind = BlockKind::Prose,
+            Event::Start(Tag::Image { .. }) => buf.push(' '),
+            Event::End(TagEnd::Image) => buf.push(' '),
+            Event::Text(t) | Event::Code(t) => buf.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => buf.push(' '),
+            _ => {}
+        }
+    }
+    flush(&mut buf, kind);
+    out
+}
+
+/// Blocks joined by blank lines; consecutive list items by single newlines.
+pub(crate) fn to_plain_text(raw_markdown: &str) -> String {
+    let mut out = String::new();
+    let mut prev: Option<BlockKind> = None;
+    for (kind, text) in blocks(raw_markdown) {
+        if let Some(p) = prev {
+            out.push_str(if p == BlockKind::Item && kind == BlockKind::Item { "\n" } else { "\n\n" });
+        }
+        out.push_str(&text);
+        prev = Some(kind);
+    }
+    out
+}
+
+/// Combining marks, ZWJ, variation selectors and emoji skin tones attach to
+/// the preceding char and must not start a cut-off tail.
+fn is_extend(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F
+        | 0x200D | 0xFE00..=0xFE0F | 0x1F3FB..=0x1F3FF | 0xE0100..=0xE01EF)
+}
+
+fn is_sentence_end(c: char, next: Option<char>) -> bool {
+    match c {
+        '.' | '!' | '?' => next.is_none_or(char::is_whitespace),
+        '。' | '！' | '？' => true,
+        _ => false,
+    }
+}
+
+/// Cut `text` to at most `max_chars` chars (see module docs).
+pub(crate) fn truncate_text(text: &str, max_chars: usize) -> String {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    if chars.len() <= max_chars {
+        return text.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+    let floor = max_chars / 2;
+    // A sentence cut needs no ellipsis, so it may use the whole budget.
+    let sentence = (floor..max_chars)
+        .rev()
+        .find(|&i| is_sentence_end(chars[i].1, chars.get(i + 1).map(|c| c.1)) && !chars.get(i + 1).is_some_and(|c| is_extend(c.1)));
+    if let Some(i) = sentence {
+        return text[..chars[i].0 + chars[i].1.len_utf8()].to_string();
+    }
+    let budget = max_chars - 1;
+    let word = (floor.max(1)..=budget).rev().find(|&i| chars[i].1.is_whitespace());
+    let mut end = word.unwrap_or(budget);
+    while end > 0 && is_extend(chars[end].1) {
+        end -= 1;
+    }
+    let mut out = text[..chars[end].0].trim_end().to_string();
+    while out.ends_with([',', ';', ':', '-', '(', '—']) {
+        out.pop();
+        out.truncate(out.trim_end().len());
+    }
+    out.push(ELLIPSIS);
+    out
+}
+
+/// Listing / search snippet: prose + list items, no headings, ≤ `max_chars`.
+pub(crate) fn summarize(raw_markdown: &str, max_chars: usize) -> String {
+    let mut text = String::new();
+    for (kind, block) in blocks(raw_markdown) {
+        if kind == BlockKind::Heading {
+            continue;
+        }
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&block);
+        if text.chars().count() > max_chars {
+            break;
+        }
+    }
+    truncate_text(&text, max_chars)
+}
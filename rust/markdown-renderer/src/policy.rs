@@ -0,0 +1,294 @@
+//! V26.6 Sanitisation policies + HTML post-processing.
+//!
+//! Named ammonia allowlists, selectable from JS:
+//!   strict — ammonia defaults without images/details/figures; relative URLs dropped
+//!   readme — defaults + `align`, highlight classes on code/pre/span
+//!   paper  — readme + footnote ids/classes, `<section>`
+//!
+//! `highlight` / `math` extend any policy with exactly what highlight.rs /
+//! math.rs emit: the `hl-*` classes on `<span>`, `language-*` on `<code>`,
+//! and the MathML element / attribute-value set.
+//!
+//! URL rewriting runs inside ammonia's attribute filter (so it sees raw HTML
+//! in READMEs as well as Markdown links): relative links resolve against the
+//! repo's blob URL, relative images against its raw/resolve URL, absolute
+//! images optionally go through the CDN proxy template. ammonia cannot add an
+//! attribute conditionally, so `rel`/`target` on external links and lazy-
+//! loading on images are added by a pass over the serialized output, whose
+//! attribute values are always double-quoted.
+
+use ammonia::url::form_urlencoded;
+use ammonia::{Builder, Url, UrlRelative};
+use napi_derive::napi;
+use regex::Regex;
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+const EXTERNAL_REL: &str = "nofollow noopener";
+const DEFAULT_LINK_TARGET: &str = "_blank";
+const DEFAULT_BRANCH: &str = "main";
+
+static RE_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<(a|img)((?:\s+[^\s"'>/=]+(?:="[^"]*")?)*)\s*/?>"#).unwrap());
+static RE_ATTR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"([^\s"'>/=]+)(?:="([^"]*)")?"#).unwrap());
+static RE_SCHEME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.\-]*:").unwrap());
+
+#[napi(object)]
+#[derive(Default, Clone)]
+pub struct SanitizeOptions {
+    /// "strict" | "readme" (default) | "paper"
+    pub policy: Option<String>,
+    /// Repository page (https://github.com/o/r, https://huggingface.co/o/r,
+    /// https://huggingface.co/datasets/o/r); relative URLs resolve against it.
+    pub repo_url: Option<String>,
+    /// Branch for `repo_url` (default "main").
+    pub branch: Option<String>,
+    /// Base for relative links; overrides the one derived from `repo_url`.
+    pub link_base: Option<String>,
+    /// Base for relative images; overrides the one derived from `repo_url`.
+    pub image_base: Option<String>,
+    /// CDN template for absolute http(s) images; `{url}` is replaced by the
+    /// percent-encoded source URL (appended when the placeholder is absent).
+    pub image_proxy: Option<String>,
+    /// Our own host; links to it (and subdomains) are not external.
+    pub site_host: Option<String>,
+    /// `target` for external links (default "_blank"; "" to omit).
+    pub link_target: Option<String>,
+    /// Add loading="lazy" decoding="async" to images (default true).
+    pub lazy_images: Option<bool>,
+    /// Highlight fenced code with `hl-*` class spans (default false).
+    pub highlight: Option<bool>,
+    /// Render `$..$` / `$$..$$` as MathML (default false).
+    pub math: Option<bool>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Strict,
+    Readme,
+    Paper,
+}
+
+/// Attribute rewriting state owned by the ammonia filter closure.
+struct Rewriter {
+    link_base: Option<Url>,
+    image_base: Option<Url>,
+    image_proxy: Option<String>,
+    class_prefixes: &'static [&'static str],
+    class_exact: &'static [&'static str],
+}
+
+pub(crate) struct Policy {
+    builder: Builder<'static>,
+    site_host: Option<String>,
+    link_target: String,
+    lazy_images: bool,
+    pub(crate) highlight: bool,
+    pub(crate) math: bool,
+}
+
+fn is_relative(url: &str) -> bool {
+    !url.starts_with('#') && !url.starts_with("//") && !RE_SCHEME.is_match(url)
+}
+
+/// (link base, image base) for a GitHub / Hugging Face repository URL.
+fn repo_bases(repo_url: &str, branch: &str) -> (Option<Url>, Option<Url>) {
+    let Ok(repo) = Url::parse(repo_url.trim_end_matches('/')) else { return (None, None) };
+    let path = repo.path().trim_matches('/').to_string();
+    let link = Url::parse(&format!("{}/blob/{branch}/", repo.as_str().trim_end_matches('/'))).ok();
+    let image = match repo.host_str() {
+        Some("github.com") | Some("www.github.com") => {
+            Url::parse(&format!("https://raw.githubusercontent.com/{path}/{branch}/")).ok()
+        }
+        Some(_) => Url::parse(&format!("{}/resolve/{branch}/", repo.as_str().trim_end_matches('/'))).ok(),
+        None => None,
+    };
+    (link, image)
+}
+
+fn parse_base(url: &str) -> Option<Url> {
+    let with_slash = if url.ends_with('/') { url.to_string() } else { format!("{url}/") };
+    Url::parse(&with_slash).ok()
+}
+
+impl Rewriter {
+    fn resolve<'u>(base: Option<&Url>, value: &'u str) -> Cow<'u, str> {
+        match base {
+            // Root-relative paths are repo-root relative on GitHub/HF pages.
+            Some(b) if is_relative(value) => b
+                .join(value.trim_start_matches('/'))
+                .map(|u| Cow::Owned(u.into()))
+                .unwrap_or(Cow::Borrowed(value)),
+            _ => Cow::Borrowed(value),
+        }
+    }
+
+    fn image<'u>(&self, value: &'u str) -> Cow<'u, str> {
+        let resolved = Self::resolve(self.image_base.as_ref(), value);
+        match &self.image_proxy {
+            Some(proxy) if resolved.starts_with("http://") || resolved.starts_with("https://") => {
+                let prefix = proxy.split("{url}").next().unwrap_or(proxy);
+                if resolved.starts_with(prefix) {
+                    return resolved;
+                }
+                let encoded: String = form_urlencoded::byte_serialize(resolved.as_bytes()).collect();
+                Cow::Owned(if proxy.contains("{url}") { proxy.replace("{url}", &encoded) } else { format!("{proxy}{encoded}") })
+            }
+            _ => resolved,
+        }
+    }
+
+    fn classes<'u>(&self, value: &'u str) -> Option<Cow<'u, str>> {
+        let kept: Vec<&str> = value
+            .split_whitespace()
+            .filter(|c| self.class_prefixes.iter().any(|p| c.starts_with(p)) || self.class_exact.contains(c))
+            .collect();
+        (!kept.is_empty()).then(|| Cow::Owned(kept.join(" ")))
+    }
+
+    fn filter<'u>(&self, element: &str, attribute: &str, value: &'u str) -> Option<Cow<'u, str>> {
+        match (element, attribute) {
+            ("a", "href") => Some(Self::resolve(self.link_base.as_ref(), value)),
+            ("img", "src") => Some(self.image(value)),
+            (_, "class") => self.classes(value),
+            _ => Some(Cow::Borrowed(value)),
+        }
+    }
+}
+
+impl Policy {
+    pub(crate) fn from_options(opts: &SanitizeOptions) -> Result<Policy, String> {
+        let kind = match opts.policy.as_deref().unwrap_or("readme") {
+            "strict" => Kind::Strict,
+            "readme" => Kind::Readme,
+            "paper" => Kind::Paper,
+            other => return Err(format!("unknown sanitize policy {other:?}")),
+        };
+        let branch = opts.branch.as_deref().unwrap_or(DEFAULT_BRANCH);
+        let (repo_link, repo_image) = opts.repo_url.as_deref().map(|r| repo_bases(r, branch)).unwrap_or((None, None));
+        let link_base = opts.link_base.as_deref().and_then(parse_base).or(repo_link);
+        let image_base = opts.image_base.as_deref().and_then(parse_base).or(repo_image).or_else(|| link_base.clone());
+
+        let mut b = Builder::default();
+        b.link_rel(None);
+        match kind {
+            Kind::Strict => {
+                b.rm_tags(["img", "details", "summary", "figure", "figcaption", "map", "area"]);
+                b.url_relative(UrlRelative::Deny);
+            }
+            Kind::Readme | Kind::Paper => {
+                for tag in ["p", "div", "h1", "h2", "h3", "h4", "h5", "h6"] {
+                    b.add_tag_attributes(tag, ["align"]);
+                }
+                for tag in ["code", "pre", "span"] {
+                    b.add_tag_attributes(tag, ["class"]);
+                }
+                if kind == Kind::Paper {
+                    b.add_tags(["section"]);
+                    for tag in ["div", "sup", "li", "section"] {
+                        b.add_tag_attributes(tag, ["id", "class"]);
+                    }
+                }
+            }
+        }
+        let highlight = opts.highlight.unwrap_or(false);
+        let math = opts.math.unwrap_or(false);
+        if highlight {
+            b.add_tag_attributes("code", ["class"]).add_tag_attributes("span", ["class"]);
+        }
+        if math {
+            b.add_tags(crate::math::TAGS);
+            for (tag, attribute, values) in crate::math::ATTRIBUTES {
+                b.add_tag_attribute_values(*tag, *attribute, values.iter().copied());
+            }
+        }
+        let rewriter = Rewriter {
+            link_base: if kind == Kind::Strict { None } else { link_base },
+            image_base: if kind == Kind::Strict { None } else { image_base },
+            image_proxy: opts.image_proxy.clone().filter(|p| !p.is_empty()),
+            class_prefixes: match kind {
+                Kind::Strict if highlight => &["language-"],
+                Kind::Strict => &[],
+                Kind::Readme => &["language-", "lang-", "hljs"],
+                Kind::Paper => &["language-", "lang-", "hljs", "footnote-"],
+            },
+            class_exact: if highlight { crate::highlight::CLASSES } else { &[] },
+        };
+        b.attribute_filter(move |element, attribute, value| rewriter.filter(element, attribute, value));
+
+        Ok(Policy {
+            builder: b,
+            site_host: opts.site_host.as_ref().map(|h| h.to_ascii_lowercase()),
+            link_target: opts.link_target.clone().unwrap_or_else(|| DEFAULT_LINK_TARGET.to_string()),
+            lazy_images: opts.lazy_images.unwrap_or(true),
+            highlight,
+            math,
+        })
+    }
+
+    /// Keep the `id`s `render_document` sets on headings.
+    pub(crate) fn allow_heading_ids(&mut self) {
+        for tag in ["h1", "h2", "h3", "h4", "h5", "h6"] {
+            self.builder.add_tag_attributes(tag, ["id"]);
+        }
+    }
+
+    fn is_external(&self, href: &str) -> bool {
+        let href = href.replace("&amp;", "&");
+        let Ok(url) = Url::parse(if href.starts_with("//") { format!("https:{href}") } else { href }.as_str()) else {
+            return false;
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+        match (&self.site_host, url.host_str()) {
+            (Some(site), Some(host)) => host != site && !host.ends_with(&format!(".{site}")),
+            _ => true,
+        }
+    }
+
+    /// Add rel/target to external links and lazy-loading to images.
+    fn post_process(&self, html: &str) -> String {
+        RE_TAG
+            .replace_all(html, |c: &regex::Captures| {
+                let tag = &c[1];
+                let mut attrs: Vec<(String, Option<String>)> = RE_ATTR
+                    .captures_iter(&c[2])
+                    .map(|a| (a[1].to_string(), a.get(2).map(|v| v.as_str().to_string())))
+                    .collect();
+                let external = tag == "a"
+                    && attrs
+                        .iter()
+                        .find(|(n, _)| n == "href")
+                        .and_then(|(_, v)| v.as_deref())
+                        .is_some_and(|href| self.is_external(href));
+                let mut set = |name: &str, value: &str| match attrs.iter_mut().find(|(n, _)| n == name) {
+                    Some(a) => a.1 = Some(value.to_string()),
+                    None => attrs.push((name.to_string(), Some(value.to_string()))),
+                };
+                if external {
+                    set("rel", EXTERNAL_REL);
+                    if !self.link_target.is_empty() {
+                        set("target", &self.link_target.replace('&', "&amp;").replace('"', "&quot;"));
+                    }
+                } else if tag == "img" && self.lazy_images {
+                    set("loading", "lazy");
+                    set("decoding", "async");
+                }
+                let mut out = format!("<{tag}");
+                for (name, value) in attrs {
+                    match value {
+                        Some(v) => out.push_str(&format!(" {name}=\"{v}\"")),
+                        None => out.push_str(&format!(" {name}")),
+                    }
+                }
+                out.push('>');
+                out
+            })
+            .into_owned()
+    }
+
+    pub(crate) fn clean(&self, html: &str) -> String {
+        self.post_process(&self.builder.clean(html).to_string())
+    }
+}
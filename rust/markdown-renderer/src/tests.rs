@@ -0,0 +1,482 @@
+use super::*;
+
+fn render(md: &str, options: SanitizeOptions) -> String {
+    render_html_with_policy(md.to_string(), Some(options)).unwrap()
+}
+
+#[test]
+fn test_render_html_default_unchanged() {
+    let html = render_html("# Title\n<details><summary>More</summary>x</details>\n<script>alert(1)</script>".into());
+    assert!(html.contains("<h1>Title</h1>"));
+    assert!(html.contains("<details>"));
+    assert!(!html.contains("script"));
+}
+
+#[test]
+fn test_readme_policy_links_images_and_classes() {
+    let md = "See [docs](docs/usage.md), [root](/LICENSE), [anchor](#install) and [site](https://example.org/x?a=1&b=2).\n\n\
+              ![logo](assets/logo.png) ![remote](https://img.shields.io/badge/x.svg)\n\n\
+              <p align=\"center\"><img src=\"./banner.png\" width=\"200\" onerror=\"x()\"></p>\n\n\
+              ```python\nprint(1)\n```\n\n<sup>1</sup> <span class=\"evil language-rust\">y</span>";
+    let opts = SanitizeOptions {
+        repo_url: Some("https://github.com/owner/repo".into()),
+        image_proxy: Some("https://cdn.example.com/img?u={url}".into()),
+        ..Default::default()
+    };
+    let html = render(md, opts);
+    assert!(html.contains(r#"href="https://github.com/owner/repo/blob/main/docs/usage.md""#), "{html}");
+    assert!(html.contains(r#"href="https://github.com/owner/repo/blob/main/LICENSE""#));
+    assert!(html.contains(r##"<a href="#install">"##));
+    assert!(html.contains(r#"href="https://example.org/x?a=1&amp;b=2" rel="nofollow noopener" target="_blank""#), "{html}");
+    assert!(html.contains(
+        "https://cdn.example.com/img?u=https%3A%2F%2Fraw.githubusercontent.com%2Fowner%2Frepo%2Fmain%2Fassets%2Flogo.png"
+    ));
+    assert!(html.contains("u=https%3A%2F%2Fimg.shields.io%2Fbadge%2Fx.svg"));
+    assert!(html.contains(r#"<p align="center">"#));
+    assert!(html.contains("banner.png"));
+    assert!(!html.contains("onerror"));
+    assert_eq!(html.matches(r#"loading="lazy" decoding="async""#).count(), 3);
+    assert!(html.contains(r#"<code class="language-python">"#));
+    assert!(html.contains(r#"<span class="language-rust">"#));
+    assert!(html.contains("<sup>1</sup>"));
+}
+
+#[test]
+fn test_strict_and_paper_policies() {
+    let md = "[rel](docs/a.md) [abs](https://hf.co/x) ![i](https://x.org/a.png)\n\n<details><summary>s</summary>d</details>";
+    let strict = SanitizeOptions {
+        policy: Some("strict".into()),
+        repo_url: Some("https://huggingface.co/org/model".into()),
+        link_target: Some(String::new()),
+        ..Default::default()
+    };
+    let html = render(md, strict);
+    assert!(!html.contains("docs/a.md"));
+    assert!(html.contains(r#"<a href="https://hf.co/x" rel="nofollow noopener">"#), "{html}");
+    assert!(!html.contains("<img") && !html.contains("<details>"));
+
+    let site = SanitizeOptions { site_host: Some("hf.co".into()), repo_url: Some("https://huggingface.co/org/model".into()), ..Default::default() };
+    let html = render("[abs](https://hf.co/x) ![i](images/a.png)", site);
+    assert!(html.contains(r#"<a href="https://hf.co/x">"#), "{html}");
+    assert!(html.contains(r#"src="https://huggingface.co/org/model/resolve/main/images/a.png""#));
+
+    let paper = SanitizeOptions { policy: Some("paper".into()), ..Default::default() };
+    let html = render("Claim[^1].\n\n[^1]: Source.", paper);
+    assert!(html.contains(r#"class="footnote-reference""#), "{html}");
+    assert!(html.contains(r#"class="footnote-definition" id="1""#) || html.contains(r#"id="1" class="footnote-definition""#), "{html}");
+
+    assert!(render_html_with_policy("x".into(), Some(SanitizeOptions { policy: Some("lax".into()), ..Default::default() })).is_err());
+}
+
+#[test]
+fn test_front_matter_yaml_subset() {
+    let md = "---\nlicense: apache-2.0 # comment\ntags:\n- text-generation\n- 'llama'\nlanguage: [en, \"fr\"]\nmodel:\n  params: 7e9\n  quantized: false\n  notes: |\n    line one\n    line two\n---\n# Body\n";
+    let (yaml, body) = frontmatter::split_front_matter(md).unwrap();
+    assert_eq!(body, "# Body\n");
+    let v = frontmatter::parse_yaml(yaml).unwrap();
+    assert_eq!(v["license"], "apache-2.0");
+    assert_eq!(v["tags"], serde_json::json!(["text-generation", "llama"]));
+    assert_eq!(v["language"], serde_json::json!(["en", "fr"]));
+    assert_eq!(v["model"]["params"], 7e9);
+    assert_eq!(v["model"]["quantized"], false);
+    assert_eq!(v["model"]["notes"], "line one\nline two\n");
+
+    assert!(frontmatter::split_front_matter("---\nno: close\n").is_none());
+    assert!(frontmatter::parse_yaml("a: [1, 2\nb: 3").is_err());
+    assert!(frontmatter::parse_yaml("a: 1\n    b: 2").is_err());
+}
+
+#[test]
+fn test_front_matter_stray_flow_closers_error() {
+    for src in ["a: [}]", "a: {]}", "a: [,]", "a: {:}", "a: [[}]]", "a: {b: ]}"] {
+        assert!(frontmatter::parse_yaml(src).is_err(), "{src}");
+    }
+    assert_eq!(frontmatter::parse_yaml("a: [1, 2,]").unwrap()["a"], serde_json::json!([1, 2]));
+    assert_eq!(frontmatter::parse_yaml("a: {b: , c: 1,}").unwrap()["a"], serde_json::json!({"b": null, "c": 1}));
+}
+
+#[test]
+fn test_front_matter_deep_nesting_errors() {
+    let n = 200_000;
+    let flow = format!("tags: {}{}", "[".repeat(n), "]".repeat(n));
+    let flow_map = format!("a: {}{}", "{b: ".repeat(n), "}".repeat(n));
+    let block_seq = format!("{}x", "- ".repeat(n));
+    let block_map: String = (0..1_000).map(|i| format!("{}k:\n", " ".repeat(i))).collect();
+    for src in [flow, flow_map, block_seq, block_map] {
+        let err = frontmatter::parse_yaml(&src).unwrap_err();
+        assert!(err.contains("nesting too deep"), "{err}");
+    }
+    // Nesting well inside the limit still parses.
+    let ok = format!("a: {}1{}", "[".repeat(32), "]".repeat(32));
+    assert!(frontmatter::parse_yaml(&ok).is_ok());
+    assert!(frontmatter::parse_yaml(&format!("{}x", "- ".repeat(32))).is_ok());
+}
+
+#[test]
+fn test_render_document_anchors_toc_and_metadata() {
+    let md = "---\ntitle: Demo\n---\n# Demo Model\n\nIntro with ![shot](img/shot.png) and words.\n\n\
+              ## Usage\n\n```Python title=x\nimport demo\n```\n\n### Usage\n\n```bash\npip install demo\n```\n\n\
+              ## Usage\n\n## C++ & Rust: `api`!\n\n```python\nx\n```\n\n# Notes\n";
+    let opts = DocumentOptions {
+        sanitize: Some(SanitizeOptions { repo_url: Some("https://github.com/o/r".into()), ..Default::default() }),
+        words_per_minute: Some(5),
+    };
+    let doc = render_document(md.into(), Some(opts)).unwrap();
+    assert!(doc.html.contains(r#"<h1 id="demo-model">Demo Model</h1>"#), "{}", doc.html);
+    assert!(doc.html.contains(r#"<h3 id="usage-1">"#));
+    assert!(doc.html.contains(r#"<h2 id="usage-2">"#));
+    assert!(doc.html.contains(r#"<h2 id="c--rust-api">"#), "{}", doc.html);
+    assert!(doc.html.contains(r#"<h1 id="notes">"#));
+    assert!(!doc.html.contains("title: Demo"));
+
+    assert_eq!(doc.toc.len(), 2);
+    let top = &doc.toc[0];
+    assert_eq!((top.level, top.anchor.as_str()), (1, "demo-model"));
+    assert_eq!(top.children.iter().map(|c| c.anchor.as_str()).collect::<Vec<_>>(), ["usage", "usage-2", "c--rust-api"]);
+    assert_eq!(top.children[0].children[0].anchor, "usage-1");
+    assert_eq!(top.children[2].text, "C++ & Rust: api!");
+
+    let m = &doc.metadata;
+    assert_eq!(m.first_image.as_deref(), Some("https://raw.githubusercontent.com/o/r/main/img/shot.png"));
+    assert_eq!(m.code_languages, ["python", "bash"]);
+    assert_eq!(m.word_count, 14);
+    assert_eq!(m.reading_time_minutes, 3);
+    assert_eq!(m.front_matter.as_deref(), Some(r#"{"title":"Demo"}"#));
+    assert!(m.front_matter_error.is_none());
+
+    let bad = render_document("---\na: [1\n---\ntext".into(), None).unwrap();
+    assert!(bad.metadata.front_matter.is_none() && bad.metadata.front_matter_error.is_some());
+    assert_eq!(bad.html.trim(), "<p>text</p>");
+}
+
+fn model_card(name: &str) -> ModelCard {
+    let path = format!("{}/fixtures/model-cards/{name}", env!("CARGO_MANIFEST_DIR"));
+    parse_model_card(std::fs::read_to_string(path).unwrap())
+}
+
+#[test]
+fn test_model_card_typed_fields() {
+    let bert = model_card("bert-base.md").metadata;
+    assert_eq!(bert.status, "ok");
+    assert_eq!(bert.language, ["en"]);
+    assert_eq!(bert.datasets, ["bookcorpus", "wikipedia"]);
+    assert_eq!(bert.license.as_deref(), Some("apache-2.0"));
+
+    let llama = model_card("llama-gated.md");
+    let m = &llama.metadata;
+    assert_eq!((m.status.as_str(), m.license.as_deref(), m.license_link.as_deref()), ("ok", Some("llama3.1"), Some("LICENSE")));
+    assert_eq!(m.language, ["en", "de", "fr"]);
+    assert_eq!(m.pipeline_tag.as_deref(), Some("text-generation"));
+    assert_eq!(m.library_name.as_deref(), Some("transformers"));
+    assert!(m.warnings.is_empty(), "{:?}", m.warnings);
+    assert!(llama.body.starts_with("\n## Model Information"));
+    let raw: serde_json::Value = serde_json::from_str(m.raw.as_deref().unwrap()).unwrap();
+    assert_eq!(raw["extra_gated_fields"]["Date of birth"], "date_picker");
+
+    let ft = model_card("finetune-model-index.md").metadata;
+    assert_eq!(ft.base_model, ["microsoft/deberta-v3-base"]);
+    assert_eq!(ft.metrics, ["accuracy", "f1"]);
+    assert_eq!(ft.eval_results.len(), 2);
+    let f1 = &ft.eval_results[1];
+    assert_eq!(f1.model_name.as_deref(), Some("deberta-v3-base-mrpc"));
+    assert_eq!((f1.task_type.as_deref(), f1.dataset_config.as_deref(), f1.dataset_split.as_deref()), (Some("text-classification"), Some("mrpc"), Some("validation")));
+    assert_eq!((f1.metric_type.as_deref(), f1.verified), (Some("f1"), Some(false)));
+    assert!((f1.value.unwrap() - 0.9225473321858864).abs() < 1e-12);
+
+    let gguf = model_card("leaderboard-gguf.md").metadata;
+    assert_eq!(gguf.status, "ok", "{:?}", gguf.warnings);
+    assert_eq!(gguf.base_model, ["mistralai/Mistral-7B-v0.1", "HuggingFaceH4/zephyr-7b-beta"]);
+    assert_eq!(gguf.base_model_relation.as_deref(), Some("merge"));
+    assert_eq!(gguf.datasets, ["HuggingFaceH4/ultrachat_200k", "HuggingFaceH4/ultrafeedback_binarized"]);
+    assert_eq!(gguf.language, ["en"]);
+    let mmlu = &gguf.eval_results[0];
+    assert_eq!((mmlu.dataset_name.as_deref(), mmlu.value, mmlu.value_text.as_deref()), (Some("MMLU (5-Shot)"), Some(63.41), Some("63.41%")));
+    assert!(mmlu.source_url.as_deref().unwrap().contains("open_llm_leaderboard"));
+    let hs = &gguf.eval_results[1];
+    assert_eq!((hs.metric_type.as_deref(), hs.value, hs.value_text.as_deref()), (Some("acc_norm"), None, Some("n/a")));
+
+    // Kept inline: .gitattributes normalises .md fixtures to LF.
+    let crlf = parse_model_card(
+        "---\r\nlicense: cc-by-4.0\r\nlanguage:\r\n- ja\r\npipeline_tag: automatic-speech-recognition\r\n---\r\n# Windows line endings\r\n".into(),
+    );
+    assert_eq!((crlf.metadata.license.as_deref(), crlf.metadata.language.clone()), (Some("cc-by-4.0"), vec!["ja".to_string()]));
+    assert_eq!(crlf.body, "# Windows line endings\r\n");
+}
+
+#[test]
+fn test_model_card_malformed_yaml_recovery() {
+    let card = model_card("malformed.md");
+    let m = &card.metadata;
+    assert_eq!(m.status, "recovered");
+    assert_eq!(m.license.as_deref(), Some("openrail"));
+    assert_eq!(m.base_model, ["runwayml/stable-diffusion-v1-5"]);
+    assert!(m.tags.is_empty() && m.datasets.is_empty());
+    let raw: serde_json::Value = serde_json::from_str(m.raw.as_deref().unwrap()).unwrap();
+    assert_eq!(raw["inference"], true);
+    for key in ["\"tags\"", "\"datasets\"", "\"widget\""] {
+        assert!(m.warnings.iter().any(|w| w.contains(key)), "{key} not in {:?}", m.warnings);
+    }
+    assert_eq!(card.body, "# Broken YAML still renders\n");
+
+    // Stray flow closers used to spin forever in the flow parser.
+    let stray = model_card("stray-closer.md").metadata;
+    assert_eq!(stray.status, "recovered");
+    assert_eq!((stray.license.as_deref(), stray.pipeline_tag.as_deref()), (Some("mit"), Some("text-classification")));
+    assert!(stray.tags.is_empty() && stray.datasets.is_empty());
+    for key in ["\"tags\"", "\"datasets\""] {
+        assert!(stray.warnings.iter().any(|w| w.contains(key)), "{key} not in {:?}", stray.warnings);
+    }
+
+    // Nesting past the front matter depth cap is a per-key error, not a crash.
+    let deep = model_card("deep-nesting.md");
+    assert_eq!(deep.metadata.status, "recovered");
+    assert_eq!((deep.metadata.license.as_deref(), deep.metadata.pipeline_tag.as_deref()), (Some("apache-2.0"), Some("fill-mask")));
+    assert!(deep.metadata.tags.is_empty() && deep.metadata.base_model.is_empty());
+    for key in ["\"tags\"", "\"base_model\""] {
+        assert!(deep.metadata.warnings.iter().any(|w| w.contains(key) && w.contains("nesting too deep")), "{key} not in {:?}", deep.metadata.warnings);
+    }
+    assert_eq!(deep.body, "# Pathologically nested front matter\n");
+
+    let unclosed = model_card("unclosed.md");
+    assert_eq!(unclosed.metadata.status, "absent");
+    assert_eq!(unclosed.metadata.warnings.len(), 1);
+    assert!(unclosed.body.starts_with("---\nlicense: mit"));
+
+    let plain = parse_model_card("# No metadata".into());
+    assert_eq!((plain.metadata.status.as_str(), plain.metadata.raw.is_none()), ("absent", true));
+    let list = parse_model_card("---\n- a\n- b\n---\nx".into());
+    assert_eq!(list.metadata.status, "invalid");
+    let wrong = parse_model_card("---\nlicense: [mit, apache-2.0]\ntags: {a: 1}\n---\n".into());
+    assert_eq!(wrong.metadata.license.as_deref(), Some("mit"));
+    assert_eq!(wrong.metadata.warnings.len(), 2, "{:?}", wrong.metadata.warnings);
+}
+
+fn enhanced(md: &str, highlight: bool, math: bool) -> String {
+    render(md, SanitizeOptions { highlight: Some(highlight), math: Some(math), ..Default::default() })
+}
+
+#[test]
+fn test_highlight_code_blocks() {
+    let md = "```rust\n#[derive(Debug)]\nfn main() { let s: &str = \"a<b\"; // hi\n    println!(\"{}\", 42); }\n```\n\n\
+              ```python\n@cache\ndef f(x=None):\n    return 'x'  # done\n```\n\n\
+              ```bash\npip install \"$PKG\" && echo ${HOME}\n```\n\n\
+              ```json\n{\"k\": [1, true]}\n```\n\n```html\n<a href=\"x\">t</a>\n```\n\n```brainfuck\n+[<>]\n```";
+    let html = enhanced(md, true, false);
+    assert!(html.contains(r#"<code class="language-rust"><span class="hl-attr">#[derive(Debug)]</span>"#), "{html}");
+    assert!(html.contains(r#"<span class="hl-keyword">fn</span> <span class="hl-function">main</span>"#));
+    assert!(html.contains(r#"<span class="hl-type">str</span>"#));
+    assert!(html.contains(r#"<span class="hl-string">"a&lt;b"</span>"#));
+    assert!(html.contains(r#"<span class="hl-comment">// hi</span>"#));
+    assert!(html.contains(r#"<span class="hl-function">println</span>!"#));
+    assert!(html.contains(r#"<span class="hl-number">42</span>"#));
+    assert!(html.contains(r#"<span class="hl-attr">@cache</span>"#));
+    assert!(html.contains(r#"<span class="hl-literal">None</span>"#));
+    assert!(html.contains(r#"<span class="hl-comment"># done</span>"#));
+    assert!(html.contains(r#"<span class="hl-string">"$PKG"</span>"#));
+    assert!(html.contains(r#"<span class="hl-variable">${HOME}</span>"#));
+    assert!(html.contains(r#"<span class="hl-key">"k"</span>"#));
+    assert!(html.contains(r#"<span class="hl-tag">&lt;a</span> <span class="hl-attr">href</span>="#));
+    assert!(html.contains("<code class=\"language-brainfuck\">+[&lt;&gt;]\n</code>"));
+
+    // Only the produced classes survive; the raw HTML spans are re-sanitised.
+    let html = enhanced("<span class=\"hl-keyword evil\">x</span> <span class=\"hl-bogus\">y</span>", true, false);
+    assert!(html.contains(r#"<span class="hl-keyword">x</span> <span>y</span>"#), "{html}");
+    let strict = SanitizeOptions { policy: Some("strict".into()), highlight: Some(true), ..Default::default() };
+    assert!(render("```py\nx = 1\n```", strict).contains(r#"<span class="hl-number">1</span>"#));
+    // Text content is exactly the unhighlighted block.
+    let plain = |h: &str| regex::Regex::new("<[^>]+>").unwrap().replace_all(h, "").into_owned();
+    assert_eq!(plain(&enhanced(md, true, false)), plain(&enhanced(md, false, false)));
+}
+
+#[test]
+fn test_math_to_mathml() {
+    let cases = [
+        ("x^2", "<math><msup><mi>x</mi><mn>2</mn></msup></math>"),
+        ("a_{ij}^{2}", "<math><msubsup><mi>a</mi><mrow><mi>i</mi><mi>j</mi></mrow><mn>2</mn></msubsup></math>"),
+        (r"\frac{1}{\sqrt{n}}", "<math><mfrac><mn>1</mn><msqrt><mi>n</mi></msqrt></mfrac></math>"),
+        (r"\alpha \leq 3.5", "<math><mrow><mi>α</mi><mo>≤</mo><mn>3.5</mn></mrow></math>"),
+        (r"\mathbb{R}^d", r#"<math><msup><mi mathvariant="double-struck">R</mi><mi>d</mi></msup></math>"#),
+        (r"\hat{y}", r#"<math><mover accent="true"><mi>y</mi><mo>^</mo></mover></math>"#),
+        (r"f'(x)", "<math><mrow><msup><mi>f</mi><mo>′</mo></msup><mo>(</mo><mi>x</mi><mo>)</mo></mrow></math>"),
+    ];
+    for (tex, expected) in cases {
+        assert_eq!(math::tex_to_mathml(tex, false).unwrap(), expected, "{tex}");
+    }
+    assert_eq!(
+        math::tex_to_mathml(r"\sum_{i=1}^n x_i", true).unwrap(),
+        "<math display=\"block\"><mrow><munderover><mo>∑</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></munderover><msub><mi>x</mi><mi>i</mi></msub></mrow></math>"
+    );
+    assert!(math::tex_to_mathml(r"\begin{matrix}a\end{matrix}", true).is_err());
+    assert!(math::tex_to_mathml("x^{2", false).is_err());
+    // Deep nesting is an error (→ literal fallback), not a stack overflow.
+    let n = 100_000;
+    for tex in [
+        format!("{}x{}", "{".repeat(n), "}".repeat(n)),
+        format!("{}x{}", "x^{".repeat(n), "}".repeat(n)),
+        format!("{}x{}", r"\sqrt[".repeat(n), "]".repeat(n)),
+        format!("{}x", r"\hat".repeat(n)),
+    ] {
+        assert_eq!(math::tex_to_mathml(&tex, false).unwrap_err(), "nesting too deep");
+    }
+    assert!(math::tex_to_mathml(&format!("{}x{}", "{".repeat(32), "}".repeat(32)), false).is_ok());
+    let deep = format!("Deep ${}x{}$ end", "{".repeat(n), "}".repeat(n));
+    assert!(enhanced(&deep, false, true).contains(&deep[..20]));
+
+    let html = enhanced("Loss $\\mathcal{L} = -\\log p$ and\n\n$$\\left( \\frac{a}{b} \\right)$$\n\nbad $\\foo{x}$, costs $5 or $6.", false, true);
+    assert!(html.contains(r#"Loss <math><mrow><mi mathvariant="script">L</mi><mo>=</mo><mo>−</mo><mi>log</mi><mi>p</mi></mrow></math> and"#), "{html}");
+    assert!(html.contains(r#"<math display="block"><mrow><mo>(</mo><mfrac><mi>a</mi><mi>b</mi></mfrac><mo>)</mo></mrow></math>"#));
+    assert!(html.contains(r"bad $\foo{x}$, costs $5 or $6."));
+    // The policy keeps exactly the MathML we emit.
+    let html = enhanced("<math display=\"inline\" href=\"x\"><mi mathvariant=\"evil\">a</mi><mglyph></mglyph></math>", false, true);
+    assert!(html.contains("<math><mi>a</mi></math>"), "{html}");
+}
+
+#[test]
+fn test_highlight_non_ascii_markup() {
+    // Multi-byte chars right after `<` and at the start of a text run.
+    let html = enhanced("```html\n<p>é</p>\n```\n\n```xml\n<é attr=\"ü\">中</é>\n```", true, false);
+    assert!(html.contains(r#"<span class="hl-tag">&lt;p</span><span class="hl-tag">&gt;</span>é"#), "{html}");
+    assert!(html.contains(r#"<span class="hl-tag">&lt;é</span>"#), "{html}");
+    let html = render_html_with_policy("```html\n<é\n```".into(), Some(SanitizeOptions { highlight: Some(true), ..Default::default() })).unwrap();
+    assert!(html.contains("&lt;é"), "{html}");
+}
+
+#[test]
+fn test_highlight_fuzz_utf8_round_trips() {
+    const LANGS: &[&str] = &[
+        "rust", "python", "javascript", "typescript", "bash", "json", "yaml", "toml", "c", "cpp", "go", "java", "sql", "html",
+    ];
+    const ALPHABET: &[char] = &[
+        'a', 'Z', '_', '0', '9', ' ', '\t', '\n', '<', '>', '/', '!', '?', '-', '"', '\'', '`', '\\', '#', '@', '$', '{', '}',
+        '[', ']', '(', ')', ':', '=', '.', ',', ';', '&', '*', '+', 'e', 'é', 'ß', '中', '🚀', '\u{a0}', '\u{301}', '\u{2028}',
+    ];
+    let unescape = |s: &str| s.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&amp;", "&");
+    let span = regex::Regex::new(r#"<span class="hl-[a-z]+">|</span>"#).unwrap();
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for lang in LANGS {
+        for _ in 0..500 {
+            let len = (next() % 24) as usize;
+            let code: String = (0..len).map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize]).collect();
+            let html = highlight::highlight(lang, &code).unwrap();
+            let body = html
+                .strip_prefix(&format!("<pre><code class=\"language-{lang}\">"))
+                .and_then(|h| h.strip_suffix("</code></pre>\n"))
+                .unwrap();
+            assert_eq!(unescape(&span.replace_all(body, "")), code, "{lang}: {code:?}");
+        }
+    }
+}
+
+#[test]
+fn test_highlight_and_math_parity() {
+    let md = "# Title\n\nSome *text* with `code`, a [link](https://example.org) and a table:\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n\
+              - [x] task\n- item ~~old~~\n\nLine one\nline two[^n].\n\n[^n]: Note.\n\n    indented code\n\n<div align=\"center\">raw</div>\n";
+    let base = enhanced(md, false, false);
+    assert_eq!(enhanced(md, true, true), base);
+    assert_eq!(render_html_with_policy(md.into(), None).unwrap(), base);
+
+    let mixed = format!("{md}\n```python\nx = 1\n```\n\nInline $x^2$ here.\n");
+    let strip = |h: &str| {
+        let h = regex::Regex::new(r"(?s)<pre>.*?</pre>").unwrap().replace_all(h, "");
+        regex::Regex::new(r"(?s)<math>.*?</math>|\$x\^2\$").unwrap().replace_all(&h, "").into_owned()
+    };
+    assert_eq!(strip(&enhanced(&mixed, true, true)), strip(&enhanced(&mixed, false, false)));
+}
+
+#[test]
+fn test_render_shard_dir_writes_sidecar() {
+    let base = std::env::temp_dir().join(format!("md_render_batch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    let shards = base.join("shards");
+    std::fs::create_dir_all(&shards).unwrap();
+    let entities: Vec<serde_json::Value> = (0..40)
+        .map(|i| serde_json::json!({"id": format!("hf-model--o--m{i}"), "readme": format!("# Model {i}\n\n## Usage\n\nRun it <script></script>.")}))
+        .collect();
+    std::fs::write(shards.join("part-000.json"), serde_json::json!({ "entities": entities }).to_string()).unwrap();
+    let second = serde_json::json!({ "entities": [
+        {"id": "gh-tool--a--b", "umid": "0a1b2c3d4e5f6789", "readme": "", "body_content": "Plain body text here."},
+        {"id": "gh-tool--a--empty"},
+        {"readme": "no id"},
+        {"id": "hf-model--o--stray", "readme": "---\ntags: [}]\n---\n# Stray"},
+    ]});
+    std::fs::write(shards.join("part-001.json"), second.to_string()).unwrap();
+
+    let opts = RenderBatchOptions { threads: Some(4), ..Default::default() };
+    let stats = RenderShardDirTask {
+        shard_dir: shards.to_string_lossy().into_owned(),
+        output_dir: base.join("out").to_string_lossy().into_owned(),
+        options: opts,
+    }
+    .compute()
+    .unwrap();
+    assert_eq!((stats.shard_count, stats.entity_count, stats.rendered_count, stats.error_count), (2, 44, 42, 1));
+    assert_eq!(stats.shards[0].part, "part-000.json");
+    assert_eq!((stats.shards[1].rendered_count, stats.shards[1].error_count), (2, 1));
+    assert!(stats.shards.iter().all(|s| s.parse_error.is_none()));
+    assert!(!base.join("out/rendered.ndjson.zst.tmp").exists());
+
+    let raw = zstd::decode_all(std::fs::File::open(&stats.output_file).unwrap()).unwrap();
+    let lines: Vec<serde_json::Value> =
+        String::from_utf8(raw).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 42);
+    // Input order is kept regardless of thread count.
+    assert_eq!(lines[7]["umid"], nxvf_core::sha256_hex16("hf-model--o--m7"));
+    assert!(lines[7]["html"].as_str().unwrap().starts_with(r#"<h1 id="model-7">Model 7</h1>"#));
+    assert!(!lines[7]["html"].as_str().unwrap().contains("script"));
+    assert_eq!(lines[7]["toc"][0]["children"][0]["anchor"], "usage");
+    assert_eq!(lines[7]["word_count"], 5);
+    assert_eq!(lines[40]["umid"], "0a1b2c3d4e5f6789");
+    assert_eq!(lines[40]["html"], "<p>Plain body text here.</p>\n");
+    // Broken front matter (`[}]`) is an error for the YAML only; the body renders.
+    assert_eq!(lines[41]["html"], r#"<h1 id="stray">Stray</h1>"#.to_string() + "\n");
+    let _ = std::fs::remove_dir_all(&base);
+}
+
+const BADGE_README: &str = "---\nlicense: mit\n---\n\
+    # Fast Tool [![PyPI](https://img.shields.io/pypi/v/fast.svg)](https://pypi.org/project/fast)\n\n\
+    [![CI](https://github.com/o/fast/actions/workflows/ci.yml/badge.svg)](https://github.com/o/fast/actions) \
+    [Docs status](https://img.shields.io/badge/docs-ok-green) <img src=\"https://badgen.net/x\">\n\n\
+    <p align=\"center\"><img src=\"logo.png\"></p>\n\n\
+    **Fast Tool** is a *tiny* library for [parsing](https://example.org/p) `YAML` files. It is 10× faster!\n\n\
+    | a | b |\n|---|---|\n| 1 | 2 |\n\n\
+    ```bash\npip install fast\n```\n\n\
+    - first item\n- second ![diagram](img/d.png) item\n\n## Usage\n\nSee footnote[^1].\n\n[^1]: Hidden note.\n";
+
+#[test]
+fn test_to_plain_text() {
+    assert_eq!(
+        to_plain_text(BADGE_README.into()),
+        "Fast Tool\n\n\
+         Fast Tool is a tiny library for parsing YAML files. It is 10× faster!\n\n\
+         first item\nsecond diagram item\n\n\
+         Usage\n\nSee footnote."
+    );
+}
+
+#[test]
+fn test_summarize_boundaries() {
+    assert_eq!(
+        summarize(BADGE_README.into(), 200),
+        "Fast Tool is a tiny library for parsing YAML files. It is 10× faster! first item second diagram item See footnote."
+    );
+    // Sentence boundary within budget wins.
+    assert_eq!(summarize(BADGE_README.into(), 60), "Fast Tool is a tiny library for parsing YAML files.");
+    // Word boundary + ellipsis, counted in the budget.
+    let s = summarize(BADGE_README.into(), 40);
+    assert_eq!(s, "Fast Tool is a tiny library for parsing…");
+    assert!(s.chars().count() <= 40);
+    // CJK sentence ends without spaces; no whitespace → hard cut by chars.
+    assert_eq!(plaintext::truncate_text("这是第一句。这是第二句很长很长很长", 10), "这是第一句。");
+    assert_eq!(plaintext::truncate_text("ааааааааааааааааааааа", 5), "аааа…");
+    // Never split a combining sequence or an emoji modifier.
+    assert_eq!(plaintext::truncate_text("abcde\u{301}fghij", 6), "abcd…");
+    assert_eq!(plaintext::truncate_text("xx👍\u{1F3FD}yyyyyy", 4), "xx…");
+    assert_eq!(summarize("Short.".into(), 100), "Short.");
+    assert_eq!(summarize("# Only a heading".into(), 100), "");
+}
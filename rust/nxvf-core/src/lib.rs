@@ -16,6 +16,8 @@ use std::{fs, path::Path};
 
 pub mod parse_report;
 pub use parse_report::{DropClass, DropRecord, ShardParseReport};
+pub mod typed;
+pub use typed::read_typed_shard_with_report;
 
 // ── NXVF V4.1 Constants ────────────────────────────────────────────
 
@@ -23,6 +25,7 @@ const HEADER_SIZE: usize = 29;
 const NXVF_MAGIC: [u8; 4] = [0x4E, 0x58, 0x56, 0x46]; // "NXVF"
 const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
 const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const NXVF_VERSION: u8 = 0x41; // V4.1 (shard-writer.js SHARD_VERSION)
 
 type Aes256Ctr = Ctr128BE<Aes256>;
 
@@ -111,6 +114,8 @@ fn decrypt_payload(key: &[u8; 32], shard_name: &str, payload: &[u8], offset: u32
 
 /// Minimal struct for stats extraction — serde skips all other fields
 /// (body_content, readme, html_readme etc.) without allocating memory.
+/// Stratum fields are read leniently: a non-string value counts as absent
+/// instead of failing the whole entity.
 #[derive(serde::Deserialize)]
 struct SlimEntity {
     #[serde(default)]
@@ -119,35 +124,94 @@ struct SlimEntity {
     fni_score: Option<f64>,
     #[serde(default)]
     fni: Option<f64>,
+    #[serde(default, rename = "type")]
+    entity_type: Option<serde_json::Value>,
+    #[serde(default, rename = "entity_type")]
+    entity_type_alt: Option<serde_json::Value>,
+    #[serde(default)]
+    primary_category: Option<serde_json::Value>,
+    #[serde(default)]
+    source: Option<serde_json::Value>,
+}
+
+/// V26.6: (id, fni_score) plus the stratum keys percentiles are also ranked
+/// within. A missing key is "".
+pub struct ScoredEntity {
+    pub id: String,
+    pub score: f64,
+    /// `type`, else `entity_type`
+    pub entity_type: String,
+    pub primary_category: String,
+    pub source: String,
+}
+
+fn lenient_str(v: Option<&serde_json::Value>) -> Option<&str> {
+    v.and_then(|v| v.as_str()).filter(|s| !s.is_empty())
+}
+
+impl ScoredEntity {
+    /// From an already-parsed entity (None without an id).
+    pub fn from_value(e: &serde_json::Value) -> Option<Self> {
+        let id = e.get("id")?.as_str()?;
+        if id.is_empty() {
+            return None;
+        }
+        let score = e
+            .get("fni_score")
+            .and_then(|v| v.as_f64())
+            .or_else(|| e.get("fni").and_then(|v| v.as_f64()))
+            .unwrap_or(0.0);
+        Some(ScoredEntity {
+            id: id.to_string(),
+            score,
+            entity_type: lenient_str(e.get("type")).or(lenient_str(e.get("entity_type"))).unwrap_or("").to_string(),
+            primary_category: lenient_str(e.get("primary_category")).unwrap_or("").to_string(),
+            source: lenient_str(e.get("source")).unwrap_or("").to_string(),
+        })
+    }
+}
+
+impl SlimEntity {
+    fn into_scored(self) -> Option<ScoredEntity> {
+        if self.id.is_empty() {
+            return None;
+        }
+        let entity_type = lenient_str(self.entity_type.as_ref())
+            .or(lenient_str(self.entity_type_alt.as_ref()))
+            .unwrap_or("")
+            .to_string();
+        Some(ScoredEntity {
+            entity_type,
+            primary_category: lenient_str(self.primary_category.as_ref()).unwrap_or("").to_string(),
+            source: lenient_str(self.source.as_ref()).unwrap_or("").to_string(),
+            score: self.fni_score.or(self.fni).unwrap_or(0.0),
+            id: self.id,
+        })
+    }
 }
 
 /// Extract (id, fni_score) from a shard file. O(1 entity) memory —
 /// each entity payload is decoded, slim-parsed (serde skips body_content),
 /// then immediately dropped. No full-entity Vec accumulation.
 pub fn extract_scores_from_shard(file_path: &str) -> Result<Vec<(String, f64)>, String> {
+    Ok(extract_scored_entities_from_shard(file_path)?
+        .into_iter()
+        .map(|s| (s.id, s.score))
+        .collect())
+}
+
+/// V26.6: Same as `extract_scores_from_shard` plus type / primary_category /
+/// source, for per-stratum FNI percentiles and thresholds.
+pub fn extract_scored_entities_from_shard(file_path: &str) -> Result<Vec<ScoredEntity>, String> {
     if file_path.ends_with(".bin") {
         return extract_scores_from_binary_shard(file_path);
     }
     // JSON.gz/.json.zst/.json fallback — must full-parse (rare legacy path)
     let entities = load_shard_entities(file_path)?;
-    Ok(entities
-        .iter()
-        .filter_map(|e| {
-            let id = e.get("id")?.as_str()?;
-            if id.is_empty() {
-                return None;
-            }
-            let score = e
-                .get("fni_score")
-                .and_then(|v| v.as_f64())
-                .or_else(|| e.get("fni").and_then(|v| v.as_f64()))
-                .unwrap_or(0.0);
-            Some((id.to_string(), score))
-        })
-        .collect())
+    Ok(entities.iter().filter_map(ScoredEntity::from_value).collect())
 }
 
-fn extract_scores_from_binary_shard(file_path: &str) -> Result<Vec<(String, f64)>, String> {
+fn extract_scores_from_binary_shard(file_path: &str) -> Result<Vec<ScoredEntity>, String> {
     let data = fs::read(file_path).map_err(|e| format!("Cannot read {}: {}", file_path, e))?;
     if data.len() < HEADER_SIZE || data[0..4] != NXVF_MAGIC {
         return Err(format!("Invalid NXVF: {}", file_path));
@@ -199,10 +263,8 @@ fn extract_scores_from_binary_shard(file_path: &str) -> Result<Vec<(String, f64)
             }
         }
         // Slim parse: serde only allocates id + fni_score, skips body_content/readme/etc.
-        if let Ok(e) = serde_json::from_slice::<SlimEntity>(&payload) {
-            if !e.id.is_empty() {
-                results.push((e.id, e.fni_score.or(e.fni).unwrap_or(0.0)));
-            }
+        if let Some(scored) = serde_json::from_slice::<SlimEntity>(&payload).ok().and_then(SlimEntity::into_scored) {
+            results.push(scored);
         }
         // payload dropped here — O(1 entity) memory
     }
@@ -714,6 +776,62 @@ pub fn write_zstd(path: &str, data: &[u8], level: i32) -> Result<(), String> {
     fs::write(path, compressed).map_err(|e| format!("Write {}: {}", path, e))
 }
 
+/// Write a plaintext NXVF V4.1 shard (no embeddings) from pre-encoded payloads.
+/// Header layout mirrors scripts/factory/lib/shard-writer.js `finalize()`;
+/// payloads are written verbatim (callers Zstd-compress them when wanted —
+/// the reader detects Zstd by magic). Used for Rust-side side-column outputs.
+pub fn write_nxvf_shard(path: &str, slot_id: u16, payloads: &[Vec<u8>]) -> Result<(), String> {
+    let out = nxvf_bytes(path, slot_id, payloads, None)?;
+    fs::write(path, out).map_err(|e| format!("Write {}: {}", path, e))
+}
+
+/// V26.6: Encode a registry NXVF V4.1 shard in memory, like shard-writer.js
+/// `writeEntity`: when AES_CRYPTO_KEY is set each payload is AES-CTR encrypted
+/// with its per-entity IV. The IV derives from `shard_name` (the FINAL file
+/// name), so encode first, then write under a temp name and rename.
+pub fn encode_nxvf_shard(shard_name: &str, slot_id: u16, payloads: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let cipher = get_aes_key().as_ref().map(|key| (key, shard_name));
+    nxvf_bytes(shard_name, slot_id, payloads, cipher)
+}
+
+fn nxvf_bytes(
+    name: &str,
+    slot_id: u16,
+    payloads: &[Vec<u8>],
+    cipher: Option<(&[u8; 32], &str)>,
+) -> Result<Vec<u8>, String> {
+    let mut header = [0u8; HEADER_SIZE];
+    let mut body = Vec::with_capacity(payloads.iter().map(|p| p.len()).sum::<usize>());
+    let mut table = Vec::with_capacity(payloads.len() * 8);
+    for p in payloads {
+        let offset = u32::try_from(HEADER_SIZE + body.len())
+            .map_err(|_| format!("NXVF shard exceeds 4 GiB: {}", name))?;
+        table.extend_from_slice(&offset.to_le_bytes());
+        table.extend_from_slice(&(p.len() as u32).to_le_bytes());
+        match cipher {
+            // CTR is symmetric: decrypt_payload doubles as encrypt.
+            Some((key, shard_name)) => body.extend_from_slice(&decrypt_payload(key, shard_name, p, offset)),
+            None => body.extend_from_slice(p),
+        }
+    }
+    let mut checksum: u32 = 0;
+    for chunk in table.chunks_exact(4) {
+        checksum ^= u32::from_le_bytes(chunk.try_into().unwrap_or([0; 4]));
+    }
+    header[0..4].copy_from_slice(&NXVF_MAGIC);
+    header[4] = NXVF_VERSION;
+    header[5..7].copy_from_slice(&slot_id.to_le_bytes());
+    header[7..11].copy_from_slice(&((HEADER_SIZE + body.len()) as u32).to_le_bytes());
+    header[11..15].copy_from_slice(&(payloads.len() as u32).to_le_bytes());
+    header[15..19].copy_from_slice(&checksum.to_le_bytes());
+
+    let mut out = Vec::with_capacity(HEADER_SIZE + body.len() + table.len());
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&table);
+    Ok(out)
+}
+
 /// D0 source_trail: deterministic edge_id = SHA-256(input)[:16] (16 hex chars).
 /// MUST be byte-identical to the JS `edgeId` (evidence-carrier.js): callers pass
 /// the exact same `src \0 type \0 tgt` string. Shared so Rust-minted structural
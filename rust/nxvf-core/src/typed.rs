@@ -0,0 +1,196 @@
+//! Typed shard decoder — deserialize each entity straight into a caller struct.
+//!
+//! Same codec path as `read_binary_shard_with_report` (AES-CTR probe → Zstd/Gzip
+//! → serde with sanitize + forced-decrypt retry), but serde targets `T` instead
+//! of `serde_json::Value`, so fields the caller does not declare (body_content,
+//! readme, html_readme …) are skipped without allocation. Used by fni-calc's
+//! streaming batch scorer.
+//!
+//! Accounting: a payload that decodes but does not fit `T` is a `JsonParse`
+//! drop — from the caller's point of view the entry is unusable either way.
+
+use super::{
+    decrypt_payload, get_aes_key, parse_header, sanitize_json_escapes, DropClass, DropRecord,
+    ShardParseReport, GZIP_MAGIC, ZSTD_MAGIC,
+};
+use flate2::read::GzDecoder;
+use serde::de::DeserializeOwned;
+use std::io::Read;
+use std::{fs, path::Path};
+
+/// Decode any shard format into `Vec<T>` plus a `ShardParseReport`.
+///
+/// `.bin` → NXVF binary path (declared = header count).
+/// JSON (`.json`/`.json.gz`/`.json.zst`) → streaming brace-match via
+/// `for_each_raw_entity` (declared = objects seen).
+pub fn read_typed_shard_with_report<T: DeserializeOwned>(
+    file_path: &str,
+) -> Result<(Vec<T>, ShardParseReport), String> {
+    if file_path.ends_with(".bin") {
+        return read_typed_binary_shard(file_path);
+    }
+    let part = Path::new(file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    let mut report = ShardParseReport::new(part, 0);
+    let mut out = Vec::new();
+    let mut index = 0usize;
+    super::for_each_raw_entity(file_path, |raw| {
+        match parse_typed::<T>(raw) {
+            Ok(v) => {
+                out.push(v);
+                report.record_parsed();
+            }
+            Err(e) => report.record_drop(DropRecord::with_payload(
+                part,
+                index,
+                DropClass::JsonParse,
+                raw,
+                e.line() as u32,
+                e.column() as u32,
+            )),
+        }
+        index += 1;
+        Ok(())
+    })?;
+    report.declared_entity_count = index as u32;
+    Ok((out, report))
+}
+
+/// serde into `T`, retrying once over `sanitize_json_escapes` (JS `\u` bugs).
+fn parse_typed<T: DeserializeOwned>(payload: &[u8]) -> Result<T, serde_json::Error> {
+    serde_json::from_slice::<T>(payload).or_else(|e| {
+        let sanitized = sanitize_json_escapes(&String::from_utf8_lossy(payload));
+        serde_json::from_str::<T>(&sanitized).map_err(|_| e)
+    })
+}
+
+/// Zstd / Gzip inflate by magic. `Err` carries the drop class on codec failure.
+fn inflate(payload: Vec<u8>) -> Result<Vec<u8>, (DropClass, Vec<u8>)> {
+    if payload.len() >= 4 && payload[0..4] == ZSTD_MAGIC {
+        zstd::decode_all(payload.as_slice()).map_err(|_| (DropClass::Zstd, payload))
+    } else if payload.len() >= 2 && payload[0..2] == GZIP_MAGIC {
+        let mut d = Vec::new();
+        match GzDecoder::new(payload.as_slice()).read_to_end(&mut d) {
+            Ok(_) => Ok(d),
+            Err(_) => Err((DropClass::Gzip, payload)),
+        }
+    } else {
+        Ok(payload)
+    }
+}
+
+fn read_typed_binary_shard<T: DeserializeOwned>(
+    file_path: &str,
+) -> Result<(Vec<T>, ShardParseReport), String> {
+    let data = fs::read(file_path).map_err(|e| format!("Cannot read {}: {}", file_path, e))?;
+    let header =
+        parse_header(&data).ok_or_else(|| format!("Invalid NXVF header: {}", file_path))?;
+    let ot_start = header.offset_table_offset as usize;
+    let ot_end = ot_start + header.entity_count as usize * 8;
+    if ot_end > data.len() {
+        return Err(format!("Offset table exceeds file size: {}", file_path));
+    }
+    let offset_table = &data[ot_start..ot_end];
+    let shard_name = Path::new(file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    let aes_key = get_aes_key();
+    let mut out = Vec::with_capacity(header.entity_count as usize);
+    let mut report = ShardParseReport::new(shard_name, header.entity_count);
+
+    for i in 0..header.entity_count as usize {
+        let base = i * 8;
+        let offset = u32::from_le_bytes(offset_table[base..base + 4].try_into().unwrap_or([0; 4]));
+        let size = u32::from_le_bytes(offset_table[base + 4..base + 8].try_into().unwrap_or([0; 4]));
+        let end = offset as usize + size as usize;
+        if end > data.len() {
+            report.record_drop(DropRecord::no_payload(shard_name, i));
+            continue;
+        }
+        let raw = &data[offset as usize..end];
+
+        let mut payload = raw.to_vec();
+        if !super::is_valid_payload(&payload) {
+            if let Some(key) = aes_key {
+                let decrypted = decrypt_payload(key, shard_name, &payload, offset);
+                if super::is_valid_payload(&decrypted) {
+                    payload = decrypted;
+                }
+            }
+        }
+        let payload = match inflate(payload) {
+            Ok(p) => p,
+            Err((class, p)) => {
+                report.record_drop(DropRecord::with_payload(shard_name, i, class, &p, 0, 0));
+                continue;
+            }
+        };
+
+        match parse_typed::<T>(&payload) {
+            Ok(v) => {
+                out.push(v);
+                report.record_parsed();
+            }
+            Err(e) => {
+                // Forced-decrypt retry: isValidPayload false positive (~1/65536)
+                let retried = aes_key.as_ref().and_then(|key| {
+                    let retry = inflate(decrypt_payload(key, shard_name, raw, offset)).ok()?;
+                    serde_json::from_slice::<T>(&retry).ok()
+                });
+                match retried {
+                    Some(v) => {
+                        out.push(v);
+                        report.record_parsed();
+                    }
+                    None => report.record_drop(DropRecord::with_payload(
+                        shard_name,
+                        i,
+                        DropClass::JsonParse,
+                        &payload,
+                        e.line() as u32,
+                        e.column() as u32,
+                    )),
+                }
+            }
+        }
+    }
+    Ok((out, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Deserialize)]
+    struct Slim {
+        id: String,
+        #[serde(default)]
+        fni_score: f64,
+    }
+
+    #[test]
+    fn typed_roundtrip_through_nxvf_writer() {
+        let dir = std::env::temp_dir().join(format!("nxvf-typed-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("part-000.bin");
+        let payloads = vec![
+            zstd::encode_all(&br#"{"id":"a","fni_score":1.5,"readme":"x"}"#[..], 3).unwrap(),
+            br#"{"id":"b","fni_score":"not-a-number"}"#.to_vec(),
+            br#"{"id":"c"}"#.to_vec(),
+        ];
+        crate::write_nxvf_shard(path.to_str().unwrap(), 0, &payloads).unwrap();
+
+        let (rows, report) = read_typed_shard_with_report::<Slim>(path.to_str().unwrap()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].id.as_str(), rows[0].fni_score), ("a", 1.5));
+        assert_eq!((rows[1].id.as_str(), rows[1].fni_score), ("c", 0.0));
+        assert_eq!(report.declared_entity_count, 3);
+        assert_eq!(report.parse_error_count(), 1);
+        assert_eq!(report.records[0].entry_index, 1);
+        assert!(report.is_conserved());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
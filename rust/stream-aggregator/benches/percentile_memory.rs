@@ -0,0 +1,127 @@
+//! V26.6 approx vs exact percentiles at corpus scale (1M entities).
+//!
+//! Peak heap is measured with a counting global allocator, so this runs as
+//! its own binary rather than in the unit-test one (where the allocator
+//! would tax every test). `sketch.rs` / `percentile.rs` are compiled in
+//! directly since the crate is cdylib-only. Run:
+//!   cargo bench -p stream-aggregator-rust --bench percentile_memory
+// Only part of each included module is used here, and their `mod tests`
+// compile without a harness to run them.
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/percentile.rs"]
+mod percentile;
+#[path = "../src/sketch.rs"]
+mod sketch;
+
+/// percentile.rs stamps `_ts` via merge.rs, which needs the NAPI surface.
+mod merge {
+    pub fn iso_now() -> String {
+        String::new()
+    }
+}
+
+use percentile::*;
+use sketch::KllSketch;
+
+/// Counts live heap bytes per thread, so a measurement is not skewed by
+/// allocations on other threads.
+struct CountingAlloc;
+
+thread_local! {
+    static LIVE: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    static PEAK: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn track(grow: usize, shrink: usize) {
+    LIVE.with(|live| {
+        let now = (live.get() + grow).saturating_sub(shrink);
+        live.set(now);
+        PEAK.with(|peak| peak.set(peak.get().max(now)));
+    });
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        track(layout.size(), 0);
+        std::alloc::System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        track(0, layout.size());
+        std::alloc::System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        track(new_size, layout.size());
+        std::alloc::System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// Peak heap growth on this thread while `f` runs.
+fn peak_bytes<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let base = LIVE.with(|l| l.get());
+    PEAK.with(|p| p.set(base));
+    let out = f();
+    (out, PEAK.with(|p| p.get()) - base)
+}
+
+fn main() {
+    let n = 1_000_000;
+    let scores: Vec<(String, f64)> =
+        sketch::corpus(n).into_iter().enumerate().map(|(i, s)| (format!("e{i}"), s)).collect();
+    let types = ["model", "dataset", "paper", "tool"];
+
+    // Measured: peak heap growth of the collection pass (the state the
+    // extraction phase carries) and of the thresholds build. The shared
+    // (id, score) list and the id → percentile map are the same in both
+    // modes and are not counted.
+    let t = std::time::Instant::now();
+    let (exact, exact_collect) = peak_bytes(|| {
+        let mut c = ScoreCollector::new(None);
+        for (i, (_, s)) in scores.iter().enumerate() {
+            c.push([types[i % 4], "", ""], *s);
+        }
+        c
+    });
+    let ScoreCollector::Exact { strata } = exact else { unreachable!() };
+    let (exact_doc, exact_build) = peak_bytes(|| build_thresholds(&scores, strata));
+    let exact_rank = calculate_rankings(&scores);
+    let exact_ms = t.elapsed().as_millis();
+
+    let t = std::time::Instant::now();
+    let (approx, approx_collect) = peak_bytes(|| {
+        let mut c = ScoreCollector::new(Some(DEFAULT_SKETCH_K));
+        for (i, (_, s)) in scores.iter().enumerate() {
+            c.push([types[i % 4], "", ""], *s);
+        }
+        c
+    });
+    let ScoreCollector::Approx { global, strata } = approx else { unreachable!() };
+    let retained = global.retained() + strata[0].values().map(KllSketch::retained).sum::<usize>();
+    let approx_rank = rank_with_sketch(&scores, &global);
+    let (approx_doc, approx_build) = peak_bytes(|| build_thresholds_approx(&global, strata));
+    let approx_ms = t.elapsed().as_millis();
+
+    let (exact_bytes, approx_bytes) = (exact_collect.max(exact_build), approx_collect.max(approx_build));
+    eprintln!(
+        "[percentile] n={n}: exact {exact_ms}ms / {} KiB peak, approx {approx_ms}ms / {} KiB peak ({retained} retained)",
+        exact_bytes / 1024,
+        approx_bytes / 1024
+    );
+    assert!(approx_bytes * 10 < exact_bytes, "approx {approx_bytes} B vs exact {exact_bytes} B");
+
+    let worst = scores.iter().map(|(id, _)| exact_rank[id].abs_diff(approx_rank[id])).max().unwrap();
+    assert!(worst <= 1, "approx percentile off by {worst}");
+    assert_eq!(approx_doc["mode"], "approx");
+    assert_eq!(exact_doc["_count"], approx_doc["_count"]);
+    for (bucket, pct) in exact_doc["scorePercentiles"].as_object().unwrap() {
+        let a = approx_doc["scorePercentiles"][bucket].as_u64().unwrap();
+        assert!(pct.as_u64().unwrap().abs_diff(a) <= 1, "bucket {bucket}");
+    }
+    for q in ["p50", "p90", "p99"] {
+        let (e, a) = (exact_doc["cutPoints"][q].as_f64().unwrap(), approx_doc["cutPoints"][q].as_f64().unwrap());
+        assert!((e - a).abs() <= 1.0, "{q}: exact {e} approx {a}");
+    }
+}
@@ -0,0 +1,466 @@
+//! V26.6 Crash-safe delta routing: staged writes, per-artifact checkpoints,
+//! completion marker.
+//!
+//! Layout of `delta_dir` while a run is in flight:
+//!
+//! ```text
+//! delta_dir/
+//!   .staging/reg-<si>.jsonl     appended per artifact
+//!   .staging/checkpoint.json    completed artifacts + committed byte lengths
+//!   reg-<si>.jsonl              previous run's output, untouched until commit
+//! ```
+//!
+//! After every artifact the staged files are fsync'd and the checkpoint is
+//! rewritten (temp + rename). A killed run resumes by truncating each staged
+//! file back to its committed length — dropping the half-routed artifact —
+//! and skipping the artifacts already done. A checkpoint is only reused when
+//! its fingerprint (the inputs that decide routing) and every completed
+//! artifact's size still match; otherwise routing starts from zero.
+//!
+//! `finish` renames the staged files into place, removes stale
+//! `reg-*.jsonl`, and writes `_COMPLETE.json` (counts + SHA-256 per file)
+//! last. No marker ⇒ the directory is not a finished run.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub(crate) const CHECKPOINT_VERSION: u32 = 1;
+pub(crate) const COMPLETE_MARKER: &str = "_COMPLETE.json";
+const STAGING_DIR: &str = ".staging";
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Write `bytes` to `<path>.tmp`, fsync, then rename over `path`.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = tmp_path(path);
+    let res = fs::File::create(&tmp)
+        .and_then(|mut f| f.write_all(bytes).and_then(|_| f.sync_all()))
+        .and_then(|_| fs::rename(&tmp, path));
+    res.map_err(|e| {
+        fs::remove_file(&tmp).ok();
+        format!("Write {}: {}", path.display(), e)
+    })
+}
+
+pub(crate) fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 over `kind` and each file's name, size and mtime — cheap identity
+/// for "same inputs as the interrupted run".
+pub(crate) fn fingerprint(kind: &str, files: &[String]) -> String {
+    let mut h = Sha256::new();
+    h.update(kind.as_bytes());
+    for f in files {
+        let meta = fs::metadata(f).ok();
+        let mtime = meta
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        let name = Path::new(f).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        h.update(format!("\n{}\t{}\t{}", name, meta.map_or(0, |m| m.len()), mtime).as_bytes());
+    }
+    hex(&h.finalize())
+}
+
+fn artifact_name(path: &str) -> String {
+    Path::new(path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ArtifactRecord {
+    pub name: String,
+    pub bytes: u64,
+    pub routed: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Checkpoint {
+    version: u32,
+    fingerprint: String,
+    completed: Vec<ArtifactRecord>,
+    /// Shard index → committed length of its staged delta file.
+    files: BTreeMap<u32, u64>,
+    routed: u32,
+    /// Set before staged files are renamed into place.
+    committing: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeltaFileDigest {
+    pub lines: u64,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CompletionMarker {
+    pub version: u32,
+    pub fingerprint: String,
+    pub routed_count: u32,
+    pub delta_shard_count: u32,
+    pub artifact_count: u32,
+    pub resumed_artifact_count: u32,
+    pub failed_artifacts: Vec<ArtifactRecord>,
+    pub files: BTreeMap<String, DeltaFileDigest>,
+    pub completed_at: String,
+}
+
+/// Read `_COMPLETE.json`; None when the directory is not a finished run.
+pub(crate) fn read_marker(delta_dir: &str) -> Option<CompletionMarker> {
+    let data = fs::read(Path::new(delta_dir).join(COMPLETE_MARKER)).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Routes delta lines into staged `reg-<si>.jsonl` files (see module docs).
+pub(crate) struct DeltaRouter {
+    delta_dir: PathBuf,
+    staging: PathBuf,
+    cp: Checkpoint,
+    writers: HashMap<u32, BufWriter<fs::File>>,
+    artifact_routed: u32,
+    resumed: u32,
+}
+
+impl DeltaRouter {
+    /// `artifacts` is this run's artifact list; a checkpoint naming an
+    /// artifact whose size changed (or vanished) is discarded.
+    pub fn open(delta_dir: &str, fingerprint: String, resume: bool, artifacts: &[String]) -> Result<Self, String> {
+        let delta_dir = PathBuf::from(delta_dir);
+        let staging = delta_dir.join(STAGING_DIR);
+        fs::create_dir_all(&delta_dir).map_err(|e| format!("Create {}: {}", delta_dir.display(), e))?;
+        match fs::remove_file(delta_dir.join(COMPLETE_MARKER)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(format!("Remove marker: {}", e)),
+            _ => {}
+        }
+
+        let sizes: HashMap<String, u64> = artifacts
+            .iter()
+            .map(|a| (artifact_name(a), fs::metadata(a).map_or(0, |m| m.len())))
+            .collect();
+        let loaded = if resume { Self::load_checkpoint(&staging) } else { None };
+        let reusable = loaded.filter(|cp| {
+            cp.version == CHECKPOINT_VERSION
+                && cp.fingerprint == fingerprint
+                && cp.completed.iter().all(|r| sizes.get(&r.name) == Some(&r.bytes))
+        });
+
+        let mut router = DeltaRouter {
+            delta_dir,
+            staging,
+            cp: Checkpoint {
+                version: CHECKPOINT_VERSION,
+                fingerprint,
+                completed: Vec::new(),
+                files: BTreeMap::new(),
+                routed: 0,
+                committing: false,
+            },
+            writers: HashMap::new(),
+            artifact_routed: 0,
+            resumed: 0,
+        };
+        if let Some(cp) = reusable {
+            match router.restore(&cp) {
+                Ok(()) => {
+                    router.resumed = cp.completed.len() as u32;
+                    eprintln!(
+                        "[RUST-DELTA] Resuming: {} artifacts, {} entities already routed",
+                        router.resumed, cp.routed
+                    );
+                    router.cp = cp;
+                    return Ok(router);
+                }
+                Err(e) => eprintln!("[RUST-DELTA] Checkpoint unusable ({}), starting fresh", e),
+            }
+        }
+        match fs::remove_dir_all(&router.staging) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(format!("Clear staging: {}", e)),
+            _ => {}
+        }
+        fs::create_dir_all(&router.staging).map_err(|e| format!("Create staging: {}", e))?;
+        router.save()?;
+        Ok(router)
+    }
+
+    fn load_checkpoint(staging: &Path) -> Option<Checkpoint> {
+        let data = fs::read(staging.join(CHECKPOINT_FILE)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Truncate staged files back to their committed lengths and delete
+    /// staged files no completed artifact wrote to (first created by the
+    /// artifact that was killed), so resumed writes never append to them.
+    fn restore(&self, cp: &Checkpoint) -> Result<(), String> {
+        let entries = fs::read_dir(&self.staging).map_err(|e| format!("Read {}: {}", self.staging.display(), e))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if delta_index(&name).is_some_and(|si| !cp.files.contains_key(&si)) {
+                fs::remove_file(entry.path()).map_err(|e| format!("Remove {}: {}", name, e))?;
+            }
+        }
+        for (&si, &len) in &cp.files {
+            let path = self.staged(si);
+            match fs::OpenOptions::new().write(true).open(&path) {
+                Ok(f) => f.set_len(len).map_err(|e| format!("truncate {}: {}", path.display(), e))?,
+                // Already renamed into place by an interrupted commit.
+                Err(e) if e.kind() == ErrorKind::NotFound && cp.committing => {}
+                Err(e) => return Err(format!("{}: {}", path.display(), e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn staged(&self, si: u32) -> PathBuf {
+        self.staging.join(format!("reg-{}.jsonl", si))
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let data = serde_json::to_vec(&self.cp).map_err(|e| format!("checkpoint: {}", e))?;
+        write_atomic(&self.staging.join(CHECKPOINT_FILE), &data)
+    }
+
+    pub fn is_completed(&self, artifact: &str) -> bool {
+        let name = artifact_name(artifact);
+        self.cp.completed.iter().any(|r| r.name == name)
+    }
+
+    /// Total routed, including artifacts restored from the checkpoint.
+    pub fn routed(&self) -> u32 {
+        self.cp.routed + self.artifact_routed
+    }
+
+    pub fn resumed(&self) -> u32 {
+        self.resumed
+    }
+
+    /// Append one delta line (newline added) to shard `si`'s staged file.
+    pub fn write(&mut self, si: u32, line: &[u8]) -> Result<(), String> {
+        let w = match self.writers.entry(si) {
+            std::collections::hash_map::Entry::Occupied(o) => o.into_mut(),
+            std::collections::hash_map::Entry::Vacant(v) => {
+                let path = self.staging.join(format!("reg-{}.jsonl", si));
+                let f = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|e| format!("Create {}: {}", path.display(), e))?;
+                v.insert(BufWriter::new(f))
+            }
+        };
+        w.write_all(line)
+            .and_then(|_| w.write_all(b"\n"))
+            .map_err(|e| format!("Write reg-{}.jsonl: {}", si, e))?;
+        self.artifact_routed += 1;
+        Ok(())
+    }
+
+    /// Make everything routed from `artifact` durable and checkpoint it.
+    /// `error` records a read failure part-way: what was routed is kept.
+    pub fn complete_artifact(&mut self, artifact: &str, error: Option<String>) -> Result<(), String> {
+        for (&si, w) in self.writers.iter_mut() {
+            w.flush()
+                .and_then(|_| w.get_ref().sync_data())
+                .map_err(|e| format!("Flush reg-{}.jsonl: {}", si, e))?;
+            let len = w.get_ref().metadata().map_err(|e| format!("stat reg-{}.jsonl: {}", si, e))?.len();
+            self.cp.files.insert(si, len);
+        }
+        self.cp.completed.push(ArtifactRecord {
+            name: artifact_name(artifact),
+            bytes: fs::metadata(artifact).map_or(0, |m| m.len()),
+            routed: self.artifact_routed,
+            error,
+        });
+        self.cp.routed += self.artifact_routed;
+        self.artifact_routed = 0;
+        self.save()
+    }
+
+    /// Move staged files into place and write the completion marker.
+    pub fn finish(mut self) -> Result<CompletionMarker, String> {
+        if self.artifact_routed > 0 {
+            return Err("finish() with an uncompleted artifact".into());
+        }
+        self.writers.clear();
+        self.cp.committing = true;
+        self.save()?;
+
+        let entries = fs::read_dir(&self.delta_dir).map_err(|e| format!("Read {}: {}", self.delta_dir.display(), e))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let stale = delta_index(&name).is_some_and(|si| !self.cp.files.contains_key(&si));
+            if stale {
+                fs::remove_file(entry.path()).map_err(|e| format!("Remove {}: {}", name, e))?;
+            }
+        }
+
+        let mut files = BTreeMap::new();
+        for &si in self.cp.files.keys() {
+            let name = format!("reg-{}.jsonl", si);
+            let target = self.delta_dir.join(&name);
+            match fs::rename(self.staged(si), &target) {
+                Err(e) if e.kind() == ErrorKind::NotFound && target.exists() => {}
+                r => r.map_err(|e| format!("Rename {}: {}", name, e))?,
+            }
+            files.insert(name, digest_file(&target)?);
+        }
+
+        let marker = CompletionMarker {
+            version: CHECKPOINT_VERSION,
+            fingerprint: self.cp.fingerprint.clone(),
+            routed_count: self.cp.routed,
+            delta_shard_count: files.len() as u32,
+            artifact_count: self.cp.completed.len() as u32,
+            resumed_artifact_count: self.resumed,
+            failed_artifacts: self.cp.completed.iter().filter(|r| r.error.is_some()).cloned().collect(),
+            files,
+            completed_at: crate::merge::iso_now(),
+        };
+        let data = serde_json::to_vec_pretty(&marker).map_err(|e| format!("marker: {}", e))?;
+        write_atomic(&self.delta_dir.join(COMPLETE_MARKER), &data)?;
+        fs::remove_dir_all(&self.staging).ok();
+        Ok(marker)
+    }
+}
+
+/// Shard index of a `reg-<N>.jsonl` delta file name.
+fn delta_index(name: &str) -> Option<u32> {
+    name.strip_prefix("reg-")?.strip_suffix(".jsonl")?.parse().ok()
+}
+
+fn digest_file(path: &Path) -> Result<DeltaFileDigest, String> {
+    let f = fs::File::open(path).map_err(|e| format!("Open {}: {}", path.display(), e))?;
+    let mut reader = BufReader::new(f);
+    let (mut h, mut lines, mut bytes) = (Sha256::new(), 0u64, 0u64);
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).map_err(|e| format!("Read {}: {}", path.display(), e))?;
+        if n == 0 {
+            break;
+        }
+        h.update(&buf[..n]);
+        lines += buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+        bytes += n as u64;
+    }
+    Ok(DeltaFileDigest { lines, bytes, sha256: hex(&h.finalize()) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(name: &str) -> (PathBuf, Vec<String>) {
+        let dir = std::env::temp_dir().join(format!("delta-ckpt-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(dir.join("artifacts")).unwrap();
+        let artifacts: Vec<String> = ["shard-0.json", "shard-1.json"]
+            .iter()
+            .map(|a| {
+                let p = dir.join("artifacts").join(a);
+                fs::write(&p, a.as_bytes()).unwrap();
+                p.to_string_lossy().into_owned()
+            })
+            .collect();
+        (dir, artifacts)
+    }
+
+    /// Route artifact `ai` as two lines into shards 0 and ai+1.
+    fn route(r: &mut DeltaRouter, artifacts: &[String], ai: usize) {
+        r.write(0, format!("{{\"a\":{ai}}}").as_bytes()).unwrap();
+        r.write(ai as u32 + 1, b"{}").unwrap();
+        r.complete_artifact(&artifacts[ai], None).unwrap();
+    }
+
+    #[test]
+    fn killed_run_resumes_without_duplicates() {
+        let (dir, artifacts) = setup("resume");
+        let dd = dir.join("deltas");
+        let dds = dd.to_string_lossy().into_owned();
+        fs::create_dir_all(&dd).unwrap();
+        fs::write(dd.join("reg-9.jsonl"), "stale\n").unwrap();
+        fs::write(dd.join(COMPLETE_MARKER), "{}").unwrap();
+
+        let mut r = DeltaRouter::open(&dds, "fp".into(), true, &artifacts).unwrap();
+        assert!(!dd.join(COMPLETE_MARKER).exists(), "in-flight run must not look complete");
+        route(&mut r, &artifacts, 0);
+        // Killed half-way through artifact 1: its bytes reach the staged file.
+        r.write(0, b"{\"half\":1}").unwrap();
+        drop(r);
+
+        let mut r = DeltaRouter::open(&dds, "fp".into(), true, &artifacts).unwrap();
+        assert_eq!(r.resumed(), 1);
+        assert!(r.is_completed(&artifacts[0]) && !r.is_completed(&artifacts[1]));
+        assert_eq!(r.routed(), 2);
+        route(&mut r, &artifacts, 1);
+        let marker = r.finish().unwrap();
+
+        assert_eq!((marker.routed_count, marker.delta_shard_count, marker.resumed_artifact_count), (4, 3, 1));
+        assert_eq!(fs::read_to_string(dd.join("reg-0.jsonl")).unwrap(), "{\"a\":0}\n{\"a\":1}\n");
+        assert!(!dd.join("reg-9.jsonl").exists());
+        assert!(!dd.join(STAGING_DIR).exists());
+        let on_disk = read_marker(&dds).unwrap();
+        assert_eq!(on_disk.files["reg-0.jsonl"].lines, 2);
+        assert_eq!(on_disk.files["reg-0.jsonl"].sha256, digest_file(&dd.join("reg-0.jsonl")).unwrap().sha256);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn resume_drops_files_first_created_by_killed_artifact() {
+        let (dir, artifacts) = setup("new-file");
+        let dds = dir.join("deltas").to_string_lossy().into_owned();
+        let mut r = DeltaRouter::open(&dds, "fp".into(), true, &artifacts).unwrap();
+        route(&mut r, &artifacts, 0);
+        // Artifact 1 is killed after opening a shard nothing completed wrote to,
+        // mid-way through a line.
+        r.write(5, b"{\"id\":\"b\"}").unwrap();
+        drop(r);
+        let staged = dir.join("deltas").join(STAGING_DIR).join("reg-5.jsonl");
+        fs::OpenOptions::new().append(true).open(&staged).unwrap().write_all(b"{\"id\":").unwrap();
+
+        let mut r = DeltaRouter::open(&dds, "fp".into(), true, &artifacts).unwrap();
+        assert_eq!(r.resumed(), 1);
+        assert!(!staged.exists());
+        r.write(5, b"{\"id\":\"b\"}").unwrap();
+        r.complete_artifact(&artifacts[1], None).unwrap();
+        let marker = r.finish().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("deltas").join("reg-5.jsonl")).unwrap(), "{\"id\":\"b\"}\n");
+        assert_eq!(marker.routed_count, 3);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn changed_inputs_start_fresh() {
+        let (dir, artifacts) = setup("fresh");
+        let dds = dir.join("deltas").to_string_lossy().into_owned();
+        let mut r = DeltaRouter::open(&dds, "fp".into(), true, &artifacts).unwrap();
+        route(&mut r, &artifacts, 0);
+        drop(r);
+
+        assert_eq!(DeltaRouter::open(&dds, "other".into(), true, &artifacts).unwrap().resumed(), 0);
+
+        let mut r = DeltaRouter::open(&dds, "fp".into(), true, &artifacts).unwrap();
+        route(&mut r, &artifacts, 0);
+        drop(r);
+        fs::write(&artifacts[0], "grown artifact").unwrap();
+        let r = DeltaRouter::open(&dds, "fp".into(), true, &artifacts).unwrap();
+        assert_eq!((r.resumed(), r.routed()), (0, 0));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
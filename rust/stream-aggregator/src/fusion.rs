@@ -2,15 +2,20 @@
 //!
 //! Reads a shard file, applies closed-world relation filter, late-binding FNI,
 //! optional enrichment injection, projects entities, and writes zstd output.
+//! V26.6: `fuse_all_shards` runs the same per-shard fusion for a whole shard
+//! list on a worker pool with run-wide inputs loaded once.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
+use rayon::prelude::*;
 use serde_json::json;
 use sha2::{Digest, Sha256};
 
-use crate::project::project_entity_for_fusion;
+use crate::percentile::{stratum_keys, Stratum};
+use crate::project::EntityPercentiles;
+use crate::projection::CompiledProjection;
 
 /// Rust-side mirror of JS `generateUMID`.
 /// Unsalted, publicly verifiable: SHA256(canonical_id) → first 8 bytes → 16 hex
@@ -142,6 +147,211 @@ fn not_applicable_accounting(part: &str) -> ParseAccounting {
     }
 }
 
+/// V26.6: Within-type / category / source percentiles from the thresholds'
+/// `types` / `categories` / `sources` tables, same `round(fni_score)` lookup
+/// as the global one. None when the entity (or the file) has no such group.
+fn stratum_percentiles(thresholds: &serde_json::Value, entity: &serde_json::Value, pct_key: &str) -> [Option<u8>; 3] {
+    let mut out = [None; 3];
+    let Some(se) = nxvf_core::ScoredEntity::from_value(entity) else {
+        return out;
+    };
+    for ((slot, stratum), key) in out.iter_mut().zip(Stratum::ALL).zip(stratum_keys(&se)) {
+        *slot = thresholds
+            .get(stratum.table())
+            .and_then(|t| t.get(key))
+            .map(|g| g.get("scorePercentiles").and_then(|m| m.get(pct_key)).and_then(|v| v.as_f64()).unwrap_or(0.0) as u8);
+    }
+    out
+}
+
+/// A local enrichment directory and its ID→umid manifest. An empty `dir`
+/// disables enrichment.
+#[derive(Default)]
+struct Enrichment {
+    dir: String,
+    /// ID→umid manifest written by JS downloadShardEnrichment.
+    umid_manifest: HashMap<String, String>,
+}
+
+impl Enrichment {
+    fn load(dir: String) -> Self {
+        let umid_manifest = if dir.is_empty() {
+            Default::default()
+        } else {
+            let manifest_path = format!("{}/manifest.json", dir);
+            std::fs::read_to_string(&manifest_path)
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default()
+        };
+        Enrichment { dir, umid_manifest }
+    }
+}
+
+/// Inputs shared by every shard of a fusion run: the closed-world id set,
+/// the FNI thresholds and the default enrichment. Loaded once per call
+/// (`fuse_shard`) or once per run (`fuse_all_shards`); read-only afterwards,
+/// so workers share it by reference.
+struct FusionInputs {
+    valid_ids: HashSet<String>,
+    thresholds: serde_json::Value,
+    enrichment: Enrichment,
+    /// `fusion` spec chain, resolved once for every shard fused with these inputs.
+    projection: CompiledProjection,
+}
+
+impl FusionInputs {
+    fn load(valid_ids_json: &str, fni_thresholds_path: &str, enrichment_dir: String) -> Result<Self> {
+        // Parse valid IDs from N-API string (no intermediate file)
+        let ids_val: serde_json::Value = serde_json::from_str(valid_ids_json)
+            .map_err(|e| Error::from_reason(format!("parse valid_ids: {e}")))?;
+        let valid_ids: HashSet<String> = ids_val
+            .as_array()
+            .ok_or_else(|| Error::from_reason("valid_ids must be JSON array"))?
+            .iter()
+            .filter_map(|v| v.as_str().map(String::from))
+            .collect();
+
+        let thresholds = nxvf_core::load_json_file(fni_thresholds_path).unwrap_or_else(|_| json!({}));
+
+        let projection = CompiledProjection::resolve("fusion").map_err(Error::from_reason)?;
+        Ok(FusionInputs { valid_ids, thresholds, enrichment: Enrichment::load(enrichment_dir), projection })
+    }
+
+    /// Read → closed-world filter → FNI → enrich → project → write one shard.
+    fn fuse(&self, shard_path: &str, output_path: &str) -> Result<FuseShardResult> {
+        self.fuse_with(shard_path, output_path, &self.enrichment)
+    }
+
+    /// `fuse` with the shard's own enrichment instead of the run-wide one.
+    fn fuse_with(&self, shard_path: &str, output_path: &str, enrichment: &Enrichment) -> Result<FuseShardResult> {
+        let score_pcts = self
+            .thresholds
+            .get("scorePercentiles")
+            .and_then(|v| v.as_object());
+
+        // Read shard. For the monitored NXVF binary path use the reporting
+        // variant (survivors byte+order identical; accounting is side-channel).
+        let part_name = std::path::Path::new(shard_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string();
+        let (entities, parse_accounting) = if shard_path.ends_with(".bin") {
+            let (e, report) = nxvf_core::read_binary_shard_with_report(shard_path)
+                .map_err(|e| Error::from_reason(format!("read shard: {e}")))?;
+            let acc = build_parse_accounting(&report);
+            (e, acc)
+        } else {
+            let e = nxvf_core::load_shard_entities(shard_path)
+                .map_err(|e| Error::from_reason(format!("read shard: {e}")))?;
+            (e, not_applicable_accounting(&part_name))
+        };
+
+        let mut fused = Vec::with_capacity(entities.len());
+        let mut filtered_rels = 0u32;
+        let mut enriched = 0u32;
+        let do_enrich = !enrichment.dir.is_empty();
+
+        for mut entity in entities {
+            let id = entity
+                .get("id")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            if id.is_empty() {
+                continue;
+            }
+
+            // Always re-stamp umid from canonical id (unsalted SHA256). Binary shards
+            // can carry stale salted umids from earlier cycles; inheriting those mixes
+            // namespaces and produces UNIQUE-constraint collisions in pack-db.
+            // Re-stamping is idempotent and guarantees one-to-one id <-> umid alignment
+            // with Phase 3 enrichment lookup keys.
+            let fresh_umid = generate_umid(&id);
+            entity["umid"] = json!(fresh_umid);
+
+            // A. Closed-world relation filter
+            if let Some(rels) = entity.get_mut("relations").and_then(|v| v.as_array_mut()) {
+                let before = rels.len();
+                rels.retain(|r| {
+                    r.get("target_id")
+                        .and_then(|v| v.as_str())
+                        .map(|tid| self.valid_ids.contains(tid))
+                        .unwrap_or(false)
+                });
+                filtered_rels += (before - rels.len()) as u32;
+            }
+
+            // B. FNI V2.0: Preserve 2/4 computed score — no recalculation in fusion
+            let fni_score = entity
+                .get("fni_score")
+                .and_then(|v| v.as_f64())
+                .or_else(|| entity.get("fni").and_then(|v| v.as_f64()))
+                .unwrap_or(0.0);
+            let pct_key = (fni_score.round() as i64).to_string();
+            let percentile = score_pcts
+                .and_then(|m| m.get(&pct_key))
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.0);
+
+            entity["fni_pScore"] = json!(fni_score);
+            entity["fni_percentile"] = json!(percentile);
+
+            // C. Enrichment from pre-downloaded local files.
+            // Use the freshly-stamped umid — Phase 4 of master-fusion.js saves
+            // enrichment files as `${generateUMID(id)}.md.gz`, so this matches.
+            // umid_manifest is kept as a belt-and-braces fallback for the rare case
+            // where a stale manifest entry is the only available lookup key.
+            if do_enrich {
+                let umid: &str = if !fresh_umid.is_empty() {
+                    &fresh_umid
+                } else {
+                    enrichment.umid_manifest.get(&id).map(|s| s.as_str()).unwrap_or("")
+                };
+                if !umid.is_empty() {
+                    if let Some(text) = try_load_enrichment(&enrichment.dir, umid) {
+                        if text.len() > 200 {
+                            let has_ft = text.len() > 1000;
+                            entity["body_content"] = json!(text);
+                            entity["has_fulltext"] = json!(has_ft);
+                            enriched += 1;
+                        }
+                    }
+                }
+            }
+
+            // D. Project (full mode — preserves body_content, has_fulltext, relations)
+            let percentiles = EntityPercentiles {
+                global: percentile as u8,
+                strata: stratum_percentiles(&self.thresholds, &entity, &pct_key),
+            };
+            fused.push(self.projection.apply(&entity, &percentiles.context()));
+        }
+
+        // Write output
+        let output = json!({ "entities": fused, "_ts": timestamp_now() });
+        let serialized =
+            serde_json::to_vec(&output).map_err(|e| Error::from_reason(format!("serialize: {e}")))?;
+
+        // Ensure parent dir exists
+        if let Some(parent) = std::path::Path::new(output_path).parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let compressed = zstd::encode_all(serialized.as_slice(), 3)
+            .map_err(|e| Error::from_reason(format!("Zstd compress: {e}")))?;
+        crate::checkpoint::write_atomic(std::path::Path::new(output_path), &compressed)
+            .map_err(Error::from_reason)?;
+
+        Ok(FuseShardResult {
+            entity_count: fused.len() as u32,
+            filtered_relations: filtered_rels,
+            enriched_count: enriched,
+            parse_accounting,
+        })
+    }
+}
+
 /// Fuse a single shard: read → closed-world filter → FNI → enrich → project → write.
 /// enrichment_dir may be empty to skip enrichment.
 #[napi]
@@ -152,144 +362,256 @@ pub fn fuse_shard(
     enrichment_dir: String,
     output_path: String,
 ) -> Result<FuseShardResult> {
-    // 1. Parse valid IDs from N-API string (no intermediate file)
-    let ids_val: serde_json::Value = serde_json::from_str(&valid_ids_json)
-        .map_err(|e| Error::from_reason(format!("parse valid_ids: {e}")))?;
-    let valid_ids: HashSet<String> = ids_val
-        .as_array()
-        .ok_or_else(|| Error::from_reason("valid_ids must be JSON array"))?
-        .iter()
-        .filter_map(|v| v.as_str().map(String::from))
-        .collect();
+    FusionInputs::load(&valid_ids_json, &fni_thresholds_path, enrichment_dir)?.fuse(&shard_path, &output_path)
+}
 
-    // 2. Load FNI thresholds
-    let thresholds = nxvf_core::load_json_file(&fni_thresholds_path).unwrap_or_else(|_| json!({}));
-    let score_pcts = thresholds
-        .get("scorePercentiles")
-        .and_then(|v| v.as_object());
-
-    // 3. Read shard. For the monitored NXVF binary path use the reporting
-    // variant (survivors byte+order identical; accounting is side-channel).
-    let part_name = std::path::Path::new(&shard_path)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("")
-        .to_string();
-    let (entities, parse_accounting) = if shard_path.ends_with(".bin") {
-        let (e, report) = nxvf_core::read_binary_shard_with_report(&shard_path)
-            .map_err(|e| Error::from_reason(format!("read shard: {e}")))?;
-        let acc = build_parse_accounting(&report);
-        (e, acc)
-    } else {
-        let e = nxvf_core::load_shard_entities(&shard_path)
-            .map_err(|e| Error::from_reason(format!("read shard: {e}")))?;
-        (e, not_applicable_accounting(&part_name))
-    };
+#[napi(object)]
+#[derive(Default)]
+pub struct FuseAllOptions {
+    /// Worker threads (default: rayon global pool = logical CPUs).
+    pub threads: Option<u32>,
+    /// Per-shard enrichment dirs, parallel to `shard_paths` ("" = none).
+    /// Overrides `enrichment_dir`; each dir's manifest is read by its shard.
+    pub enrichment_dirs: Option<Vec<String>>,
+    /// Global index of `shard_paths[0]` when fusing in batches (default 0),
+    /// so each batch writes its own `part-NNN` files.
+    pub start_index: Option<u32>,
+}
 
-    let mut fused = Vec::with_capacity(entities.len());
-    let mut filtered_rels = 0u32;
-    let mut enriched = 0u32;
-    let do_enrich = !enrichment_dir.is_empty();
-
-    // Load ID→umid manifest written by JS downloadShardEnrichment
-    let umid_manifest: std::collections::HashMap<String, String> = if do_enrich {
-        let manifest_path = format!("{}/manifest.json", enrichment_dir);
-        std::fs::read_to_string(&manifest_path)
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default()
-    } else {
-        Default::default()
-    };
+#[napi(object)]
+pub struct FuseAllShardResult {
+    /// Input shard file name.
+    pub part: String,
+    /// `<output_dir>/part-NNN.json.zst`, NNN = `start_index` + position in
+    /// `shard_paths`.
+    pub output_file: String,
+    pub entity_count: u32,
+    pub filtered_relations: u32,
+    pub enriched_count: u32,
+    /// Set when the shard failed; its output is not written.
+    pub error: Option<String>,
+    pub duration_ms: u32,
+    /// Same per-shard accounting `fuse_shard` returns; None for failed shards.
+    pub parse_accounting: Option<ParseAccounting>,
+}
 
-    for mut entity in entities {
-        let id = entity
-            .get("id")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-        if id.is_empty() {
-            continue;
-        }
+/// Run-wide fold of the per-shard `ParseAccounting` (drop records stay on
+/// the shards). `conserved` holds only when every binary shard conserved.
+#[napi(object)]
+pub struct ParseAccountingSummary {
+    pub protocol_version: u32,
+    pub binary_shard_count: u32,
+    pub not_applicable_shard_count: u32,
+    pub declared_entity_count: u32,
+    pub parsed_entity_count: u32,
+    pub dropped_entity_count: u32,
+    pub parse_error_count: u32,
+    pub drop_records_seen: u32,
+    pub conserved: bool,
+}
+
+#[napi(object)]
+pub struct FuseAllResult {
+    pub shard_count: u32,
+    pub failed_shard_count: u32,
+    pub entity_count: u32,
+    pub filtered_relations: u32,
+    pub enriched_count: u32,
+    pub duration_ms: u32,
+    pub parse_accounting: ParseAccountingSummary,
+    /// In `shard_paths` order.
+    pub shards: Vec<FuseAllShardResult>,
+}
 
-        // Always re-stamp umid from canonical id (unsalted SHA256). Binary shards
-        // can carry stale salted umids from earlier cycles; inheriting those mixes
-        // namespaces and produces UNIQUE-constraint collisions in pack-db.
-        // Re-stamping is idempotent and guarantees one-to-one id <-> umid alignment
-        // with Phase 3 enrichment lookup keys.
-        let fresh_umid = generate_umid(&id);
-        entity["umid"] = json!(fresh_umid);
-
-        // A. Closed-world relation filter
-        if let Some(rels) = entity.get_mut("relations").and_then(|v| v.as_array_mut()) {
-            let before = rels.len();
-            rels.retain(|r| {
-                r.get("target_id")
-                    .and_then(|v| v.as_str())
-                    .map(|tid| valid_ids.contains(tid))
-                    .unwrap_or(false)
-            });
-            filtered_rels += (before - rels.len()) as u32;
+fn summarize_accounting<'a>(accs: impl Iterator<Item = &'a ParseAccounting>) -> ParseAccountingSummary {
+    let mut s = ParseAccountingSummary {
+        protocol_version: PARSE_ACCOUNTING_PROTOCOL,
+        binary_shard_count: 0,
+        not_applicable_shard_count: 0,
+        declared_entity_count: 0,
+        parsed_entity_count: 0,
+        dropped_entity_count: 0,
+        parse_error_count: 0,
+        drop_records_seen: 0,
+        conserved: true,
+    };
+    for a in accs {
+        if a.engine_path == "binary" {
+            s.binary_shard_count += 1;
+            s.conserved &= a.conserved;
+        } else {
+            s.not_applicable_shard_count += 1;
         }
+        s.declared_entity_count += a.declared_entity_count;
+        s.parsed_entity_count += a.parsed_entity_count;
+        s.dropped_entity_count += a.dropped_entity_count;
+        s.parse_error_count += a.parse_error_count;
+        s.drop_records_seen += a.drop_records.len() as u32;
+    }
+    s
+}
 
-        // B. FNI V2.0: Preserve 2/4 computed score — no recalculation in fusion
-        let fni_score = entity
-            .get("fni_score")
-            .and_then(|v| v.as_f64())
-            .or_else(|| entity.get("fni").and_then(|v| v.as_f64()))
-            .unwrap_or(0.0);
-        let pct_key = (fni_score.round() as i64).to_string();
-        let percentile = score_pcts
-            .and_then(|m| m.get(&pct_key))
-            .and_then(|v| v.as_f64())
-            .unwrap_or(0.0);
-
-        entity["fni_pScore"] = json!(fni_score);
-        entity["fni_percentile"] = json!(percentile);
-
-        // C. Enrichment from pre-downloaded local files.
-        // Use the freshly-stamped umid — Phase 4 of master-fusion.js saves
-        // enrichment files as `${generateUMID(id)}.md.gz`, so this matches.
-        // umid_manifest is kept as a belt-and-braces fallback for the rare case
-        // where a stale manifest entry is the only available lookup key.
-        if do_enrich {
-            let umid: &str = if !fresh_umid.is_empty() {
-                &fresh_umid
-            } else {
-                umid_manifest.get(&id).map(|s| s.as_str()).unwrap_or("")
-            };
-            if !umid.is_empty() {
-                if let Some(text) = try_load_enrichment(&enrichment_dir, umid) {
-                    if text.len() > 200 {
-                        let has_ft = text.len() > 1000;
-                        entity["body_content"] = json!(text);
-                        entity["has_fulltext"] = json!(has_ft);
-                        enriched += 1;
-                    }
-                }
+/// Run one shard's fusion, turning a panic into a shard error so one bad
+/// shard cannot take down the pool (or the Node process) mid-run.
+fn catch_shard(f: impl FnOnce() -> Result<FuseShardResult>) -> Result<FuseShardResult> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(Error::from_reason(format!("panicked: {msg}")))
+    })
+}
+
+pub struct FuseAllTask {
+    shard_paths: Vec<String>,
+    valid_ids_json: String,
+    fni_thresholds_path: String,
+    enrichment_dir: String,
+    output_dir: String,
+    options: FuseAllOptions,
+}
+
+impl napi::Task for FuseAllTask {
+    type Output = FuseAllResult;
+    type JsValue = FuseAllResult;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let start = std::time::Instant::now();
+        if let Some(dirs) = &self.options.enrichment_dirs {
+            if dirs.len() != self.shard_paths.len() {
+                return Err(Error::from_reason(format!(
+                    "enrichment_dirs has {} entries for {} shards",
+                    dirs.len(),
+                    self.shard_paths.len()
+                )));
             }
         }
-
-        // D. Project (full mode — preserves body_content, has_fulltext, relations)
-        fused.push(project_entity_for_fusion(&entity, percentile as u8));
+        let inputs = FusionInputs::load(
+            &std::mem::take(&mut self.valid_ids_json),
+            &self.fni_thresholds_path,
+            std::mem::take(&mut self.enrichment_dir),
+        )?;
+        let output_dir = &self.output_dir;
+        std::fs::create_dir_all(output_dir).map_err(|e| Error::from_reason(format!("create output dir: {e}")))?;
+
+        let pool = match self.options.threads {
+            Some(n) if n > 0 => Some(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(n as usize)
+                    .build()
+                    .map_err(|e| Error::from_reason(format!("thread pool: {e}")))?,
+            ),
+            _ => None,
+        };
+        let enrichment_dirs = self.options.enrichment_dirs.as_deref();
+        let start_index = self.options.start_index.unwrap_or(0) as usize;
+        let fuse_all = || -> Vec<FuseAllShardResult> {
+            self.shard_paths
+                .par_iter()
+                .enumerate()
+                .map(|(i, shard_path)| {
+                    let shard_start = std::time::Instant::now();
+                    let output_file = std::path::Path::new(output_dir)
+                        .join(format!("part-{:03}.json.zst", start_index + i))
+                        .to_string_lossy()
+                        .into_owned();
+                    let part = std::path::Path::new(shard_path)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let res = catch_shard(|| match enrichment_dirs {
+                        Some(dirs) => inputs.fuse_with(shard_path, &output_file, &Enrichment::load(dirs[i].clone())),
+                        None => inputs.fuse(shard_path, &output_file),
+                    });
+                    let mut out = FuseAllShardResult {
+                        part,
+                        output_file,
+                        entity_count: 0,
+                        filtered_relations: 0,
+                        enriched_count: 0,
+                        error: None,
+                        duration_ms: 0,
+                        parse_accounting: None,
+                    };
+                    match res {
+                        Ok(r) => {
+                            out.entity_count = r.entity_count;
+                            out.filtered_relations = r.filtered_relations;
+                            out.enriched_count = r.enriched_count;
+                            out.parse_accounting = Some(r.parse_accounting);
+                        }
+                        Err(e) => {
+                            eprintln!("[RUST-FUSION] Shard {} ({}) failed: {}", i, out.part, e.reason);
+                            out.error = Some(e.reason);
+                        }
+                    }
+                    out.duration_ms = shard_start.elapsed().as_millis() as u32;
+                    out
+                })
+                .collect()
+        };
+        let shards = match &pool {
+            Some(p) => p.install(fuse_all),
+            None => fuse_all(),
+        };
+
+        let result = FuseAllResult {
+            shard_count: shards.len() as u32,
+            failed_shard_count: shards.iter().filter(|s| s.error.is_some()).count() as u32,
+            entity_count: shards.iter().map(|s| s.entity_count).sum(),
+            filtered_relations: shards.iter().map(|s| s.filtered_relations).sum(),
+            enriched_count: shards.iter().map(|s| s.enriched_count).sum(),
+            duration_ms: start.elapsed().as_millis() as u32,
+            parse_accounting: summarize_accounting(shards.iter().filter_map(|s| s.parse_accounting.as_ref())),
+            shards,
+        };
+        eprintln!(
+            "[RUST-FUSION] {} shards ({} failed): {} entities, {} relations filtered, {} enriched in {}ms",
+            result.shard_count, result.failed_shard_count, result.entity_count, result.filtered_relations,
+            result.enriched_count, result.duration_ms
+        );
+        Ok(result)
     }
 
-    // 5. Write output
-    let output = json!({ "entities": fused, "_ts": timestamp_now() });
-    let serialized =
-        serde_json::to_vec(&output).map_err(|e| Error::from_reason(format!("serialize: {e}")))?;
-
-    // Ensure parent dir exists
-    if let Some(parent) = std::path::Path::new(&output_path).parent() {
-        std::fs::create_dir_all(parent).ok();
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
     }
-    nxvf_core::write_zstd(&output_path, &serialized, 3).map_err(|e| Error::from_reason(e))?;
+}
 
-    Ok(FuseShardResult {
-        entity_count: fused.len() as u32,
-        filtered_relations: filtered_rels,
-        enriched_count: enriched,
-        parse_accounting,
+/// V26.6: Fuse many shards in one call, as an AsyncTask on a libuv worker
+/// thread (returns a Promise; the event loop stays free). The valid-id set,
+/// thresholds and enrichment manifest are loaded once (`fuse_shard`
+/// re-parses all three per shard) and shards fuse in parallel on a rayon
+/// pool. Each shard's output and survivor order are exactly what
+/// `fuse_shard` writes for it; a failed or panicking shard is reported in
+/// `shards[i].error` and does not stop the others.
+///
+/// Enrichment comes from `enrichment_dir` (shared by every shard, or empty)
+/// unless `options.enrichment_dirs` gives each shard its own dir. Every dir
+/// must stay on disk until the Promise settles, so disk use is the sum over
+/// the shards passed; callers that download and delete enrichment per shard
+/// (master-fusion.js) keep that bounded by calling with a batch of shards at
+/// a time, each with `options.start_index` set to the batch's first global
+/// shard index, and deleting the batch's dirs afterwards.
+#[napi]
+#[cfg_attr(test, allow(dead_code))] // tests drive FuseAllTask::compute directly
+pub fn fuse_all_shards(
+    shard_paths: Vec<String>,
+    valid_ids_json: String,
+    fni_thresholds_path: String,
+    enrichment_dir: String,
+    output_dir: String,
+    options: Option<FuseAllOptions>,
+) -> AsyncTask<FuseAllTask> {
+    AsyncTask::new(FuseAllTask {
+        shard_paths,
+        valid_ids_json,
+        fni_thresholds_path,
+        enrichment_dir,
+        output_dir,
+        options: options.unwrap_or_default(),
     })
 }
 
@@ -367,6 +689,222 @@ mod tests {
         assert_eq!(rec2.serde_line, Some(1));
         assert_eq!(rec2.serde_column, Some(2));
     }
+
+    /// StatsTask Phase 4 output is what fuse_shard late-binds `fni_percentile`
+    /// from: `scorePercentiles[round(fni_score)]`, 0 for unknown buckets.
+    #[test]
+    fn fuse_shard_reads_built_thresholds() {
+        let dir = std::env::temp_dir().join(format!("fuse-thresholds-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let scores: Vec<(String, f64)> =
+            vec![("a".into(), 80.4), ("b".into(), 40.0), ("c".into(), 39.6), ("d".into(), 0.0)];
+        let mut by_type = std::collections::HashMap::new();
+        by_type.insert("model".to_string(), scores.iter().map(|(_, s)| *s).collect::<Vec<_>>());
+        let mut by_category = std::collections::HashMap::new();
+        by_category.insert("nlp".to_string(), vec![80.4, 40.0]);
+        let thresholds = crate::percentile::build_thresholds(&scores, [by_type, by_category, Default::default()]);
+        let tp = dir.join("fni-thresholds.json");
+        std::fs::write(&tp, thresholds.to_string()).unwrap();
+
+        let shard = dir.join("part-000.json");
+        let entities = json!({"entities": [
+            {"id": "a", "type": "model", "primary_category": "nlp", "fni_score": 80.4},
+            {"id": "b", "type": "model", "primary_category": "nlp", "fni_score": 40.0},
+            {"id": "c", "type": "model", "fni": 39.6},
+            {"id": "e", "type": "model", "fni_score": 55.0},
+        ]});
+        std::fs::write(&shard, entities.to_string()).unwrap();
+        let out = dir.join("fused-000.json.zst");
+        let res = fuse_shard(
+            shard.to_string_lossy().into_owned(),
+            "[]".into(),
+            tp.to_string_lossy().into_owned(),
+            String::new(),
+            out.to_string_lossy().into_owned(),
+        )
+        .unwrap();
+        assert_eq!(res.entity_count, 4);
+
+        let fused = nxvf_core::load_shard_entities(&out.to_string_lossy()).unwrap();
+        let pct: std::collections::HashMap<&str, f64> = fused
+            .iter()
+            .map(|e| (e["id"].as_str().unwrap(), e["fni_percentile"].as_f64().unwrap()))
+            .collect();
+        // 80 → rank 0 → 100; b and c share bucket 40 (midrank 1.5) → round(62.5) = 63.
+        assert_eq!(pct["a"], 100.0);
+        assert_eq!(pct["b"], 63.0);
+        assert_eq!(pct["c"], 63.0);
+        // 55 is not a bucket in the table.
+        assert_eq!(pct["e"], 0.0);
+
+        // Stratified: every entity is a model; only a and b have a category.
+        let by_id: std::collections::HashMap<&str, &serde_json::Value> =
+            fused.iter().map(|e| (e["id"].as_str().unwrap(), e)).collect();
+        assert_eq!(by_id["b"]["fni_percentile_type"], 63);
+        assert_eq!(by_id["a"]["fni_percentile_category"], 100);
+        assert_eq!(by_id["b"]["fni_percentile_category"], 50);
+        assert!(by_id["c"]["fni_percentile_category"].is_null());
+        assert!(by_id["a"]["fni_percentile_source"].is_null());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// fuse_all_shards writes, per shard, exactly the survivors (and order)
+    /// fuse_shard writes, and folds the accounting + relation counts.
+    #[test]
+    fn fuse_all_matches_per_shard_fusion() {
+        let dir = std::env::temp_dir().join(format!("fuse-all-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let rel = |t: &str| json!({"relation_type": "based_on", "target_id": t});
+        let mut shard_paths = Vec::new();
+        for s in 0..4 {
+            let entities: Vec<serde_json::Value> = (0..5)
+                .map(|i| json!({"id": format!("e{s}-{i}"), "fni_score": (s * 5 + i) as f64,
+                                "relations": [rel("e0-0"), rel("gone"), rel(&format!("e{s}-0"))]}))
+                .collect();
+            let path = if s == 3 {
+                // One NXVF shard so binary parse accounting is folded too.
+                let payloads: Vec<Vec<u8>> =
+                    entities.iter().map(|e| zstd::encode_all(e.to_string().as_bytes(), 3).unwrap()).collect();
+                let p = dir.join("shard-3.bin");
+                std::fs::write(&p, nxvf_core::encode_nxvf_shard("shard-3.bin", 3, &payloads).unwrap()).unwrap();
+                p
+            } else {
+                let p = dir.join(format!("shard-{s}.json"));
+                std::fs::write(&p, json!({ "entities": entities }).to_string()).unwrap();
+                p
+            };
+            shard_paths.push(path.to_string_lossy().into_owned());
+        }
+        shard_paths.push(dir.join("missing.json").to_string_lossy().into_owned());
+        let valid_ids: Vec<String> = (0..4).map(|s| format!("e{s}-0")).collect();
+        let valid_ids_json = serde_json::to_string(&valid_ids).unwrap();
+        let tp = dir.join("fni-thresholds.json");
+        std::fs::write(&tp, json!({"scorePercentiles": {"3": 40, "12": 90}}).to_string()).unwrap();
+        let tps = tp.to_string_lossy().into_owned();
+
+        let out_dir = dir.join("all");
+        let all = FuseAllTask {
+            shard_paths: shard_paths.clone(),
+            valid_ids_json: valid_ids_json.clone(),
+            fni_thresholds_path: tps.clone(),
+            enrichment_dir: String::new(),
+            output_dir: out_dir.to_string_lossy().into_owned(),
+            options: FuseAllOptions { threads: Some(3), ..Default::default() },
+        }
+        .compute()
+        .unwrap();
+        assert_eq!((all.shard_count, all.failed_shard_count), (5, 1));
+        assert!(all.shards[4].error.is_some() && all.shards[4].parse_accounting.is_none());
+        assert_eq!(all.entity_count, 20);
+        // Per entity: "gone" always drops; e{s}-0 self-links survive.
+        assert_eq!(all.filtered_relations, 20);
+        let acc = &all.parse_accounting;
+        assert_eq!((acc.binary_shard_count, acc.not_applicable_shard_count), (1, 3));
+        assert_eq!((acc.declared_entity_count, acc.parsed_entity_count, acc.dropped_entity_count), (5, 5, 0));
+        assert!(acc.conserved);
+
+        let entities_of = |p: &std::path::Path| {
+            let v: serde_json::Value =
+                serde_json::from_slice(&zstd::decode_all(std::fs::read(p).unwrap().as_slice()).unwrap()).unwrap();
+            serde_json::to_string(&v["entities"]).unwrap()
+        };
+        for (i, shard) in shard_paths.iter().take(4).enumerate() {
+            let single = dir.join(format!("single-{i}.json.zst"));
+            let r = fuse_shard(shard.clone(), valid_ids_json.clone(), tps.clone(), String::new(), single.to_string_lossy().into_owned())
+                .unwrap();
+            let s = &all.shards[i];
+            assert_eq!(s.output_file, out_dir.join(format!("part-{:03}.json.zst", i)).to_string_lossy());
+            assert_eq!((s.entity_count, s.filtered_relations), (r.entity_count, r.filtered_relations));
+            assert_eq!(entities_of(std::path::Path::new(&s.output_file)), entities_of(&single), "shard {i}");
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// Each shard reads enrichment from its own dir when `enrichment_dirs`
+    /// is given; a mismatched list is rejected up front.
+    #[test]
+    fn fuse_all_uses_per_shard_enrichment_dirs() {
+        let dir = std::env::temp_dir().join(format!("fuse-all-enrich-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let body = "x".repeat(300);
+        let mut shard_paths = Vec::new();
+        let mut enrichment_dirs = Vec::new();
+        for s in 0..3 {
+            let id = format!("e{s}");
+            let p = dir.join(format!("shard-{s}.json"));
+            let ed = dir.join(format!("enrich-{s}"));
+            std::fs::create_dir_all(&ed).unwrap();
+            std::fs::write(&p, json!({"entities": [{"id": id, "fni_score": 1.0}]}).to_string()).unwrap();
+            // Shard 1 gets no enrichment; the others only find their own file.
+            if s != 1 {
+                let zst = zstd::encode_all(body.as_bytes(), 3).unwrap();
+                std::fs::write(ed.join(format!("{}.md.zst", generate_umid(&id))), zst).unwrap();
+            }
+            shard_paths.push(p.to_string_lossy().into_owned());
+            enrichment_dirs.push(ed.to_string_lossy().into_owned());
+        }
+        let task = |dirs: Vec<String>| FuseAllTask {
+            shard_paths: shard_paths.clone(),
+            valid_ids_json: "[]".into(),
+            fni_thresholds_path: dir.join("none.json").to_string_lossy().into_owned(),
+            enrichment_dir: String::new(),
+            output_dir: dir.join("out").to_string_lossy().into_owned(),
+            options: FuseAllOptions { threads: Some(2), enrichment_dirs: Some(dirs), ..Default::default() },
+        };
+        let all = task(enrichment_dirs.clone()).compute().unwrap();
+        assert_eq!(all.failed_shard_count, 0);
+        let enriched: Vec<u32> = all.shards.iter().map(|s| s.enriched_count).collect();
+        assert_eq!(enriched, vec![1, 0, 1]);
+
+        let err = task(enrichment_dirs[..2].to_vec()).compute().err().unwrap();
+        assert!(err.reason.contains("2 entries for 3 shards"), "{}", err.reason);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// Batches fused into one output dir keep global part numbers instead of
+    /// overwriting each other's part-000.
+    #[test]
+    fn fuse_all_batches_share_output_dir() {
+        let dir = std::env::temp_dir().join(format!("fuse-all-batches-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let shard_paths: Vec<String> = (0..4)
+            .map(|s| {
+                let p = dir.join(format!("shard-{s}.json"));
+                std::fs::write(&p, json!({"entities": [{"id": format!("e{s}"), "fni_score": 1.0}]}).to_string()).unwrap();
+                p.to_string_lossy().into_owned()
+            })
+            .collect();
+        let out_dir = dir.join("out");
+        for (start, batch) in [(0, &shard_paths[..2]), (2, &shard_paths[2..])] {
+            let res = FuseAllTask {
+                shard_paths: batch.to_vec(),
+                valid_ids_json: "[]".into(),
+                fni_thresholds_path: dir.join("none.json").to_string_lossy().into_owned(),
+                enrichment_dir: String::new(),
+                output_dir: out_dir.to_string_lossy().into_owned(),
+                options: FuseAllOptions { start_index: Some(start), ..Default::default() },
+            }
+            .compute()
+            .unwrap();
+            assert_eq!(res.failed_shard_count, 0);
+        }
+        for s in 0..4 {
+            let fused = nxvf_core::load_shard_entities(&out_dir.join(format!("part-{s:03}.json.zst")).to_string_lossy()).unwrap();
+            assert_eq!(fused[0]["id"], format!("e{s}"), "part {s}");
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// A panic inside one shard's fusion becomes that shard's error.
+    #[test]
+    fn catch_shard_reports_panics() {
+        let err = catch_shard(|| panic!("bad shard {}", 7)).err().unwrap();
+        assert_eq!(err.reason, "panicked: bad shard 7");
+        let err = catch_shard(|| panic!("static")).err().unwrap();
+        assert_eq!(err.reason, "panicked: static");
+    }
 }
 
 fn try_load_enrichment(dir: &str, umid: &str) -> Option<String> {
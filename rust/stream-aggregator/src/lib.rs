@@ -4,9 +4,13 @@ use std::collections::HashMap;
 use std::fs;
 use std::io::{BufReader, BufWriter, Write};
 
+mod checkpoint;
 mod fusion;
+mod merge;
 mod percentile;
 mod project;
+mod projection;
+mod sketch;
 
 #[napi(object)]
 pub struct AggregateResult {
@@ -26,13 +30,15 @@ pub fn stream_aggregate(shard_dir: String, output_path: String) -> Result<Aggreg
     let shard_files = discover_shards(&shard_dir)?;
 
     // Phase 2: Extract all (id, fni_score) from shards — O(1) memory per shard
-    let scores = extract_scores(&shard_files)?;
+    let (scores, strata) = extract_scores(&shard_files)?;
 
-    // Phase 3: Calculate global percentile rankings
+    // Phase 3: Calculate global + per type/category/source percentile rankings
     let rankings = percentile::calculate_rankings(&scores);
+    let strata = percentile::StrataRanker::exact(strata);
 
     // Phase 4: Re-read shards, project to slim format, write NDJSON
-    let entity_count = project::project_and_write(&shard_files, &rankings, &output_path)?;
+    let slim = projection::CompiledProjection::resolve("slim").map_err(Error::from_reason)?;
+    let entity_count = project::project_and_write(&shard_files, &rankings, &strata, &slim, &output_path)?;
 
     Ok(AggregateResult {
         entity_count: entity_count as u32,
@@ -48,13 +54,43 @@ pub fn stream_aggregate(shard_dir: String, output_path: String) -> Result<Aggreg
 /// (scripts/factory/lib/relations-generator.js via registry-loader.js) can use
 /// the Rust projector as primary, with the symmetric JS `projectEntityForRelations`
 /// as fallback. Carries ONLY the fields `extractEntityRelations` reads — no slim
-/// FNI/metrics fields, no fusion cold-tier text. Slim `project_entity` and
-/// `project_entity_for_fusion` are untouched (P1 streaming + cold-tier protected).
+/// FNI/metrics fields, no fusion cold-tier text (`specs/relations.json`).
 #[napi]
 pub fn project_entity_relations(entity_json: String) -> Result<String> {
     let value: serde_json::Value = serde_json::from_str(&entity_json)
         .map_err(|e| Error::from_reason(format!("Entity parse error: {}", e)))?;
-    let projected = project::project_entity_for_relations(&value);
+    let projected = project::project_entity_for_relations(&value)?;
+    serde_json::to_string(&projected)
+        .map_err(|e| Error::from_reason(format!("Projection serialize error: {}", e)))
+}
+
+/// V26.6: Register (or replace) a declarative projection spec at runtime —
+/// see projection.rs for the format. Replacing "slim" / "fusion" /
+/// "relations" changes what the streaming and fusion paths emit without a
+/// new addon. Returns the spec name.
+#[napi]
+pub fn register_projection_spec(spec_json: String) -> Result<String> {
+    projection::register(&spec_json).map_err(Error::from_reason)
+}
+
+/// V26.6: Register every `*.json` spec in `dir` (same as PROJECTION_SPEC_DIR
+/// at first use). Returns the registered names.
+#[napi]
+pub fn load_projection_specs(dir: String) -> Result<Vec<String>> {
+    projection::load_dir(&dir).map_err(Error::from_reason)
+}
+
+/// V26.6: Project one entity with a named spec. `context_json` supplies
+/// `context` fields (e.g. `{"fni_percentile": 87}`).
+#[napi]
+pub fn project_entity_with_spec(spec_name: String, entity_json: String, context_json: Option<String>) -> Result<String> {
+    let value: serde_json::Value = serde_json::from_str(&entity_json)
+        .map_err(|e| Error::from_reason(format!("Entity parse error: {}", e)))?;
+    let ctx: serde_json::Map<String, serde_json::Value> = match context_json.as_deref() {
+        Some(c) => serde_json::from_str(c).map_err(|e| Error::from_reason(format!("Context parse error: {}", e)))?,
+        None => Default::default(),
+    };
+    let projected = projection::project(&spec_name, &value, &ctx).map_err(Error::from_reason)?;
     serde_json::to_string(&projected)
         .map_err(|e| Error::from_reason(format!("Projection serialize error: {}", e)))
 }
@@ -95,8 +131,19 @@ fn discover_shards(dir: &str) -> Result<Vec<String>> {
     nxvf_core::discover_shards(dir).map_err(|e| Error::from_reason(e))
 }
 
-fn extract_scores(files: &[String]) -> Result<Vec<(String, f64)>> {
+/// Delta routing index of a registry shard: `part-NNN.bin` → NNN, otherwise
+/// its position in discovery order. Shared by stats routing and delta merge.
+pub(crate) fn registry_shard_index(path: &str, position: usize) -> u32 {
+    std::path::Path::new(path).file_stem().and_then(|s| s.to_str())
+        .and_then(|s| s.strip_prefix("part-")).and_then(|s| s.parse::<u32>().ok()).unwrap_or(position as u32)
+}
+
+/// (id, fni_score) pairs plus every score grouped per type / category / source.
+type ExtractedScores = (Vec<(String, f64)>, percentile::Strata<Vec<f64>>);
+
+fn extract_scores(files: &[String]) -> Result<ExtractedScores> {
     let mut scores = Vec::new();
+    let mut strata: percentile::Strata<Vec<f64>> = Default::default();
     for file_path in files {
         let entities = match load_shard_entities(file_path) {
             Ok(e) => e,
@@ -105,20 +152,12 @@ fn extract_scores(files: &[String]) -> Result<Vec<(String, f64)>> {
                 continue;
             }
         };
-        for e in entities {
-            let id = e.get("id").and_then(|v| v.as_str()).unwrap_or_default();
-            if id.is_empty() {
-                continue;
-            }
-            let score = e
-                .get("fni_score")
-                .and_then(|v| v.as_f64())
-                .or_else(|| e.get("fni").and_then(|v| v.as_f64()))
-                .unwrap_or(0.0);
-            scores.push((id.to_string(), score));
+        for se in entities.iter().filter_map(nxvf_core::ScoredEntity::from_value) {
+            percentile::push_strata(&mut strata, percentile::stratum_keys(&se), Vec::new, |v| v.push(se.score));
+            scores.push((se.id, se.score));
         }
     }
-    Ok(scores)
+    Ok((scores, strata))
 }
 
 pub(crate) fn load_shard_entities(path: &str) -> Result<Vec<serde_json::Value>> {
@@ -176,11 +215,30 @@ pub fn zstd_decompress_file(input_path: String, output_path: String) -> Result<u
 /// V26.5: Route 2/4 artifact entities to per-registry-shard delta JSONL files.
 /// Replaces JS preProcessDeltas — no V8 string limit, no GC pressure,
 /// streaming I/O via BufWriter. O(1) memory per entity.
+/// V26.6: staged + checkpointed per artifact (see checkpoint.rs); `resume`
+/// (default true) continues a killed run from its last completed artifact.
 #[napi(object)]
 pub struct RouteDeltaResult {
     pub routed_count: u32,
     pub shard_count: u32,
     pub duration_ms: u32,
+    /// Artifacts skipped because an interrupted run already routed them.
+    pub resumed_artifact_count: u32,
+}
+
+/// `shard-*.json{,.gz,.zst}` artifact files, sorted (checkpoint order).
+fn discover_artifacts(artifact_dir: &str) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    if let Ok(entries) = fs::read_dir(artifact_dir) {
+        for entry in entries.flatten() {
+            let n = entry.file_name().to_string_lossy().to_string();
+            if n.starts_with("shard-") && (n.ends_with(".json.zst") || n.ends_with(".json.gz") || n.ends_with(".json")) {
+                files.push(entry.path().to_string_lossy().to_string());
+            }
+        }
+    }
+    files.sort();
+    files
 }
 
 #[napi]
@@ -188,6 +246,7 @@ pub fn route_artifacts_to_deltas(
     artifact_dir: String,
     registry_map_path: String,
     delta_dir: String,
+    resume: Option<bool>,
 ) -> Result<RouteDeltaResult> {
     let start = std::time::Instant::now();
 
@@ -197,35 +256,21 @@ pub fn route_artifacts_to_deltas(
     let registry_map: HashMap<String, u32> = serde_json::from_str(&map_data)
         .map_err(|e| Error::from_reason(format!("Cannot parse registry map: {}", e)))?;
 
-    // Ensure delta dir exists and is clean
-    fs::create_dir_all(&delta_dir).ok();
-    if let Ok(entries) = fs::read_dir(&delta_dir) {
-        for entry in entries.flatten() {
-            fs::remove_file(entry.path()).ok();
-        }
-    }
-
-    // Open BufWriters lazily per shard index
-    let mut writers: HashMap<u32, BufWriter<fs::File>> = HashMap::new();
-    let mut routed = 0u32;
-
     // Discover and process artifact shards
-    let mut artifact_files: Vec<String> = Vec::new();
-    if let Ok(entries) = fs::read_dir(&artifact_dir) {
-        for entry in entries.flatten() {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if name.starts_with("shard-") && (name.ends_with(".json.zst") || name.ends_with(".json.gz") || name.ends_with(".json")) {
-                artifact_files.push(entry.path().to_string_lossy().to_string());
-            }
-        }
-    }
-    artifact_files.sort();
+    let artifact_files = discover_artifacts(&artifact_dir);
+    let fp = checkpoint::fingerprint("enriched", std::slice::from_ref(&registry_map_path));
+    let mut router = checkpoint::DeltaRouter::open(&delta_dir, fp, resume.unwrap_or(true), &artifact_files)
+        .map_err(Error::from_reason)?;
 
     for artifact_path in &artifact_files {
+        if router.is_completed(artifact_path) {
+            continue;
+        }
         let entities = match nxvf_core::load_shard_entities(artifact_path) {
             Ok(e) => e,
             Err(e) => {
                 eprintln!("[RUST-DELTA] Skipping {}: {}", artifact_path, e);
+                router.complete_artifact(artifact_path, Some(e)).map_err(Error::from_reason)?;
                 continue;
             }
         };
@@ -237,34 +282,27 @@ pub fn route_artifacts_to_deltas(
             if id.is_empty() { continue; }
 
             if let Some(&shard_idx) = registry_map.get(id) {
-                let writer = writers.entry(shard_idx).or_insert_with(|| {
-                    let path = format!("{}/reg-{}.jsonl", delta_dir, shard_idx);
-                    BufWriter::new(fs::File::create(&path).expect("Cannot create delta file"))
-                });
-                serde_json::to_writer(&mut *writer, incoming).ok();
-                writer.write_all(b"\n").ok();
-                routed += 1;
+                let line = serde_json::to_vec(incoming)
+                    .map_err(|e| Error::from_reason(format!("Serialize {}: {}", id, e)))?;
+                router.write(shard_idx, &line).map_err(Error::from_reason)?;
             }
         }
+        router.complete_artifact(artifact_path, None).map_err(Error::from_reason)?;
 
-        if routed % 50000 == 0 && routed > 0 {
-            eprintln!("[RUST-DELTA] {} entities routed...", routed);
-        }
+        eprintln!("[RUST-DELTA] {} entities routed...", router.routed());
     }
 
-    // Flush all writers
-    let shard_count = writers.len() as u32;
-    for (_, mut w) in writers {
-        w.flush().ok();
-    }
+    let resumed = router.resumed();
+    let marker = router.finish().map_err(Error::from_reason)?;
 
     eprintln!("[RUST-DELTA] Complete: {} entities → {} delta files in {}ms",
-        routed, shard_count, start.elapsed().as_millis());
+        marker.routed_count, marker.delta_shard_count, start.elapsed().as_millis());
 
     Ok(RouteDeltaResult {
-        routed_count: routed,
-        shard_count,
+        routed_count: marker.routed_count,
+        shard_count: marker.delta_shard_count,
         duration_ms: start.elapsed().as_millis() as u32,
+        resumed_artifact_count: resumed,
     })
 }
 
@@ -278,9 +316,36 @@ pub struct RegistryStatsResult {
     pub duration_ms: u32,
     pub rankings: HashMap<String, u8>,
     pub scores: HashMap<String, f64>,
+    /// "exact" | "approx"
+    pub percentile_mode: String,
+    /// Artifacts Phase 3 skipped because an interrupted run already routed them.
+    pub resumed_artifact_count: u32,
+}
+
+/// V26.6: Phase 2 percentile mode. "exact" (default) sorts every score;
+/// "approx" ranks off a KLL sketch filled during Phase 1 — no full sort, no
+/// per-score HashMaps — with normalised rank error ≈ 2 / sketch_k
+/// (default 2048 → percentiles within ±1 of exact).
+#[napi(object)]
+#[derive(Default)]
+pub struct RegistryStatsOptions {
+    pub percentile_mode: Option<String>,
+    pub sketch_k: Option<u32>,
+    /// Phase 3 resumes from a killed run's checkpoint when its inputs match (default true).
+    pub resume: Option<bool>,
 }
 
-pub struct StatsTask { shard_dir: String, artifact_dir: String, delta_dir: String, output_dir: String }
+pub struct StatsTask { shard_dir: String, artifact_dir: String, delta_dir: String, output_dir: String, options: RegistryStatsOptions }
+
+impl StatsTask {
+    fn sketch_k(&self) -> Result<Option<u32>> {
+        match self.options.percentile_mode.as_deref() {
+            None | Some("exact") => Ok(None),
+            Some("approx") => Ok(Some(self.options.sketch_k.unwrap_or(percentile::DEFAULT_SKETCH_K))),
+            Some(other) => Err(Error::from_reason(format!("Unknown percentile_mode {:?} (expected \"exact\" or \"approx\")", other))),
+        }
+    }
+}
 
 impl napi::Task for StatsTask {
     type Output = RegistryStatsResult;
@@ -288,6 +353,7 @@ impl napi::Task for StatsTask {
 
     fn compute(&mut self) -> Result<Self::Output> {
         let start = std::time::Instant::now();
+        let mut collector = percentile::ScoreCollector::new(self.sketch_k()?);
         let shard_files = discover_shards(&self.shard_dir)?;
         let shard_count = shard_files.len() as u32;
 
@@ -295,43 +361,36 @@ impl napi::Task for StatsTask {
         let mut scores: Vec<(String, f64)> = Vec::new();
         let mut registry_map: HashMap<String, u32> = HashMap::new();
         for (fi, fp) in shard_files.iter().enumerate() {
-            let ss = match nxvf_core::extract_scores_from_shard(fp) {
+            let ss = match nxvf_core::extract_scored_entities_from_shard(fp) {
                 Ok(s) => s, Err(e) => { eprintln!("[RUST-STATS] Skipping {}: {}", fp, e); continue; }
             };
-            let si = std::path::Path::new(fp).file_stem().and_then(|s| s.to_str())
-                .and_then(|s| s.strip_prefix("part-")).and_then(|s| s.parse::<u32>().ok()).unwrap_or(fi as u32);
-            for (id, score) in ss { registry_map.insert(id.clone(), si); scores.push((id, score)); }
+            let si = registry_shard_index(fp, fi);
+            for se in ss {
+                registry_map.insert(se.id.clone(), si);
+                collector.push(percentile::stratum_keys(&se), se.score);
+                scores.push((se.id, se.score));
+            }
             if (fi + 1) % 50 == 0 { eprintln!("[RUST-STATS] Phase 1: {}/{} shards, {} entities", fi+1, shard_count, scores.len()); }
         }
         let entity_count = scores.len() as u32;
         eprintln!("[RUST-STATS] Phase 1 done: {} entities from {} shards ({}ms)", entity_count, shard_count, start.elapsed().as_millis());
 
         // Phase 2: percentile rankings (returned directly via N-API, no TSV intermediate file)
-        eprintln!("[RUST-STATS] Phase 2: percentile rankings...");
-        let rankings = percentile::calculate_rankings(&scores);
-        fs::create_dir_all(&self.output_dir).ok();
+        let percentile_mode = collector.mode().to_string();
+        eprintln!("[RUST-STATS] Phase 2: percentile rankings ({})...", percentile_mode);
+        let (rankings, thresholds) = collector.finish(&scores);
+        fs::create_dir_all(&self.output_dir).map_err(|e| Error::from_reason(format!("Create {}: {}", self.output_dir, e)))?;
         let score_map: HashMap<String, f64> = scores.into_iter().filter(|(_, s)| *s > 0.0).collect();
         eprintln!("[RUST-STATS] Phase 2: {} rankings + {} scores", rankings.len(), score_map.len());
         eprintln!("[RUST-STATS] Phase 2 done: {} rankings ({}ms)", rankings.len(), start.elapsed().as_millis());
 
-        // Phase 3: route artifacts → deltas (in-memory registry_map)
+        // Phase 3: route artifacts → deltas (in-memory registry_map), staged +
+        // checkpointed per artifact so a killed run resumes (see checkpoint.rs)
         eprintln!("[RUST-STATS] Phase 3: delta routing...");
-        fs::create_dir_all(&self.delta_dir).ok();
-        if let Ok(entries) = fs::read_dir(&self.delta_dir) {
-            for entry in entries.flatten() { fs::remove_file(entry.path()).ok(); }
-        }
-        let mut writers: HashMap<u32, BufWriter<fs::File>> = HashMap::new();
-        let mut routed = 0u32;
-        let mut af: Vec<String> = Vec::new();
-        if let Ok(entries) = fs::read_dir(&self.artifact_dir) {
-            for entry in entries.flatten() {
-                let n = entry.file_name().to_string_lossy().to_string();
-                if n.starts_with("shard-") && (n.ends_with(".json.zst") || n.ends_with(".json.gz") || n.ends_with(".json")) {
-                    af.push(entry.path().to_string_lossy().to_string());
-                }
-            }
-        }
-        af.sort();
+        let af = discover_artifacts(&self.artifact_dir);
+        let fp = checkpoint::fingerprint("raw", &shard_files);
+        let mut router = checkpoint::DeltaRouter::open(&self.delta_dir, fp, self.options.resume.unwrap_or(true), &af)
+            .map_err(Error::from_reason)?;
         // Diagnostic: check RSS before Phase 3
         if let Ok(status) = fs::read_to_string("/proc/self/status") {
             for line in status.lines() {
@@ -342,35 +401,41 @@ impl napi::Task for StatsTask {
         }
         eprintln!("[RUST-DELTA] Found {} artifact shards", af.len());
         for (ai, ap) in af.iter().enumerate() {
-            let before = routed;
+            if router.is_completed(ap) {
+                eprintln!("[RUST-DELTA] {}/{}: already routed (checkpoint)", ai+1, af.len());
+                continue;
+            }
+            let before = router.routed();
+            // Write failures abort the task; read failures skip the rest of the artifact.
+            let mut write_err: Option<String> = None;
             let r = nxvf_core::for_each_raw_entity(ap, |raw| {
                 let id = extract_id_from_raw(raw);
                 if id.is_empty() { return Ok(()); }
                 if let Some(&si) = registry_map.get(id) {
-                    let w = writers.entry(si).or_insert_with(|| {
-                        BufWriter::new(fs::File::create(format!("{}/reg-{}.jsonl", self.delta_dir, si)).expect("delta file"))
-                    });
-                    w.write_all(raw).ok();
-                    w.write_all(b"\n").ok();
-                    routed += 1;
+                    router.write(si, raw).inspect_err(|e| write_err = Some(e.clone()))?;
                 }
                 Ok(())
             });
-            match r {
-                Ok(n) => eprintln!("[RUST-DELTA] {}/{}: {} entities, {} routed ({}ms)", ai+1, af.len(), n, routed-before, start.elapsed().as_millis()),
-                Err(e) => eprintln!("[RUST-DELTA] {}/{} ERROR: {}", ai+1, af.len(), e),
-            }
+            if let Some(e) = write_err { return Err(Error::from_reason(format!("Phase 3: {}", e))); }
+            let read_err = match r {
+                Ok(n) => { eprintln!("[RUST-DELTA] {}/{}: {} entities, {} routed ({}ms)", ai+1, af.len(), n, router.routed()-before, start.elapsed().as_millis()); None }
+                Err(e) => { eprintln!("[RUST-DELTA] {}/{} ERROR: {}", ai+1, af.len(), e); Some(e) }
+            };
+            router.complete_artifact(ap, read_err).map_err(Error::from_reason)?;
         }
-        let dsc = writers.len() as u32;
-        for (_, mut w) in writers { w.flush().ok(); }
+        let marker = router.finish().map_err(Error::from_reason)?;
+        let (routed, dsc) = (marker.routed_count, marker.delta_shard_count);
         drop(registry_map);
 
-        // Phase 4: fni-thresholds.json
-        if let Ok(f) = fs::File::create(format!("{}/fni-thresholds.json", self.output_dir)) {
-            write!(BufWriter::new(f), "{{\"_count\":{}}}\n", entity_count).ok();
-        }
+        // Phase 4: fni-thresholds.json (versioned score→percentile + per-type tables, see percentile::build_thresholds)
+        let tp = format!("{}/fni-thresholds.json", self.output_dir);
+        let data = serde_json::to_vec(&thresholds).map_err(|e| Error::from_reason(format!("Phase 4: {}", e)))?;
+        checkpoint::write_atomic(std::path::Path::new(&tp), &data).map_err(|e| Error::from_reason(format!("Phase 4: {}", e)))?;
+        eprintln!("[RUST-STATS] Phase 4: {} score buckets, {} types → {}",
+            thresholds["scorePercentiles"].as_object().map_or(0, |m| m.len()),
+            thresholds["types"].as_object().map_or(0, |m| m.len()), tp);
         eprintln!("[RUST-STATS] Complete: {} entities, {} routed → {} deltas ({}ms)", entity_count, routed, dsc, start.elapsed().as_millis());
-        Ok(RegistryStatsResult { entity_count, shard_count, routed_count: routed, delta_shard_count: dsc, duration_ms: start.elapsed().as_millis() as u32, rankings, scores: score_map })
+        Ok(RegistryStatsResult { entity_count, shard_count, routed_count: routed, delta_shard_count: dsc, duration_ms: start.elapsed().as_millis() as u32, rankings, scores: score_map, percentile_mode, resumed_artifact_count: marker.resumed_artifact_count })
     }
 
     fn resolve(&mut self, _env: napi::Env, output: Self::Output) -> Result<Self::JsValue> { Ok(output) }
@@ -378,8 +443,9 @@ impl napi::Task for StatsTask {
 
 #[napi]
 pub fn build_stats_and_route_deltas(shard_dir: String, artifact_dir: String, delta_dir: String, output_dir: String,
+    options: Option<RegistryStatsOptions>,
 ) -> napi::bindgen_prelude::AsyncTask<StatsTask> {
-    napi::bindgen_prelude::AsyncTask::new(StatsTask { shard_dir, artifact_dir, delta_dir, output_dir })
+    napi::bindgen_prelude::AsyncTask::new(StatsTask { shard_dir, artifact_dir, delta_dir, output_dir, options: options.unwrap_or_default() })
 }
 
 /// Compress a Buffer with Zstd, returning compressed Buffer.
@@ -0,0 +1,540 @@
+//! V26.6 Native delta merge — replaces JS mergePartitionedShard.
+//!
+//! For each registry shard, the routed `reg-<index>.jsonl` delta file is
+//! loaded (one shard's worth of updates), the shard is streamed entity by
+//! entity, and the merged shard is written to `output_dir` under a temp name,
+//! fsynced and renamed — a crash never leaves half (or an empty) shard. A shard that fails to
+//! decode part-way is reported and NOT written.
+//!
+//! Field rules (entity-merger.js `mergeEntities`, plus honest-contract):
+//!  - last-write-wins: delta fields replace shard fields; a delta `null`
+//!    never erases a value;
+//!  - keep-max: popularity counters and scores (`KEEP_MAX`);
+//!  - union: `tags` (comma strings coerced to lists) and `relations`
+//!    (keyed by relation type + target; the delta's copy wins);
+//!  - `id` is never rewritten; `_updated` is stamped only when something
+//!    actually changed, so re-running a merge is a no-op.
+//!
+//! Delta ids missing from the shard are appended (`inserted`) unless
+//! `insert_missing` is false.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde_json::{json, Map, Value};
+
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+const KEEP_MAX: &[&str] = &[
+    "fni_score", "fni", "quality_score", "likes", "downloads",
+    "stars", "github_stars", "forks", "citations", "citation_count",
+];
+
+#[napi(object)]
+#[derive(Default)]
+pub struct MergeDeltasOptions {
+    /// "same" (default: .bin → "nxvf", anything else → "json.zst") | "nxvf" | "json.zst"
+    pub output_format: Option<String>,
+    pub zstd_level: Option<i32>,
+    /// Append delta entities the shard does not hold (default true).
+    pub insert_missing: Option<bool>,
+}
+
+#[napi(object)]
+pub struct ShardMergeStats {
+    /// Input shard file name.
+    pub part: String,
+    /// Written shard path; None when the shard failed.
+    pub output_file: Option<String>,
+    pub entity_count: u32,
+    pub inserted: u32,
+    pub updated: u32,
+    pub unchanged: u32,
+    pub delta_count: u32,
+    /// Delta lines that were not a JSON object with an id.
+    pub invalid_delta_lines: u32,
+    pub error: Option<String>,
+    pub duration_ms: u32,
+}
+
+#[napi(object)]
+pub struct MergeDeltasResult {
+    pub shard_count: u32,
+    pub failed_shard_count: u32,
+    pub inserted: u32,
+    pub updated: u32,
+    pub unchanged: u32,
+    pub invalid_delta_lines: u32,
+    pub duration_ms: u32,
+    pub shards: Vec<ShardMergeStats>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Nxvf,
+    JsonZst,
+}
+
+/// Tags as a list; a comma string (string-emitting adapters) is split.
+fn tag_list(v: Option<&Value>) -> Vec<Value> {
+    match v {
+        Some(Value::Array(a)) => a.clone(),
+        Some(Value::String(s)) => s.split(',').map(str::trim).filter(|t| !t.is_empty()).map(|t| json!(t)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn union_tags(existing: Option<&Value>, incoming: Option<&Value>) -> Value {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut out = Vec::new();
+    for t in tag_list(existing).into_iter().chain(tag_list(incoming)) {
+        if seen.insert(t.to_string()) {
+            out.push(t);
+        }
+    }
+    Value::Array(out)
+}
+
+fn relation_key(r: &Value) -> String {
+    let field = |a: &str, b: &str| r.get(a).or_else(|| r.get(b)).and_then(Value::as_str).map(str::to_string);
+    match (field("relation_type", "type"), field("target_id", "target")) {
+        (Some(t), Some(target)) => format!("{t}\0{target}"),
+        _ => r.to_string(),
+    }
+}
+
+/// Union keyed by (type, target); the incoming copy replaces the existing one
+/// in place, new relations are appended.
+fn union_relations(existing: Option<&Value>, incoming: Option<&Value>) -> Value {
+    let mut out: Vec<Value> = existing.and_then(Value::as_array).cloned().unwrap_or_default();
+    let mut index: HashMap<String, usize> = out.iter().enumerate().map(|(i, r)| (relation_key(r), i)).collect();
+    for r in incoming.and_then(Value::as_array).into_iter().flatten() {
+        match index.get(&relation_key(r)) {
+            Some(&i) => out[i] = r.clone(),
+            None => {
+                index.insert(relation_key(r), out.len());
+                out.push(r.clone());
+            }
+        }
+    }
+    Value::Array(out)
+}
+
+/// Apply one delta to an entity (see module docs for the rules).
+pub(crate) fn merge_entity(existing: &Map<String, Value>, incoming: &Map<String, Value>) -> Map<String, Value> {
+    let mut merged = existing.clone();
+    for (k, v) in incoming {
+        if v.is_null() || k == "id" || k == "_updated" {
+            continue;
+        }
+        match k.as_str() {
+            "tags" => {
+                merged.insert(k.clone(), union_tags(existing.get(k), Some(v)));
+            }
+            "relations" => {
+                merged.insert(k.clone(), union_relations(existing.get(k), Some(v)));
+            }
+            f if KEEP_MAX.contains(&f) => {
+                let best = match (existing.get(k).and_then(Value::as_f64), v.as_f64()) {
+                    (Some(a), Some(b)) if a >= b => existing[k].clone(),
+                    (_, Some(_)) => v.clone(),
+                    (Some(_), None) => existing[k].clone(),
+                    (None, None) => v.clone(),
+                };
+                merged.insert(k.clone(), best);
+            }
+            _ => {
+                merged.insert(k.clone(), v.clone());
+            }
+        }
+    }
+    merged
+}
+
+/// Current time as ISO-8601 UTC with milliseconds, like JS `toISOString()`.
+pub(crate) fn iso_now() -> String {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    let (secs, millis) = (now.as_secs() as i64, now.subsec_millis());
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Civil-from-days (H. Hinnant).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{millis:03}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// id → deltas for that id, in file order.
+type DeltaMap = HashMap<String, Vec<Map<String, Value>>>;
+
+/// (deltas, valid lines, invalid lines). Missing file = no deltas.
+fn load_deltas(path: &Path) -> std::result::Result<(DeltaMap, u32, u32), String> {
+    let mut deltas = DeltaMap::new();
+    let (mut count, mut invalid) = (0u32, 0u32);
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((deltas, 0, 0)),
+        Err(e) => return Err(format!("Cannot open {}: {}", path.display(), e)),
+    };
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Read {}: {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Value>(&line) {
+            Ok(Value::Object(m)) if m.get("id").and_then(Value::as_str).is_some_and(|id| !id.is_empty()) => {
+                let id = m["id"].as_str().unwrap_or_default().to_string();
+                deltas.entry(id).or_default().push(m);
+                count += 1;
+            }
+            _ => invalid += 1,
+        }
+    }
+    Ok((deltas, count, invalid))
+}
+
+/// `part-000.json.zst` → `part-000`.
+fn part_stem(name: &str) -> &str {
+    [".bin", ".json.zst", ".json.gz", ".json"]
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .unwrap_or(name)
+}
+
+/// Merged shard sink: NXVF needs every payload for its offset table; the
+/// JSON envelope is streamed through a Zstd encoder.
+enum Sink {
+    Nxvf(Vec<Vec<u8>>),
+    Json(zstd::Encoder<'static, BufWriter<fs::File>>, bool),
+}
+
+impl Sink {
+    fn push(&mut self, entity: &Value, level: i32) -> std::result::Result<(), String> {
+        let bytes = serde_json::to_vec(entity).map_err(|e| format!("serialize: {e}"))?;
+        match self {
+            Sink::Nxvf(payloads) => payloads.push(zstd::encode_all(bytes.as_slice(), level).map_err(|e| format!("zstd: {e}"))?),
+            Sink::Json(enc, first) => {
+                if !*first {
+                    enc.write_all(b",").map_err(|e| format!("write: {e}"))?;
+                }
+                *first = false;
+                enc.write_all(&bytes).map_err(|e| format!("write: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct ShardJob<'a> {
+    path: &'a str,
+    index: u32,
+    delta_dir: &'a str,
+    output_dir: &'a str,
+    format: Option<Format>,
+    level: i32,
+    insert_missing: bool,
+}
+
+fn merge_shard(job: &ShardJob, stats: &mut ShardMergeStats) -> std::result::Result<String, String> {
+    let (mut deltas, delta_count, invalid) = load_deltas(&Path::new(job.delta_dir).join(format!("reg-{}.jsonl", job.index)))?;
+    stats.delta_count = delta_count;
+    stats.invalid_delta_lines = invalid;
+
+    let format = job.format.unwrap_or(if job.path.ends_with(".bin") { Format::Nxvf } else { Format::JsonZst });
+    let out_name = format!("{}{}", part_stem(&stats.part), if format == Format::Nxvf { ".bin" } else { ".json.zst" });
+    let out_path = Path::new(job.output_dir).join(&out_name);
+    let tmp_path = Path::new(job.output_dir).join(format!("{out_name}.tmp"));
+
+    let mut sink = match format {
+        Format::Nxvf => Sink::Nxvf(Vec::new()),
+        Format::JsonZst => {
+            let file = fs::File::create(&tmp_path).map_err(|e| format!("Create {}: {}", tmp_path.display(), e))?;
+            let mut enc = zstd::Encoder::new(BufWriter::new(file), job.level).map_err(|e| format!("zstd init: {e}"))?;
+            enc.write_all(b"{\"entities\":[").map_err(|e| format!("write: {e}"))?;
+            Sink::Json(enc, true)
+        }
+    };
+
+    let now = iso_now();
+    let res = nxvf_core::for_each_entity_in_file(job.path, |entity| {
+        stats.entity_count += 1;
+        let id = entity.get("id").and_then(Value::as_str).unwrap_or("").to_string();
+        let out = match (deltas.remove(&id), entity) {
+            (Some(updates), Value::Object(existing)) if !id.is_empty() => {
+                let stamp = updates.iter().rev().find_map(|u| u.get("_updated").filter(|v| v.is_string()).cloned());
+                let merged = updates.iter().fold(existing.clone(), |acc, u| merge_entity(&acc, u));
+                if merged == existing {
+                    stats.unchanged += 1;
+                    Value::Object(merged)
+                } else {
+                    stats.updated += 1;
+                    let mut merged = merged;
+                    merged.insert("_updated".into(), stamp.unwrap_or_else(|| json!(now)));
+                    Value::Object(merged)
+                }
+            }
+            (_, entity) => {
+                stats.unchanged += 1;
+                entity
+            }
+        };
+        sink.push(&out, job.level)
+    });
+    let written = res.and_then(|_| {
+        if job.insert_missing {
+            // Deterministic order for ids the shard did not hold.
+            let mut rest: Vec<_> = deltas.into_iter().collect();
+            rest.sort_by(|a, b| a.0.cmp(&b.0));
+            for (_, updates) in rest {
+                let mut updates = updates.into_iter();
+                let first = updates.next().unwrap_or_default();
+                let mut merged = updates.fold(first, |acc, u| merge_entity(&acc, &u));
+                merged.entry("_updated").or_insert_with(|| json!(now));
+                sink.push(&Value::Object(merged), job.level)?;
+                stats.inserted += 1;
+            }
+        }
+        match sink {
+            Sink::Nxvf(payloads) => {
+                let bytes = nxvf_core::encode_nxvf_shard(&out_name, job.index as u16, &payloads)?;
+                fs::File::create(&tmp_path)
+                    .and_then(|mut f| f.write_all(&bytes).and_then(|_| f.sync_all()))
+                    .map_err(|e| format!("Write {}: {}", tmp_path.display(), e))
+            }
+            Sink::Json(mut enc, _) => {
+                enc.write_all(b"]}").map_err(|e| format!("write: {e}"))?;
+                enc.finish()
+                    .and_then(|w| w.into_inner().map_err(|e| e.into_error()))
+                    .and_then(|f| f.sync_all())
+                    .map_err(|e| format!("finish {}: {}", tmp_path.display(), e))
+            }
+        }
+    });
+    if let Err(e) = written {
+        fs::remove_file(&tmp_path).ok();
+        return Err(e);
+    }
+    fs::rename(&tmp_path, &out_path).map_err(|e| format!("Rename {}: {}", tmp_path.display(), e))?;
+    Ok(out_path.to_string_lossy().into_owned())
+}
+
+pub struct MergeTask { shard_dir: String, delta_dir: String, output_dir: String, options: MergeDeltasOptions }
+
+impl napi::Task for MergeTask {
+    type Output = MergeDeltasResult;
+    type JsValue = MergeDeltasResult;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let start = std::time::Instant::now();
+        let opts = &self.options;
+        let (shard_dir, delta_dir, output_dir) = (&self.shard_dir, &self.delta_dir, &self.output_dir);
+        let format = match opts.output_format.as_deref() {
+            None | Some("same") => None,
+            Some("nxvf") => Some(Format::Nxvf),
+            Some("json.zst") => Some(Format::JsonZst),
+            Some(other) => {
+                return Err(Error::from_reason(format!(
+                    "Unknown output_format {:?} (expected \"same\", \"nxvf\" or \"json.zst\")",
+                    other
+                )))
+            }
+        };
+        fs::create_dir_all(output_dir).map_err(|e| Error::from_reason(format!("create output dir: {e}")))?;
+        let shard_files = crate::discover_shards(shard_dir)?;
+        if crate::checkpoint::read_marker(delta_dir).is_none() {
+            eprintln!("[RUST-MERGE] No {} in {} — deltas may be from an unfinished routing run", crate::checkpoint::COMPLETE_MARKER, delta_dir);
+        }
+
+        let mut shards = Vec::with_capacity(shard_files.len());
+        for (fi, path) in shard_files.iter().enumerate() {
+            let shard_start = std::time::Instant::now();
+            let part = Path::new(path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let mut stats = ShardMergeStats {
+                part,
+                output_file: None,
+                entity_count: 0,
+                inserted: 0,
+                updated: 0,
+                unchanged: 0,
+                delta_count: 0,
+                invalid_delta_lines: 0,
+                error: None,
+                duration_ms: 0,
+            };
+            let job = ShardJob {
+                path,
+                index: crate::registry_shard_index(path, fi),
+                delta_dir,
+                output_dir,
+                format,
+                level: opts.zstd_level.unwrap_or(DEFAULT_ZSTD_LEVEL),
+                insert_missing: opts.insert_missing.unwrap_or(true),
+            };
+            match merge_shard(&job, &mut stats) {
+                Ok(out) => stats.output_file = Some(out),
+                Err(e) => {
+                    eprintln!("[RUST-MERGE] {} failed, not written: {}", stats.part, e);
+                    stats.error = Some(e);
+                }
+            }
+            stats.duration_ms = shard_start.elapsed().as_millis() as u32;
+            eprintln!(
+                "[RUST-MERGE] {}/{} {}: {} inserted, {} updated, {} unchanged ({} deltas)",
+                fi + 1, shard_files.len(), stats.part, stats.inserted, stats.updated, stats.unchanged, stats.delta_count
+            );
+            shards.push(stats);
+        }
+
+        let ok = || shards.iter().filter(|s| s.error.is_none());
+        Ok(MergeDeltasResult {
+            shard_count: shard_files.len() as u32,
+            failed_shard_count: shards.iter().filter(|s| s.error.is_some()).count() as u32,
+            inserted: ok().map(|s| s.inserted).sum(),
+            updated: ok().map(|s| s.updated).sum(),
+            unchanged: ok().map(|s| s.unchanged).sum(),
+            invalid_delta_lines: shards.iter().map(|s| s.invalid_delta_lines).sum(),
+            duration_ms: start.elapsed().as_millis() as u32,
+            shards,
+        })
+    }
+
+    fn resolve(&mut self, _env: napi::Env, output: Self::Output) -> Result<Self::JsValue> { Ok(output) }
+}
+
+/// Merge routed `reg-<index>.jsonl` deltas into every registry shard of
+/// `shard_dir`, writing merged shards to `output_dir` (see module docs).
+/// Shard indices are derived exactly as `build_stats_and_route_deltas` routed them.
+/// AsyncTask: the whole registry merges on a libuv worker thread.
+#[napi]
+#[cfg_attr(test, allow(dead_code))] // tests drive MergeTask::compute directly
+pub fn merge_deltas_into_shards(
+    shard_dir: String,
+    delta_dir: String,
+    output_dir: String,
+    options: Option<MergeDeltasOptions>,
+) -> AsyncTask<MergeTask> {
+    AsyncTask::new(MergeTask { shard_dir, delta_dir, output_dir, options: options.unwrap_or_default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn obj(v: Value) -> Map<String, Value> {
+        v.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn merge_rules() {
+        let existing = obj(json!({
+            "id": "m1", "name": "old", "likes": 10, "downloads": 500, "summary": "keep",
+            "tags": "nlp, llm",
+            "relations": [{"relation_type": "based_on", "target_id": "x", "confidence": 0.5}],
+        }));
+        let incoming = obj(json!({
+            "id": "ignored", "name": "new", "likes": 7, "downloads": 900, "summary": null,
+            "tags": ["llm", "chat"],
+            "relations": [
+                {"relation_type": "based_on", "target_id": "x", "confidence": 0.9},
+                {"relation_type": "trained_on", "target_id": "d"},
+            ],
+        }));
+        let m = merge_entity(&existing, &incoming);
+        assert_eq!(m["id"], "m1");
+        assert_eq!(m["name"], "new");
+        assert_eq!(m["summary"], "keep");
+        assert_eq!(m["likes"], 10);
+        assert_eq!(m["downloads"], 900);
+        assert_eq!(m["tags"], json!(["nlp", "llm", "chat"]));
+        assert_eq!(m["relations"].as_array().unwrap().len(), 2);
+        assert_eq!(m["relations"][0]["confidence"], 0.9);
+        // Idempotent: re-applying the same delta changes nothing.
+        assert_eq!(merge_entity(&m, &incoming), m);
+    }
+
+    #[test]
+    fn iso_now_shape() {
+        let s = iso_now();
+        assert_eq!(s.len(), 24, "{s}");
+        assert!(s.starts_with("20") && s.ends_with('Z') && &s[10..11] == "T");
+    }
+
+    fn run(shard_name: &str, format: Option<&str>) -> (MergeDeltasResult, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("merge-deltas-{}-{}", std::process::id(), shard_name));
+        let (shards, deltas, out) = (dir.join("shards"), dir.join("deltas"), dir.join("out"));
+        std::fs::create_dir_all(&shards).unwrap();
+        std::fs::create_dir_all(&deltas).unwrap();
+        let entities = vec![
+            json!({"id": "a", "likes": 5, "tags": ["x"]}),
+            json!({"id": "b", "likes": 9, "tags": ["y"]}),
+            json!({"id": "c", "likes": 1}),
+        ];
+        let shard = shards.join(shard_name);
+        if shard_name.ends_with(".bin") {
+            let payloads: Vec<Vec<u8>> =
+                entities.iter().map(|e| zstd::encode_all(e.to_string().as_bytes(), 3).unwrap()).collect();
+            std::fs::write(&shard, nxvf_core::encode_nxvf_shard(shard_name, 7, &payloads).unwrap()).unwrap();
+        } else {
+            std::fs::write(&shard, json!({ "entities": entities }).to_string()).unwrap();
+        }
+        let lines = [
+            r#"{"id":"a","likes":8,"tags":["z"],"_updated":"2026-01-01T00:00:00.000Z"}"#,
+            r#"{"id":"b","likes":3}"#,
+            r#"{"id":"n","name":"fresh"}"#,
+            "not json",
+        ];
+        std::fs::write(deltas.join("reg-7.jsonl"), lines.join("\n")).unwrap();
+        let res = MergeTask {
+            shard_dir: shards.to_string_lossy().into_owned(),
+            delta_dir: deltas.to_string_lossy().into_owned(),
+            output_dir: out.to_string_lossy().into_owned(),
+            options: MergeDeltasOptions { output_format: format.map(str::to_string), ..Default::default() },
+        }
+        .compute()
+        .unwrap();
+        (res, out)
+    }
+
+    #[test]
+    fn merges_json_shard_with_counts() {
+        let (res, out) = run("part-007.json", None);
+        assert_eq!(res.failed_shard_count, 0);
+        let s = &res.shards[0];
+        assert_eq!((s.inserted, s.updated, s.unchanged), (1, 1, 2));
+        assert_eq!((s.delta_count, s.invalid_delta_lines), (3, 1));
+        let merged = nxvf_core::load_shard_entities(&out.join("part-007.json.zst").to_string_lossy()).unwrap();
+        let by_id: HashMap<&str, &Value> = merged.iter().map(|e| (e["id"].as_str().unwrap(), e)).collect();
+        assert_eq!(by_id["a"]["likes"], 8);
+        assert_eq!(by_id["a"]["tags"], json!(["x", "z"]));
+        assert_eq!(by_id["a"]["_updated"], "2026-01-01T00:00:00.000Z");
+        // Lower counter only: unchanged, no stamp.
+        assert_eq!(by_id["b"]["likes"], 9);
+        assert!(by_id["b"].get("_updated").is_none());
+        assert_eq!(by_id["n"]["name"], "fresh");
+        assert_eq!(merged.len(), 4);
+        assert!(!out.join("part-007.json.zst.tmp").exists());
+    }
+
+    #[test]
+    fn nxvf_round_trip() {
+        let (res, out) = run("part-007.bin", None);
+        assert_eq!(res.failed_shard_count, 0, "{:?}", res.shards[0].error);
+        assert_eq!((res.inserted, res.updated, res.unchanged), (1, 1, 2));
+        let merged = nxvf_core::load_shard_entities(&out.join("part-007.bin").to_string_lossy()).unwrap();
+        assert_eq!(merged.len(), 4);
+        assert_eq!(merged[0]["likes"], 8);
+    }
+}
@@ -1,4 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::{json, Map, Value};
+
+use crate::sketch::{self, KllSketch, RankSummary};
+
+/// `fni-thresholds.json` format version (see `build_thresholds`).
+pub const THRESHOLDS_VERSION: u32 = 1;
 
 /// Calculate global FNI percentile rankings.
 /// Port of aggregator-utils.js calculateGlobalStats (lines 28-53).
@@ -44,6 +51,230 @@ pub fn calculate_rankings(scores: &[(String, f64)]) -> HashMap<String, u8> {
     rankings
 }
 
+/// Rounded-score → percentile table, keyed like `fuse_shard` looks it up
+/// (`round(fni_score)` as a decimal string). Each integer bucket gets the
+/// same midrank percentile `calculate_rankings` gives a tie group, so
+/// late-bound `fni_percentile` agrees with the global rankings. A sketch
+/// only retains some scores, so with `fill_gaps` every integer between the
+/// rounded min and max gets a row (an empty bucket ranks like its neighbours),
+/// clamped to the FNI range 0..=100 so an outlier cannot blow up the table.
+fn score_percentiles(summary: &RankSummary, fill_gaps: bool) -> Map<String, Value> {
+    let mut buckets: BTreeMap<i64, u64> = BTreeMap::new();
+    for (v, w) in summary.weighted() {
+        *buckets.entry(v.round() as i64).or_insert(0) += w;
+    }
+    if fill_gaps && summary.count() > 0 {
+        let (lo, hi) = (summary.min.round().max(0.0) as i64, summary.max.round().min(100.0) as i64);
+        for b in lo..=hi {
+            buckets.entry(b).or_insert(0);
+        }
+    }
+    let mut above = 0u64;
+    buckets
+        .into_iter()
+        .rev()
+        .map(|(bucket, at)| {
+            let pct = summary.midrank_percentile(above, at);
+            above += at;
+            (bucket.to_string(), json!(pct))
+        })
+        .collect()
+}
+
+/// Nearest-rank quantile cut points: at least NN% of entities score at or
+/// below `pNN`.
+fn cut_points(summary: &RankSummary) -> Value {
+    if summary.count() == 0 {
+        return Value::Null;
+    }
+    json!({ "p50": summary.quantile(0.50), "p90": summary.quantile(0.90), "p99": summary.quantile(0.99) })
+}
+
+fn table(summary: &RankSummary, approx: bool) -> Map<String, Value> {
+    let mut t = Map::new();
+    t.insert("_count".into(), json!(summary.count()));
+    t.insert("scorePercentiles".into(), Value::Object(score_percentiles(summary, approx)));
+    t.insert("cutPoints".into(), cut_points(summary));
+    t
+}
+
+/// V26.6: Groups an entity is also ranked within besides the whole corpus,
+/// so a mid-tier paper is compared with papers, not with every model.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stratum {
+    Type,
+    Category,
+    Source,
+}
+
+impl Stratum {
+    pub const ALL: [Stratum; 3] = [Stratum::Type, Stratum::Category, Stratum::Source];
+
+    /// Table name in `fni-thresholds.json`.
+    pub fn table(self) -> &'static str {
+        match self {
+            Stratum::Type => "types",
+            Stratum::Category => "categories",
+            Stratum::Source => "sources",
+        }
+    }
+
+    /// Projected percentile field.
+    pub fn field(self) -> &'static str {
+        match self {
+            Stratum::Type => "fni_percentile_type",
+            Stratum::Category => "fni_percentile_category",
+            Stratum::Source => "fni_percentile_source",
+        }
+    }
+}
+
+/// Group keys of one entity, indexed like `Stratum::ALL` ("" = no group).
+pub fn stratum_keys(s: &nxvf_core::ScoredEntity) -> [&str; 3] {
+    [&s.entity_type, &s.primary_category, &s.source]
+}
+
+/// Per-stratum group → T (score list, sketch or summary), indexed like `Stratum::ALL`.
+pub type Strata<T> = [HashMap<String, T>; 3];
+
+/// Add one score to each of the entity's groups (`make` creates a new group).
+pub fn push_strata<T>(strata: &mut Strata<T>, keys: [&str; 3], make: impl Fn() -> T, mut add: impl FnMut(&mut T)) {
+    for (groups, key) in strata.iter_mut().zip(keys) {
+        if key.is_empty() {
+            continue;
+        }
+        if !groups.contains_key(key) {
+            groups.insert(key.to_string(), make());
+        }
+        add(groups.get_mut(key).expect("group inserted above"));
+    }
+}
+
+fn thresholds_doc(global: &RankSummary, strata: Strata<RankSummary>, sketch_k: Option<usize>) -> Value {
+    let approx = sketch_k.is_some();
+    let mut doc = table(global, approx);
+    doc.insert("version".into(), json!(THRESHOLDS_VERSION));
+    doc.insert("_ts".into(), json!(crate::merge::iso_now()));
+    doc.insert("mode".into(), json!(if approx { "approx" } else { "exact" }));
+    if let Some(k) = sketch_k {
+        doc.insert("rankError".into(), json!(sketch::rank_error(k)));
+    }
+    for (stratum, groups) in Stratum::ALL.into_iter().zip(strata) {
+        let tables: Map<String, Value> = groups
+            .into_iter()
+            .filter(|(g, _)| !g.is_empty())
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(g, s)| (g, Value::Object(table(&s, approx))))
+            .collect();
+        doc.insert(stratum.table().into(), Value::Object(tables));
+    }
+    Value::Object(doc)
+}
+
+fn map_strata<T, U>(strata: Strata<T>, f: impl Fn(T) -> U) -> Strata<U> {
+    strata.map(|groups| groups.into_iter().map(|(g, t)| (g, f(t))).collect())
+}
+
+/// Build the versioned `fni-thresholds.json` document:
+///
+/// ```json
+/// { "version": 1, "_ts": "<ISO-8601>", "_count": N, "mode": "exact",
+///   "scorePercentiles": { "87": 99, ... },
+///   "cutPoints": { "p50": s, "p90": s, "p99": s },
+///   "types": { "model": { "_count": n, "scorePercentiles": {..}, "cutPoints": {..} } },
+///   "categories": { .. }, "sources": { .. } }
+/// ```
+///
+/// The top-level `_count` / `scorePercentiles` keep the shape the JS
+/// aggregator-utils.js writes, so existing readers keep working. Entities
+/// without a type / category / source count globally but in no group table.
+pub fn build_thresholds(scores: &[(String, f64)], strata: Strata<Vec<f64>>) -> Value {
+    let global = RankSummary::exact(scores.iter().map(|(_, s)| *s));
+    thresholds_doc(&global, map_strata(strata, RankSummary::exact), None)
+}
+
+/// `build_thresholds` from sketches (approx mode): same document plus
+/// `"mode": "approx"` and the sketch's normalised `rankError`.
+pub fn build_thresholds_approx(global: &KllSketch, strata: Strata<KllSketch>) -> Value {
+    thresholds_doc(&global.summary(), map_strata(strata, |s| s.summary()), Some(global.k()))
+}
+
+/// Approx-mode rankings: each entity's percentile read off a sketch that saw
+/// every score in the extraction pass (see `sketch` for the error bound).
+/// No sort of the full score list and no per-score HashMaps.
+pub fn rank_with_sketch(scores: &[(String, f64)], sketch: &KllSketch) -> HashMap<String, u8> {
+    let summary = sketch.summary();
+    scores.iter().map(|(id, s)| (id.clone(), summary.percentile(*s))).collect()
+}
+
+/// Exact per-group rankings: an entity's percentile within its type /
+/// category / source, same tie-aware midrank formula as `calculate_rankings`.
+pub struct StrataRanker(Strata<RankSummary>);
+
+impl StrataRanker {
+    pub fn exact(strata: Strata<Vec<f64>>) -> Self {
+        StrataRanker(map_strata(strata, RankSummary::exact))
+    }
+
+    /// Percentile per stratum; None when the entity has no group there.
+    pub fn percentiles(&self, keys: [&str; 3], score: f64) -> [Option<u8>; 3] {
+        let mut out = [None; 3];
+        for ((slot, groups), key) in out.iter_mut().zip(&self.0).zip(keys) {
+            *slot = groups.get(key).map(|s| s.percentile(score));
+        }
+        out
+    }
+}
+
+/// Default sketch size for approx mode (≈ 0.1% rank error, see `sketch`).
+pub const DEFAULT_SKETCH_K: u32 = 2048;
+
+/// Per-score side state collected during the StatsTask extraction pass.
+/// Exact keeps every score per group for `build_thresholds`; approx feeds
+/// one global and one per-group sketch, so Phase 2 needs no sort.
+pub enum ScoreCollector {
+    Exact { strata: Strata<Vec<f64>> },
+    Approx { global: KllSketch, strata: Strata<KllSketch> },
+}
+
+impl ScoreCollector {
+    pub fn new(sketch_k: Option<u32>) -> Self {
+        match sketch_k {
+            None => ScoreCollector::Exact { strata: Default::default() },
+            Some(k) => ScoreCollector::Approx { global: KllSketch::new(k as usize), strata: Default::default() },
+        }
+    }
+
+    pub fn mode(&self) -> &'static str {
+        match self {
+            ScoreCollector::Exact { .. } => "exact",
+            ScoreCollector::Approx { .. } => "approx",
+        }
+    }
+
+    pub fn push(&mut self, keys: [&str; 3], score: f64) {
+        match self {
+            ScoreCollector::Exact { strata } => push_strata(strata, keys, Vec::new, |v| v.push(score)),
+            ScoreCollector::Approx { global, strata } => {
+                global.update(score);
+                let k = global.k();
+                push_strata(strata, keys, || KllSketch::new(k), |s| s.update(score));
+            }
+        }
+    }
+
+    /// (rankings, fni-thresholds document) for the collected scores.
+    pub fn finish(self, scores: &[(String, f64)]) -> (HashMap<String, u8>, Value) {
+        match self {
+            ScoreCollector::Exact { strata } => (calculate_rankings(scores), build_thresholds(scores, strata)),
+            ScoreCollector::Approx { global, strata } => {
+                (rank_with_sketch(scores, &global), build_thresholds_approx(&global, strata))
+            }
+        }
+    }
+}
+
 /// Wrapper for f64 that implements Hash + Eq (for HashMap keys).
 /// Uses bit-level comparison (NaN == NaN, -0.0 == 0.0 for our purposes).
 #[derive(Clone, Copy)]
@@ -120,6 +351,95 @@ mod tests {
         assert!(rankings.is_empty());
     }
 
+    #[test]
+    fn test_thresholds_match_rankings() {
+        let scores: Vec<(String, f64)> =
+            [90.2, 89.8, 50.0, 10.0, 10.4, 0.0].iter().enumerate().map(|(i, s)| (format!("e{i}"), *s)).collect();
+        let mut by_type: HashMap<String, Vec<f64>> = HashMap::new();
+        by_type.insert("model".into(), vec![90.2, 50.0, 10.0]);
+        by_type.insert("".into(), vec![0.0]);
+        let t = build_thresholds(&scores, [by_type, HashMap::new(), HashMap::new()]);
+        assert_eq!(t["version"], THRESHOLDS_VERSION);
+        assert_eq!(t["_count"], 6);
+        assert!(t["_ts"].as_str().is_some_and(|ts| ts.len() == 24 && ts.ends_with('Z')), "{}", t["_ts"]);
+        // 90.2 and 89.8 both round to 90: one bucket at midrank 0.5 -> 92.
+        let pcts = &t["scorePercentiles"];
+        assert_eq!(pcts["90"], 92);
+        assert_eq!(pcts["50"], 67);
+        assert_eq!(pcts["10"], 42);
+        assert_eq!(pcts["0"], 17);
+        // Integer scores (no rounding merges) agree with calculate_rankings.
+        let rankings = calculate_rankings(&scores);
+        assert_eq!(rankings["e2"], 67);
+        assert_eq!(rankings["e5"], 17);
+        assert_eq!(t["cutPoints"]["p50"], 10.4);
+        assert_eq!(t["cutPoints"]["p90"], 90.2);
+        assert_eq!(t["cutPoints"]["p99"], 90.2);
+        let model = &t["types"]["model"];
+        assert_eq!(model["_count"], 3);
+        assert_eq!(model["scorePercentiles"]["90"], 100);
+        assert_eq!(model["scorePercentiles"]["10"], 33);
+        assert_eq!(model["cutPoints"]["p50"], 50.0);
+        assert!(t["types"].get("").is_none());
+    }
+
+    #[test]
+    fn test_thresholds_empty() {
+        let t = build_thresholds(&[], Default::default());
+        assert_eq!(t["_count"], 0);
+        assert!(t["scorePercentiles"].as_object().unwrap().is_empty());
+        assert!(t["cutPoints"].is_null());
+    }
+
+    #[test]
+    fn test_exact_summary_matches_rankings() {
+        let scores: Vec<(String, f64)> =
+            crate::sketch::corpus(20_000).into_iter().enumerate().map(|(i, s)| (i.to_string(), s)).collect();
+        let exact = calculate_rankings(&scores);
+        let summary = RankSummary::exact(scores.iter().map(|(_, s)| *s));
+        for (id, s) in &scores {
+            assert_eq!(exact[id], summary.percentile(*s), "score {s}");
+        }
+    }
+
+    #[test]
+    fn test_fill_gaps_clamped_to_fni_range() {
+        let mut sketch = KllSketch::new(64);
+        for s in [-1e15, 0.0, 50.0, 100.0, 1e15] {
+            sketch.update(s);
+        }
+        let doc = build_thresholds_approx(&sketch, Default::default());
+        let pcts = doc["scorePercentiles"].as_object().unwrap();
+        assert!(pcts.len() <= 103, "{} buckets", pcts.len());
+        assert!(pcts.contains_key("0") && pcts.contains_key("100") && pcts.contains_key("37"));
+    }
+
+    #[test]
+    fn test_strata_rank_within_group() {
+        // Models dominate globally; the best paper is still top of its type.
+        let entities = [
+            ("m1", 90.0, "model", "nlp", "huggingface"),
+            ("m2", 80.0, "model", "vision", "huggingface"),
+            ("m3", 70.0, "model", "nlp", "huggingface"),
+            ("p1", 30.0, "paper", "nlp", "arxiv"),
+            ("p2", 20.0, "paper", "", "arxiv"),
+            ("p3", 20.0, "paper", "", "arxiv"),
+        ];
+        let mut strata: Strata<Vec<f64>> = Default::default();
+        for (_, s, t, c, src) in entities {
+            push_strata(&mut strata, [t, c, src], Vec::new, |v| v.push(s));
+        }
+        let scores: Vec<(String, f64)> = entities.iter().map(|e| (e.0.to_string(), e.1)).collect();
+        let global = calculate_rankings(&scores);
+        let ranker = StrataRanker::exact(strata);
+
+        assert_eq!(global["p1"], 50);
+        assert_eq!(ranker.percentiles(["paper", "nlp", "arxiv"], 30.0), [Some(100), Some(33), Some(100)]);
+        // Tied p2/p3 share the midrank within their type: 1 + 0.5 of 3 → 50.
+        assert_eq!(ranker.percentiles(["paper", "", "arxiv"], 20.0), [Some(50), None, Some(50)]);
+        assert_eq!(ranker.percentiles(["model", "vision", "huggingface"], 80.0), [Some(67), Some(100), Some(67)]);
+    }
+
     #[test]
     fn test_single_entity() {
         let scores = vec![("only".to_string(), 42.0)];
@@ -4,12 +4,37 @@ use std::io::{BufWriter, Write};
 
 use napi::Error;
 use napi::Result;
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 
-/// Re-read shards, project entities to slim format, inject percentiles, write NDJSON.
-pub fn project_and_write(
+use crate::percentile::{Stratum, StrataRanker};
+use crate::projection::CompiledProjection;
+
+/// V26.6: Global percentile plus the within-type / category / source ones
+/// (indexed like `Stratum::ALL`; None = no group, projected as null).
+pub(crate) struct EntityPercentiles {
+    pub global: u8,
+    pub strata: [Option<u8>; 3],
+}
+
+impl EntityPercentiles {
+    /// Projection-spec context: `fni_percentile` plus the stratum fields.
+    pub(crate) fn context(&self) -> Map<String, Value> {
+        let mut ctx = Map::new();
+        ctx.insert("fni_percentile".into(), json!(self.global));
+        for (stratum, pct) in Stratum::ALL.into_iter().zip(self.strata) {
+            ctx.insert(stratum.field().into(), json!(pct));
+        }
+        ctx
+    }
+}
+
+/// Re-read shards, project entities with `spec` (slim), inject percentiles,
+/// write NDJSON.
+pub(crate) fn project_and_write(
     shard_files: &[String],
     rankings: &HashMap<String, u8>,
+    strata: &StrataRanker,
+    spec: &CompiledProjection,
     output_path: &str,
 ) -> Result<usize> {
     // Ensure parent directory exists
@@ -17,7 +42,9 @@ pub fn project_and_write(
         fs::create_dir_all(parent).ok();
     }
 
-    let out_file = fs::File::create(output_path)
+    // Written under <output>.tmp and renamed once complete.
+    let tmp = crate::checkpoint::tmp_path(std::path::Path::new(output_path));
+    let out_file = fs::File::create(&tmp)
         .map_err(|e| Error::from_reason(format!("Cannot create output: {}", e)))?;
     let mut writer = BufWriter::new(out_file);
     let mut total = 0usize;
@@ -31,13 +58,15 @@ pub fn project_and_write(
             }
         };
         for e in &entities {
-            let id = e.get("id").and_then(|v| v.as_str()).unwrap_or_default();
-            if id.is_empty() {
+            let Some(se) = nxvf_core::ScoredEntity::from_value(e) else {
                 continue;
-            }
+            };
 
-            let percentile = rankings.get(id).copied().unwrap_or(0);
-            let slim = project_entity(e, percentile);
+            let percentiles = EntityPercentiles {
+                global: rankings.get(&se.id).copied().unwrap_or(0),
+                strata: strata.percentiles(crate::percentile::stratum_keys(&se), se.score),
+            };
+            let slim = spec.apply(e, &percentiles.context());
 
             serde_json::to_writer(&mut writer, &slim)
                 .map_err(|e| Error::from_reason(format!("Write error: {}", e)))?;
@@ -52,290 +81,71 @@ pub fn project_and_write(
     writer
         .flush()
         .map_err(|e| Error::from_reason(format!("Flush error: {}", e)))?;
+    drop(writer);
+    fs::rename(&tmp, output_path)
+        .map_err(|e| Error::from_reason(format!("Rename {}: {}", output_path, e)))?;
     Ok(total)
 }
 
-/// Port of registry-loader.js projectEntity (slim mode)
-pub(crate) fn project_entity(e: &Value, fni_percentile: u8) -> Value {
-    let description = get_description(e);
-
-    json!({
-        "id": str_field(e, "id"),
-        "umid": str_field(e, "umid"),
-        "slug": e.get("slug").and_then(|v| v.as_str()).unwrap_or(""),
-        "name": e.get("name")
-            .or(e.get("title"))
-            .or(e.get("displayName"))
-            .and_then(|v| v.as_str()).unwrap_or(""),
-        "type": e.get("type")
-            .or(e.get("entity_type"))
-            .and_then(|v| v.as_str()).unwrap_or("model"),
-        "author": e.get("author")
-            .or(e.get("creator"))
-            .or(e.get("organization"))
-            .and_then(|v| v.as_str()).unwrap_or(""),
-        "description": description,
-        "tags": e.get("tags").cloned().unwrap_or(json!([])),
-        "metrics": e.get("metrics").cloned().unwrap_or(json!({})),
-        // PR-3 (R3): honest-contract. Was num_field(..)=0.0, which forced a measured-zero
-        // onto HF entities that have no stars concept (should be null). num_field_or_null
-        // preserves null when no real stars/github_stars value is present; the distiller
-        // (v25-distiller.js) then recovers the true gh count from meta_json.stars.
-        "stars": num_field_or_null(e, &["stars", "github_stars"]),
-        "downloads": num_field(e, &["downloads"]),
-        "likes": num_field(e, &["likes"]),
-        "citations": num_field(e, &["citations"]),
-        "fni_score": e.get("fni_score").and_then(|v| v.as_f64())
-            .or_else(|| e.get("fni").and_then(|v| v.as_f64()))
-            .unwrap_or(0.0),
-        "fni_percentile": fni_percentile,
-        "fni_s": nested_f64(e, &["fni_s"], &["fni_metrics", "s"]),
-        "fni_a": nested_f64(e, &["fni_a"], &["fni_metrics", "a"]),
-        "fni_p": nested_f64(e, &["fni_p"], &["fni_metrics", "p"]),
-        "fni_r": nested_f64(e, &["fni_r"], &["fni_metrics", "r"]),
-        "fni_q": nested_f64(e, &["fni_q"], &["fni_metrics", "q"]),
-        "primary_category": str_field(e, "primary_category"),
-        "pipeline_tag": str_field(e, "pipeline_tag"),
-        "last_modified": e.get("last_modified")
-            .or(e.get("last_updated"))
-            .or(e.get("lastModified"))
-            .or(e.get("_updated"))
-            .and_then(|v| v.as_str()).unwrap_or(""),
-        "license": e.get("license")
-            .or(e.get("license_spdx"))
-            .and_then(|v| v.as_str()).unwrap_or(""),
-        "source": str_field(e, "source"),
-    })
+/// Port of registry-loader.js projectEntity (slim mode) — `specs/slim.json`.
+/// One-off form; `project_and_write` applies a chain resolved once per run.
+#[cfg(test)]
+pub(crate) fn project_entity(e: &Value, percentiles: &EntityPercentiles) -> Value {
+    CompiledProjection::resolve("slim").unwrap().apply(e, &percentiles.context())
 }
 
-/// V27.94: DEDICATED relation-aware projection.
+/// V27.94: DEDICATED relation-aware projection — `specs/relations.json`.
 ///
-/// Root-cause fix for the P0 mesh/relation data void: the slim `project_entity`
+/// Root-cause fix for the P0 mesh/relation data void: the slim projection
 /// strips every relation-source field, so `extractEntityRelations`
-/// (scripts/factory/lib/relation-extractors.js) only ever emitted STACK edges
-/// (61.8% zero-rel in prod; zero BASED_ON/TRAINED_ON/CITES/USES/IMPLEMENTS).
-///
-/// This is intentionally SEPARATE from both slim and fusion:
-///  - NOT the slim projector: adding a flag there would pollute the
-///    rankings/FNI P1 streaming path with Option<T> bloat.
-///  - NOT `project_entity_for_fusion`: it is missing ~8 of the relation field
-///    clusters AND carries cold-tier bloat (body_content/html_readme/readme/
-///    search_vector) that would blow V8 heap in the extraction stream.
-///
-/// Field set is the EXACT set read by `extractEntityRelations` (plus
-/// fni_score, which generateRelations uses for node force weighting):
-///   identity/STACK inputs: id, slug, type, name, tags, description
-///   relation clusters: base_model, datasets, datasets_used, arxiv_refs,
-///   paper_refs, references, models_used, models, model_id, sdk,
-///   implementations, dependencies, features, highlights, velocity,
-///   knowledge_tags
-/// (the diagnosis list's `relations` is NOT read by the extractor — dropped.)
-pub(crate) fn project_entity_for_relations(e: &Value) -> Value {
-    let mut out = json!({
-        "id": str_field(e, "id"),
-        "slug": str_field(e, "slug"),
-        "type": e.get("type")
-            .or(e.get("entity_type"))
-            .and_then(|v| v.as_str()).unwrap_or("model"),
-        "name": e.get("name")
-            .or(e.get("title"))
-            .or(e.get("displayName"))
-            .and_then(|v| v.as_str()).unwrap_or(""),
-        "description": str_field(e, "description"),
-        // generateRelations weights graph nodes by fni_score (single float, not
-        // the S/A/P/R/Q metric cluster) — preserve node force without slim bloat.
-        "fni_score": e.get("fni_score").and_then(|v| v.as_f64())
-            .or_else(|| e.get("fni").and_then(|v| v.as_f64())).unwrap_or(0.0),
-    });
-    const REL_FIELDS: &[&str] = &[
-        "tags", "base_model", "datasets", "datasets_used",
-        "arxiv_refs", "paper_refs", "references",
-        "models_used", "models", "model_id", "sdk",
-        "implementations", "dependencies", "features", "highlights",
-        "velocity", "knowledge_tags",
-        // benchmark 5th-type: EVALUATED_ON (model->benchmark) is derived from the
-        // per-model score object on `entity.benchmarks`. Without it in this
-        // allowlist the relation extractor never sees the field and the edge emits
-        // 0 (the documented V27.44 silent-strip class).
-        "benchmarks",
-    ];
-    for key in REL_FIELDS {
-        if let Some(v) = e.get(*key) {
-            out[*key] = v.clone();
-        }
-    }
-    out
-}
-
-fn str_field<'a>(e: &'a Value, key: &str) -> &'a str {
-    e.get(key).and_then(|v| v.as_str()).unwrap_or("")
-}
-
-fn num_field(e: &Value, keys: &[&str]) -> f64 {
-    for k in keys {
-        if let Some(v) = e.get(*k).and_then(|v| v.as_f64()) {
-            return v;
-        }
-    }
-    0.0
-}
-
-/// PR-3 (R3): like num_field but returns JSON null (not 0.0) when no key carries a
-/// numeric value. honest-contract: null = not-measured, 0 = measured-zero (per llms.txt).
-fn num_field_or_null(e: &Value, keys: &[&str]) -> Value {
-    for k in keys {
-        if let Some(v) = e.get(*k).and_then(|v| v.as_f64()) {
-            return json!(v);
-        }
-    }
-    Value::Null
+/// (scripts/factory/lib/relation-extractors.js) only ever emitted STACK edges.
+/// Kept separate from slim (P1 streaming path) and fusion (cold-tier bloat).
+pub(crate) fn project_entity_for_relations(e: &Value) -> Result<Value> {
+    crate::projection::project("relations", e, &Map::new()).map_err(Error::from_reason)
 }
 
-fn nested_f64(e: &Value, direct: &[&str], nested_path: &[&str; 2]) -> f64 {
-    for k in direct {
-        if let Some(v) = e.get(*k).and_then(|v| v.as_f64()) {
-            return v;
-        }
-    }
-    e.get(nested_path[0])
-        .and_then(|v| v.get(nested_path[1]))
-        .and_then(|v| v.as_f64())
-        .unwrap_or(0.0)
-}
-
-/// Full-mode projection for fusion — pass through every field pack-db /
-/// distiller / row-builders / bundle-builder need downstream.
-///
-/// V27.61: prior to this PR the whitelist silently dropped ~30 SQL schema
-/// columns, including all 4 confirmed (citation/benchmarks/datasets_used/
-/// quick_start) plus ~26 others (specs.*, stats.*, links.*, etc).
-/// V27.44-V27.49 JS-side data-quality PRs all delivered net zero impact
-/// because the field never survived this projection step. See brain memo
-/// 2026-05-26 后段三连 audit for evidence.
+/// Full-mode projection for fusion — slim plus every field pack-db /
+/// distiller / row-builders / bundle-builder need downstream
+/// (`specs/fusion.json`).
 ///
-/// Slim `project_entity` above is unchanged — `project_and_write` callers
-/// (rankings NDJSON) intentionally need the slim form for FNI compute.
-pub(crate) fn project_entity_for_fusion(e: &Value, fni_percentile: u8) -> Value {
-    let mut base = project_entity(e, fni_percentile);
-    const PASSTHROUGH: &[&str] = &[
-        // Cold-tier essentials (existing 3, now in the list for consistency)
-        "body_content", "has_fulltext", "relations",
-        // Structured metadata used by distiller / row-builders
-        "category", "summary", "params_billions", "architecture", "context_length",
-        "is_trending", "trend_7d", "_trend_7d",
-        "source_url", "image_url", "raw_image_url", "canonical_url",
-        "vram_estimate_gb", "vram_fp16_gb", "vram_int8_gb", "vram_int4_gb",
-        "task_categories", "num_rows", "primary_language", "forks", "citation_count",
-        "runtime_hardware", "vocab_size", "num_layers", "hidden_size",
-        "datasets_used", "quick_start", "citation",
-        "has_ollama", "has_gguf", "ollama_compatible", "can_run_local",
-        "hosted_on", "hosted_on_checked_at",
-        "ui_related_mesh", "search_vector",
-        // Fields the distiller re-reads as fallback inputs
-        "meta_json", "fni_metrics", "license_spdx", "source_platform",
-        // Bundle JSON fields (row-builders.js:28-50 builds .bin shards from these)
-        "benchmarks", "paper_abstract", "mesh_profile", "changelog",
-        "quick_insights", "use_cases", "quantization", "html_readme",
-        "created_at", "display_description", "readme",
-        // Adapter raw fields used as fallback inputs
-        "base_model", "gguf_variants",
-        // raw_pop: idx_fni secondary sort key (CREATE INDEX idx_fni ON entities(
-        // fni_score DESC, raw_pop DESC, slug ASC) in pack-schemas.js). Set pre-fusion
-        // at processor-core.js:106; was silently dropped here -> 0 in every meta-NN.db
-        // row -> popularity tie-break degraded to slug-alpha. Anti-strip (V27.61): a
-        // column declared in pack-schemas MUST survive this projection.
-        "raw_pop",
-        // PR-3 (R1): hot-column promotion sources. Some adapters set these top-level
-        // (hf-normalizer sdk/running_status, datasets primary fields); pass them through
-        // so the distiller's `??=` keeps an adapter-set value. The rest are recovered by
-        // the distiller from meta_json/meta_json.config (also passed through above).
-        // Repeating the documented anti-strip rule (V27.61): a column declared in
-        // pack-schemas MUST also survive this projection, never be silently dropped.
-        "num_heads", "kv_heads", "moe_experts", "moe_active",
-        "sdk", "running_status", "size_category", "files_count",
-        "modality", "published_year", "published_date",
-    ];
-    for key in PASSTHROUGH {
-        if let Some(v) = e.get(*key) {
-            base[*key] = v.clone();
-        }
-    }
-    base
+/// V27.61: a column declared in pack-schemas MUST survive this projection;
+/// the previous hand-kept whitelist silently dropped ~30 of them.
+/// One-off form; `FusionInputs` applies a chain resolved once per run.
+#[cfg(test)]
+pub(crate) fn project_entity_for_fusion(e: &Value, percentiles: &EntityPercentiles) -> Value {
+    CompiledProjection::resolve("fusion").unwrap().apply(e, &percentiles.context())
 }
 
-/// Build description with fallback from readme/content/html_readme/body_content
-fn get_description(e: &Value) -> String {
-    // Try direct description fields
-    let raw = e
-        .get("description")
-        .and_then(|v| v.as_str())
-        .or_else(|| e.get("summary").and_then(|v| v.as_str()))
-        .or_else(|| {
-            e.get("seo_summary")
-                .and_then(|v| v.get("description"))
-                .and_then(|v| v.as_str())
-        })
-        .unwrap_or("");
-
-    if raw.len() >= 5 {
-        return raw.to_string();
-    }
-
-    // Fallback: extract from readme/content
-    let source = e
-        .get("readme")
-        .and_then(|v| v.as_str())
-        .or_else(|| e.get("content").and_then(|v| v.as_str()))
-        .or_else(|| e.get("html_readme").and_then(|v| v.as_str()))
-        .or_else(|| e.get("body_content").and_then(|v| v.as_str()))
-        .unwrap_or("");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if source.is_empty() {
-        return String::new();
+    fn global_only(global: u8) -> EntityPercentiles {
+        EntityPercentiles { global, strata: [None; 3] }
     }
 
-    // Strip HTML tags and markdown, take first 250 chars
-    let stripped: String = source
-        .chars()
-        .take(300)
-        .collect::<String>()
-        .replace(|c: char| c == '#' || c == '*' || c == '`', "");
-    // Simple HTML tag removal
-    let mut result = String::with_capacity(250);
-    let mut in_tag = false;
-    for ch in stripped.chars() {
-        if ch == '<' {
-            in_tag = true;
-            continue;
-        }
-        if ch == '>' {
-            in_tag = false;
-            result.push(' ');
-            continue;
-        }
-        if !in_tag {
-            result.push(ch);
-        }
+    #[test]
+    fn stratified_percentiles_projected_null_when_ungrouped() {
+        let e = json!({ "id": "arxiv-paper--x", "type": "paper", "fni_score": 40.0 });
+        let out = project_entity(&e, &EntityPercentiles { global: 12, strata: [Some(95), None, Some(70)] });
+        assert_eq!(out["fni_percentile"], 12);
+        assert_eq!(out["fni_percentile_type"], 95);
+        assert!(out["fni_percentile_category"].is_null());
+        assert_eq!(out["fni_percentile_source"], 70);
+        let fused = project_entity_for_fusion(&e, &global_only(12));
+        assert!(fused["fni_percentile_type"].is_null());
     }
-    // Normalize whitespace and truncate
-    let normalized: String = result.split_whitespace().collect::<Vec<&str>>().join(" ");
-    normalized.chars().take(250).collect()
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     // PR-3 (R3): honest-contract stars. HF (no stars/github_stars key) -> JSON null,
     // never a fabricated measured-zero; gh (real value) -> that number.
     #[test]
     fn fusion_stars_null_for_hf_value_for_gh() {
         let hf = json!({ "id": "hf-model--x--y", "type": "model", "likes": 1200 });
-        let out = project_entity_for_fusion(&hf, 0);
+        let out = project_entity_for_fusion(&hf, &global_only(0));
         assert!(out["stars"].is_null(), "HF stars must be null, got {}", out["stars"]);
 
         let gh = json!({ "id": "gh-tool--x--y", "type": "tool", "stars": 6500 });
-        let out = project_entity_for_fusion(&gh, 0);
+        let out = project_entity_for_fusion(&gh, &global_only(0));
         // num_field_or_null normalizes via as_f64 (same as the existing num_field for
         // downloads/likes), so the JSON number is a float — assert on the f64 value.
         assert_eq!(out["stars"].as_f64(), Some(6500.0));
@@ -353,7 +163,7 @@ mod tests {
             "modality": "text", "published_year": 2023,
             "meta_json": { "config": { "hidden_size": 4096 } },
         });
-        let out = project_entity_for_fusion(&e, 0);
+        let out = project_entity_for_fusion(&e, &global_only(0));
         for k in ["num_heads", "kv_heads", "moe_experts", "moe_active", "sdk",
                   "running_status", "size_category", "files_count", "modality",
                   "published_year", "meta_json"] {
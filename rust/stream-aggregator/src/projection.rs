@@ -0,0 +1,569 @@
+//! V26.6 Declarative projection specs — one engine for slim / fusion /
+//! relations (and any spec registered at runtime).
+//!
+//! A spec is JSON (see `specs/*.json`):
+//!
+//! ```json
+//! { "version": 1, "name": "fusion", "base": "slim",
+//!   "fields": [
+//!     { "field": "name", "from": ["name", "title"], "alias": "first_present",
+//!       "type": "string", "missing": "zero" },
+//!     { "field": "fni_percentile", "context": "fni_percentile" } ],
+//!   "passthrough": [ { "note": "...", "fields": ["meta_json", "raw_pop"] } ] }
+//! ```
+//!
+//! Per field:
+//!  - `from`: alias chain of entity paths (`a.b` = nested); or `context`:
+//!    a per-call value supplied by the caller (percentiles);
+//!  - `alias`: `first_valid` (default) takes the first alias that coerces to
+//!    `type`; `first_present` stops at the first key present, even when it
+//!    then fails to coerce (the JS `a ?? b` / Rust `.or()` chains);
+//!  - `type`: `any` (default, clone as-is, `null` included) | `string` |
+//!    `number` (emitted as a float);
+//!  - `missing`: `omit` (default) | `null` | `zero` (`""` / `0.0`), or a
+//!    literal `default` — the honest-contract null-vs-zero choice;
+//!  - strings only: `min_len` (UTF-8 bytes) with a `fallback` source,
+//!    `transform: "strip_markup"`, `window_chars` (before the transform) and
+//!    `max_chars` (after).
+//!
+//! Fields apply in order: `base` spec, then `fields`, then `passthrough`
+//! (shorthand for `{from: [k]}` of type any); later writes win, omitted
+//! fields never erase. Unknown keys are rejected so a typo cannot silently
+//! drop a column. Built-in specs are embedded; `PROJECTION_SPEC_DIR` and
+//! `register_projection_spec` / `load_projection_specs` override or extend
+//! them without a new addon. `specs/golden.json` pins byte parity with the
+//! hand-written projectors these specs replaced.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+pub(crate) const SPEC_VERSION: u32 = 1;
+const BUILTIN_SPECS: &[&str] = &[
+    include_str!("../specs/slim.json"),
+    include_str!("../specs/fusion.json"),
+    include_str!("../specs/relations.json"),
+];
+/// Base chains longer than this are rejected (also catches cycles).
+const MAX_BASE_DEPTH: usize = 8;
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Kind {
+    #[default]
+    Any,
+    String,
+    Number,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Alias {
+    #[default]
+    FirstValid,
+    FirstPresent,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Missing {
+    #[default]
+    Omit,
+    Null,
+    Zero,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Transform {
+    StripMarkup,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSource {
+    from: Vec<String>,
+    #[serde(default)]
+    alias: Alias,
+    transform: Option<Transform>,
+    window_chars: Option<usize>,
+    max_chars: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawField {
+    field: String,
+    #[serde(default)]
+    from: Vec<String>,
+    context: Option<String>,
+    #[serde(default, rename = "type")]
+    kind: Kind,
+    #[serde(default)]
+    alias: Alias,
+    #[serde(default)]
+    missing: Option<Missing>,
+    default: Option<Value>,
+    min_len: Option<usize>,
+    fallback: Option<RawSource>,
+    transform: Option<Transform>,
+    window_chars: Option<usize>,
+    max_chars: Option<usize>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGroup {
+    fields: Vec<String>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSpec {
+    version: u32,
+    name: String,
+    base: Option<String>,
+    #[serde(default)]
+    fields: Vec<RawField>,
+    #[serde(default)]
+    passthrough: Vec<RawGroup>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    note: Option<String>,
+}
+
+struct Source {
+    paths: Vec<Vec<String>>,
+    alias: Alias,
+    transform: Option<Transform>,
+    window_chars: Option<usize>,
+    max_chars: Option<usize>,
+}
+
+enum Input {
+    Entity(Source),
+    Context(String),
+}
+
+struct Field {
+    name: String,
+    kind: Kind,
+    input: Input,
+    min_len: Option<usize>,
+    fallback: Option<Source>,
+    /// None = omit.
+    missing: Option<Value>,
+}
+
+pub(crate) struct ProjectionSpec {
+    name: String,
+    base: Option<String>,
+    fields: Vec<Field>,
+}
+
+fn compile_source(
+    field: &str,
+    kind: Kind,
+    from: Vec<String>,
+    alias: Alias,
+    (transform, window_chars, max_chars): (Option<Transform>, Option<usize>, Option<usize>),
+) -> Result<Source, String> {
+    if from.iter().any(|p| p.is_empty() || p.split('.').any(str::is_empty)) {
+        return Err(format!("field {:?}: empty path in `from`", field));
+    }
+    if kind != Kind::String && (transform.is_some() || window_chars.is_some() || max_chars.is_some()) {
+        return Err(format!("field {:?}: transform / window_chars / max_chars need type \"string\"", field));
+    }
+    Ok(Source {
+        paths: from.iter().map(|p| p.split('.').map(str::to_string).collect()).collect(),
+        alias,
+        transform,
+        window_chars,
+        max_chars,
+    })
+}
+
+fn compile_field(raw: RawField) -> Result<Field, String> {
+    let name = raw.field;
+    if name.is_empty() {
+        return Err("field with empty name".into());
+    }
+    let input = match (raw.from.is_empty(), raw.context) {
+        (false, None) => Input::Entity(compile_source(
+            &name,
+            raw.kind,
+            raw.from,
+            raw.alias,
+            (raw.transform, raw.window_chars, raw.max_chars),
+        )?),
+        (true, Some(key)) if raw.transform.is_none() && raw.max_chars.is_none() => Input::Context(key),
+        (true, Some(_)) => return Err(format!("field {:?}: context fields take no transform", name)),
+        _ => return Err(format!("field {:?}: exactly one of `from` / `context` is required", name)),
+    };
+    if raw.kind != Kind::String && (raw.min_len.is_some() || raw.fallback.is_some()) {
+        return Err(format!("field {:?}: min_len / fallback need type \"string\"", name));
+    }
+    let fallback = match raw.fallback {
+        Some(fb) => Some(compile_source(&name, raw.kind, fb.from, fb.alias, (fb.transform, fb.window_chars, fb.max_chars))?),
+        None => None,
+    };
+    let missing = match (raw.default, raw.missing) {
+        (Some(_), Some(_)) => return Err(format!("field {:?}: `default` and `missing` are exclusive", name)),
+        (Some(v), None) => Some(v),
+        (None, None | Some(Missing::Omit)) => None,
+        (None, Some(Missing::Null)) => Some(Value::Null),
+        (None, Some(Missing::Zero)) => Some(match raw.kind {
+            Kind::String => Value::from(""),
+            Kind::Number => Value::from(0.0),
+            Kind::Any => return Err(format!("field {:?}: `missing: zero` needs a typed field (or use `default`)", name)),
+        }),
+    };
+    Ok(Field { name, kind: raw.kind, input, min_len: raw.min_len, fallback, missing })
+}
+
+impl ProjectionSpec {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let raw: RawSpec = serde_json::from_str(json).map_err(|e| format!("Invalid projection spec: {}", e))?;
+        if raw.version != SPEC_VERSION {
+            return Err(format!("Projection spec {:?}: unsupported version {} (expected {})", raw.name, raw.version, SPEC_VERSION));
+        }
+        if raw.name.is_empty() {
+            return Err("Projection spec without a name".into());
+        }
+        let mut fields = raw
+            .fields
+            .into_iter()
+            .map(compile_field)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Projection spec {:?}: {}", raw.name, e))?;
+        for key in raw.passthrough.into_iter().flat_map(|g| g.fields) {
+            fields.push(Field {
+                kind: Kind::Any,
+                input: Input::Entity(compile_source(&key, Kind::Any, vec![key.clone()], Alias::FirstValid, (None, None, None))?),
+                name: key,
+                min_len: None,
+                fallback: None,
+                missing: None,
+            });
+        }
+        Ok(ProjectionSpec { name: raw.name, base: raw.base, fields })
+    }
+
+    fn apply_into(&self, e: &Value, ctx: &Map<String, Value>, out: &mut Map<String, Value>) {
+        for f in &self.fields {
+            let v = match &f.input {
+                Input::Context(key) => ctx.get(key).cloned(),
+                Input::Entity(src) => {
+                    let primary = resolve(e, src, f.kind);
+                    match (f.min_len, &f.fallback) {
+                        (Some(min), Some(fb)) if primary.as_ref().and_then(Value::as_str).is_none_or(|s| s.len() < min) => {
+                            resolve(e, fb, f.kind)
+                        }
+                        _ => primary,
+                    }
+                }
+            };
+            if let Some(v) = v.or_else(|| f.missing.clone()) {
+                out.insert(f.name.clone(), v);
+            }
+        }
+    }
+}
+
+fn lookup<'a>(e: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(e, |v, k| v.get(k))
+}
+
+fn coerce(v: &Value, kind: Kind) -> Option<Value> {
+    match kind {
+        Kind::Any => Some(v.clone()),
+        Kind::String => v.as_str().map(Value::from),
+        Kind::Number => v.as_f64().map(Value::from),
+    }
+}
+
+fn resolve(e: &Value, src: &Source, kind: Kind) -> Option<Value> {
+    let v = match src.alias {
+        Alias::FirstValid => src.paths.iter().find_map(|p| lookup(e, p).and_then(|v| coerce(v, kind))),
+        Alias::FirstPresent => src.paths.iter().find_map(|p| lookup(e, p)).and_then(|v| coerce(v, kind)),
+    }?;
+    if src.transform.is_none() && src.window_chars.is_none() && src.max_chars.is_none() {
+        return Some(v);
+    }
+    let mut s = v.as_str().unwrap_or_default().to_string();
+    if let Some(n) = src.window_chars {
+        s = s.chars().take(n).collect();
+    }
+    if let Some(Transform::StripMarkup) = src.transform {
+        s = strip_markup(&s);
+    }
+    if let Some(n) = src.max_chars {
+        s = s.chars().take(n).collect();
+    }
+    Some(Value::from(s))
+}
+
+/// Drop `#`, `*`, backticks and HTML tags (a tag becomes a space), then
+/// collapse whitespace.
+fn strip_markup(s: &str) -> String {
+    let stripped = s.replace(['#', '*', '`'], "");
+    let mut result = String::with_capacity(stripped.len());
+    let mut in_tag = false;
+    for ch in stripped.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                result.push(' ');
+            }
+            _ if !in_tag => result.push(ch),
+            _ => {}
+        }
+    }
+    result.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+type Registry = RwLock<HashMap<String, Arc<ProjectionSpec>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let reg = RwLock::new(HashMap::new());
+        for json in BUILTIN_SPECS {
+            let spec = ProjectionSpec::parse(json).expect("built-in projection spec");
+            insert(&reg, spec).expect("built-in projection spec base");
+        }
+        if let Ok(dir) = std::env::var("PROJECTION_SPEC_DIR") {
+            match load_dir_into(&reg, &dir) {
+                Ok(names) => eprintln!("[RUST-PROJECT] PROJECTION_SPEC_DIR: loaded {:?}", names),
+                Err(e) => eprintln!("[RUST-PROJECT] PROJECTION_SPEC_DIR ignored: {}", e),
+            }
+        }
+        reg
+    })
+}
+
+fn insert(reg: &Registry, spec: ProjectionSpec) -> Result<String, String> {
+    let mut map = reg.write().unwrap_or_else(|p| p.into_inner());
+    // The base chain must already exist and must not loop back to this spec.
+    let mut base = spec.base.clone();
+    for _ in 0..MAX_BASE_DEPTH {
+        let Some(b) = base else { break };
+        if b == spec.name {
+            return Err(format!("Projection spec {:?}: base chain loops", spec.name));
+        }
+        base = map
+            .get(&b)
+            .ok_or_else(|| format!("Projection spec {:?}: unknown base {:?}", spec.name, b))?
+            .base
+            .clone();
+    }
+    if base.is_some() {
+        return Err(format!("Projection spec {:?}: base chain deeper than {}", spec.name, MAX_BASE_DEPTH));
+    }
+    // Replacing a spec also lengthens the chain of every spec built on it.
+    let name = spec.name.clone();
+    let old = map.insert(name.clone(), Arc::new(spec));
+    if let Some(deep) = map.keys().find(|k| chain_too_deep(&map, k)).cloned() {
+        match old {
+            Some(old) => map.insert(name.clone(), old),
+            None => map.remove(&name),
+        };
+        return Err(format!(
+            "Projection spec {:?}: base chain of {:?} would be deeper than {}",
+            name, deep, MAX_BASE_DEPTH
+        ));
+    }
+    Ok(name)
+}
+
+/// Same bound `insert` enforces on a new spec, for one already registered.
+fn chain_too_deep(map: &HashMap<String, Arc<ProjectionSpec>>, name: &str) -> bool {
+    let mut base = map.get(name).and_then(|s| s.base.clone());
+    for _ in 0..MAX_BASE_DEPTH {
+        let Some(b) = base else { return false };
+        base = map.get(&b).and_then(|s| s.base.clone());
+    }
+    base.is_some()
+}
+
+/// Register every `*.json` in `dir`; specs whose base comes later in the
+/// directory are retried until nothing more resolves.
+fn load_dir_into(reg: &Registry, dir: &str) -> Result<Vec<String>, String> {
+    let mut pending: Vec<(String, ProjectionSpec)> = Vec::new();
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Read {}: {}", dir, e))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "json"))
+        .collect();
+    paths.sort();
+    for p in paths {
+        let json = std::fs::read_to_string(&p).map_err(|e| format!("Read {}: {}", p.display(), e))?;
+        pending.push((p.display().to_string(), ProjectionSpec::parse(&json).map_err(|e| format!("{}: {}", p.display(), e))?));
+    }
+    let mut loaded = Vec::new();
+    while !pending.is_empty() {
+        let before = pending.len();
+        let mut failed = Vec::new();
+        for (path, spec) in pending {
+            let base_known = spec.base.as_ref().is_none_or(|b| registry_has(reg, b));
+            if base_known {
+                loaded.push(insert(reg, spec).map_err(|e| format!("{}: {}", path, e))?);
+            } else {
+                failed.push((path, spec));
+            }
+        }
+        if failed.len() == before {
+            let (path, spec) = &failed[0];
+            return Err(format!("{}: unknown base {:?}", path, spec.base.as_deref().unwrap_or_default()));
+        }
+        pending = failed;
+    }
+    Ok(loaded)
+}
+
+fn registry_has(reg: &Registry, name: &str) -> bool {
+    reg.read().unwrap_or_else(|p| p.into_inner()).contains_key(name)
+}
+
+/// Register (or replace) a spec; returns its name.
+pub(crate) fn register(json: &str) -> Result<String, String> {
+    insert(registry(), ProjectionSpec::parse(json)?)
+}
+
+pub(crate) fn load_dir(dir: &str) -> Result<Vec<String>, String> {
+    load_dir_into(registry(), dir)
+}
+
+pub(crate) fn spec(name: &str) -> Result<Arc<ProjectionSpec>, String> {
+    registry()
+        .read()
+        .unwrap_or_else(|p| p.into_inner())
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("Unknown projection spec {:?}", name))
+}
+
+/// A spec with its base chain resolved (base first). Resolve once per shard
+/// or run and reuse: applying it takes no lock and allocates nothing but the
+/// output map. Later registrations do not affect an already resolved chain.
+pub(crate) struct CompiledProjection {
+    chain: Vec<Arc<ProjectionSpec>>,
+}
+
+impl CompiledProjection {
+    pub(crate) fn resolve(name: &str) -> Result<Self, String> {
+        let mut chain = vec![spec(name)?];
+        while let Some(base) = chain.last().and_then(|s| s.base.clone()) {
+            if chain.len() > MAX_BASE_DEPTH {
+                return Err(format!("Projection spec {:?}: base chain deeper than {}", name, MAX_BASE_DEPTH));
+            }
+            chain.push(spec(&base)?);
+        }
+        chain.reverse();
+        Ok(CompiledProjection { chain })
+    }
+
+    pub(crate) fn apply(&self, e: &Value, ctx: &Map<String, Value>) -> Value {
+        let mut out = Map::new();
+        for s in &self.chain {
+            s.apply_into(e, ctx, &mut out);
+        }
+        Value::Object(out)
+    }
+}
+
+/// Project `e` with spec `name` (base chain first, see module docs). One-off
+/// calls only; loops resolve a `CompiledProjection` up front.
+pub(crate) fn project(name: &str, e: &Value, ctx: &Map<String, Value>) -> Result<Value, String> {
+    Ok(CompiledProjection::resolve(name)?.apply(e, ctx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{project_entity, project_entity_for_fusion, project_entity_for_relations, EntityPercentiles};
+    use serde_json::json;
+
+    /// Byte parity with the hand-written projectors the built-in specs replaced.
+    #[test]
+    fn builtin_specs_match_golden_bytes() {
+        let golden: Value = serde_json::from_str(include_str!("../specs/golden.json")).unwrap();
+        for case in golden["cases"].as_array().unwrap() {
+            let strata: Vec<Option<u8>> = serde_json::from_value(case["context"]["strata"].clone()).unwrap();
+            let p = EntityPercentiles {
+                global: case["context"]["global"].as_u64().unwrap() as u8,
+                strata: [strata[0], strata[1], strata[2]],
+            };
+            let e = &case["entity"];
+            let name = case["name"].as_str().unwrap();
+            for (projector, got) in [
+                ("slim", project_entity(e, &p)),
+                ("fusion", project_entity_for_fusion(e, &p)),
+                ("relations", project_entity_for_relations(e).unwrap()),
+            ] {
+                assert_eq!(serde_json::to_string(&got).unwrap(), case[projector].as_str().unwrap(), "{name}/{projector}");
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        for (json, why) in [
+            (r#"{"version":1,"name":"x","fields":[{"field":"a","from":["a"],"typo":1}]}"#, "unknown field"),
+            (r#"{"version":2,"name":"x"}"#, "version"),
+            (r#"{"version":1,"name":"x","fields":[{"field":"a"}]}"#, "exactly one"),
+            (r#"{"version":1,"name":"x","fields":[{"field":"a","from":["a"],"missing":"zero"}]}"#, "typed"),
+            (r#"{"version":1,"name":"x","fields":[{"field":"a","from":["a"],"type":"number","max_chars":3}]}"#, "string"),
+        ] {
+            let err = ProjectionSpec::parse(json).err().unwrap_or_default();
+            assert!(err.contains(why), "{json}: {err}");
+        }
+        assert!(register(r#"{"version":1,"name":"orphan","base":"nope"}"#).unwrap_err().contains("unknown base"));
+    }
+
+    /// Re-basing a spec must not push a dependent's chain past the limit
+    /// (resolving it would then fail for every caller).
+    #[test]
+    fn rejects_replacement_that_deepens_dependents() {
+        let def = |name: &str, base: Option<&str>| match base {
+            Some(b) => format!(r#"{{"version":1,"name":"{name}","base":"{b}"}}"#),
+            None => format!(r#"{{"version":1,"name":"{name}"}}"#),
+        };
+        register(&def("deep-0", None)).unwrap();
+        for i in 1..=MAX_BASE_DEPTH {
+            register(&def(&format!("deep-{i}"), Some(&format!("deep-{}", i - 1)))).unwrap();
+        }
+        register(&def("deep-x", None)).unwrap();
+        let top = format!("deep-{MAX_BASE_DEPTH}");
+        let err = register(&def("deep-0", Some("deep-x"))).unwrap_err();
+        assert!(err.contains(&format!("base chain of {top:?}")), "{err}");
+        // Rejected outright: the old deep-0 (and every chain on it) still resolves.
+        assert!(spec("deep-0").unwrap().base.is_none());
+        assert!(CompiledProjection::resolve(&top).is_ok());
+    }
+
+    #[test]
+    fn runtime_spec_extends_builtin() {
+        let name = register(
+            r#"{"version":1,"name":"test-slim-plus","base":"slim",
+                "fields":[{"field":"title","from":["meta.title","name"],"type":"string","max_chars":4,"missing":"null"},
+                          {"field":"likes","from":["likes"],"type":"number","missing":"null"}]}"#,
+        )
+        .unwrap();
+        let out = project(&name, &json!({"id": "a", "name": "Longname", "likes": "n/a"}), &Map::new()).unwrap();
+        assert_eq!(out["title"], "Long");
+        // Later writes win: the slim zero becomes the honest null.
+        assert!(out["likes"].is_null());
+        assert_eq!(out["type"], "model");
+    }
+}
@@ -0,0 +1,270 @@
+//! V26.6 KLL quantile sketch for single-pass approximate FNI percentiles.
+//!
+//! Karnin–Lang–Liberty compactor stack (lazy variant, capacity decay 2/3):
+//! level h holds items of weight 2^h; a full level is sorted and every other
+//! item (random offset) is promoted. Retained items stay O(k) regardless of
+//! corpus size, so ranking needs no full sort and no per-score HashMaps.
+//!
+//! Error bound: every rank query is off by at most ε·n, where ε ≈ 2/k with
+//! high probability (the KLL bound; the 1M-entity bench measures well inside
+//! it). With the default k = 2048 that is ≈ 0.1% of n, i.e. a percentile
+//! within ±1 of exact mode after rounding. The compaction coin is a fixed-seed
+//! xorshift, so the same input order always produces the same rankings.
+//!
+//! Measured at 1M entities (`cargo bench --bench percentile_memory`, peak
+//! heap via a counting allocator): global + 4 per-type sketches retain ~28k scores and
+//! peak at ~1.5 MiB vs ~31 MiB for exact mode's score copies; wall time is
+//! about equal (~0.6 s) because building the id → percentile map dominates
+//! both modes.
+
+use std::cmp::Ordering;
+
+/// Normalised rank error bound for a sketch of parameter `k` (see module docs).
+pub fn rank_error(k: usize) -> f64 {
+    2.0 / k as f64
+}
+
+pub struct KllSketch {
+    k: usize,
+    levels: Vec<Vec<f64>>,
+    size: usize,
+    max_size: usize,
+    n: u64,
+    min: f64,
+    max: f64,
+    rng: u64,
+}
+
+impl KllSketch {
+    pub fn new(k: usize) -> Self {
+        let mut s = KllSketch {
+            k: k.max(8),
+            levels: Vec::new(),
+            size: 0,
+            max_size: 0,
+            n: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        };
+        s.grow();
+        s
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Items currently held (memory is `retained() * 8` bytes plus level Vecs).
+    #[cfg(test)]
+    pub fn retained(&self) -> usize {
+        self.size
+    }
+
+    /// NaN counts as 0.0, matching how missing scores are extracted.
+    pub fn update(&mut self, x: f64) {
+        let x = if x.is_nan() { 0.0 } else { x };
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        self.n += 1;
+        self.levels[0].push(x);
+        self.size += 1;
+        if self.size >= self.max_size {
+            self.compress();
+        }
+    }
+
+    fn capacity(&self, h: usize) -> usize {
+        let depth = (self.levels.len() - h - 1) as i32;
+        ((2.0f64 / 3.0).powi(depth) * self.k as f64).ceil() as usize + 1
+    }
+
+    fn grow(&mut self) {
+        self.levels.push(Vec::new());
+        self.max_size = (0..self.levels.len()).map(|h| self.capacity(h)).sum();
+    }
+
+    fn coin(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng & 1) as usize
+    }
+
+    fn compress(&mut self) {
+        for h in 0..self.levels.len() {
+            if self.levels[h].len() < self.capacity(h) {
+                continue;
+            }
+            if h + 1 == self.levels.len() {
+                self.grow();
+            }
+            let offset = self.coin();
+            let level = &mut self.levels[h];
+            level.sort_by(f64::total_cmp);
+            // Odd count: the largest item stays behind at this level.
+            let leftover = if level.len() % 2 == 1 { level.pop() } else { None };
+            let promoted: Vec<f64> = level.iter().skip(offset).step_by(2).copied().collect();
+            level.clear();
+            level.extend(leftover);
+            self.levels[h + 1].extend(promoted);
+            self.size = self.levels.iter().map(Vec::len).sum();
+            // Lazy: stop as soon as there is room again.
+            if self.size < self.max_size {
+                break;
+            }
+        }
+    }
+
+    /// Freeze into a sorted weighted summary for rank / quantile queries.
+    pub fn summary(&self) -> RankSummary {
+        let mut items: Vec<(f64, u64)> = self
+            .levels
+            .iter()
+            .enumerate()
+            .flat_map(|(h, level)| level.iter().map(move |&x| (x, 1u64 << h)))
+            .collect();
+        items.sort_by(|a, b| a.0.total_cmp(&b.0));
+        RankSummary::from_sorted(items, self.n, self.min, self.max)
+    }
+}
+
+/// Sorted (value, cumulative weight) table. Built exactly from every score
+/// (weight 1 each) or approximately from a `KllSketch`.
+pub struct RankSummary {
+    values: Vec<f64>,
+    cum: Vec<u64>,
+    n: u64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl RankSummary {
+    /// Exact summary: every score, weight 1. Equal scores collapse to one row.
+    pub fn exact(scores: impl IntoIterator<Item = f64>) -> Self {
+        let mut sorted: Vec<f64> = scores.into_iter().map(|x| if x.is_nan() { 0.0 } else { x }).collect();
+        sorted.sort_by(f64::total_cmp);
+        let (n, min, max) = (sorted.len() as u64, sorted.first().copied(), sorted.last().copied());
+        let items = sorted.into_iter().map(|x| (x, 1)).collect();
+        RankSummary::from_sorted(items, n, min.unwrap_or(0.0), max.unwrap_or(0.0))
+    }
+
+    fn from_sorted(items: Vec<(f64, u64)>, n: u64, min: f64, max: f64) -> Self {
+        let mut values: Vec<f64> = Vec::with_capacity(items.len());
+        let mut cum: Vec<u64> = Vec::with_capacity(items.len());
+        let mut total = 0u64;
+        for (x, w) in items {
+            total += w;
+            if values.last().is_some_and(|&v| v.total_cmp(&x) == Ordering::Equal) {
+                *cum.last_mut().unwrap() = total;
+            } else {
+                values.push(x);
+                cum.push(total);
+            }
+        }
+        RankSummary { values, cum, n, min, max }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.n
+    }
+
+    /// Distinct values held (every distinct score in exact mode).
+    #[cfg(test)]
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Weight of scores strictly below `x`.
+    pub fn rank_lt(&self, x: f64) -> u64 {
+        let i = self.values.partition_point(|&v| v < x);
+        if i == 0 { 0 } else { self.cum[i - 1] }
+    }
+
+    /// Weight of scores at or below `x`.
+    pub fn rank_le(&self, x: f64) -> u64 {
+        let i = self.values.partition_point(|&v| v <= x);
+        if i == 0 { 0 } else { self.cum[i - 1] }
+    }
+
+    /// Nearest-rank quantile: the smallest value with `rank_le >= ceil(q·n)`.
+    pub fn quantile(&self, q: f64) -> f64 {
+        let target = ((q * self.n as f64).ceil() as u64).clamp(1, self.n.max(1));
+        let i = self.cum.partition_point(|&c| c < target);
+        self.values.get(i).or(self.values.last()).copied().unwrap_or(0.0)
+    }
+
+    /// (value, weight) rows in ascending order.
+    pub fn weighted(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.values.iter().zip(&self.cum).scan(0u64, |prev, (&v, &c)| {
+            let w = c - *prev;
+            *prev = c;
+            Some((v, w))
+        })
+    }
+
+    /// Percentile of the exact score `x`.
+    pub fn percentile(&self, x: f64) -> u8 {
+        let le = self.rank_le(x);
+        self.midrank_percentile(self.n - le, le - self.rank_lt(x))
+    }
+
+    /// `calculate_rankings` formula for a tie group of weight `at` with
+    /// weight `above` scoring higher: `max(1, round((1 - (above + (at-1)/2) / n) * 100))`.
+    pub fn midrank_percentile(&self, above: u64, at: u64) -> u8 {
+        let effective_rank = above as f64 + (at.max(1) - 1) as f64 / 2.0;
+        ((1.0 - effective_rank / self.n.max(1) as f64) * 100.0).round().max(1.0) as u8
+    }
+}
+
+/// Deterministic skewed FNI-like scores: a large 0 tie group, a long tail
+/// of integer-ish scores, and a few high outliers. Test and bench input only.
+#[allow(dead_code)]
+pub(crate) fn corpus(n: usize) -> Vec<f64> {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    (0..n)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+            if u < 0.3 { 0.0 } else { (u * u * 100.0 * 10.0).round() / 10.0 }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_input_is_exact() {
+        let mut s = KllSketch::new(64);
+        for x in [3.0, 1.0, 2.0, 2.0] {
+            s.update(x);
+        }
+        let sum = s.summary();
+        assert_eq!(sum.count(), 4);
+        assert_eq!(sum.rank_lt(2.0), 1);
+        assert_eq!(sum.rank_le(2.0), 3);
+        assert_eq!(sum.quantile(0.5), 2.0);
+        assert_eq!(sum.percentile(3.0), 100);
+        assert_eq!(sum.percentile(2.0), 63);
+    }
+
+    #[test]
+    fn rank_error_within_bound() {
+        let scores = corpus(200_000);
+        let exact = RankSummary::exact(scores.iter().copied());
+        let mut sketch = KllSketch::new(256);
+        scores.iter().for_each(|&x| sketch.update(x));
+        let approx = sketch.summary();
+        assert_eq!(approx.count(), exact.count());
+        let bound = rank_error(256) * scores.len() as f64;
+        for &v in exact.values().iter().step_by(7) {
+            let err = (approx.rank_le(v) as f64 - exact.rank_le(v) as f64).abs();
+            assert!(err <= bound, "rank error {err} at {v} exceeds {bound}");
+        }
+        assert!(sketch.retained() < 3 * 256 + 64);
+    }
+}
@@ -76,7 +76,9 @@ async function main() {
     // Phase 1-4 in Rust AsyncTask (worker thread, event loop stays alive)
     const outputCache = './output/cache';
     const deltaDir = './cache/deltas';
-    const rustStats = await buildStatsAndRouteDeltasFFI(shardDir, CONFIG.ARTIFACT_DIR, deltaDir, outputCache);
+    // V26.6: FNI_PERCENTILE_MODE=approx ranks off a KLL sketch (±1 percentile) instead of a full sort.
+    const statsOptions = process.env.FNI_PERCENTILE_MODE ? { percentileMode: process.env.FNI_PERCENTILE_MODE } : undefined;
+    const rustStats = await buildStatsAndRouteDeltasFFI(shardDir, CONFIG.ARTIFACT_DIR, deltaDir, outputCache, statsOptions);
     let rankingsMap;
     if (rustStats && rustStats.entityCount > 0) {
         console.log(`[AGGREGATOR] Rust Phase 1-4: ${rustStats.entityCount} entities, ${rustStats.routedCount} routed → ${rustStats.deltaShardCount} shards (${rustStats.durationMs}ms)`);
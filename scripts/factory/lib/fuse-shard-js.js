@@ -34,6 +34,13 @@ export async function fuseShardJS(shardPath, allValidIds, fniThresholds, entityE
         }
         entity.fni_pScore = entity.fni_score ?? entity.fni ?? 0;
         entity.fni_percentile = fniThresholds.scorePercentiles?.[entity.fni_pScore] || 0;
+        // Per-group percentiles (Rust stratum_percentiles parity): null when the
+        // entity has no group in that table, 0 for an unknown bucket.
+        const bucket = Math.round(entity.fni_pScore);
+        const stratum = (table, key) => (key && fniThresholds[table]?.[key]) ? (fniThresholds[table][key].scorePercentiles?.[bucket] || 0) : null;
+        entity.fni_percentile_type = stratum('types', entity.type || entity.entity_type);
+        entity.fni_percentile_category = stratum('categories', entity.primary_category);
+        entity.fni_percentile_source = stratum('sources', entity.source);
         pending.push(entity);
     };
 
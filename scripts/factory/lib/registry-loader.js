@@ -202,6 +202,7 @@ export function projectEntity(e, slim) {
         citations: e.citations || 0,
         fni_score: e.fni_score ?? e.fni ?? 0,
         fni_percentile: e.fni_percentile || e.percentile || '',
+        fni_percentile_type: e.fni_percentile_type ?? null, fni_percentile_category: e.fni_percentile_category ?? null, fni_percentile_source: e.fni_percentile_source ?? null,
         // PR-C honest-contract: A/P/R/Q null when genuinely absent (not fabricated
         // measured-zero); S keeps the documented neutral 50.0 query-time-overridden default.
         fni_s: e.fni_s ?? e.fni_metrics?.s ?? 50.0,
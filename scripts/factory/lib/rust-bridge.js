@@ -115,7 +115,7 @@ function _jsFallbackClassify(text) {
 export function buildEnrichmentManifestFFI(keys) {
     const manifest = new Map();
     if (_contentExtractor) { for (const [u, k] of _contentExtractor.buildEnrichmentManifest(Buffer.from(keys.join('\n')))) manifest.set(u, k); }
-    else { const re = /enrichment\/fulltext\/[a-f0-9]{2}\/([a-f0-9]+)\.md\.gz$/; for (const k of keys) { const m = k.match(re); if (m) manifest.set(m[1], k); } }
+    else { const re = /^enrichment\/fulltext\/([a-f0-9]{2})\/([a-f0-9]{16})\.md\.(?:gz|zst)$/; for (const k of keys.map(k => k.trim())) { const m = k.match(re); if (m && m[2].startsWith(m[1]) && !manifest.has(m[2])) manifest.set(m[2], k); } } // Rust parity: 16-hex UMID, prefix check, first key wins
     return manifest;
 }
 
@@ -230,14 +230,16 @@ export function buildMeshGraphFFI(explicitJson, knowledgeLinksJson, reportsJson)
     return null;
 }
 
-/** V26.9: AsyncTask — returns Promise, worker thread execution. */
-export async function buildStatsAndRouteDeltasFFI(shardDir, artifactDir, deltaDir, outputDir) {
+/** V26.9: AsyncTask — returns Promise, worker thread execution. options: { percentileMode: 'exact'|'approx', sketchK, resume } (resume defaults to true: Phase 3 continues a killed run's delta checkpoint) */
+export async function buildStatsAndRouteDeltasFFI(shardDir, artifactDir, deltaDir, outputDir, options) {
     if (_streamAggregator?.buildStatsAndRouteDeltas) {
-        try { return await _streamAggregator.buildStatsAndRouteDeltas(shardDir, artifactDir, deltaDir, outputDir); }
+        try { return await _streamAggregator.buildStatsAndRouteDeltas(shardDir, artifactDir, deltaDir, outputDir, options); }
         catch (e) { console.error(`[RUST-BRIDGE] ❌ FFI FAILED (JS fallback): buildStatsAndRouteDeltas: ${e.message}`); }
     }
     return null;
 }
+/** V26.6: merge reg-<shard>.jsonl deltas into registry shards natively. options: { outputFormat: 'same'|'nxvf'|'json.zst', zstdLevel, insertMissing }. Null = caller falls back to JS mergePartitionedShard. */
+export async function mergeDeltasIntoShardsFFI(shardDir, deltaDir, outputDir, options) { if (!_streamAggregator?.mergeDeltasIntoShards) return null; try { return await _streamAggregator.mergeDeltasIntoShards(shardDir, deltaDir, outputDir, options); } catch (e) { console.error(`[RUST-BRIDGE] ❌ FFI FAILED (JS fallback): mergeDeltasIntoShards: ${e.message}`); return null; } }
 // V25.12: Markdown -> sanitized HTML via Rust (pulldown-cmark + ammonia). Null = caller falls back to JS.
 export function renderHtmlFFI(md) { if (!_markdownRenderer) return null; try { return _markdownRenderer.renderHtml(md); } catch (e) { console.error(`[RUST-BRIDGE] ❌ FFI FAILED (JS fallback): renderHtml: ${e.message}`); return null; } }
 export function getRustMode() { return _mode; }